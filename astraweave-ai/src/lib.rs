@@ -7,7 +7,7 @@ pub trait Orchestrator {
 /// Minimal rule-based orchestrator:
 /// If enemy in LOS-ish and "smoke" not on cooldown:
 ///   throw smoke midway, move up, cover fire.
-/// Else: advance towards nearest enemy.
+/// Else: advance towards nearest enemy, or to its "flank:<id>" POI if the snapshot has one.
 pub struct RuleOrchestrator;
 
 impl Orchestrator for RuleOrchestrator {
@@ -44,14 +44,19 @@ impl Orchestrator for RuleOrchestrator {
                     ],
                 };
             } else {
-                // advance cautiously
+                // advance cautiously, to a flanking spot if tactical analysis found one
+                let flank = format!("flank:{}", first.id);
+                let to = snap.pois.iter().find(|p| p.k == flank).map_or(
+                    IVec2 {
+                        x: m.pos.x + (first.pos.x - m.pos.x).signum(),
+                        y: m.pos.y + (first.pos.y - m.pos.y).signum(),
+                    },
+                    |p| p.pos,
+                );
                 return PlanIntent {
                     plan_id,
                     steps: vec![
-                        ActionStep::MoveTo {
-                            x: m.pos.x + (first.pos.x - m.pos.x).signum(),
-                            y: m.pos.y + (first.pos.y - m.pos.y).signum(),
-                        },
+                        ActionStep::MoveTo { x: to.x, y: to.y },
                         ActionStep::CoverFire {
                             target_id: first.id,
                             duration: 1.5,
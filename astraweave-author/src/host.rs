@@ -0,0 +1,414 @@
+use crate::{budget_from_output, meta_to_map, MapMeta};
+use anyhow::{anyhow, Result};
+use astraweave_core::DirectorBudget;
+use rhai::{
+    CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, ParseError, Position, AST,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Which stage of the script lifecycle produced an error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+    Io,
+    Compile,
+    Runtime,
+    Migrate,
+}
+
+impl fmt::Display for ScriptErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ScriptErrorKind::Io => "io",
+            ScriptErrorKind::Compile => "compile",
+            ScriptErrorKind::Runtime => "runtime",
+            ScriptErrorKind::Migrate => "migrate",
+        };
+        f.write_str(s)
+    }
+}
+
+/// A script error with file/line info, ready to be pushed into a debug event log.
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    pub kind: ScriptErrorKind,
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ScriptError {
+    fn new(kind: ScriptErrorKind, path: &Path, pos: Position, message: String) -> Self {
+        Self {
+            kind,
+            path: path.to_path_buf(),
+            line: pos.line(),
+            column: pos.position(),
+            message,
+        }
+    }
+
+    fn from_parse(path: &Path, e: &ParseError) -> Self {
+        Self::new(
+            ScriptErrorKind::Compile,
+            path,
+            e.position(),
+            e.err_type().to_string(),
+        )
+    }
+
+    fn from_eval(kind: ScriptErrorKind, path: &Path, e: &EvalAltResult) -> Self {
+        // Calls into script functions wrap the real failure; report the innermost position.
+        let mut inner = e;
+        while let EvalAltResult::ErrorInFunctionCall(_, _, err, _) = inner {
+            inner = err;
+        }
+        Self::new(kind, path, inner.position(), e.to_string())
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+            if let Some(col) = self.column {
+                write!(f, ":{col}")?;
+            }
+        }
+        write!(f, ": {} error: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+struct LoadedScript {
+    ast: AST,
+    modified: Option<SystemTime>,
+    /// Script-scoped persistent state, bound to `this` in every call.
+    state: Dynamic,
+    generation: u32,
+}
+
+/// Caches compiled Rhai scripts and hot-reloads them when their files change.
+///
+/// Each script owns a persistent state value that is bound to `this` whenever one of
+/// its functions is called. On first load the state comes from `fn init_state()` (or an
+/// empty map). On reload the new script's `fn migrate(old)` receives the previous state
+/// and returns the state to keep; without a `migrate` the state is carried over as-is.
+/// If a reload fails to compile or migrate, the last good version stays active.
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: HashMap<PathBuf, LoadedScript>,
+    errors: Vec<ScriptError>,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        Self::with_engine(Engine::new())
+    }
+
+    pub fn with_engine(engine: Engine) -> Self {
+        Self {
+            engine,
+            scripts: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Access the engine to register host functions before loading scripts.
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    pub fn is_loaded(&self, path: impl AsRef<Path>) -> bool {
+        self.scripts.contains_key(path.as_ref())
+    }
+
+    /// Number of successful reloads since the script was first loaded.
+    pub fn generation(&self, path: impl AsRef<Path>) -> Option<u32> {
+        self.scripts.get(path.as_ref()).map(|s| s.generation)
+    }
+
+    pub fn state(&self, path: impl AsRef<Path>) -> Option<&Dynamic> {
+        self.scripts.get(path.as_ref()).map(|s| &s.state)
+    }
+
+    /// Errors recorded since the last call, oldest first.
+    pub fn drain_errors(&mut self) -> Vec<ScriptError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Compile and cache a script. Does nothing if it is already loaded.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if self.scripts.contains_key(path) {
+            return Ok(());
+        }
+        let (ast, modified) = self.compile(path)?;
+        let mut state = Dynamic::from(Map::new());
+        if has_fn(&ast, "init_state", 0) {
+            state = self.call_raw(path, &ast, "init_state", (), ScriptErrorKind::Runtime)?;
+        }
+        self.scripts.insert(
+            path.to_path_buf(),
+            LoadedScript {
+                ast,
+                modified,
+                state,
+                generation: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Load every `.rhai` file in `dir` that is not cached yet, e.g. after a watcher saw
+    /// a file being created. Returns the paths that loaded; failures are recorded.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir.as_ref()) else {
+            return vec![];
+        };
+        let mut fresh: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|x| x == "rhai") && !self.is_loaded(p))
+            .collect();
+        fresh.sort();
+        fresh.into_iter().filter(|p| self.load(p).is_ok()).collect()
+    }
+
+    /// Recompile a loaded script. Returns `true` if the new version replaced the old one;
+    /// on failure the error is recorded and the last good version is kept.
+    pub fn reload(&mut self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        if !self.scripts.contains_key(path) {
+            return self.load(path).is_ok();
+        }
+        let Ok((ast, modified)) = self.compile(path) else {
+            // Remember the timestamp so a broken file is not retried every poll.
+            if let Some(s) = self.scripts.get_mut(path) {
+                s.modified = file_modified(path);
+            }
+            return false;
+        };
+        let old_state = self.scripts[path].state.clone();
+        let state = if has_fn(&ast, "migrate", 1) {
+            match self.call_raw(
+                path,
+                &ast,
+                "migrate",
+                (old_state,),
+                ScriptErrorKind::Migrate,
+            ) {
+                Ok(s) => s,
+                Err(_) => {
+                    if let Some(s) = self.scripts.get_mut(path) {
+                        s.modified = modified;
+                    }
+                    return false;
+                }
+            }
+        } else {
+            old_state
+        };
+        let entry = self.scripts.get_mut(path).expect("checked above");
+        entry.ast = ast;
+        entry.modified = modified;
+        entry.state = state;
+        entry.generation += 1;
+        true
+    }
+
+    /// Reload every cached script whose file modification time changed.
+    /// Returns the paths that were successfully swapped in.
+    pub fn reload_changed(&mut self) -> Vec<PathBuf> {
+        let stale: Vec<PathBuf> = self
+            .scripts
+            .iter()
+            .filter(|(p, s)| file_modified(p) != s.modified)
+            .map(|(p, _)| p.clone())
+            .collect();
+        stale.into_iter().filter(|p| self.reload(p)).collect()
+    }
+
+    /// Call a function in a cached script (loading it on first use) with the
+    /// script's persistent state bound to `this`.
+    pub fn call_fn(
+        &mut self,
+        path: impl AsRef<Path>,
+        name: &str,
+        args: impl FuncArgs,
+    ) -> Result<Dynamic> {
+        let path = path.as_ref();
+        self.load(path)?;
+        let entry = self.scripts.get_mut(path).expect("loaded above");
+        let mut scope = rhai::Scope::new();
+        let res = self.engine.call_fn_with_options::<Dynamic>(
+            CallFnOptions::new().bind_this_ptr(&mut entry.state),
+            &mut scope,
+            &entry.ast,
+            name,
+            args,
+        );
+        res.map_err(|e| {
+            let err = ScriptError::from_eval(ScriptErrorKind::Runtime, path, &e);
+            self.errors.push(err.clone());
+            anyhow!(err)
+        })
+    }
+
+    /// Hot-reloadable counterpart of [`crate::run_author_script`].
+    pub fn run_configure(
+        &mut self,
+        path: impl AsRef<Path>,
+        meta: &MapMeta,
+    ) -> Result<(DirectorBudget, serde_json::Value)> {
+        let out = self.call_fn(path, "configure", (meta_to_map(meta),))?;
+        budget_from_output(out)
+    }
+
+    fn compile(&mut self, path: &Path) -> Result<(AST, Option<SystemTime>)> {
+        let modified = file_modified(path);
+        let src = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                let err =
+                    ScriptError::new(ScriptErrorKind::Io, path, Position::NONE, e.to_string());
+                self.errors.push(err.clone());
+                return Err(anyhow!(err));
+            }
+        };
+        match self.engine.compile(&src) {
+            Ok(mut ast) => {
+                ast.set_source(path.to_string_lossy().as_ref());
+                Ok((ast, modified))
+            }
+            Err(e) => {
+                let err = ScriptError::from_parse(path, &e);
+                self.errors.push(err.clone());
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    fn call_raw(
+        &mut self,
+        path: &Path,
+        ast: &AST,
+        name: &str,
+        args: impl FuncArgs,
+        kind: ScriptErrorKind,
+    ) -> Result<Dynamic> {
+        let mut scope = rhai::Scope::new();
+        self.engine
+            .call_fn::<Dynamic>(&mut scope, ast, name, args)
+            .map_err(|e| {
+                let err = ScriptError::from_eval(kind, path, &e);
+                self.errors.push(err.clone());
+                anyhow!(err)
+            })
+    }
+}
+
+fn has_fn(ast: &AST, name: &str, arity: usize) -> bool {
+    ast.iter_functions()
+        .any(|f| f.name == name && f.params.len() == arity)
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_script(dir: &TempDir, name: &str, src: &str) -> PathBuf {
+        let p = dir.path().join(name);
+        std::fs::write(&p, src).unwrap();
+        p
+    }
+
+    #[test]
+    fn keeps_last_good_version_on_compile_error() {
+        let dir = TempDir::new().unwrap();
+        let p = temp_script(&dir, "good_then_bad.rhai", "fn value() { 1 }");
+        let mut host = ScriptHost::new();
+        assert_eq!(host.call_fn(&p, "value", ()).unwrap().as_int().unwrap(), 1);
+
+        std::fs::write(&p, "fn value() {\n  1 +\n}").unwrap();
+        assert!(!host.reload(&p));
+        let errs = host.drain_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, ScriptErrorKind::Compile);
+        assert!(errs[0].line.is_some());
+        assert_eq!(host.call_fn(&p, "value", ()).unwrap().as_int().unwrap(), 1);
+        assert_eq!(host.generation(&p), Some(0));
+    }
+
+    #[test]
+    fn migrates_state_across_reloads() {
+        let dir = TempDir::new().unwrap();
+        let p = temp_script(
+            &dir,
+            "migrate.rhai",
+            "fn init_state() { #{ count: 0 } }\nfn tick() { this.count += 1; this.count }",
+        );
+        let mut host = ScriptHost::new();
+        for expected in 1..=2 {
+            assert_eq!(
+                host.call_fn(&p, "tick", ()).unwrap().as_int().unwrap(),
+                expected
+            );
+        }
+
+        std::fs::write(
+            &p,
+            "fn migrate(old) { #{ total: old.count * 10 } }\nfn tick() { this.total += 1; this.total }",
+        )
+        .unwrap();
+        assert!(host.reload(&p));
+        assert_eq!(host.call_fn(&p, "tick", ()).unwrap().as_int().unwrap(), 21);
+        assert_eq!(host.generation(&p), Some(1));
+    }
+
+    #[test]
+    fn runtime_errors_carry_line_info() {
+        let dir = TempDir::new().unwrap();
+        let p = temp_script(
+            &dir,
+            "runtime.rhai",
+            "fn boom() {\n  let x = 1;\n  x.no_such_method()\n}",
+        );
+        let mut host = ScriptHost::new();
+        assert!(host.call_fn(&p, "boom", ()).is_err());
+        let errs = host.drain_errors();
+        assert_eq!(errs[0].kind, ScriptErrorKind::Runtime);
+        assert_eq!(errs[0].line, Some(3));
+    }
+
+    #[test]
+    fn load_dir_picks_up_new_scripts() {
+        let dir = TempDir::new().unwrap();
+        let a = temp_script(&dir, "a.rhai", "fn value() { 1 }");
+        temp_script(&dir, "notes.txt", "not a script");
+        let mut host = ScriptHost::new();
+        assert_eq!(host.load_dir(dir.path()), vec![a.clone()]);
+        assert!(host.load_dir(dir.path()).is_empty());
+
+        let b = temp_script(&dir, "b.rhai", "fn value() { 2 }");
+        assert_eq!(host.load_dir(dir.path()), vec![b.clone()]);
+        assert_eq!(host.call_fn(&b, "value", ()).unwrap().as_int().unwrap(), 2);
+        assert_eq!(host.generation(&a), Some(0));
+    }
+}
@@ -2,6 +2,9 @@ use anyhow::Result;
 use astraweave_core::DirectorBudget;
 use rhai::{Dynamic, Engine, Map};
 
+mod host;
+pub use host::{ScriptError, ScriptErrorKind, ScriptHost};
+
 #[derive(Clone)]
 pub struct MapMeta {
     pub width: i32,
@@ -15,22 +18,31 @@ pub fn run_author_script(
     meta: &MapMeta,
 ) -> Result<(DirectorBudget, serde_json::Value)> {
     let engine = Engine::new();
-    // Provide meta as a map
-    let mut m = Map::new();
-    m.insert("width".into(), Dynamic::from(meta.width));
-    m.insert("height".into(), Dynamic::from(meta.height));
-    m.insert("enemy_count".into(), Dynamic::from(meta.enemy_count));
-    m.insert("difficulty".into(), Dynamic::from(meta.difficulty));
-
     let ast = engine
         .compile_file(path.into())
         .map_err(|e| anyhow::anyhow!("{}", e))?;
-    // `configure(meta)` returns object `{ traps, terrain_edits, spawns, hints: #{...} }`
     let mut scope = rhai::Scope::new();
     let out: Dynamic = engine
-        .call_fn(&mut scope, &ast, "configure", (m,))
+        .call_fn(&mut scope, &ast, "configure", (meta_to_map(meta),))
         .map_err(|e| anyhow::anyhow!("{}", e))?;
-    let o: rhai::Map = out.cast();
+    budget_from_output(out)
+}
+
+// Provide meta as a map
+pub(crate) fn meta_to_map(meta: &MapMeta) -> Map {
+    let mut m = Map::new();
+    m.insert("width".into(), Dynamic::from(meta.width));
+    m.insert("height".into(), Dynamic::from(meta.height));
+    m.insert("enemy_count".into(), Dynamic::from(meta.enemy_count));
+    m.insert("difficulty".into(), Dynamic::from(meta.difficulty));
+    m
+}
+
+// `configure(meta)` returns object `{ traps, terrain_edits, spawns, hints: #{...} }`
+pub(crate) fn budget_from_output(out: Dynamic) -> Result<(DirectorBudget, serde_json::Value)> {
+    let o: rhai::Map = out
+        .try_cast()
+        .ok_or_else(|| anyhow::anyhow!("configure() must return a map"))?;
 
     let traps = o
         .get("traps")
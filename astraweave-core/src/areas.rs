@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Kind of ground under a grid cell or navmesh polygon.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum AreaType {
+    #[default]
+    Ground,
+    /// Shallow water: walkable but slow.
+    Water,
+    /// Fire, acid, traps; most agents avoid it.
+    Hazard,
+    /// Cover and shadows that stealthy agents prefer.
+    Cover,
+}
+
+/// How much an agent dislikes each area type, and which it may enter at all.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AreaCosts {
+    /// Cost multiplier per area; unlisted areas cost 1.0.
+    #[serde(default)]
+    pub multipliers: BTreeMap<AreaType, f32>,
+    /// If non-empty, only these areas may be entered.
+    #[serde(default)]
+    pub include: BTreeSet<AreaType>,
+    /// Areas that are never entered.
+    #[serde(default)]
+    pub exclude: BTreeSet<AreaType>,
+}
+
+impl AreaCosts {
+    pub fn with_cost(mut self, area: AreaType, multiplier: f32) -> Self {
+        self.multipliers.insert(area, multiplier);
+        self
+    }
+
+    pub fn excluding(mut self, area: AreaType) -> Self {
+        self.exclude.insert(area);
+        self
+    }
+
+    pub fn allows(&self, area: AreaType) -> bool {
+        !self.exclude.contains(&area) && (self.include.is_empty() || self.include.contains(&area))
+    }
+
+    /// Multiplier for moving through `area`, or `None` if it may not be entered.
+    pub fn cost(&self, area: AreaType) -> Option<f32> {
+        self.allows(area)
+            .then(|| self.multipliers.get(&area).copied().unwrap_or(1.0))
+    }
+
+    /// Smallest multiplier in use; scales A* heuristics so they stay admissible.
+    pub fn min_multiplier(&self) -> f32 {
+        self.multipliers
+            .values()
+            .copied()
+            .fold(1.0f32, f32::min)
+            .max(0.0)
+    }
+
+    /// Typical soldier: wades through water, stays out of hazards.
+    pub fn soldier() -> Self {
+        Self::default()
+            .with_cost(AreaType::Water, 2.0)
+            .excluding(AreaType::Hazard)
+    }
+
+    /// Stealthy companion: prefers cover, avoids open water, will cross hazards only if it must.
+    pub fn stealthy() -> Self {
+        Self::default()
+            .with_cost(AreaType::Cover, 0.5)
+            .with_cost(AreaType::Water, 3.0)
+            .with_cost(AreaType::Hazard, 10.0)
+    }
+}
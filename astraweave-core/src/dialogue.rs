@@ -0,0 +1,44 @@
+//! Dialogue graph data, shared by the runtime in `astraweave-gameplay` and by content
+//! packs that ship dialogue without depending on gameplay.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Cond {
+    Eq { key: String, val: String },
+    Ne { key: String, val: String },
+    Has { key: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Line {
+    pub speaker: String,
+    pub text: String,
+    #[serde(default)]
+    pub set_vars: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Choice {
+    pub text: String,
+    pub go_to: String,
+    #[serde(default)]
+    pub require: Vec<Cond>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub id: String,
+    pub line: Option<Line>,
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub end: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dialogue {
+    pub id: String,
+    pub start: String,
+    pub nodes: Vec<Node>,
+}
@@ -0,0 +1,524 @@
+//! Hierarchical grid pathfinding (HPA*). The grid is cut into square clusters; the cells
+//! where neighbouring clusters connect become nodes of a small abstract graph. A path is
+//! found on that graph first and then refined one cluster at a time, so long trips never
+//! flood the whole grid. [`GridHierarchy::request`] hands the refinement back to the caller
+//! to spread over ticks under a budget.
+
+use crate::{astar_path_weighted, AreaCosts, AreaType, IVec2};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+type Cell = (i32, i32);
+
+/// How the bounds are cut into clusters.
+#[derive(Clone, Copy, Debug)]
+struct Clusters {
+    bounds: (i32, i32, i32, i32),
+    size: i32,
+}
+
+impl Clusters {
+    fn contains(&self, (x, y): Cell) -> bool {
+        let (minx, miny, maxx, maxy) = self.bounds;
+        x >= minx && y >= miny && x <= maxx && y <= maxy
+    }
+
+    fn count(&self) -> (i32, i32) {
+        let (minx, miny, maxx, maxy) = self.bounds;
+        ((maxx - minx) / self.size + 1, (maxy - miny) / self.size + 1)
+    }
+
+    fn exists(&self, (cx, cy): Cell) -> bool {
+        let (nx, ny) = self.count();
+        cx >= 0 && cy >= 0 && cx < nx && cy < ny
+    }
+
+    fn of(&self, (x, y): Cell) -> Cell {
+        let (minx, miny, _, _) = self.bounds;
+        (
+            (x - minx).div_euclid(self.size),
+            (y - miny).div_euclid(self.size),
+        )
+    }
+
+    /// Cells of a cluster, as bounds for [`astar_path_weighted`].
+    fn rect(&self, (cx, cy): Cell) -> (i32, i32, i32, i32) {
+        let (minx, miny, maxx, maxy) = self.bounds;
+        let (x0, y0) = (minx + cx * self.size, miny + cy * self.size);
+        (
+            x0,
+            y0,
+            (x0 + self.size - 1).min(maxx),
+            (y0 + self.size - 1).min(maxy),
+        )
+    }
+}
+
+/// A grid path planned across clusters and refined one leg at a time; see
+/// [`GridHierarchy::request`].
+#[derive(Clone, Debug)]
+pub struct GridPathRequest {
+    /// Start, entrance cells and goal, in order.
+    route: Vec<Cell>,
+    /// Index of the route cell the next leg starts from.
+    next: usize,
+    path: Vec<IVec2>,
+}
+
+impl GridPathRequest {
+    pub fn is_done(&self) -> bool {
+        self.next + 1 >= self.route.len()
+    }
+
+    /// Refine up to `budget` legs of the route, each a search confined to one cluster.
+    /// Returns the cells from start to goal once the last leg is done (empty if the goal
+    /// is unreachable), and `None` while legs remain.
+    pub fn step(
+        &mut self,
+        h: &GridHierarchy,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        budget: usize,
+    ) -> Option<Vec<IVec2>> {
+        for _ in 0..budget {
+            if self.is_done() {
+                break;
+            }
+            let (a, b) = (self.route[self.next], self.route[self.next + 1]);
+            self.next += 1;
+            let leg = if (a.0 - b.0).abs() + (a.1 - b.1).abs() == 1 {
+                match h.enter_cost(obstacles, areas, b) {
+                    Some(_) => vec![IVec2 { x: a.0, y: a.1 }, IVec2 { x: b.0, y: b.1 }],
+                    None => vec![],
+                }
+            } else {
+                h.local_path(obstacles, areas, h.clusters.of(a), a, b)
+            };
+            if leg.is_empty() {
+                // Blocked since the route was planned.
+                self.route.clear();
+                self.path.clear();
+                break;
+            }
+            self.path.extend(leg.into_iter().skip(1));
+        }
+        self.is_done().then(|| self.path.clone())
+    }
+}
+
+/// Abstract graph over a grid for one set of [`AreaCosts`].
+#[derive(Clone, Debug)]
+pub struct GridHierarchy {
+    clusters: Clusters,
+    costs: AreaCosts,
+    /// Entrance cells and their edges, to entrances of the same cluster or across to the
+    /// neighbouring one.
+    graph: HashMap<Cell, Vec<(Cell, f32)>>,
+}
+
+impl GridHierarchy {
+    pub fn build(
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        bounds: (i32, i32, i32, i32),
+        cluster_size: i32,
+        costs: AreaCosts,
+    ) -> Self {
+        let clusters = Clusters {
+            bounds,
+            size: cluster_size.max(1),
+        };
+        let (nx, ny) = clusters.count();
+        let all = (0..nx).flat_map(|x| (0..ny).map(move |y| (x, y))).collect();
+        let mut h = Self {
+            clusters,
+            costs,
+            graph: HashMap::new(),
+        };
+        h.rebuild(obstacles, areas, &all);
+        h
+    }
+
+    /// Redo the clusters holding `changed` cells after obstacles or areas moved there,
+    /// along with their neighbours, whose shared entrances may have shifted.
+    pub fn update(
+        &mut self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        changed: &[Cell],
+    ) {
+        let mut touched = BTreeSet::new();
+        for &c in changed.iter().filter(|c| self.clusters.contains(**c)) {
+            let (cx, cy) = self.clusters.of(c);
+            for (dx, dy) in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+                if self.clusters.exists((cx + dx, cy + dy)) {
+                    touched.insert((cx + dx, cy + dy));
+                }
+            }
+        }
+        self.rebuild(obstacles, areas, &touched);
+    }
+
+    /// Entrance cells in the abstract graph.
+    pub fn node_count(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Same contract as [`astar_path_weighted`]: the cells from `start` to `goal`, or empty
+    /// if unreachable. The path is optimal within each cluster but may detour slightly
+    /// through entrances.
+    pub fn find_path(
+        &self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        start: IVec2,
+        goal: IVec2,
+    ) -> Vec<IVec2> {
+        self.request(obstacles, areas, start, goal)
+            .step(self, obstacles, areas, usize::MAX)
+            .unwrap_or_default()
+    }
+
+    /// Plan the route from `start` to `goal` across clusters, leaving the cell-by-cell
+    /// refinement to [`GridPathRequest::step`] so it can be spread over several ticks.
+    pub fn request(
+        &self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        start: IVec2,
+        goal: IVec2,
+    ) -> GridPathRequest {
+        let (s, g) = ((start.x, start.y), (goal.x, goal.y));
+        let unreachable = GridPathRequest {
+            route: vec![],
+            next: 0,
+            path: vec![],
+        };
+        if !self.clusters.contains(s) || !self.clusters.contains(g) {
+            return unreachable;
+        }
+        let (cs, cg) = (self.clusters.of(s), self.clusters.of(g));
+        if cs == cg {
+            let local = self.local_path(obstacles, areas, cs, s, g);
+            if !local.is_empty() {
+                return GridPathRequest {
+                    route: vec![],
+                    next: 0,
+                    path: local,
+                };
+            }
+        }
+
+        // Temporary edges from the start to its cluster's entrances, and from the goal
+        // cluster's entrances to the goal.
+        let step_cost = |p: &[IVec2]| self.path_cost(obstacles, areas, p);
+        let from_start: Vec<(Cell, f32)> = self
+            .nodes_in(cs)
+            .filter_map(|n| {
+                let p = self.local_path(obstacles, areas, cs, s, n);
+                (!p.is_empty()).then(|| (n, step_cost(&p[1..])))
+            })
+            .collect();
+        let to_goal: HashMap<Cell, f32> = self
+            .nodes_in(cg)
+            .filter_map(|n| {
+                let p = self.local_path(obstacles, areas, cg, n, g);
+                (!p.is_empty()).then(|| (n, step_cost(&p[1..])))
+            })
+            .collect();
+
+        match self.abstract_path(s, g, &from_start, &to_goal) {
+            Some(route) => GridPathRequest {
+                route,
+                next: 0,
+                path: vec![start],
+            },
+            None => unreachable,
+        }
+    }
+
+    fn abstract_path(
+        &self,
+        s: Cell,
+        g: Cell,
+        from_start: &[(Cell, f32)],
+        to_goal: &HashMap<Cell, f32>,
+    ) -> Option<Vec<Cell>> {
+        #[derive(Copy, Clone, PartialEq)]
+        struct Node {
+            f: f32,
+            c: Cell,
+        }
+        impl Eq for Node {}
+        impl Ord for Node {
+            fn cmp(&self, other: &Self) -> Ordering {
+                other.f.total_cmp(&self.f)
+            }
+        }
+        impl PartialOrd for Node {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        let h_scale = self.costs.min_multiplier();
+        let h = |c: Cell| ((c.0 - g.0).abs() + (c.1 - g.1).abs()) as f32 * h_scale;
+        let mut open = BinaryHeap::from([Node { f: h(s), c: s }]);
+        let mut came: HashMap<Cell, Cell> = HashMap::new();
+        let mut gscore: HashMap<Cell, f32> = HashMap::from([(s, 0.0)]);
+        while let Some(Node { c, .. }) = open.pop() {
+            if c == g {
+                let mut route = vec![g];
+                while let Some(&prev) = came.get(route.last().unwrap()) {
+                    route.push(prev);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            let start_edges = if c == s { from_start } else { &[] };
+            let edges = start_edges
+                .iter()
+                .chain(self.graph.get(&c).into_iter().flatten())
+                .copied()
+                .chain(to_goal.get(&c).map(|&cost| (g, cost)));
+            let cur = gscore[&c];
+            for (n, cost) in edges {
+                let ng = cur + cost;
+                if ng < *gscore.get(&n).unwrap_or(&f32::INFINITY) {
+                    came.insert(n, c);
+                    gscore.insert(n, ng);
+                    open.push(Node { f: ng + h(n), c: n });
+                }
+            }
+        }
+        None
+    }
+
+    fn rebuild(
+        &mut self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        touched: &BTreeSet<Cell>,
+    ) {
+        let clusters = self.clusters;
+        self.graph
+            .retain(|c, _| !touched.contains(&clusters.of(*c)));
+        for edges in self.graph.values_mut() {
+            edges.retain(|(to, _)| !touched.contains(&clusters.of(*to)));
+        }
+        for &c in touched {
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let n = (c.0 + dx, c.1 + dy);
+                if clusters.exists(n) {
+                    self.add_entrances(obstacles, areas, c, n);
+                }
+            }
+        }
+        for &c in touched {
+            let nodes: Vec<Cell> = self.nodes_in(c).collect();
+            for (i, &a) in nodes.iter().enumerate() {
+                for &b in &nodes[i + 1..] {
+                    let p = self.local_path(obstacles, areas, c, a, b);
+                    if p.is_empty() {
+                        continue;
+                    }
+                    let there = self.path_cost(obstacles, areas, &p[1..]);
+                    let back = self.path_cost(obstacles, areas, &p[..p.len() - 1]);
+                    self.connect(a, b, there);
+                    self.connect(b, a, back);
+                }
+            }
+        }
+    }
+
+    /// One entrance in the middle of each run of open cell pairs along the border of
+    /// clusters `a` and `b`.
+    fn add_entrances(
+        &mut self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        a: Cell,
+        b: Cell,
+    ) {
+        let (ra, rb) = (self.clusters.rect(a), self.clusters.rect(b));
+        let pairs: Vec<(Cell, Cell)> = if a.0 != b.0 {
+            let (xa, xb) = if b.0 > a.0 {
+                (ra.2, rb.0)
+            } else {
+                (ra.0, rb.2)
+            };
+            (ra.1..=ra.3).map(|y| ((xa, y), (xb, y))).collect()
+        } else {
+            let (ya, yb) = if b.1 > a.1 {
+                (ra.3, rb.1)
+            } else {
+                (ra.1, rb.3)
+            };
+            (ra.0..=ra.2).map(|x| ((x, ya), (x, yb))).collect()
+        };
+        let mut run = vec![];
+        for pair in pairs.into_iter().map(Some).chain([None]) {
+            let open = pair.and_then(|(ca, cb)| {
+                Some((
+                    ca,
+                    cb,
+                    self.enter_cost(obstacles, areas, ca)?,
+                    self.enter_cost(obstacles, areas, cb)?,
+                ))
+            });
+            match open {
+                Some(e) => run.push(e),
+                None if !run.is_empty() => {
+                    let (ca, cb, into_a, into_b) = run[run.len() / 2];
+                    self.connect(ca, cb, into_b);
+                    self.connect(cb, ca, into_a);
+                    run.clear();
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn connect(&mut self, from: Cell, to: Cell, cost: f32) {
+        let edges = self.graph.entry(from).or_default();
+        if !edges.iter().any(|(t, _)| *t == to) {
+            edges.push((to, cost));
+        }
+        self.graph.entry(to).or_default();
+    }
+
+    fn nodes_in(&self, cluster: Cell) -> impl Iterator<Item = Cell> + '_ {
+        self.graph
+            .keys()
+            .copied()
+            .filter(move |n| self.clusters.of(*n) == cluster)
+    }
+
+    fn enter_cost(
+        &self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        c: Cell,
+    ) -> Option<f32> {
+        if obstacles.contains(&c) {
+            return None;
+        }
+        self.costs.cost(areas.get(&c).copied().unwrap_or_default())
+    }
+
+    fn path_cost(
+        &self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        entered: &[IVec2],
+    ) -> f32 {
+        entered
+            .iter()
+            .map(|p| self.enter_cost(obstacles, areas, (p.x, p.y)).unwrap_or(1.0))
+            .sum()
+    }
+
+    fn local_path(
+        &self,
+        obstacles: &HashSet<Cell>,
+        areas: &HashMap<Cell, AreaType>,
+        cluster: Cell,
+        from: Cell,
+        to: Cell,
+    ) -> Vec<IVec2> {
+        astar_path_weighted(
+            obstacles,
+            areas,
+            IVec2 {
+                x: from.0,
+                y: from.1,
+            },
+            IVec2 { x: to.0, y: to.1 },
+            self.clusters.rect(cluster),
+            &self.costs,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GridMapping, GridPathfinder, Pathfinder};
+    use glam::Vec3;
+
+    const BOUNDS: (i32, i32, i32, i32) = (0, 0, 31, 31);
+
+    /// A wall down x = 15 with a gap at the top.
+    fn walled() -> HashSet<Cell> {
+        (0..=27).map(|y| (15, y)).collect()
+    }
+
+    fn is_walk(obstacles: &HashSet<Cell>, p: &[IVec2]) -> bool {
+        p.windows(2)
+            .all(|w| (w[0].x - w[1].x).abs() + (w[0].y - w[1].y).abs() == 1)
+            && p.iter().all(|c| !obstacles.contains(&(c.x, c.y)))
+    }
+
+    #[test]
+    fn cluster_paths_stay_close_to_full_searches() {
+        let (obstacles, areas) = (walled(), HashMap::new());
+        let h = GridHierarchy::build(&obstacles, &areas, BOUNDS, 8, AreaCosts::default());
+        let (s, g) = (IVec2 { x: 2, y: 2 }, IVec2 { x: 28, y: 2 });
+        let path = h.find_path(&obstacles, &areas, s, g);
+        let full = astar_path_weighted(&obstacles, &areas, s, g, BOUNDS, &AreaCosts::default());
+        assert_eq!((path[0], *path.last().unwrap()), (s, g));
+        assert!(is_walk(&obstacles, &path), "{path:?}");
+        assert!(path.len() as f32 <= full.len() as f32 * 1.1, "{path:?}");
+
+        // Same cluster: a plain local search.
+        let near = h.find_path(&obstacles, &areas, s, IVec2 { x: 5, y: 6 });
+        assert_eq!(near.len(), 8);
+    }
+
+    #[test]
+    fn requests_refine_a_few_legs_per_step() {
+        let (obstacles, areas) = (walled(), HashMap::new());
+        let h = GridHierarchy::build(&obstacles, &areas, BOUNDS, 8, AreaCosts::default());
+        let (s, g) = (IVec2 { x: 2, y: 2 }, IVec2 { x: 28, y: 2 });
+        let mut req = h.request(&obstacles, &areas, s, g);
+        let mut steps = 1;
+        let path = loop {
+            if let Some(p) = req.step(&h, &obstacles, &areas, 1) {
+                break p;
+            }
+            steps += 1;
+        };
+        assert!(steps > 2);
+        assert_eq!(path, h.find_path(&obstacles, &areas, s, g));
+    }
+
+    #[test]
+    fn updates_follow_new_obstacles() {
+        let (mut obstacles, areas) = (walled(), HashMap::new());
+        let mut h = GridHierarchy::build(&obstacles, &areas, BOUNDS, 8, AreaCosts::default());
+        let (s, g) = (IVec2 { x: 2, y: 2 }, IVec2 { x: 28, y: 2 });
+        let open = h.request(&obstacles, &areas, s, g);
+
+        // Close the gap.
+        let gap: Vec<Cell> = (28..=31).map(|y| (15, y)).collect();
+        obstacles.extend(gap.iter().copied());
+        h.update(&obstacles, &areas, &gap);
+        assert!(h.find_path(&obstacles, &areas, s, g).is_empty());
+        // A request planned before the change finds its route blocked.
+        let mut open = open;
+        assert_eq!(open.step(&h, &obstacles, &areas, usize::MAX), Some(vec![]));
+
+        let pf = GridPathfinder {
+            obstacles: &obstacles,
+            areas: &areas,
+            bounds: BOUNDS,
+            costs: AreaCosts::default(),
+            mapping: GridMapping::default(),
+            hierarchy: None,
+        }
+        .with_hierarchy(&h);
+        let to_world = |c: IVec2| Vec3::new(c.x as f32, 0.0, c.y as f32);
+        assert!(!pf.path_exists(to_world(s), to_world(g)));
+        assert!(pf.path_exists(to_world(s), to_world(IVec2 { x: 10, y: 20 })));
+    }
+}
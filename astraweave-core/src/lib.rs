@@ -1,17 +1,29 @@
+pub mod areas;
+pub mod dialogue;
+pub mod hpa;
+pub mod pathing;
 pub mod perception;
 pub mod schema;
 pub mod sim;
+pub mod tactics;
+pub mod throwing;
 pub mod tools;
 pub mod util;
 pub mod validation;
 pub mod world;
 
+pub use areas::*;
+pub use hpa::{GridHierarchy, GridPathRequest};
+pub use pathing::{GridMapping, GridPathfinder, Pathfinder};
 pub use perception::*;
 pub use schema::*;
 pub use sim::*;
+pub use tactics::*;
+pub use throwing::Thrower;
 // Note: tools::Poi and schema::Poi are different types - using qualified imports where needed
 pub use tools::{
-    astar_path, find_cover_positions, glam_to_schema, los_clear, path_exists, schema_to_glam,
+    astar_path, astar_path_weighted, find_cover_positions, glam_to_schema, los_clear, path_exists,
+    schema_to_glam,
 };
 pub use validation::*;
 pub use world::*;
@@ -0,0 +1,131 @@
+//! Path queries shared by the tactical grid and the 3D navmesh, and the mapping between
+//! world coordinates and grid cells.
+
+use crate::{astar_path_weighted, AreaCosts, AreaType, GridHierarchy, IVec2, World};
+use glam::Vec3;
+use std::collections::{HashMap, HashSet};
+
+/// How the tactical grid sits in the world. Cell `(x, y)` is centred on
+/// `origin + (x * cell_size, 0, y * cell_size)` (grid `y` runs along world `z`) and covers
+/// half a cell to either side, lower edge included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridMapping {
+    pub origin: Vec3,
+    pub cell_size: f32,
+}
+
+impl Default for GridMapping {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            cell_size: 1.0,
+        }
+    }
+}
+
+impl GridMapping {
+    /// Cell containing `p`; its height is ignored.
+    pub fn to_cell(&self, p: Vec3) -> IVec2 {
+        let cell = |v: f32, o: f32| ((v - o) / self.cell_size + 0.5).floor() as i32;
+        IVec2 {
+            x: cell(p.x, self.origin.x),
+            y: cell(p.z, self.origin.z),
+        }
+    }
+
+    /// Centre of `c`, at the grid's height.
+    pub fn to_world(&self, c: IVec2) -> Vec3 {
+        self.origin + Vec3::new(c.x as f32, 0.0, c.y as f32) * self.cell_size
+    }
+
+    /// Corners of `c` on the ground plane.
+    pub fn cell_bounds(&self, c: IVec2) -> (Vec3, Vec3) {
+        let half = Vec3::new(0.5, 0.0, 0.5) * self.cell_size;
+        let centre = self.to_world(c);
+        (centre - half, centre + half)
+    }
+}
+
+/// Anything agents can path over, in world coordinates: the tactical grid
+/// ([`GridPathfinder`]) or a navmesh.
+pub trait Pathfinder {
+    /// Waypoints from `start` to `goal`, including both ends; empty if `goal` is unreachable.
+    fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3>;
+
+    fn path_exists(&self, start: Vec3, goal: Vec3) -> bool {
+        !self.find_path(start, goal).is_empty()
+    }
+
+    /// Whether an agent can stand at `p`, a point on the ground.
+    fn is_walkable(&self, p: Vec3) -> bool;
+}
+
+/// The tactical grid as a [`Pathfinder`]: 4-connected A* within `bounds`, weighted by
+/// area costs, or through a [`GridHierarchy`] when one is given. Waypoints are cell centres.
+#[derive(Clone, Debug)]
+pub struct GridPathfinder<'a> {
+    pub obstacles: &'a HashSet<(i32, i32)>,
+    pub areas: &'a HashMap<(i32, i32), AreaType>,
+    pub bounds: (i32, i32, i32, i32),
+    pub costs: AreaCosts,
+    pub mapping: GridMapping,
+    pub hierarchy: Option<&'a GridHierarchy>,
+}
+
+impl<'a> GridPathfinder<'a> {
+    /// The world's grid, using its obstacles, areas and mapping.
+    pub fn new(w: &'a World, bounds: (i32, i32, i32, i32)) -> Self {
+        Self {
+            obstacles: &w.obstacles,
+            areas: &w.areas,
+            bounds,
+            costs: AreaCosts::default(),
+            mapping: w.grid,
+            hierarchy: None,
+        }
+    }
+
+    pub fn with_costs(mut self, costs: AreaCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Path through clusters instead of searching the whole grid. The hierarchy must be
+    /// built over the same bounds and costs and kept up to date with the obstacles.
+    pub fn with_hierarchy(mut self, hierarchy: &'a GridHierarchy) -> Self {
+        self.hierarchy = Some(hierarchy);
+        self
+    }
+}
+
+impl Pathfinder for GridPathfinder<'_> {
+    fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3> {
+        let (from, to) = (self.mapping.to_cell(start), self.mapping.to_cell(goal));
+        match self.hierarchy {
+            Some(h) => h.find_path(self.obstacles, self.areas, from, to),
+            None => astar_path_weighted(
+                self.obstacles,
+                self.areas,
+                from,
+                to,
+                self.bounds,
+                &self.costs,
+            ),
+        }
+        .into_iter()
+        .map(|c| self.mapping.to_world(c))
+        .collect()
+    }
+
+    fn is_walkable(&self, p: Vec3) -> bool {
+        let c = self.mapping.to_cell(p);
+        let (minx, miny, maxx, maxy) = self.bounds;
+        let area = self.areas.get(&(c.x, c.y)).copied().unwrap_or_default();
+        c.x >= minx
+            && c.y >= miny
+            && c.x <= maxx
+            && c.y <= maxy
+            && !self.obstacles.contains(&(c.x, c.y))
+            && self.costs.cost(area).is_some()
+    }
+}
@@ -1,9 +1,15 @@
 use crate::schema::Poi;
-use crate::{CompanionState, EnemyState, Entity, IVec2, PlayerState, World, WorldSnapshot};
+use crate::{
+    CompanionState, EnemyState, Entity, IVec2, PlayerState, TacticalMap, TacticsConfig, World,
+    WorldSnapshot,
+};
 use std::collections::BTreeMap;
 
 pub struct PerceptionConfig {
     pub los_max: i32,
+    /// Rate enemy cover and add tactical POIs (see [`TacticalMap::annotate`]); without
+    /// it enemies in range get a rough "low".
+    pub tactics: Option<TacticsConfig>,
 }
 
 pub fn build_snapshot(
@@ -55,7 +61,7 @@ pub fn build_snapshot(
         })
         .collect::<Vec<_>>();
 
-    WorldSnapshot {
+    let mut snap = WorldSnapshot {
         t: w.t,
         player,
         me,
@@ -65,5 +71,14 @@ pub fn build_snapshot(
             pos: IVec2 { x: 15, y: 8 },
         }],
         objective,
+        relationships: w.relationships(t_companion).to_vec(),
+    };
+    if let Some(tc) = &cfg.tactics {
+        let mut map = TacticalMap::build(w, tc.bounds);
+        if let Some(team) = w.team(t_companion) {
+            map.update_influence(w, team.id, tc.influence_radius);
+        }
+        map.annotate(w, t_companion, &mut snap, tc.max_steps);
     }
+    snap
 }
@@ -17,6 +17,9 @@ pub struct WorldSnapshot {
     pub enemies: Vec<EnemyState>,
     pub pois: Vec<Poi>,
     pub objective: Option<String>,
+    /// The companion's standing toward the player, NPCs and factions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relationships: Vec<RelationshipView>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -50,6 +53,17 @@ pub struct Poi {
     pub pos: IVec2,
 }
 
+/// A relationship as seen by planners, e.g. `"player"` or `"faction:raiders"`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RelationshipView {
+    pub target: String,
+    pub affinity: f32,
+    pub trust: f32,
+    pub fear: f32,
+    /// Mood word such as "friendly", "wary" or "afraid".
+    pub label: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlanIntent {
     pub plan_id: String,
@@ -95,6 +109,8 @@ pub enum EngineError {
     LosBlocked,
     #[error("path not found")]
     NoPath,
+    #[error("throw target out of reach")]
+    OutOfReach,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -0,0 +1,467 @@
+//! Tactical position analysis over the grid: per-cell cover, ally/enemy influence maps and
+//! queries such as the best flanking spot on a target or the safest place to fall back to.
+
+use crate::{los_clear, Entity, IVec2, Poi, World, WorldSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+type Cell = (i32, i32);
+
+/// +x, -x, +y, -y; the order of [`CellCover::sides`].
+const SIDES: [Cell; 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Cover a cell offers against fire from each side, from 0.0 (open) to 1.0 (full).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CellCover {
+    /// Against fire from +x, -x, +y and -y.
+    pub sides: [f32; 4],
+}
+
+impl CellCover {
+    /// A wall right next to the cell is full cover on that side; one only diagonally
+    /// ahead is half cover.
+    fn compute(w: &World, (x, y): Cell) -> Self {
+        let mut sides = [0.0; 4];
+        for (i, (dx, dy)) in SIDES.into_iter().enumerate() {
+            sides[i] = if w.obstacles.contains(&(x + dx, y + dy)) {
+                1.0
+            } else if w.obstacles.contains(&(x + dx + dy, y + dy + dx))
+                || w.obstacles.contains(&(x + dx - dy, y + dy - dx))
+            {
+                0.5
+            } else {
+                0.0
+            };
+        }
+        Self { sides }
+    }
+
+    /// How well a cell at `at` is covered from a shooter at `from`.
+    pub fn against(&self, at: IVec2, from: IVec2) -> f32 {
+        let (dx, dy) = ((from.x - at.x) as f32, (from.y - at.y) as f32);
+        let total = dx.abs() + dy.abs();
+        if total == 0.0 {
+            return 0.0;
+        }
+        let x = if dx > 0.0 {
+            self.sides[0]
+        } else {
+            self.sides[1]
+        };
+        let y = if dy > 0.0 {
+            self.sides[2]
+        } else {
+            self.sides[3]
+        };
+        (x * dx.abs() + y * dy.abs()) / total
+    }
+
+    pub fn best(&self) -> f32 {
+        self.sides.into_iter().fold(0.0, f32::max)
+    }
+}
+
+/// Word for a cover value, as used in [`crate::EnemyState::cover`].
+pub fn cover_label(cover: f32) -> &'static str {
+    if cover >= 0.75 {
+        "high"
+    } else if cover >= 0.25 {
+        "low"
+    } else {
+        "none"
+    }
+}
+
+/// Tactical analysis done while building a snapshot (see [`crate::PerceptionConfig`]).
+#[derive(Clone, Copy, Debug)]
+pub struct TacticsConfig {
+    /// Grid area to rate, as `(min x, min y, max x, max y)`.
+    pub bounds: (i32, i32, i32, i32),
+    /// Walking steps over which unit influence fades.
+    pub influence_radius: i32,
+    /// How far from the companion to look for cover, retreat and flank spots.
+    pub max_steps: i32,
+}
+
+/// A scored candidate from a [`TacticalMap`] query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TacticalPoint {
+    pub pos: IVec2,
+    pub score: f32,
+    /// Grid steps from the querying unit.
+    pub steps: i32,
+}
+
+/// Precomputed cover and influence for one team's point of view.
+#[derive(Clone, Debug, Default)]
+pub struct TacticalMap {
+    pub bounds: (i32, i32, i32, i32),
+    cover: HashMap<Cell, CellCover>,
+    threat: HashMap<Cell, f32>,
+    support: HashMap<Cell, f32>,
+}
+
+impl TacticalMap {
+    /// Cover for every open cell in `bounds`; influence starts empty (see
+    /// [`TacticalMap::update_influence`]).
+    pub fn build(w: &World, bounds: (i32, i32, i32, i32)) -> Self {
+        let mut map = Self {
+            bounds,
+            ..Self::default()
+        };
+        let (minx, miny, maxx, maxy) = bounds;
+        for x in minx..=maxx {
+            for y in miny..=maxy {
+                map.refresh_cover(w, (x, y));
+            }
+        }
+        map
+    }
+
+    /// Redo cover around cells whose obstacles changed.
+    pub fn update_cover(&mut self, w: &World, changed: &[Cell]) {
+        for &(x, y) in changed {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    self.refresh_cover(w, (x + dx, y + dy));
+                }
+            }
+        }
+    }
+
+    fn refresh_cover(&mut self, w: &World, c: Cell) {
+        let cover = CellCover::compute(w, c);
+        if !self.in_bounds(c) || w.obstacles.contains(&c) || cover.best() == 0.0 {
+            self.cover.remove(&c);
+        } else {
+            self.cover.insert(c, cover);
+        }
+    }
+
+    /// Rebuild the influence maps as seen by `team`: its members spread support, everyone
+    /// else threat. Influence fades linearly over `radius` walking steps and scales with
+    /// health (100 hp counts as 1.0).
+    pub fn update_influence(&mut self, w: &World, team: u8, radius: i32) {
+        self.threat.clear();
+        self.support.clear();
+        for (e, t) in w
+            .all_of_team(team)
+            .into_iter()
+            .map(|e| (e, true))
+            .chain(w.enemies_of(team).into_iter().map(|e| (e, false)))
+        {
+            let (Some(pos), Some(hp)) = (w.pos_of(e), w.health(e)) else {
+                continue;
+            };
+            if hp.hp <= 0 {
+                continue;
+            }
+            let strength = hp.hp as f32 / 100.0;
+            let map = if t {
+                &mut self.support
+            } else {
+                &mut self.threat
+            };
+            for (c, steps) in reachable(w, self.bounds, pos, radius) {
+                let falloff = 1.0 - steps as f32 / (radius + 1) as f32;
+                *map.entry(c).or_default() += strength * falloff;
+            }
+        }
+    }
+
+    pub fn cover_at(&self, p: IVec2) -> CellCover {
+        self.cover.get(&(p.x, p.y)).copied().unwrap_or_default()
+    }
+
+    pub fn threat_at(&self, p: IVec2) -> f32 {
+        self.threat.get(&(p.x, p.y)).copied().unwrap_or(0.0)
+    }
+
+    pub fn support_at(&self, p: IVec2) -> f32 {
+        self.support.get(&(p.x, p.y)).copied().unwrap_or(0.0)
+    }
+
+    /// How open `p` is to the worst of `shooters`: 0.0 if none can see it, 1.0 if one has
+    /// a clear shot.
+    pub fn exposure(&self, w: &World, p: IVec2, shooters: &[IVec2]) -> f32 {
+        let cover = self.cover_at(p);
+        shooters
+            .iter()
+            .filter(|s| los_clear(&w.obstacles, **s, p))
+            .map(|s| 1.0 - cover.against(p, *s))
+            .fold(0.0, f32::max)
+    }
+
+    /// Most protected cell within `max_steps` of `from`, preferring nearby ones.
+    pub fn best_cover(
+        &self,
+        w: &World,
+        from: IVec2,
+        max_steps: i32,
+        shooters: &[IVec2],
+    ) -> Option<TacticalPoint> {
+        self.best(w, from, max_steps, |p, steps| {
+            let exposure = self.exposure(w, p, shooters);
+            Some(
+                (1.0 - exposure)
+                    - 0.3 * self.threat_at(p)
+                    - 0.2 * steps as f32 / max_steps.max(1) as f32,
+            )
+        })
+    }
+
+    /// Cell within `max_steps` of `from` with a shot at `target` from its side or back.
+    /// The target is assumed to face its enemies; cover from it and low threat count too.
+    pub fn best_flank(
+        &self,
+        w: &World,
+        from: IVec2,
+        target: Entity,
+        max_steps: i32,
+    ) -> Option<TacticalPoint> {
+        let t = w.pos_of(target)?;
+        let foes: Vec<IVec2> = w
+            .team(target)
+            .map(|team| w.enemies_of(team.id))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|e| w.pos_of(e))
+            .collect();
+        let front = if foes.is_empty() {
+            (from.x - t.x, from.y - t.y)
+        } else {
+            let n = foes.len() as f32;
+            let (sx, sy) = foes.iter().fold((0.0, 0.0), |(x, y), p| {
+                (x + (p.x - t.x) as f32, y + (p.y - t.y) as f32)
+            });
+            ((sx / n).round() as i32, (sy / n).round() as i32)
+        };
+        let front_len = ((front.0 * front.0 + front.1 * front.1) as f32).sqrt();
+        self.best(w, from, max_steps, |p, steps| {
+            let (ox, oy) = (p.x - t.x, p.y - t.y);
+            if ox.abs() + oy.abs() < 2 || !los_clear(&w.obstacles, p, t) {
+                return None;
+            }
+            let off_len = ((ox * ox + oy * oy) as f32).sqrt();
+            let cos = if front_len > 0.0 {
+                (ox * front.0 + oy * front.1) as f32 / (off_len * front_len)
+            } else {
+                1.0
+            };
+            let flank = (1.0 - cos) * 0.5;
+            Some(
+                flank + 0.5 * self.cover_at(p).against(p, t)
+                    - 0.5 * self.threat_at(p)
+                    - 0.25 * steps as f32 / max_steps.max(1) as f32,
+            )
+        })
+    }
+
+    /// Cell within `max_steps` of `from` that is far from `shooters`, out of their sight,
+    /// low in threat and close to allies.
+    pub fn safest_retreat(
+        &self,
+        w: &World,
+        from: IVec2,
+        max_steps: i32,
+        shooters: &[IVec2],
+    ) -> Option<TacticalPoint> {
+        let reach = (max_steps.max(1) * 2) as f32;
+        self.best(w, from, max_steps, |p, _| {
+            let away = shooters
+                .iter()
+                .map(|s| (s.x - p.x).abs() + (s.y - p.y).abs())
+                .min()
+                .map_or(1.0, |d| (d as f32 / reach).min(1.0));
+            Some(
+                0.6 * away + 0.4 * (1.0 - self.exposure(w, p, shooters)) + 0.3 * self.support_at(p)
+                    - 0.5 * self.threat_at(p),
+            )
+        })
+    }
+
+    /// Rate each perceived enemy's cover against `me` and add "cover", "retreat" and
+    /// "flank:<enemy id>" POIs for orchestrators.
+    pub fn annotate(&self, w: &World, me: Entity, snap: &mut WorldSnapshot, max_steps: i32) {
+        let Some(pos) = w.pos_of(me) else {
+            return;
+        };
+        for e in &mut snap.enemies {
+            if e.cover != "unknown" {
+                e.cover = cover_label(self.cover_at(e.pos).against(e.pos, pos)).into();
+            }
+        }
+        let shooters: Vec<IVec2> = snap.enemies.iter().map(|e| e.pos).collect();
+        let mut pois = vec![];
+        if let Some(p) = self.best_cover(w, pos, max_steps, &shooters) {
+            pois.push(("cover".to_string(), p.pos));
+        }
+        if let Some(p) = self.safest_retreat(w, pos, max_steps, &shooters) {
+            pois.push(("retreat".to_string(), p.pos));
+        }
+        for e in &snap.enemies {
+            if let Some(p) = self.best_flank(w, pos, e.id, max_steps) {
+                pois.push((format!("flank:{}", e.id), p.pos));
+            }
+        }
+        snap.pois
+            .extend(pois.into_iter().map(|(k, pos)| Poi { k, pos }));
+    }
+
+    /// Highest-scoring reachable cell; `score` returns `None` to skip a cell.
+    fn best(
+        &self,
+        w: &World,
+        from: IVec2,
+        max_steps: i32,
+        score: impl Fn(IVec2, i32) -> Option<f32>,
+    ) -> Option<TacticalPoint> {
+        let mut cells: Vec<(Cell, i32)> = reachable(w, self.bounds, from, max_steps)
+            .into_iter()
+            .collect();
+        // Same answer regardless of hash order.
+        cells.sort_unstable();
+        cells
+            .into_iter()
+            .filter_map(|((x, y), steps)| {
+                let pos = IVec2 { x, y };
+                Some(TacticalPoint {
+                    pos,
+                    score: score(pos, steps)?,
+                    steps,
+                })
+            })
+            .fold(None, |best: Option<TacticalPoint>, p| match best {
+                Some(b) if b.score >= p.score => Some(b),
+                _ => Some(p),
+            })
+    }
+
+    fn in_bounds(&self, (x, y): Cell) -> bool {
+        let (minx, miny, maxx, maxy) = self.bounds;
+        x >= minx && y >= miny && x <= maxx && y <= maxy
+    }
+}
+
+/// Open cells within `max_steps` 4-neighbour steps of `from`, with their distance.
+fn reachable(
+    w: &World,
+    bounds: (i32, i32, i32, i32),
+    from: IVec2,
+    max_steps: i32,
+) -> HashMap<Cell, i32> {
+    let (minx, miny, maxx, maxy) = bounds;
+    let mut seen = HashMap::from([((from.x, from.y), 0)]);
+    let mut queue = VecDeque::from([(from.x, from.y)]);
+    while let Some(c) = queue.pop_front() {
+        let d = seen[&c];
+        if d >= max_steps {
+            continue;
+        }
+        for (dx, dy) in SIDES {
+            let n = (c.0 + dx, c.1 + dy);
+            if n.0 < minx || n.1 < miny || n.0 > maxx || n.1 > maxy {
+                continue;
+            }
+            if w.obstacles.contains(&n) || seen.contains_key(&n) {
+                continue;
+            }
+            seen.insert(n, d + 1);
+            queue.push_back(n);
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_snapshot, PerceptionConfig, Team};
+
+    const BOUNDS: (i32, i32, i32, i32) = (0, 0, 12, 10);
+
+    /// Player and companion in the open south-west, an enemy east of a wall at x = 6.
+    fn arena() -> (World, Entity, Entity, Entity) {
+        let mut w = World::new();
+        for y in 4..=8 {
+            w.obstacles.insert((6, y));
+        }
+        let player = w.spawn("Player", IVec2 { x: 1, y: 2 }, Team { id: 0 }, 100, 0);
+        let comp = w.spawn("Comp", IVec2 { x: 3, y: 2 }, Team { id: 1 }, 80, 30);
+        let enemy = w.spawn("Enemy", IVec2 { x: 10, y: 2 }, Team { id: 2 }, 60, 0);
+        (w, player, comp, enemy)
+    }
+
+    #[test]
+    fn cover_is_rated_per_side() {
+        let (w, ..) = arena();
+        let map = TacticalMap::build(&w, BOUNDS);
+        let behind = IVec2 { x: 5, y: 6 };
+        let cover = map.cover_at(behind);
+        assert_eq!(cover.against(behind, IVec2 { x: 10, y: 6 }), 1.0);
+        assert_eq!(cover.against(behind, IVec2 { x: 0, y: 6 }), 0.0);
+        // Fire along the wall only meets it diagonally.
+        assert_eq!(cover.against(behind, IVec2 { x: 5, y: 0 }), 0.5);
+        assert_eq!(cover.against(behind, IVec2 { x: 10, y: 1 }), 0.75);
+        assert_eq!(map.cover_at(IVec2 { x: 3, y: 2 }), CellCover::default());
+        assert_eq!(
+            [cover_label(1.0), cover_label(0.5), cover_label(0.0)],
+            ["high", "low", "none"]
+        );
+    }
+
+    #[test]
+    fn best_cover_gets_out_of_sight() {
+        let (w, _, comp, enemy) = arena();
+        let map = TacticalMap::build(&w, BOUNDS);
+        let shooter = w.pos_of(enemy).unwrap();
+        let from = w.pos_of(comp).unwrap();
+        assert_eq!(map.exposure(&w, from, &[shooter]), 1.0);
+        let spot = map.best_cover(&w, from, 5, &[shooter]).unwrap();
+        assert_eq!(map.exposure(&w, spot.pos, &[shooter]), 0.0, "{spot:?}");
+        assert!(spot.steps <= 5);
+        // Nothing to hide behind within one step.
+        let near = map.best_cover(&w, from, 1, &[shooter]).unwrap();
+        assert!(near.score < spot.score);
+    }
+
+    #[test]
+    fn flank_comes_from_the_side_the_target_is_not_facing() {
+        let (w, _, comp, enemy) = arena();
+        let mut map = TacticalMap::build(&w, BOUNDS);
+        map.update_influence(&w, 1, 4);
+        let from = w.pos_of(comp).unwrap();
+        let t = w.pos_of(enemy).unwrap();
+        // The enemy faces west, toward the player and companion.
+        let spot = map.best_flank(&w, from, enemy, 20).unwrap();
+        assert!(spot.pos.x > t.x, "{spot:?}");
+        assert!(los_clear(&w.obstacles, spot.pos, t));
+        let short = map.best_flank(&w, from, enemy, 4).unwrap();
+        assert!(short.pos.x < t.x && short.score < spot.score, "{short:?}");
+    }
+
+    #[test]
+    fn snapshots_carry_cover_and_tactical_pois() {
+        let (mut w, player, comp, enemy) = arena();
+        // Put the enemy behind the wall's south end.
+        w.pose_mut(enemy).unwrap().pos = IVec2 { x: 7, y: 5 };
+        let cfg = |tactics| PerceptionConfig {
+            los_max: 20,
+            tactics,
+        };
+        let plain = build_snapshot(&w, player, comp, &[enemy], None, &cfg(None));
+        assert!(plain.pois.iter().all(|p| p.k != "cover"));
+
+        let tc = TacticsConfig {
+            bounds: BOUNDS,
+            influence_radius: 4,
+            max_steps: 8,
+        };
+        let snap = build_snapshot(&w, player, comp, &[enemy], None, &cfg(Some(tc)));
+        assert_eq!(snap.enemies[0].cover, "high");
+        let keys: Vec<&str> = snap.pois.iter().map(|p| p.k.as_str()).collect();
+        for k in ["cover", "retreat", &format!("flank:{enemy}")] {
+            assert!(keys.contains(&k), "{k} missing from {keys:?}");
+        }
+    }
+}
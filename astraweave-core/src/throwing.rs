@@ -0,0 +1,14 @@
+//! Letting plan validation throw things in the 3D world instead of checking grid sight lines.
+
+use glam::Vec3;
+
+/// Something that can throw plan items for real, e.g. the physics world. Positions are on
+/// the ground: `from` where the thrower stands, `to` where the item should land.
+pub trait Thrower {
+    /// Launch velocity that lands `item` at `to`, or `None` if it can't get there: out of
+    /// range, or behind a wall too high to lob it over.
+    fn solve_throw(&self, item: &str, from: Vec3, to: Vec3) -> Option<Vec3>;
+
+    /// Throw `item` at a velocity from [`Thrower::solve_throw`].
+    fn throw(&mut self, item: &str, from: Vec3, velocity: Vec3);
+}
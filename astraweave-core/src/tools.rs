@@ -133,21 +133,42 @@ pub fn astar_path(
     start: crate::IVec2,
     goal: crate::IVec2,
     bounds: (i32, i32, i32, i32),
+) -> Vec<crate::IVec2> {
+    astar_path_weighted(
+        obstacles,
+        &HashMap::new(),
+        start,
+        goal,
+        bounds,
+        &crate::AreaCosts::default(),
+    )
+}
+
+/// Grid A* where entering a cell costs its area's multiplier in `costs`; cells in
+/// excluded areas are treated as obstacles. Cells missing from `areas` are ground.
+pub fn astar_path_weighted(
+    obstacles: &HashSet<(i32, i32)>,
+    areas: &HashMap<(i32, i32), crate::AreaType>,
+    start: crate::IVec2,
+    goal: crate::IVec2,
+    bounds: (i32, i32, i32, i32),
+    costs: &crate::AreaCosts,
 ) -> Vec<crate::IVec2> {
     // Convert schema::IVec2 to glam::IVec2
     let start_glam = schema_to_glam(start);
     let goal_glam = schema_to_glam(goal);
 
-    use std::collections::{BinaryHeap, HashMap, HashSet};
-    #[derive(Copy, Clone, Eq, PartialEq)]
+    use std::collections::BinaryHeap;
+    #[derive(Copy, Clone, PartialEq)]
     struct Node {
-        f: i32,
+        f: f32,
         x: i32,
         y: i32,
     }
+    impl Eq for Node {}
     impl Ord for Node {
         fn cmp(&self, other: &Self) -> Ordering {
-            other.f.cmp(&self.f)
+            other.f.total_cmp(&self.f)
         }
     }
     impl PartialOrd for Node {
@@ -157,21 +178,20 @@ pub fn astar_path(
     }
 
     let (minx, miny, maxx, maxy) = bounds;
-    let h = |a: GlamIVec2, b: GlamIVec2| (a.x - b.x).abs() + (a.y - b.y).abs();
+    let h_scale = costs.min_multiplier();
+    let h = |a: GlamIVec2, b: GlamIVec2| ((a.x - b.x).abs() + (a.y - b.y).abs()) as f32 * h_scale;
     let mut open = BinaryHeap::new();
     let mut came: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
-    let mut g: HashMap<(i32, i32), i32> = HashMap::new();
-    let mut seen: HashSet<(i32, i32)> = HashSet::new();
+    let mut g: HashMap<(i32, i32), f32> = HashMap::new();
 
     let s = (start_glam.x, start_glam.y);
     let t = (goal_glam.x, goal_glam.y);
-    g.insert(s, 0);
+    g.insert(s, 0.0);
     open.push(Node {
         f: h(start_glam, goal_glam),
         x: start_glam.x,
         y: start_glam.y,
     });
-    seen.insert(s);
 
     while let Some(Node { f: _, x, y }) = open.pop() {
         if (x, y) == t {
@@ -188,7 +208,7 @@ pub fn astar_path(
             path.reverse();
             return path;
         }
-        let cur_g = *g.get(&(x, y)).unwrap_or(&i32::MAX);
+        let cur_g = *g.get(&(x, y)).unwrap_or(&f32::INFINITY);
         for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
             let nx = x + dx;
             let ny = y + dy;
@@ -198,9 +218,13 @@ pub fn astar_path(
             if obstacles.contains(&(nx, ny)) {
                 continue;
             }
-            let ng = cur_g + 1;
+            let area = areas.get(&(nx, ny)).copied().unwrap_or_default();
+            let Some(step) = costs.cost(area) else {
+                continue;
+            };
+            let ng = cur_g + step;
             let pos = (nx, ny);
-            if ng < *g.get(&pos).unwrap_or(&i32::MAX) {
+            if ng < *g.get(&pos).unwrap_or(&f32::INFINITY) {
                 came.insert(pos, (x, y));
                 g.insert(pos, ng);
                 let prio = ng + h(GlamIVec2::new(nx, ny), goal_glam);
@@ -251,3 +275,56 @@ pub fn find_cover_positions(
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaCosts, AreaType, IVec2};
+
+    const BOUNDS: (i32, i32, i32, i32) = (0, 0, 10, 4);
+
+    /// A hazard strip across the middle of the corridor, open at both ends.
+    fn hazard_strip() -> HashMap<(i32, i32), AreaType> {
+        (1..=3).map(|y| ((5, y), AreaType::Hazard)).collect()
+    }
+
+    fn route(costs: &AreaCosts, areas: &HashMap<(i32, i32), AreaType>) -> Vec<IVec2> {
+        let (start, goal) = (IVec2 { x: 0, y: 2 }, IVec2 { x: 10, y: 2 });
+        astar_path_weighted(&HashSet::new(), areas, start, goal, BOUNDS, costs)
+    }
+
+    #[test]
+    fn weighted_path_routes_around_costly_areas() {
+        let areas = hazard_strip();
+        let through = |p: &[IVec2]| p.iter().any(|c| areas.contains_key(&(c.x, c.y)));
+
+        let direct = route(&AreaCosts::default(), &areas);
+        assert_eq!(direct.len(), 11);
+        assert!(through(&direct));
+
+        let careful = route(
+            &AreaCosts::default().with_cost(AreaType::Hazard, 10.0),
+            &areas,
+        );
+        assert!(!careful.is_empty() && !through(&careful));
+        // Around the end of the strip: two cells out and two back.
+        assert_eq!(careful.len(), 15);
+
+        // A mild cost is cheaper to cross than to walk around.
+        let tolerant = route(
+            &AreaCosts::default().with_cost(AreaType::Hazard, 2.0),
+            &areas,
+        );
+        assert_eq!(tolerant.len(), 11);
+    }
+
+    #[test]
+    fn excluded_areas_block_like_walls() {
+        let mut areas = hazard_strip();
+        areas.insert((5, 0), AreaType::Hazard);
+        areas.insert((5, 4), AreaType::Hazard);
+        let costs = AreaCosts::default().excluding(AreaType::Hazard);
+        assert!(route(&costs, &areas).is_empty());
+        assert_eq!(route(&AreaCosts::default(), &areas).len(), 11);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     tools::{los_clear, path_exists},
-    ActionStep, EngineError, Entity, IVec2, PlanIntent, World,
+    ActionStep, EngineError, Entity, IVec2, Pathfinder, PlanIntent, Thrower, World,
 };
 
 pub struct ValidateCfg {
@@ -13,6 +13,22 @@ pub fn validate_and_execute(
     intent: &PlanIntent,
     cfg: &ValidateCfg,
     log: &mut impl FnMut(String),
+) -> Result<(), EngineError> {
+    validate_and_execute_with(w, actor, intent, cfg, None, None, log)
+}
+
+/// Like [`validate_and_execute`], but `MoveTo` is checked against `paths` (e.g. the level's
+/// navmesh) instead of the world's obstacle grid, and `Throw` needs an arc from `throws`
+/// that reaches the target, which it then throws along. Grid cells are mapped to world
+/// space with [`World::grid`].
+pub fn validate_and_execute_with(
+    w: &mut World,
+    actor: Entity,
+    intent: &PlanIntent,
+    cfg: &ValidateCfg,
+    paths: Option<&dyn Pathfinder>,
+    mut throws: Option<&mut dyn Thrower>,
+    log: &mut impl FnMut(String),
 ) -> Result<(), EngineError> {
     log(format!(
         "Plan {} with {} steps",
@@ -24,7 +40,11 @@ pub fn validate_and_execute(
             ActionStep::MoveTo { x, y } => {
                 let from = w.pos_of(actor).unwrap();
                 let to = IVec2 { x: *x, y: *y };
-                if !path_exists(&w.obstacles, from, to, cfg.world_bounds) {
+                let reachable = match paths {
+                    Some(p) => p.path_exists(w.grid.to_world(from), w.grid.to_world(to)),
+                    None => path_exists(&w.obstacles, from, to, cfg.world_bounds),
+                };
+                if !reachable {
                     return Err(EngineError::NoPath);
                 }
                 w.pose_mut(actor).unwrap().pos = to;
@@ -33,15 +53,26 @@ pub fn validate_and_execute(
             ActionStep::Throw { item, x, y } => {
                 let from = w.pos_of(actor).unwrap();
                 let target = IVec2 { x: *x, y: *y };
-                if !los_clear(&w.obstacles, from, target) {
-                    return Err(EngineError::LosBlocked);
-                }
+                let (from_w, target_w) = (w.grid.to_world(from), w.grid.to_world(target));
+                let velocity = match throws.as_deref() {
+                    Some(t) => Some(
+                        t.solve_throw(item, from_w, target_w)
+                            .ok_or(EngineError::OutOfReach)?,
+                    ),
+                    None if !los_clear(&w.obstacles, from, target) => {
+                        return Err(EngineError::LosBlocked)
+                    }
+                    None => None,
+                };
                 let cds = w.cooldowns_mut(actor).unwrap();
                 let cd_key = format!("throw:{}", item);
                 if cds.map.get(&cd_key).copied().unwrap_or(0.0) > 0.0 {
                     return Err(EngineError::Cooldown(cd_key));
                 }
                 cds.map.insert(cd_key.clone(), 8.0);
+                if let (Some(t), Some(v)) = (throws.as_deref_mut(), velocity) {
+                    t.throw(item, from_w, v);
+                }
                 log(format!("  [{}] THROW {} -> ({},{})", i, item, x, y));
             }
             ActionStep::CoverFire {
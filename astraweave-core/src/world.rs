@@ -1,4 +1,4 @@
-use crate::{Entity, IVec2};
+use crate::{AreaType, Entity, GridMapping, IVec2, RelationshipView};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Copy, Debug)]
@@ -31,12 +31,18 @@ pub struct World {
     pub t: f32,
     pub next_id: Entity,
     pub obstacles: HashSet<(i32, i32)>,
+    /// Area type of grid cells; cells not listed are [`AreaType::Ground`].
+    pub areas: HashMap<(i32, i32), AreaType>,
+    /// Where the grid cells lie in world space.
+    pub grid: GridMapping,
     poses: HashMap<Entity, Pose>,
     health: HashMap<Entity, Health>,
     team: HashMap<Entity, Team>,
     ammo: HashMap<Entity, Ammo>,
     cds: HashMap<Entity, Cooldowns>,
     names: HashMap<Entity, String>,
+    /// How each companion currently feels about others, as pushed by its profile.
+    relationships: HashMap<Entity, Vec<RelationshipView>>,
 }
 
 impl World {
@@ -105,6 +111,12 @@ impl World {
     pub fn name(&self, e: Entity) -> Option<&str> {
         self.names.get(&e).map(|s| s.as_str())
     }
+    pub fn relationships(&self, e: Entity) -> &[RelationshipView] {
+        self.relationships.get(&e).map_or(&[], |r| r.as_slice())
+    }
+    pub fn set_relationships(&mut self, e: Entity, views: Vec<RelationshipView>) {
+        self.relationships.insert(e, views);
+    }
 
     pub fn all_of_team(&self, team_id: u8) -> Vec<Entity> {
         self.team
@@ -1,7 +1,6 @@
-use glam::Vec3;
-// use rapier3d::prelude::*; // Commented out due to API compatibility issues
 use crate::{DamageType, Stats};
-use astraweave_physics::PhysicsWorld;
+use astraweave_physics::{Layers, PhysicsWorld};
+use glam::Vec3;
 
 #[derive(Clone, Copy, Debug)]
 pub struct IFrame {
@@ -28,56 +27,51 @@ pub struct HitResult {
 }
 
 /// Sweep a capsule from `from` to `to`, apply damage to first hit collider body that isn't `self_id`.
-/// TODO: Fix rapier3d API compatibility issues
+/// Whatever the attacker's layer queries by default, such as walls, blocks the swing.
 #[allow(clippy::too_many_arguments)]
 pub fn perform_attack_sweep(
-    _phys: &mut PhysicsWorld,
-    _self_id: u64,
-    _from: Vec3,
-    _to: Vec3,
-    _radius: f32,
-    _base_damage: i32,
-    _dtype: DamageType,
-    _targets: &mut [Combatant],
+    phys: &PhysicsWorld,
+    self_id: u64,
+    from: Vec3,
+    to: Vec3,
+    radius: f32,
+    base_damage: i32,
+    dtype: DamageType,
+    targets: &mut [Combatant],
 ) -> Option<HitResult> {
-    // Temporarily disabled due to rapier3d API changes
-    // TODO: Update to use new rapier3d cast_shape API
-    unimplemented!("perform_attack_sweep is not yet implemented due to rapier3d API changes")
-    /*
-    let shape = SharedShape::capsule_y(radius * 0.5, radius);
-    let dir = to - from;
-    let len = dir.length();
-    if len <= 1e-3 { return None; }
-    let ray = Ray::new(point![from.x, from.y, from.z], vector![(dir/len).x, (dir/len).y, (dir/len).z]);
-    let max_toi = len;
-
-    let filter = QueryFilter::default();
-    if let Some((hcoll, toi)) = phys.query_pipeline.cast_shape(
-        &phys.bodies, &phys.colliders,
-        &Isometry::translation(from.x, from.y, from.z),
-        &shape, vector![dir.x, dir.y, dir.z], max_toi, filter, None
-    ) {
-        let hbody = phys.colliders.get(hcoll).and_then(|c| c.parent()).unwrap();
-        if let Some(tgt_id) = phys.id_of(hbody) {
-            if tgt_id == self_id { return None; }
-
-            // apply to matching target
-            if let Some(tgt) = targets.iter_mut().find(|t| t.body == tgt_id) {
-                // parry check
-                if let Some(p) = &mut tgt.parry {
-                    if p.active && p.window > 0.0 {
-                        p.window = 0.0;
-                        return Some(HitResult { target: tgt_id, damage: 0, parried: true });
-                    }
-                }
-                // iframe check
-                if let Some(i) = &tgt.iframes { if i.time_left > 0.0 { return Some(HitResult { target: tgt_id, damage: 0, parried: false }); } }
+    let mask = phys
+        .body_layer(self_id)
+        .and_then(|l| phys.layer_matrix().query_mask(l.into()).ok())
+        .unwrap_or(Layers::all());
+    let hit = phys.capsule_sweep(from, to, radius * 0.5, radius, mask, &[self_id])?;
+    let tgt = targets.iter_mut().find(|t| t.body == hit.body)?;
 
-                let dmg = tgt.stats.apply_damage(base_damage, dtype);
-                return Some(HitResult { target: tgt_id, damage: dmg, parried: false });
-            }
+    // parry check
+    if let Some(p) = &mut tgt.parry {
+        if p.active && p.window > 0.0 {
+            p.window = 0.0;
+            return Some(HitResult {
+                target: hit.body,
+                damage: 0,
+                parried: true,
+            });
         }
     }
-    None
-    */
+    // iframe check
+    if let Some(i) = &tgt.iframes {
+        if i.time_left > 0.0 {
+            return Some(HitResult {
+                target: hit.body,
+                damage: 0,
+                parried: false,
+            });
+        }
+    }
+
+    let dmg = tgt.stats.apply_damage(base_damage, dtype);
+    Some(HitResult {
+        target: hit.body,
+        damage: dmg,
+        parried: false,
+    })
 }
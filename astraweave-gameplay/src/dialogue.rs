@@ -1,45 +1,6 @@
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum Cond {
-    Eq { key: String, val: String },
-    Ne { key: String, val: String },
-    Has { key: String },
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Line {
-    pub speaker: String,
-    pub text: String,
-    #[serde(default)]
-    pub set_vars: Vec<(String, String)>,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Choice {
-    pub text: String,
-    pub go_to: String,
-    #[serde(default)]
-    pub require: Vec<Cond>,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Node {
-    pub id: String,
-    pub line: Option<Line>,
-    #[serde(default)]
-    pub choices: Vec<Choice>,
-    #[serde(default)]
-    pub end: bool,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Dialogue {
-    pub id: String,
-    pub start: String,
-    pub nodes: Vec<Node>,
-}
+pub use astraweave_core::dialogue::{Choice, Cond, Dialogue, Line, Node};
 
 pub struct DialogueState {
     pub idx: usize,
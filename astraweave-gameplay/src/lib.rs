@@ -8,6 +8,7 @@ pub mod harvesting;
 pub mod items;
 pub mod quests;
 pub mod stats;
+pub mod throwing;
 pub mod types;
 pub mod weaving;
 
@@ -21,6 +22,7 @@ pub use harvesting::*;
 pub use items::*;
 pub use quests::*;
 pub use stats::*;
+pub use throwing::*;
 pub use types::*;
 pub use weaving::*;
 
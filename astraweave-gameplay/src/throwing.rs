@@ -0,0 +1,201 @@
+use astraweave_core::Thrower;
+use astraweave_physics::{BodyId, Layers, PhysicsWorld, ProjectileKind};
+use glam::Vec3;
+
+/// Projectile, radius and mass for a plan's throwable `item`; unknown items fly as plain
+/// objects.
+pub fn throwable(item: &str) -> (ProjectileKind, f32, f32) {
+    match item {
+        "grenade" => (
+            ProjectileKind::Grenade {
+                fuse: 2.5,
+                blast_radius: 4.0,
+            },
+            0.08,
+            0.4,
+        ),
+        "smoke" => (
+            ProjectileKind::Smoke {
+                fuse: 1.0,
+                radius: 3.0,
+                duration: 12.0,
+            },
+            0.08,
+            0.5,
+        ),
+        _ => (ProjectileKind::Ballistic, 0.1, 0.5),
+    }
+}
+
+/// Throws plan items into the physics world on arcs that clear the level geometry.
+pub struct PhysicsThrower<'a> {
+    pub phys: &'a mut PhysicsWorld,
+    /// Launch speed in m/s, which limits range to about speed² / g.
+    pub speed: f32,
+    /// Height above the thrower's feet the item leaves their hand.
+    pub hand_height: f32,
+    pub layer: Layers,
+    /// The thrower's own body, which arcs may start inside.
+    pub thrower: Option<BodyId>,
+    /// Everything thrown so far.
+    pub thrown: Vec<BodyId>,
+}
+
+impl<'a> PhysicsThrower<'a> {
+    pub fn new(phys: &'a mut PhysicsWorld) -> Self {
+        Self {
+            phys,
+            speed: 12.0,
+            hand_height: 1.5,
+            layer: Layers::PROJECTILE,
+            thrower: None,
+            thrown: Vec::new(),
+        }
+    }
+}
+
+impl Thrower for PhysicsThrower<'_> {
+    fn solve_throw(&self, item: &str, from: Vec3, to: Vec3) -> Option<Vec3> {
+        let (_, radius, _) = throwable(item);
+        let mask = self
+            .phys
+            .layer_matrix()
+            .query_mask(self.layer.into())
+            .ok()?;
+        let exclude: Vec<BodyId> = self.thrower.into_iter().collect();
+        self.phys.solve_throw(
+            from + Vec3::Y * self.hand_height,
+            to,
+            self.speed,
+            radius,
+            mask,
+            &exclude,
+        )
+    }
+
+    fn throw(&mut self, item: &str, from: Vec3, velocity: Vec3) {
+        let (kind, radius, mass) = throwable(item);
+        let pos = from + Vec3::Y * self.hand_height;
+        let id = self
+            .phys
+            .spawn_projectile(pos, velocity, radius, mass, kind, self.layer)
+            .expect("layers given as bits always resolve");
+        self.thrown.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astraweave_core::{
+        validate_and_execute_with, ActionStep, EngineError, IVec2, PlanIntent, Team, ValidateCfg,
+        World,
+    };
+    use astraweave_physics::ProjectileEvent;
+    use glam::vec3;
+
+    fn phys() -> PhysicsWorld {
+        let mut p = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        p.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        p
+    }
+
+    /// A wall across +x at `x`, `height` tall.
+    fn wall(p: &mut PhysicsWorld, x: f32, height: f32) {
+        let corners = [
+            vec3(x, 0.0, -10.0),
+            vec3(x, height, -10.0),
+            vec3(x, 0.0, 10.0),
+            vec3(x, height, 10.0),
+        ];
+        p.add_static_trimesh(&corners, &[[0, 1, 2], [2, 1, 3]], Layers::DEFAULT)
+            .unwrap();
+    }
+
+    #[test]
+    fn arcs_land_on_the_target_and_clear_low_walls() {
+        let mut p = phys();
+        let to = vec3(8.0, 0.0, 0.0);
+        let t = PhysicsThrower::new(&mut p);
+        let v = t.solve_throw("grenade", Vec3::ZERO, to).unwrap();
+        // Follow the arc from the hand to where it crosses the target.
+        let time = to.x / v.x;
+        let y = t.hand_height + v.y * time - 0.5 * 9.81 * time * time;
+        assert!(y.abs() < 0.2, "comes down at height {y}");
+        assert!(v.length() <= t.speed + 1e-3);
+
+        // Out of range at this speed.
+        assert!(t
+            .solve_throw("grenade", Vec3::ZERO, vec3(40.0, 0.0, 0.0))
+            .is_none());
+
+        // A low wall is lobbed over; a tall one blocks every arc.
+        wall(&mut p, 4.0, 1.0);
+        p.step();
+        let t = PhysicsThrower::new(&mut p);
+        assert!(t.solve_throw("grenade", Vec3::ZERO, to).is_some());
+        wall(&mut p, 5.0, 30.0);
+        p.step();
+        let t = PhysicsThrower::new(&mut p);
+        assert!(t.solve_throw("grenade", Vec3::ZERO, to).is_none());
+    }
+
+    #[test]
+    fn plans_throw_into_the_physics_world() {
+        let mut p = phys();
+        let mut w = World::new();
+        let me = w.spawn("Comp", IVec2 { x: 0, y: 0 }, Team { id: 1 }, 80, 30);
+        let cfg = ValidateCfg {
+            world_bounds: (-50, -50, 50, 50),
+        };
+        let throw = |x| PlanIntent {
+            plan_id: "t".into(),
+            steps: vec![ActionStep::Throw {
+                item: "grenade".into(),
+                x,
+                y: 0,
+            }],
+        };
+
+        let mut t = PhysicsThrower::new(&mut p);
+        let far = validate_and_execute_with(
+            &mut w,
+            me,
+            &throw(40),
+            &cfg,
+            None,
+            Some(&mut t),
+            &mut |_| {},
+        );
+        assert!(matches!(far, Err(EngineError::OutOfReach)));
+        assert!(t.thrown.is_empty());
+
+        validate_and_execute_with(&mut w, me, &throw(8), &cfg, None, Some(&mut t), &mut |_| {})
+            .unwrap();
+        let again =
+            validate_and_execute_with(&mut w, me, &throw(8), &cfg, None, Some(&mut t), &mut |_| {});
+        assert!(matches!(again, Err(EngineError::Cooldown(_))));
+        let thrown = t.thrown.clone();
+        assert_eq!(thrown.len(), 1);
+        assert!(p.projectile(thrown[0]).is_some());
+
+        for _ in 0..240 {
+            p.step();
+        }
+        let events = p.drain_projectile_events();
+        let landed = events
+            .iter()
+            .find_map(|e| match *e {
+                ProjectileEvent::Impact { position, .. } => Some(position),
+                _ => None,
+            })
+            .expect("the grenade landed");
+        assert!(
+            landed.distance(vec3(8.0, 0.0, 0.0)) < 1.0,
+            "landed at {landed}"
+        );
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, ProjectileEvent::Exploded { .. })));
+    }
+}
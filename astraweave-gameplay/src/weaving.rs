@@ -1,17 +1,54 @@
 use crate::{WeaveBudget, WeaveConsequence, WeaveOp, WeaveOpKind};
 use anyhow::Result;
 use astraweave_core::{
-    apply_director_plan, DirectorBudget, DirectorOp, DirectorPlan, IVec2, World,
+    apply_director_plan, DirectorBudget, DirectorOp, DirectorPlan, GridMapping, IVec2, World,
 };
-use astraweave_nav::{NavMesh, Triangle};
+use astraweave_nav::{NavObstacle, OffMeshLink, TiledNavMesh};
 use astraweave_physics::PhysicsWorld;
 use glam::vec3;
 
+/// Seconds a `RaisePlatform` bridge stays up.
+pub const BRIDGE_LIFETIME_S: f32 = 30.0;
+/// Metres a `LowerWater` weave drains from the water under its target.
+pub const WATER_DROP_M: f32 = 1.0;
+
+/// Nav obstacle matching a director terrain op on `grid`, standing on `floor_y`.
+pub fn director_obstacle(op: &DirectorOp, grid: &GridMapping, floor_y: f32) -> Option<NavObstacle> {
+    let at = |x: i32, y: i32| grid.to_world(IVec2 { x, y }).with_y(floor_y);
+    match op {
+        DirectorOp::Fortify { rect } => {
+            let (min, _) = grid.cell_bounds(IVec2 {
+                x: rect.x0.min(rect.x1),
+                y: rect.y0.min(rect.y1),
+            });
+            let (_, max) = grid.cell_bounds(IVec2 {
+                x: rect.x0.max(rect.x1),
+                y: rect.y0.max(rect.y1),
+            });
+            Some(NavObstacle::Box {
+                min: min.with_y(floor_y - 0.5),
+                max: max.with_y(floor_y + 2.0),
+            })
+        }
+        DirectorOp::Collapse { a, b } => Some(NavObstacle::Line {
+            a: at(a.x, a.y),
+            b: at(b.x, b.y),
+            radius: 0.75 * grid.cell_size,
+        }),
+        DirectorOp::SpawnWave { .. } => None,
+    }
+}
+
 /// Apply a single weave op to the world + physics, consume budgets, and return consequences.
+///
+/// Terrain ops also carve `nav`; the touched tiles rebake on the next `nav.update()`.
+/// `RaisePlatform` with a `b` point adds a temporary bridge link from `a` to `b` instead.
+/// `CollapseBridge` also breaks the physics joints along its line, dropping jointed bridges.
+/// `LowerWater` drains the water under `a`, and still succeeds where there is none.
 pub fn apply_weave_op(
     w: &mut World,
     phys: &mut PhysicsWorld,
-    nav_src: &[Triangle],
+    nav: &mut TiledNavMesh,
     budget: &mut WeaveBudget,
     op: &WeaveOp,
     log: &mut impl FnMut(String),
@@ -29,13 +66,13 @@ pub fn apply_weave_op(
                 anyhow::bail!("No terrain budget");
             }
             // fortify a small rect around A
-            let a = op.a;
+            let a = w.grid.to_cell(op.a);
             plan.ops.push(DirectorOp::Fortify {
                 rect: astraweave_core::Rect {
-                    x0: a.x as i32 - 1,
-                    y0: a.z as i32 - 1,
-                    x1: a.x as i32 + 1,
-                    y1: a.z as i32 + 1,
+                    x0: a.x - 1,
+                    y0: a.y - 1,
+                    x1: a.x + 1,
+                    y1: a.y + 1,
                 },
             });
             budget.terrain_edits -= 1;
@@ -44,18 +81,15 @@ pub fn apply_weave_op(
             if budget.terrain_edits <= 0 {
                 anyhow::bail!("No terrain budget");
             }
-            let a = op.a;
             let b = op.b.ok_or_else(|| anyhow::anyhow!("Collapse needs A->B"))?;
             plan.ops.push(DirectorOp::Collapse {
-                a: IVec2 {
-                    x: a.x as i32,
-                    y: a.z as i32,
-                },
-                b: IVec2 {
-                    x: b.x as i32,
-                    y: b.z as i32,
-                },
+                a: w.grid.to_cell(op.a),
+                b: w.grid.to_cell(b),
             });
+            let broken = phys.break_joints_near(op.a, b, 0.75 * w.grid.cell_size);
+            if !broken.is_empty() {
+                log(format!("Weave: Bridge collapsed ({} joints)", broken.len()));
+            }
             budget.terrain_edits -= 1;
         }
         WeaveOpKind::RedirectWind => {
@@ -71,35 +105,54 @@ pub fn apply_weave_op(
             if budget.weather_ops <= 0 {
                 anyhow::bail!("No weather budget");
             }
-            // crude: clear water volumes entirely (demo)
-            phys.clear_water();
             budget.weather_ops -= 1;
-            log("Weave: Waters receded".into());
+            if phys.change_water_level_at(op.a, -WATER_DROP_M) > 0 {
+                log("Weave: Waters receded".into());
+            } else {
+                log("Weave: No water to drain".into());
+            }
         }
         WeaveOpKind::RaisePlatform => {
             if budget.terrain_edits <= 0 {
                 anyhow::bail!("No terrain budget");
             }
             let a = op.a;
-            plan.ops.push(DirectorOp::Fortify {
-                rect: astraweave_core::Rect {
-                    x0: a.x as i32,
-                    y0: a.z as i32,
-                    x1: a.x as i32,
-                    y1: a.z as i32,
-                },
-            });
+            match op.b {
+                // A platform raised towards B bridges the gap for a while.
+                Some(b) => {
+                    let mut bridge = OffMeshLink::bridge(a, b);
+                    bridge.lifetime = Some(BRIDGE_LIFETIME_S);
+                    nav.add_link(bridge);
+                    log("Weave: Bridge raised".into());
+                }
+                None => {
+                    let c = w.grid.to_cell(a);
+                    plan.ops.push(DirectorOp::Fortify {
+                        rect: astraweave_core::Rect {
+                            x0: c.x,
+                            y0: c.y,
+                            x1: c.x,
+                            y1: c.y,
+                        },
+                    })
+                }
+            }
             budget.terrain_edits -= 1;
         }
     }
 
     if !plan.ops.is_empty() {
         apply_director_plan(w, &mut core_budget, &plan, log);
+        let floor_y = nav.mesh().locate(op.a).map_or(op.a.y, |(_, p)| p.y);
+        for o in plan
+            .ops
+            .iter()
+            .filter_map(|o| director_obstacle(o, &w.grid, floor_y))
+        {
+            nav.add_obstacle(o);
+        }
     }
 
-    // Re-bake a small navmesh from provided triangles (demo pathing update)
-    let _nav = NavMesh::bake(nav_src, 0.5, 55.0);
-
     // Return a rough “world consequence”
     let consequence = match op.kind {
         WeaveOpKind::ReinforcePath => WeaveConsequence {
@@ -131,3 +184,140 @@ pub fn apply_weave_op(
 
     Ok(consequence)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astraweave_nav::{BakeConfig, LinkKind, NavEvent, Triangle};
+    use astraweave_physics::{Layers, PhysicsEvent, WaterVolume};
+    use glam::Vec3;
+
+    fn setup() -> (World, PhysicsWorld, TiledNavMesh) {
+        let mut p = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        p.create_ground_plane(vec3(20.0, 0.0, 20.0), 0.9);
+        let (lo, hi) = (-10.0, 10.0);
+        let floor = [
+            Triangle {
+                a: vec3(lo, 0.0, lo),
+                b: vec3(hi, 0.0, hi),
+                c: vec3(hi, 0.0, lo),
+            },
+            Triangle {
+                a: vec3(lo, 0.0, lo),
+                b: vec3(lo, 0.0, hi),
+                c: vec3(hi, 0.0, hi),
+            },
+        ];
+        let nav = TiledNavMesh::new(&floor, BakeConfig::default(), 8.0);
+        (World::new(), p, nav)
+    }
+
+    fn op(kind: WeaveOpKind, a: Vec3, b: Option<Vec3>) -> WeaveOp {
+        WeaveOp {
+            kind,
+            a,
+            b,
+            budget_cost: 1,
+        }
+    }
+
+    fn budget() -> WeaveBudget {
+        WeaveBudget {
+            terrain_edits: 3,
+            weather_ops: 3,
+        }
+    }
+
+    #[test]
+    fn lower_water_drains_the_pool_under_its_target() {
+        let (mut w, mut p, mut nav) = setup();
+        let pool = p.add_water(WaterVolume::new(
+            vec3(-4.0, -3.0, -4.0),
+            vec3(4.0, 0.0, 4.0),
+            1000.0,
+        ));
+        let mut budget = budget();
+        let mut logs = vec![];
+
+        let drain = op(WeaveOpKind::LowerWater, vec3(1.0, 0.0, 1.0), None);
+        apply_weave_op(&mut w, &mut p, &mut nav, &mut budget, &drain, &mut |s| {
+            logs.push(s)
+        })
+        .unwrap();
+        assert_eq!(p.water(pool).unwrap().surface, -WATER_DROP_M);
+        assert_eq!(budget.weather_ops, 2);
+
+        // Nothing to drain out here: still a success, and still a weather op spent.
+        let dry = op(WeaveOpKind::LowerWater, vec3(8.0, 0.0, 8.0), None);
+        apply_weave_op(&mut w, &mut p, &mut nav, &mut budget, &dry, &mut |s| {
+            logs.push(s)
+        })
+        .unwrap();
+        assert_eq!(p.water(pool).unwrap().surface, -WATER_DROP_M);
+        assert_eq!(budget.weather_ops, 1);
+        assert!(logs.iter().any(|l| l == "Weave: Waters receded"));
+        assert!(logs.iter().any(|l| l == "Weave: No water to drain"));
+    }
+
+    #[test]
+    fn raised_bridges_link_the_gap_until_they_expire() {
+        let (mut w, mut p, mut nav) = setup();
+        let mut budget = budget();
+        let (a, b) = (vec3(-3.0, 0.0, 0.0), vec3(3.0, 0.0, 0.0));
+        let raise = op(WeaveOpKind::RaisePlatform, a, Some(b));
+        apply_weave_op(&mut w, &mut p, &mut nav, &mut budget, &raise, &mut |_| {}).unwrap();
+        assert_eq!(budget.terrain_edits, 2);
+
+        let links: Vec<_> = nav.mesh().links().map(|(id, l)| (id, l.clone())).collect();
+        assert_eq!(links.len(), 1);
+        let (id, link) = &links[0];
+        assert_eq!((link.start, link.end), (a, b));
+        assert!(matches!(link.kind, LinkKind::Bridge));
+        assert_eq!(link.lifetime, Some(BRIDGE_LIFETIME_S));
+
+        nav.tick_links(BRIDGE_LIFETIME_S - 1.0);
+        assert_eq!(nav.mesh().links().count(), 1);
+        nav.tick_links(2.0);
+        assert_eq!(nav.mesh().links().count(), 0);
+        assert!(nav
+            .drain_events()
+            .iter()
+            .any(|e| matches!(e, NavEvent::LinkExpired(x) if x == id)));
+    }
+
+    #[test]
+    fn collapsing_a_bridge_breaks_its_joints() {
+        let (mut w, mut p, mut nav) = setup();
+        let cliffs = [
+            p.create_ground_plane(vec3(1.0, 0.0, 1.0), 0.9),
+            p.create_ground_plane(vec3(1.0, 0.0, 1.0), 0.9),
+        ];
+        let (from, to) = (vec3(-3.0, 2.0, 0.0), vec3(3.0, 2.0, 0.0));
+        let bridge = p
+            .add_rope_bridge(cliffs, from, to, 6, 0.5, 30.0, Layers::DEFAULT)
+            .unwrap();
+        assert!(!bridge.joints.is_empty());
+        p.drain_events();
+
+        let mut budget = budget();
+        let mut logs = vec![];
+        let collapse = op(WeaveOpKind::CollapseBridge, from, Some(to));
+        apply_weave_op(&mut w, &mut p, &mut nav, &mut budget, &collapse, &mut |s| {
+            logs.push(s)
+        })
+        .unwrap();
+        for j in &bridge.joints {
+            assert!(p.joint_bodies(*j).is_none(), "{j:?} survived");
+        }
+        let broken = p
+            .drain_events()
+            .into_iter()
+            .filter(|e| matches!(e, PhysicsEvent::JointBroken { .. }))
+            .count();
+        assert_eq!(broken, bridge.joints.len());
+        assert!(logs
+            .iter()
+            .any(|l| l == &format!("Weave: Bridge collapsed ({broken} joints)")));
+        assert_eq!(budget.terrain_edits, 2);
+    }
+}
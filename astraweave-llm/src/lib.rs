@@ -198,12 +198,30 @@ Strict JSON schema:
 }
 Return ONLY JSON with no commentary.
 "#;
+    let relationships = if snap.relationships.is_empty() {
+        String::new()
+    } else {
+        let lines = snap
+            .relationships
+            .iter()
+            .map(|r| {
+                format!(
+                    " - {}: {} (affinity {:.2}, trust {:.2}, fear {:.2})",
+                    r.target, r.label, r.affinity, r.trust, r.fear
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "\nRelationships (let these shape how closely you follow orders and whom you protect):\n{lines}\n"
+        )
+    };
     format!(
         r#"You are an AI game companion planner. Convert the world snapshot into a legal action plan.
 Use ONLY allowed tools and arguments. Do not exceed cooldown or LOS checks (the engine will validate).
 Allowed tools:
 {tools}
-
+{relationships}
 Snapshot (redacted):
 {snap}
 
@@ -321,6 +339,7 @@ mod tests {
             }],
             pois: vec![],
             objective: Some("extract".into()),
+            relationships: vec![],
         }
     }
 
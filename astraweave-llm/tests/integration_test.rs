@@ -201,6 +201,7 @@ fn create_complex_scenario() -> WorldSnapshot {
             },
         ],
         objective: Some("Reach extraction zone while eliminating hostiles".into()),
+        relationships: vec![],
     }
 }
 
@@ -0,0 +1,504 @@
+use crate::{CompanionProfile, Episode, Fact};
+use std::collections::{BTreeMap, HashMap};
+
+/// Relative weight of each importance signal. Scores are normalized to `0..=1`.
+#[derive(Clone, Debug)]
+pub struct ImportanceWeights {
+    pub recency: f32,
+    pub emotion: f32,
+    pub player: f32,
+}
+
+impl Default for ImportanceWeights {
+    fn default() -> Self {
+        Self {
+            recency: 0.4,
+            emotion: 0.35,
+            player: 0.25,
+        }
+    }
+}
+
+/// Exponential (Ebbinghaus-style) decay of episode recency. Salient episodes
+/// (emotional or involving the player) decay more slowly.
+#[derive(Clone, Debug)]
+pub struct ForgettingCurve {
+    pub half_life_days: f32,
+    /// Half-life multiplier per unit of salience: `half_life * (1 + boost * salience)`.
+    pub salience_boost: f32,
+}
+
+impl Default for ForgettingCurve {
+    fn default() -> Self {
+        Self {
+            half_life_days: 7.0,
+            salience_boost: 3.0,
+        }
+    }
+}
+
+impl ForgettingCurve {
+    pub fn retention(&self, age_days: f32, salience: f32) -> f32 {
+        let half_life = self.half_life_days.max(1e-3) * (1.0 + self.salience_boost * salience);
+        0.5f32.powf(age_days.max(0.0) / half_life)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConsolidationConfig {
+    pub weights: ImportanceWeights,
+    pub curve: ForgettingCurve,
+    /// Episodes at or above this score always survive as their own fact.
+    pub protect_threshold: f32,
+    /// Episodes below this score are forgotten instead of summarized.
+    pub forget_threshold: f32,
+    /// Tag -> emotional weight (`0..=1`); an episode uses its strongest tag.
+    pub emotional_tags: HashMap<String, f32>,
+    /// Tags that mark the player as directly involved.
+    pub player_tags: Vec<String>,
+    /// Maximum facts kept per category (the episode's first tag).
+    pub default_cap: usize,
+    pub category_caps: HashMap<String, usize>,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        let emotional_tags = [
+            ("death", 1.0),
+            ("betrayal", 1.0),
+            ("revive", 0.8),
+            ("rescue", 0.8),
+            ("victory", 0.6),
+            ("defeat", 0.6),
+            ("gift", 0.5),
+            ("argument", 0.5),
+            ("flank", 0.2),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        Self {
+            weights: ImportanceWeights::default(),
+            curve: ForgettingCurve::default(),
+            protect_threshold: 0.7,
+            forget_threshold: 0.15,
+            emotional_tags,
+            player_tags: vec!["player".into(), "revive".into(), "gift".into()],
+            default_cap: 20,
+            category_caps: HashMap::new(),
+        }
+    }
+}
+
+impl ConsolidationConfig {
+    pub fn cap_for(&self, category: &str) -> usize {
+        self.category_caps
+            .get(category)
+            .copied()
+            .unwrap_or(self.default_cap)
+    }
+}
+
+/// An episode with its computed importance.
+#[derive(Clone, Debug)]
+pub struct ScoredEpisode {
+    pub episode: Episode,
+    pub category: String,
+    pub importance: f32,
+}
+
+/// What a consolidation pass decided, before summaries are written.
+#[derive(Clone, Debug, Default)]
+pub struct ConsolidationPlan {
+    /// Kept verbatim as one fact each.
+    pub protected: Vec<ScoredEpisode>,
+    /// category -> episodes to merge into a single summary fact
+    pub merge: BTreeMap<String, Vec<ScoredEpisode>>,
+    pub forgotten: Vec<ScoredEpisode>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsolidationReport {
+    pub protected: usize,
+    pub summarized: usize,
+    pub forgotten: usize,
+    /// Facts evicted to respect category caps.
+    pub evicted_facts: usize,
+}
+
+pub fn episode_category(e: &Episode) -> String {
+    e.tags.first().cloned().unwrap_or_else(|| "general".into())
+}
+
+/// Score one episode. `age_days` is the time between the episode and "now".
+pub fn score_episode(e: &Episode, age_days: f32, cfg: &ConsolidationConfig) -> f32 {
+    let emotion = e
+        .tags
+        .iter()
+        .filter_map(|t| cfg.emotional_tags.get(t))
+        .fold(0.0f32, |a, &b| a.max(b))
+        .clamp(0.0, 1.0);
+    let player = if e.tags.iter().any(|t| cfg.player_tags.contains(t)) {
+        1.0
+    } else {
+        0.0
+    };
+    let salience = 0.5 * (emotion + player);
+    let recency = cfg.curve.retention(age_days, salience);
+    let w = &cfg.weights;
+    let total = (w.recency + w.emotion + w.player).max(1e-6);
+    (w.recency * recency + w.emotion * emotion + w.player * player) / total
+}
+
+/// Deterministic summary used when no LLM is available (or it fails).
+pub fn fallback_summary(category: &str, episodes: &[ScoredEpisode]) -> String {
+    let mut parts: Vec<String> = episodes
+        .iter()
+        .map(|s| format!("{}: {}", s.episode.title, s.episode.summary))
+        .collect();
+    parts.truncate(5);
+    let more = episodes.len().saturating_sub(parts.len());
+    let mut out = format!(
+        "{} {} memories — {}",
+        episodes.len(),
+        category,
+        parts.join("; ")
+    );
+    if more > 0 {
+        out.push_str(&format!(" (+{more} more)"));
+    }
+    out
+}
+
+impl CompanionProfile {
+    /// Score and bucket all pending episodes without modifying the profile.
+    /// `now` defaults to the newest episode timestamp.
+    pub fn plan_consolidation(
+        &self,
+        cfg: &ConsolidationConfig,
+        now: Option<&str>,
+    ) -> ConsolidationPlan {
+        let now_days = now.and_then(parse_ts_days).or_else(|| {
+            self.episodes
+                .iter()
+                .filter_map(|e| parse_ts_days(&e.ts))
+                .reduce(f64::max)
+        });
+        let n = self.episodes.len();
+        let mut plan = ConsolidationPlan::default();
+        for (i, e) in self.episodes.iter().enumerate() {
+            // Episodes without a parseable timestamp age by their position in the log.
+            let age_days = match (now_days, parse_ts_days(&e.ts)) {
+                (Some(now), Some(t)) => (now - t) as f32,
+                _ => (n - 1 - i) as f32,
+            };
+            let scored = ScoredEpisode {
+                category: episode_category(e),
+                importance: score_episode(e, age_days, cfg),
+                episode: e.clone(),
+            };
+            if scored.importance >= cfg.protect_threshold {
+                plan.protected.push(scored);
+            } else if scored.importance < cfg.forget_threshold {
+                plan.forgotten.push(scored);
+            } else {
+                plan.merge
+                    .entry(scored.category.clone())
+                    .or_default()
+                    .push(scored);
+            }
+        }
+        plan
+    }
+
+    /// Consolidate episodes into facts using the deterministic summarizer.
+    pub fn consolidate(
+        &mut self,
+        cfg: &ConsolidationConfig,
+        now: Option<&str>,
+    ) -> ConsolidationReport {
+        let plan = self.plan_consolidation(cfg, now);
+        let summaries = plan
+            .merge
+            .iter()
+            .map(|(cat, eps)| (cat.clone(), fallback_summary(cat, eps)))
+            .collect();
+        self.apply_consolidation(plan, summaries, cfg)
+    }
+
+    /// Like [`CompanionProfile::consolidate`] but asks an LLM to write each
+    /// category summary, falling back to the deterministic summary on error.
+    #[cfg(feature = "llm")]
+    pub async fn consolidate_with_llm(
+        &mut self,
+        cfg: &ConsolidationConfig,
+        now: Option<&str>,
+        client: &dyn astraweave_llm::LlmClient,
+    ) -> ConsolidationReport {
+        let plan = self.plan_consolidation(cfg, now);
+        let mut summaries = HashMap::new();
+        for (cat, eps) in &plan.merge {
+            let mut prompt = format!(
+                "Summarize these companion memories about '{cat}' in one short sentence \
+                 written from the companion's point of view. Reply with the sentence only.\n"
+            );
+            for s in eps {
+                prompt.push_str(&format!("- {}: {}\n", s.episode.title, s.episode.summary));
+            }
+            let text = match client.complete(&prompt).await {
+                Ok(t) if !t.trim().is_empty() => t.trim().to_string(),
+                _ => fallback_summary(cat, eps),
+            };
+            summaries.insert(cat.clone(), text);
+        }
+        self.apply_consolidation(plan, summaries, cfg)
+    }
+
+    fn apply_consolidation(
+        &mut self,
+        plan: ConsolidationPlan,
+        mut summaries: HashMap<String, String>,
+        cfg: &ConsolidationConfig,
+    ) -> ConsolidationReport {
+        let mut report = ConsolidationReport {
+            protected: plan.protected.len(),
+            summarized: plan.merge.values().map(Vec::len).sum(),
+            forgotten: plan.forgotten.len(),
+            evicted_facts: 0,
+        };
+        self.episodes.clear();
+        for s in plan.protected {
+            self.facts.push(Fact {
+                k: format!("ep:{}", s.episode.title),
+                v: s.episode.summary,
+                t: s.episode.ts,
+                category: s.category,
+                importance: s.importance,
+            });
+        }
+        for (cat, eps) in plan.merge {
+            let newest = eps
+                .iter()
+                .map(|s| s.episode.ts.clone())
+                .max()
+                .unwrap_or_default();
+            let importance = eps.iter().map(|s| s.importance).fold(0.0, f32::max);
+            self.facts.push(Fact {
+                k: format!("sum:{cat}"),
+                v: summaries
+                    .remove(&cat)
+                    .unwrap_or_else(|| fallback_summary(&cat, &eps)),
+                t: newest,
+                category: cat,
+                importance,
+            });
+        }
+        report.evicted_facts = self.enforce_fact_caps(cfg);
+        report
+    }
+
+    /// Drop the least important facts of every category that exceeds its cap.
+    /// Protected facts are never evicted, even if that leaves a category over its cap.
+    fn enforce_fact_caps(&mut self, cfg: &ConsolidationConfig) -> usize {
+        let mut by_cat: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, f) in self.facts.iter().enumerate() {
+            if !f.category.is_empty() {
+                by_cat.entry(f.category.as_str()).or_default().push(i);
+            }
+        }
+        let mut evict = vec![];
+        for (cat, mut idx) in by_cat {
+            let cap = cfg.cap_for(cat);
+            if idx.len() <= cap {
+                continue;
+            }
+            idx.sort_by(|&a, &b| {
+                self.facts[a]
+                    .importance
+                    .total_cmp(&self.facts[b].importance)
+            });
+            let excess = idx.len() - cap;
+            evict.extend(
+                idx.into_iter()
+                    .filter(|&i| self.facts[i].importance < cfg.protect_threshold)
+                    .take(excess),
+            );
+        }
+        evict.sort_unstable();
+        for &i in evict.iter().rev() {
+            self.facts.remove(i);
+        }
+        evict.len()
+    }
+}
+
+/// Days since the Unix epoch for timestamps like `2025-09-04T12:00:00Z` (date-only works too).
+pub fn parse_ts_days(ts: &str) -> Option<f64> {
+    let (date, time) = ts.split_once('T').unwrap_or((ts, ""));
+    let mut d = date.splitn(3, '-');
+    let y: i64 = d.next()?.parse().ok()?;
+    let m: i64 = d.next()?.parse().ok()?;
+    let day: i64 = d.next()?.parse().ok()?;
+    if !(1..=12).contains(&m) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days_from_civil (H. Hinnant)
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs: f64 = time
+        .trim_end_matches('Z')
+        .split(':')
+        .take(3)
+        .zip([3600.0, 60.0, 1.0])
+        .map(|(part, mul)| part.parse::<f64>().unwrap_or(0.0) * mul)
+        .sum();
+    Some(days as f64 + secs / 86_400.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ep(title: &str, tags: &[&str], ts: &str) -> Episode {
+        Episode {
+            title: title.into(),
+            summary: format!("{title} happened"),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ts: ts.into(),
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_ts_days("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_ts_days("1970-01-02"), Some(1.0));
+        assert_eq!(parse_ts_days("2025-09-04T12:00:00Z"), Some(20335.5));
+        assert_eq!(parse_ts_days("yesterday"), None);
+    }
+
+    #[test]
+    fn emotional_player_episodes_outrank_old_routine_ones() {
+        let cfg = ConsolidationConfig::default();
+        let revive = ep("clutch_revive", &["revive", "player"], "2025-01-01");
+        let patrol = ep("patrol", &["patrol"], "2025-01-01");
+        assert!(score_episode(&revive, 30.0, &cfg) > score_episode(&patrol, 30.0, &cfg));
+        assert!(score_episode(&patrol, 0.0, &cfg) > score_episode(&patrol, 30.0, &cfg));
+    }
+
+    #[test]
+    fn high_importance_episodes_are_never_dropped() {
+        let mut cfg = ConsolidationConfig {
+            default_cap: 1,
+            ..Default::default()
+        };
+        cfg.category_caps.insert("revive".into(), 0);
+
+        // Deterministic pseudo-random mix of routine and salient episodes over a year.
+        let tags = [
+            vec!["patrol"],
+            vec!["revive", "player"],
+            vec!["death"],
+            vec!["flank"],
+            vec!["loot"],
+        ];
+        let mut seed = 0x2545_f491u32;
+        let mut p = CompanionProfile::new_default();
+        for i in 0..200 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let t = &tags[(seed >> 16) as usize % tags.len()];
+            let day = 1 + (seed >> 8) % 28;
+            let month = 1 + (seed >> 4) % 12;
+            p.episodes.push(ep(
+                &format!("e{i}"),
+                t,
+                &format!("2025-{month:02}-{day:02}"),
+            ));
+        }
+        let plan = p.plan_consolidation(&cfg, None);
+        let protected: Vec<String> = plan
+            .protected
+            .iter()
+            .map(|s| format!("ep:{}", s.episode.title))
+            .collect();
+        assert!(!protected.is_empty());
+
+        let report = p.consolidate(&cfg, None);
+        assert!(p.episodes.is_empty());
+        assert_eq!(report.protected + report.summarized + report.forgotten, 200);
+        for k in &protected {
+            assert!(p.facts.iter().any(|f| &f.k == k), "{k} was dropped");
+        }
+
+        // A second pass with older facts already present must not evict them either.
+        p.episodes
+            .push(ep("late_patrol", &["patrol"], "2026-01-01"));
+        p.consolidate(&cfg, None);
+        for k in &protected {
+            assert!(p.facts.iter().any(|f| &f.k == k), "{k} was evicted");
+        }
+    }
+
+    #[test]
+    fn caps_limit_summaries_per_category() {
+        let cfg = ConsolidationConfig {
+            default_cap: 2,
+            ..Default::default()
+        };
+        let mut p = CompanionProfile::new_default();
+        for round in 0..4 {
+            p.episodes
+                .push(ep(&format!("loot{round}"), &["loot"], "2025-01-01"));
+            p.consolidate(&cfg, Some("2025-01-03"));
+        }
+        let loot = p.facts.iter().filter(|f| f.category == "loot").count();
+        assert_eq!(loot, 2);
+    }
+
+    #[cfg(feature = "llm")]
+    #[tokio::test]
+    async fn llm_summaries_fall_back_when_the_client_fails() {
+        use astraweave_llm::{LlmClient, MockLlm};
+
+        struct Offline;
+        #[async_trait::async_trait]
+        impl LlmClient for Offline {
+            async fn complete(&self, _prompt: &str) -> anyhow::Result<String> {
+                anyhow::bail!("model unavailable")
+            }
+        }
+
+        let cfg = ConsolidationConfig::default();
+        let now = Some("2025-01-03");
+        let loot = || {
+            let mut p = CompanionProfile::new_default();
+            p.episodes.push(ep("loot0", &["loot"], "2025-01-01"));
+            p.episodes.push(ep("loot1", &["loot"], "2025-01-02"));
+            p
+        };
+        let summary = |p: &CompanionProfile| {
+            p.facts
+                .iter()
+                .find(|f| f.k == "sum:loot")
+                .map(|f| f.v.clone())
+                .unwrap()
+        };
+
+        let mut p = loot();
+        let report = p.consolidate_with_llm(&cfg, now, &MockLlm).await;
+        assert_eq!(report.summarized, 2);
+        let expected = MockLlm.complete("").await.unwrap();
+        assert_eq!(summary(&p), expected.trim());
+
+        let mut p = loot();
+        p.consolidate_with_llm(&cfg, now, &Offline).await;
+        let mut q = loot();
+        q.consolidate(&cfg, now);
+        assert_eq!(summary(&p), summary(&q));
+    }
+}
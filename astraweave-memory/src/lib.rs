@@ -1,5 +1,18 @@
+use astraweave_core::{Entity, PlanIntent, World};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+
+mod consolidation;
+mod relationship;
+mod signing;
+pub use consolidation::{
+    episode_category, fallback_summary, parse_ts_days, score_episode, ConsolidationConfig,
+    ConsolidationPlan, ConsolidationReport, ForgettingCurve, ImportanceWeights, ScoredEpisode,
+};
+pub use relationship::{
+    target_id, RelationStat, Relationship, RelationshipEvent, RelationshipTuning, Relationships,
+    DIALOGUE_VAR_PREFIX, PLAYER,
+};
+pub use signing::{canonical_json, key_id_of, ProfileSigningKey, SignatureError, TrustedKeys};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Persona {
@@ -14,6 +27,17 @@ pub struct Fact {
     pub k: String,
     pub v: String,
     pub t: String,
+    /// Category the fact was consolidated under (empty for hand-authored facts).
+    /// Defaults are not serialized, so older signed profiles keep their canonical bytes.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub category: String,
+    /// Importance score at consolidation time, used when enforcing category caps.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub importance: f32,
+}
+
+fn is_zero(v: &f32) -> bool {
+    *v == 0.0
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -39,6 +63,13 @@ pub struct CompanionProfile {
     pub facts: Vec<Fact>,
     pub episodes: Vec<Episode>,
     pub skills: Vec<Skill>,
+    /// Affinity/trust/fear toward the player, NPCs and factions. Left out when empty so
+    /// profiles signed before relationships existed keep their canonical bytes.
+    #[serde(default, skip_serializing_if = "Relationships::is_empty")]
+    pub relationships: Relationships,
+    /// Id of the Ed25519 key that produced `signature` (see [`key_id_of`]).
+    #[serde(default)]
+    pub key_id: Option<String>,
     pub signature: Option<String>,
 }
 
@@ -56,32 +87,77 @@ impl CompanionProfile {
             facts: vec![],
             episodes: vec![],
             skills: vec![],
+            relationships: Relationships::default(),
+            key_id: None,
             signature: None,
         }
     }
 
-    pub fn distill(&mut self) {
-        // naive: convert older episodes into facts and truncate
-        let mut new_facts = vec![];
-        for e in self.episodes.drain(..).take(10) {
-            new_facts.push(Fact {
-                k: format!("ep:{}", e.title),
-                v: e.summary,
-                t: e.ts,
-            });
+    /// Consolidate all pending episodes into facts with the default settings.
+    /// See [`CompanionProfile::consolidate`] for control over scoring and caps.
+    pub fn distill(&mut self) -> ConsolidationReport {
+        self.consolidate(&ConsolidationConfig::default(), None)
+    }
+
+    /// Canonical bytes covered by the signature: every field except `signature`,
+    /// serialized as JSON with sorted keys.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut v = serde_json::to_value(self).expect("profile serializes to JSON");
+        if let Some(m) = v.as_object_mut() {
+            m.remove("signature");
         }
-        self.facts.extend(new_facts);
+        canonical_json(&v)
+    }
+
+    /// Sign the profile with an Ed25519 key; records the key id alongside the signature.
+    pub fn sign(&mut self, key: &ProfileSigningKey) {
+        self.key_id = Some(key.key_id());
+        self.signature = Some(key.sign_bytes(&self.canonical_bytes()));
+    }
+
+    /// Attach a signature produced elsewhere (e.g. shipped inside a persona pack).
+    pub fn attach_signature(&mut self, key_id: String, signature: String) {
+        self.key_id = Some(key_id);
+        self.signature = Some(signature);
+    }
+
+    /// Record an event against the companion's relationships with the default tuning.
+    pub fn record_relationship_event(&mut self, event: &RelationshipEvent) -> Option<String> {
+        self.relationships
+            .apply(event, &RelationshipTuning::default())
     }
 
-    pub fn sign(&mut self) {
-        // simple content hash as "signature" (not cryptographically signed)
-        let mut hasher = Sha256::new();
-        let mut clone = self.clone();
-        clone.signature = None;
-        let bytes = serde_json::to_vec(&clone).unwrap();
-        hasher.update(bytes);
-        let out = hasher.finalize();
-        self.signature = Some(hex::encode(out));
+    /// Record what executing `intent` as `actor` means for this companion (see
+    /// [`RelationshipEvent::from_step`]); call before executing it. Returns the targets
+    /// that changed.
+    pub fn observe_plan(
+        &mut self,
+        w: &World,
+        actor: Entity,
+        intent: &PlanIntent,
+        companion: Entity,
+    ) -> Vec<String> {
+        intent
+            .steps
+            .iter()
+            .filter_map(|step| RelationshipEvent::from_step(w, actor, step, companion))
+            .filter_map(|ev| self.record_relationship_event(&ev))
+            .collect()
+    }
+
+    /// Publish the relationships on `companion` so `build_snapshot` hands them to planners.
+    pub fn sync_to_world(&self, w: &mut World, companion: Entity) {
+        w.set_relationships(companion, self.relationships.to_views());
+    }
+
+    /// Feed every variable a dialogue line sets; `rel.<target>.<stat>` keys adjust relationships.
+    pub fn apply_dialogue_vars<'a>(
+        &mut self,
+        vars: impl IntoIterator<Item = &'a (String, String)>,
+    ) -> Vec<String> {
+        vars.into_iter()
+            .filter_map(|(k, v)| self.relationships.apply_dialogue_var(k, v))
+            .collect()
     }
 
     pub fn save_to_file(&self, path: &str) -> anyhow::Result<()> {
@@ -96,16 +172,189 @@ impl CompanionProfile {
         Ok(p)
     }
 
-    pub fn verify(&self) -> bool {
-        if let Some(sig) = &self.signature {
-            let mut hasher = Sha256::new();
-            let mut clone = self.clone();
-            clone.signature = None;
-            let bytes = serde_json::to_vec(&clone).unwrap();
-            hasher.update(bytes);
-            let out = hasher.finalize();
-            return *sig == hex::encode(out);
-        }
-        false
+    /// Check the signature against a set of trusted public keys.
+    pub fn verify(&self, trusted: &TrustedKeys) -> Result<(), SignatureError> {
+        let (Some(key_id), Some(sig)) = (&self.key_id, &self.signature) else {
+            return Err(SignatureError::Unsigned);
+        };
+        trusted.verify_bytes(key_id, &self.canonical_bytes(), sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_profile(key: &ProfileSigningKey) -> CompanionProfile {
+        let mut p = CompanionProfile::new_default();
+        p.facts.push(Fact {
+            k: "safehouse".into(),
+            v: "west alcove".into(),
+            t: "2025-09-04T12:00:00Z".into(),
+            category: String::new(),
+            importance: 0.0,
+        });
+        p.sign(key);
+        p
+    }
+
+    #[test]
+    fn signed_profile_verifies_with_trusted_key() {
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+        let p = signed_profile(&key);
+        assert_eq!(p.verify(&trusted), Ok(()));
+
+        let reloaded: CompanionProfile =
+            serde_json::from_str(&serde_json::to_string_pretty(&p).unwrap()).unwrap();
+        assert_eq!(reloaded.verify(&trusted), Ok(()));
+    }
+
+    #[test]
+    fn tampering_and_unknown_keys_are_rejected() {
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+
+        let mut p = signed_profile(&key);
+        p.persona.risk = "reckless".into();
+        assert_eq!(p.verify(&trusted), Err(SignatureError::Mismatch));
+
+        let other = signed_profile(&ProfileSigningKey::generate());
+        assert!(matches!(
+            other.verify(&trusted),
+            Err(SignatureError::UntrustedKey(_))
+        ));
+        assert_eq!(
+            CompanionProfile::new_default().verify(&trusted),
+            Err(SignatureError::Unsigned)
+        );
+    }
+
+    #[test]
+    fn relationship_events_move_stats_and_labels() {
+        let mut p = CompanionProfile::new_default();
+        p.record_relationship_event(&RelationshipEvent::Revived { by: PLAYER.into() });
+        p.record_relationship_event(&RelationshipEvent::QuestChoice {
+            by: PLAYER.into(),
+            approval: 1.0,
+        });
+        let r = p.relationships.get(PLAYER);
+        assert!(r.affinity > 0.3 && r.trust > 0.6);
+        assert_eq!(r.label(), "friendly");
+
+        p.record_relationship_event(&RelationshipEvent::FriendlyFire {
+            by: PLAYER.into(),
+            damage: 500,
+        });
+        let after = p.relationships.get(PLAYER);
+        assert!((r.affinity - after.affinity - 0.25).abs() < 1e-5);
+        assert_eq!(after.interactions, 3);
+    }
+
+    #[test]
+    fn dialogue_vars_adjust_relationships() {
+        let mut p = CompanionProfile::new_default();
+        let vars = vec![
+            ("rel.faction:raiders.fear".to_string(), "0.7".to_string()),
+            ("rel.player.affinity".to_string(), "+0.3".to_string()),
+            ("rel.player.affinity".to_string(), "-0.1".to_string()),
+            ("mood".to_string(), "happy".to_string()),
+        ];
+        let changed = p.apply_dialogue_vars(&vars);
+        assert_eq!(changed.len(), 3);
+        assert_eq!(p.relationships.get("faction:raiders").label(), "afraid");
+        assert!((p.relationships.get(PLAYER).affinity - 0.2).abs() < 1e-5);
+        assert!(p
+            .relationships
+            .dialogue_vars()
+            .contains(&("rel.player".to_string(), "friendly".to_string())));
+
+        let json = serde_json::to_string(&p).unwrap();
+        let back: CompanionProfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.relationships.get("faction:raiders").fear, 0.7);
+    }
+
+    #[test]
+    fn profiles_signed_before_fact_scoring_still_verify() {
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+        // A profile as written before facts carried a category and importance.
+        let mut old = serde_json::json!({
+            "version": "1.0.0",
+            "persona": {"tone": "dry", "risk": "medium", "humor": "light", "voice": "v01"},
+            "player_prefs": {"stealth_bias": 0.5},
+            "facts": [{"k": "safehouse", "v": "west alcove", "t": "2025-09-04T12:00:00Z"}],
+            "episodes": [],
+            "skills": [],
+            "key_id": key.key_id(),
+        });
+        let sig = key.sign_bytes(&canonical_json(&old));
+        old["signature"] = sig.into();
+
+        let p: CompanionProfile = serde_json::from_value(old).unwrap();
+        assert_eq!(p.verify(&trusted), Ok(()));
+    }
+
+    #[test]
+    fn executed_plans_reach_the_snapshot() {
+        use astraweave_core::{build_snapshot, ActionStep, IVec2, PerceptionConfig, Team};
+        let mut w = World::new();
+        let player = w.spawn("Player", IVec2 { x: 2, y: 2 }, Team { id: 0 }, 100, 0);
+        let comp = w.spawn("Comp", IVec2 { x: 2, y: 3 }, Team { id: 1 }, 80, 30);
+        let plan = |steps| PlanIntent {
+            plan_id: "p".into(),
+            steps,
+        };
+
+        let mut p = CompanionProfile::new_default();
+        let revive = plan(vec![ActionStep::Revive { ally_id: comp }]);
+        assert_eq!(p.observe_plan(&w, player, &revive, comp), vec![PLAYER]);
+        // The companion's own actions and fire at others do not count.
+        assert!(p.observe_plan(&w, comp, &revive, comp).is_empty());
+        let fire = |target_id| {
+            plan(vec![ActionStep::CoverFire {
+                target_id,
+                duration: 2.0,
+            }])
+        };
+        assert!(p.observe_plan(&w, player, &fire(player), comp).is_empty());
+        p.observe_plan(&w, player, &fire(comp), comp);
+        let r = p.relationships.get(PLAYER);
+        assert_eq!(r.interactions, 2);
+        assert!(r.affinity > 0.0 && r.affinity < 0.15);
+
+        p.sync_to_world(&mut w, comp);
+        let cfg = PerceptionConfig {
+            los_max: 12,
+            tactics: None,
+        };
+        let snap = build_snapshot(&w, player, comp, &[], None, &cfg);
+        assert_eq!(snap.relationships.len(), 1);
+        assert_eq!(snap.relationships[0].target, PLAYER);
+        assert_eq!(snap.relationships[0].label, r.label());
+    }
+
+    #[cfg(feature = "save")]
+    #[test]
+    fn relationships_survive_the_save_layout() {
+        let mut p = CompanionProfile::new_default();
+        p.record_relationship_event(&RelationshipEvent::Revived { by: PLAYER.into() });
+        p.apply_dialogue_vars(&[("rel.faction:raiders.fear".into(), "0.7".into())]);
+        let states = p.relationships.to_save_states();
+        assert_eq!(states.len(), 2);
+        let back = Relationships::from_save_states(&states);
+        assert_eq!(back.entries, p.relationships.entries);
+    }
+
+    #[test]
+    fn canonical_bytes_ignore_key_order() {
+        let mut a = CompanionProfile::new_default();
+        a.player_prefs = serde_json::from_str(r#"{"a":1,"b":{"y":2,"x":3}}"#).unwrap();
+        let mut b = a.clone();
+        b.player_prefs = serde_json::from_str(r#"{"b":{"x":3,"y":2},"a":1}"#).unwrap();
+        assert_eq!(a.canonical_bytes(), b.canonical_bytes());
     }
 }
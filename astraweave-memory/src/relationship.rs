@@ -0,0 +1,354 @@
+use astraweave_core::{ActionStep, Entity, RelationshipView, World};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Relationship target id for the player.
+pub const PLAYER: &str = "player";
+
+/// Prefix for dialogue variables that adjust a relationship: `rel.<target>.<stat>`.
+pub const DIALOGUE_VAR_PREFIX: &str = "rel.";
+
+/// How a companion feels about one entity or faction.
+///
+/// Targets are free-form ids such as `"player"`, `"npc:42"` or `"faction:raiders"`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Relationship {
+    /// Like/dislike in `-1.0..=1.0`.
+    pub affinity: f32,
+    /// Confidence the target will act in the companion's interest, `0.0..=1.0`.
+    pub trust: f32,
+    /// Fear of the target, `0.0..=1.0`.
+    pub fear: f32,
+    /// Number of events applied so far.
+    #[serde(default)]
+    pub interactions: u32,
+}
+
+impl Default for Relationship {
+    fn default() -> Self {
+        Self {
+            affinity: 0.0,
+            trust: 0.5,
+            fear: 0.0,
+            interactions: 0,
+        }
+    }
+}
+
+impl Relationship {
+    /// Short mood word used in prompts and as a dialogue variable value.
+    pub fn label(&self) -> &'static str {
+        if self.fear >= 0.6 {
+            "afraid"
+        } else if self.affinity >= 0.5 && self.trust >= 0.6 {
+            "devoted"
+        } else if self.affinity >= 0.2 {
+            "friendly"
+        } else if self.affinity <= -0.5 {
+            "hostile"
+        } else if self.affinity <= -0.2 {
+            "resentful"
+        } else if self.trust < 0.25 {
+            "wary"
+        } else {
+            "neutral"
+        }
+    }
+
+    fn adjust(&mut self, stat: RelationStat, delta: f32) {
+        let v = self.stat_mut(stat);
+        *v += delta;
+        self.clamp();
+    }
+
+    fn set(&mut self, stat: RelationStat, value: f32) {
+        *self.stat_mut(stat) = value;
+        self.clamp();
+    }
+
+    fn stat_mut(&mut self, stat: RelationStat) -> &mut f32 {
+        match stat {
+            RelationStat::Affinity => &mut self.affinity,
+            RelationStat::Trust => &mut self.trust,
+            RelationStat::Fear => &mut self.fear,
+        }
+    }
+
+    fn clamp(&mut self) {
+        self.affinity = self.affinity.clamp(-1.0, 1.0);
+        self.trust = self.trust.clamp(0.0, 1.0);
+        self.fear = self.fear.clamp(0.0, 1.0);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelationStat {
+    Affinity,
+    Trust,
+    Fear,
+}
+
+impl RelationStat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "affinity" => Some(Self::Affinity),
+            "trust" => Some(Self::Trust),
+            "fear" => Some(Self::Fear),
+            _ => None,
+        }
+    }
+}
+
+/// Something that happened between the companion and a target.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum RelationshipEvent {
+    /// `by` revived the companion.
+    Revived { by: String },
+    /// `by` hit the companion with friendly fire.
+    FriendlyFire { by: String, damage: i32 },
+    /// `by` made a quest decision; `approval` in `-1.0..=1.0` is how much the companion agrees.
+    QuestChoice { by: String, approval: f32 },
+    /// `by` threatened or intimidated the companion.
+    Threatened { by: String, severity: f32 },
+    /// A dialogue line set a variable; only `rel.<target>.<stat>` keys are applied.
+    DialogueVar { key: String, value: String },
+}
+
+impl RelationshipEvent {
+    /// The event, if any, that `actor` carrying out `step` means for `companion`: being
+    /// revived, or being caught by cover fire from its own side. Call it before the step
+    /// is executed.
+    pub fn from_step(
+        w: &World,
+        actor: Entity,
+        step: &ActionStep,
+        companion: Entity,
+    ) -> Option<Self> {
+        if actor == companion {
+            return None;
+        }
+        match step {
+            ActionStep::Revive { ally_id } if *ally_id == companion => Some(Self::Revived {
+                by: target_id(w, actor),
+            }),
+            ActionStep::CoverFire {
+                target_id: hit,
+                duration,
+            } if *hit == companion && is_friendly(w, actor, companion) => {
+                Some(Self::FriendlyFire {
+                    by: target_id(w, actor),
+                    // Same damage `validate_and_execute` deals.
+                    damage: ((*duration * 5.0) as i32).max(1),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Relationship target id of an entity: [`PLAYER`] for the player's team, `npc:<id>`
+/// for everyone else.
+pub fn target_id(w: &World, e: Entity) -> String {
+    match w.team(e) {
+        Some(t) if t.id == 0 => PLAYER.to_string(),
+        _ => format!("npc:{e}"),
+    }
+}
+
+fn is_friendly(w: &World, a: Entity, b: Entity) -> bool {
+    match (w.team(a), w.team(b)) {
+        (Some(a), Some(b)) => a.id == b.id || a.id == 0 || b.id == 0,
+        _ => false,
+    }
+}
+
+/// How strongly each event moves a relationship.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RelationshipTuning {
+    pub revive_affinity: f32,
+    pub revive_trust: f32,
+    /// Affinity lost per point of friendly-fire damage, capped by `friendly_fire_max`.
+    pub friendly_fire_per_hp: f32,
+    pub friendly_fire_max: f32,
+    pub friendly_fire_trust: f32,
+    pub quest_affinity: f32,
+    pub quest_trust: f32,
+    pub threat_fear: f32,
+    pub threat_affinity: f32,
+}
+
+impl Default for RelationshipTuning {
+    fn default() -> Self {
+        Self {
+            revive_affinity: 0.15,
+            revive_trust: 0.1,
+            friendly_fire_per_hp: 0.01,
+            friendly_fire_max: 0.25,
+            friendly_fire_trust: 0.05,
+            quest_affinity: 0.2,
+            quest_trust: 0.1,
+            threat_fear: 0.3,
+            threat_affinity: 0.1,
+        }
+    }
+}
+
+/// All of a companion's relationships, keyed by target id.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Relationships {
+    pub entries: BTreeMap<String, Relationship>,
+}
+
+impl Relationships {
+    /// Current standing toward `target` (the neutral default if never met).
+    pub fn get(&self, target: &str) -> Relationship {
+        self.entries.get(target).cloned().unwrap_or_default()
+    }
+
+    pub fn entry(&mut self, target: &str) -> &mut Relationship {
+        self.entries.entry(target.to_string()).or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Apply an event; returns the target it changed, if any.
+    pub fn apply(
+        &mut self,
+        event: &RelationshipEvent,
+        tuning: &RelationshipTuning,
+    ) -> Option<String> {
+        let (target, changes): (&str, Vec<(RelationStat, f32)>) = match event {
+            RelationshipEvent::Revived { by } => (
+                by,
+                vec![
+                    (RelationStat::Affinity, tuning.revive_affinity),
+                    (RelationStat::Trust, tuning.revive_trust),
+                ],
+            ),
+            RelationshipEvent::FriendlyFire { by, damage } => {
+                let hit = ((*damage).max(0) as f32 * tuning.friendly_fire_per_hp)
+                    .min(tuning.friendly_fire_max);
+                (
+                    by,
+                    vec![
+                        (RelationStat::Affinity, -hit),
+                        (RelationStat::Trust, -tuning.friendly_fire_trust),
+                    ],
+                )
+            }
+            RelationshipEvent::QuestChoice { by, approval } => {
+                let a = approval.clamp(-1.0, 1.0);
+                (
+                    by,
+                    vec![
+                        (RelationStat::Affinity, a * tuning.quest_affinity),
+                        (RelationStat::Trust, a * tuning.quest_trust),
+                    ],
+                )
+            }
+            RelationshipEvent::Threatened { by, severity } => {
+                let s = severity.clamp(0.0, 1.0);
+                (
+                    by,
+                    vec![
+                        (RelationStat::Fear, s * tuning.threat_fear),
+                        (RelationStat::Affinity, -s * tuning.threat_affinity),
+                    ],
+                )
+            }
+            RelationshipEvent::DialogueVar { key, value } => {
+                return self.apply_dialogue_var(key, value);
+            }
+        };
+        let rel = self.entry(target);
+        for (stat, delta) in changes {
+            rel.adjust(stat, delta);
+        }
+        rel.interactions += 1;
+        Some(target.to_string())
+    }
+
+    /// Apply a dialogue `set_vars` entry of the form `rel.<target>.<stat>`.
+    ///
+    /// Values starting with `+` or `-` are deltas; anything else sets the stat outright.
+    /// Other keys are ignored so this can be fed every variable a line sets.
+    pub fn apply_dialogue_var(&mut self, key: &str, value: &str) -> Option<String> {
+        let rest = key.strip_prefix(DIALOGUE_VAR_PREFIX)?;
+        let (target, stat) = rest.rsplit_once('.')?;
+        let stat = RelationStat::parse(stat)?;
+        let value = value.trim();
+        let amount: f32 = value.parse().ok()?;
+        if target.is_empty() {
+            return None;
+        }
+        let rel = self.entry(target);
+        if value.starts_with('+') || value.starts_with('-') {
+            rel.adjust(stat, amount);
+        } else {
+            rel.set(stat, amount);
+        }
+        rel.interactions += 1;
+        Some(target.to_string())
+    }
+
+    /// Mood labels as dialogue variables (`rel.player = "friendly"`), so banter scripts
+    /// can branch with conditions like `? rel.player == friendly : goto n3`.
+    pub fn dialogue_vars(&self) -> Vec<(String, String)> {
+        self.entries
+            .iter()
+            .map(|(t, r)| (format!("{DIALOGUE_VAR_PREFIX}{t}"), r.label().to_string()))
+            .collect()
+    }
+
+    /// Relationships in the shape planners see in the `WorldSnapshot`.
+    pub fn to_views(&self) -> Vec<RelationshipView> {
+        self.entries
+            .iter()
+            .map(|(t, r)| RelationshipView {
+                target: t.clone(),
+                affinity: r.affinity,
+                trust: r.trust,
+                fear: r.fear,
+                label: r.label().to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "save")]
+impl Relationships {
+    /// Entries in the save-game layout.
+    pub fn to_save_states(&self) -> Vec<aw_save::RelationshipState> {
+        self.entries
+            .iter()
+            .map(|(t, r)| aw_save::RelationshipState {
+                target: t.clone(),
+                affinity: r.affinity,
+                trust: r.trust,
+                fear: r.fear,
+                interactions: r.interactions,
+            })
+            .collect()
+    }
+
+    pub fn from_save_states(states: &[aw_save::RelationshipState]) -> Self {
+        let entries = states
+            .iter()
+            .map(|s| {
+                let mut r = Relationship {
+                    affinity: s.affinity,
+                    trust: s.trust,
+                    fear: s.fear,
+                    interactions: s.interactions,
+                };
+                r.clamp();
+                (s.target.clone(), r)
+            })
+            .collect();
+        Self { entries }
+    }
+}
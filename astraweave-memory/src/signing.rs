@@ -0,0 +1,178 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("profile is not signed")]
+    Unsigned,
+    #[error("signing key {0} is not trusted")]
+    UntrustedKey(String),
+    #[error("malformed key or signature: {0}")]
+    Malformed(String),
+    #[error("signature does not match profile contents")]
+    Mismatch,
+}
+
+/// Short, stable identifier for a public key: first 8 bytes of SHA-256(pubkey), hex encoded.
+pub fn key_id_of(key: &VerifyingKey) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// Ed25519 key pair used by content tools to sign companion profiles and persona packs.
+#[derive(Clone)]
+pub struct ProfileSigningKey {
+    key: SigningKey,
+}
+
+impl ProfileSigningKey {
+    pub fn generate() -> Self {
+        Self::from_seed(rand::random())
+    }
+
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Parse a 32-byte secret seed stored as hex.
+    pub fn from_hex(s: &str) -> Result<Self, SignatureError> {
+        Ok(Self::from_seed(decode_fixed(s.trim())?))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.key.to_bytes())
+    }
+
+    pub fn key_id(&self) -> String {
+        key_id_of(&self.key.verifying_key())
+    }
+
+    pub fn public_hex(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Sign arbitrary bytes and return the hex encoded signature.
+    pub fn sign_bytes(&self, msg: &[u8]) -> String {
+        hex::encode(self.key.sign(msg).to_bytes())
+    }
+}
+
+/// Public keys that are allowed to sign profiles, indexed by key id.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrustedKeys {
+    /// key id -> hex encoded Ed25519 public key
+    keys: HashMap<String, String>,
+}
+
+impl TrustedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust a hex encoded public key. Returns its key id.
+    pub fn add_public_hex(&mut self, public_hex: &str) -> Result<String, SignatureError> {
+        let vk = parse_public(public_hex.trim())?;
+        let id = key_id_of(&vk);
+        self.keys.insert(id.clone(), hex::encode(vk.as_bytes()));
+        Ok(id)
+    }
+
+    pub fn add(&mut self, key: &ProfileSigningKey) -> String {
+        let id = key.key_id();
+        self.keys.insert(id.clone(), key.public_hex());
+        id
+    }
+
+    pub fn contains(&self, key_id: &str) -> bool {
+        self.keys.contains_key(key_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn save_to_file(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load_from_file(path: &str) -> anyhow::Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    /// Verify a hex signature over `msg` made by the key `key_id`.
+    pub fn verify_bytes(
+        &self,
+        key_id: &str,
+        msg: &[u8],
+        sig_hex: &str,
+    ) -> Result<(), SignatureError> {
+        let public = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| SignatureError::UntrustedKey(key_id.to_string()))?;
+        let vk = parse_public(public)?;
+        let sig = Signature::from_bytes(&decode_fixed(sig_hex)?);
+        vk.verify_strict(msg, &sig)
+            .map_err(|_| SignatureError::Mismatch)
+    }
+}
+
+fn parse_public(s: &str) -> Result<VerifyingKey, SignatureError> {
+    VerifyingKey::from_bytes(&decode_fixed(s)?)
+        .map_err(|e| SignatureError::Malformed(e.to_string()))
+}
+
+fn decode_fixed<const N: usize>(s: &str) -> Result<[u8; N], SignatureError> {
+    let bytes = hex::decode(s).map_err(|e| SignatureError::Malformed(e.to_string()))?;
+    bytes
+        .try_into()
+        .map_err(|_| SignatureError::Malformed(format!("expected {N} bytes")))
+}
+
+/// Serialize a JSON value with object keys sorted and no whitespace, so the
+/// signed bytes do not depend on struct field order or map iteration order.
+pub fn canonical_json(v: &serde_json::Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_canonical(v, &mut out);
+    out
+}
+
+fn write_canonical(v: &serde_json::Value, out: &mut Vec<u8>) {
+    match v {
+        serde_json::Value::Object(m) => {
+            let mut keys: Vec<&String> = m.keys().collect();
+            keys.sort();
+            out.push(b'{');
+            for (i, k) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                out.extend(serde_json::to_vec(k).unwrap());
+                out.push(b':');
+                write_canonical(&m[k], out);
+            }
+            out.push(b'}');
+        }
+        serde_json::Value::Array(a) => {
+            out.push(b'[');
+            for (i, x) in a.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(x, out);
+            }
+            out.push(b']');
+        }
+        other => out.extend(serde_json::to_vec(other).unwrap()),
+    }
+}
@@ -0,0 +1,1113 @@
+//! Recast-style navmesh baking.
+//!
+//! Pipeline: rasterize the input into a voxel heightfield, filter spans by slope, step
+//! height and ceiling clearance, erode by the agent radius, partition the walkable surface
+//! into monotone regions, trace and simplify each region's outline, then triangulate it.
+
+use crate::{
+    link_shared_edges, AreaType, AreaVolume, NavMesh, NavObstacle, NavPoly, NavTri, Triangle,
+};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Settings for [`NavMesh::bake_with`]. Distances are in world units.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BakeConfig {
+    /// Horizontal voxel size.
+    pub cell_size: f32,
+    /// Vertical voxel size.
+    pub cell_height: f32,
+    /// Minimum ceiling clearance for a surface to be walkable.
+    pub agent_height: f32,
+    /// Walkable area is shrunk by this much away from walls and ledges.
+    pub agent_radius: f32,
+    /// Highest step or ledge the agent can climb.
+    pub max_step: f32,
+    pub max_slope_deg: f32,
+    /// Connected walkable areas smaller than this many cells are dropped.
+    pub min_region_area: usize,
+    /// How far a simplified wall outline may stray from the voxel boundary.
+    pub max_edge_error: f32,
+    /// Volumes that tag the surface inside them; later entries win where they overlap.
+    pub areas: Vec<AreaVolume>,
+}
+
+impl Default for BakeConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 0.25,
+            cell_height: 0.1,
+            agent_height: 1.8,
+            agent_radius: 0.4,
+            max_step: 0.4,
+            max_slope_deg: 45.0,
+            min_region_area: 8,
+            max_edge_error: 0.3,
+            areas: vec![],
+        }
+    }
+}
+
+const MAX_HEIGHT: i32 = i32::MAX / 4;
+/// Region flag for spans in a tile's border: never outlined, but seen as a portal by
+/// neighbours. Each side gets its own id so outlines keep a vertex at the tile corners.
+const BORDER_REG: u32 = 0x8000_0000;
+const DX: [i32; 4] = [-1, 0, 1, 0];
+const DZ: [i32; 4] = [0, 1, 0, -1];
+
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    smin: i32,
+    smax: i32,
+    walkable: bool,
+}
+
+struct Heightfield {
+    width: usize,
+    depth: usize,
+    bmin: Vec3,
+    cs: f32,
+    ch: f32,
+    cols: Vec<Vec<Span>>,
+}
+
+impl Heightfield {
+    fn new(tris: &[Triangle], cs: f32, ch: f32) -> Option<Self> {
+        let (bmin, bmax) = bounds(tris)?;
+        let width = (((bmax.x - bmin.x) / cs).ceil() as usize).max(1);
+        let depth = (((bmax.z - bmin.z) / cs).ceil() as usize).max(1);
+        Some(Self::with_size(bmin, width, depth, cs, ch))
+    }
+
+    /// Heightfield covering a tile plus `border` cells on every side.
+    fn for_tile(tile: &TileBounds, border: usize, cs: f32, ch: f32) -> Self {
+        let width = ((tile.max.x - tile.min.x) / cs).round() as usize + border * 2;
+        let depth = ((tile.max.z - tile.min.z) / cs).round() as usize + border * 2;
+        let pad = border as f32 * cs;
+        let bmin = Vec3::new(tile.min.x - pad, tile.min.y, tile.min.z - pad);
+        Self::with_size(bmin, width.max(1), depth.max(1), cs, ch)
+    }
+
+    fn with_size(bmin: Vec3, width: usize, depth: usize, cs: f32, ch: f32) -> Self {
+        Self {
+            width,
+            depth,
+            bmin,
+            cs,
+            ch,
+            cols: vec![Vec::new(); width * depth],
+        }
+    }
+
+    fn col(&self, x: i32, z: i32) -> Option<&[Span]> {
+        if x < 0 || z < 0 || x as usize >= self.width || z as usize >= self.depth {
+            return None;
+        }
+        Some(&self.cols[z as usize * self.width + x as usize])
+    }
+
+    fn add_span(&mut self, x: usize, z: usize, mut s: Span, merge_thr: i32) {
+        let col = &mut self.cols[z * self.width + x];
+        let mut i = 0;
+        while i < col.len() {
+            let cur = col[i];
+            if cur.smax < s.smin {
+                i += 1;
+                continue;
+            }
+            if cur.smin > s.smax {
+                break;
+            }
+            // Overlap: the surface that ends up on top decides walkability.
+            s.walkable = if (s.smax - cur.smax).abs() <= merge_thr {
+                s.walkable || cur.walkable
+            } else if cur.smax > s.smax {
+                cur.walkable
+            } else {
+                s.walkable
+            };
+            s.smin = s.smin.min(cur.smin);
+            s.smax = s.smax.max(cur.smax);
+            col.remove(i);
+        }
+        col.insert(i, s);
+    }
+
+    fn rasterize(&mut self, t: &Triangle, walkable: bool, merge_thr: i32) {
+        let (cs, ch, bmin) = (self.cs, self.ch, self.bmin);
+        let tmin = t.a.min(t.b).min(t.c);
+        let tmax = t.a.max(t.b).max(t.c);
+        let cell = |v: f32, o: f32, n: usize| {
+            (((v - o) / cs).floor() as i64).clamp(0, n as i64 - 1) as usize
+        };
+        let (x0, x1) = (
+            cell(tmin.x, bmin.x, self.width),
+            cell(tmax.x, bmin.x, self.width),
+        );
+        let (z0, z1) = (
+            cell(tmin.z, bmin.z, self.depth),
+            cell(tmax.z, bmin.z, self.depth),
+        );
+        let tri = [t.a, t.b, t.c];
+        for z in z0..=z1 {
+            let cz = bmin.z + z as f32 * cs;
+            let row = clip(&clip(&tri, 2, cz, true), 2, cz + cs, false);
+            if row.len() < 3 {
+                continue;
+            }
+            for x in x0..=x1 {
+                let cx = bmin.x + x as f32 * cs;
+                let poly = clip(&clip(&row, 0, cx, true), 0, cx + cs, false);
+                if poly.len() < 3 {
+                    continue;
+                }
+                let (ymin, ymax) = poly
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+                        (lo.min(p.y), hi.max(p.y))
+                    });
+                let smin = ((ymin - bmin.y) / ch).floor() as i32;
+                let smax = (((ymax - bmin.y) / ch).ceil() as i32).max(smin + 1);
+                self.add_span(
+                    x,
+                    z,
+                    Span {
+                        smin,
+                        smax,
+                        walkable,
+                    },
+                    merge_thr,
+                );
+            }
+        }
+    }
+
+    /// Let agents step onto low obstacles (curbs, stair nosings) on top of walkable spans.
+    fn filter_low_hanging_obstacles(&mut self, climb: i32) {
+        for col in &mut self.cols {
+            let mut prev: Option<Span> = None;
+            for s in col.iter_mut() {
+                let was_walkable = s.walkable;
+                if let Some(p) = prev {
+                    if !s.walkable && p.walkable && s.smax - p.smax <= climb {
+                        s.walkable = true;
+                    }
+                }
+                prev = Some(Span {
+                    walkable: was_walkable,
+                    ..*s
+                });
+            }
+        }
+    }
+
+    /// Drop spans next to a drop deeper than `climb`, or on slopes steeper than `climb` per cell.
+    fn filter_ledges(&mut self, walkable_height: i32, climb: i32) {
+        let mut ledges = vec![];
+        for z in 0..self.depth as i32 {
+            for x in 0..self.width as i32 {
+                let col = self.col(x, z).unwrap();
+                for (i, s) in col.iter().enumerate() {
+                    if !s.walkable {
+                        continue;
+                    }
+                    let bot = s.smax;
+                    let top = col.get(i + 1).map_or(MAX_HEIGHT, |n| n.smin);
+                    let mut minh = MAX_HEIGHT;
+                    let (mut asmin, mut asmax) = (bot, bot);
+                    for d in 0..4 {
+                        let Some(ncol) = self.col(x + DX[d], z + DZ[d]) else {
+                            minh = minh.min(-climb - bot);
+                            continue;
+                        };
+                        // From minus infinity up to the first neighbour span.
+                        let ntop = ncol.first().map_or(MAX_HEIGHT, |n| n.smin);
+                        if top.min(ntop) - bot.max(-climb) > walkable_height {
+                            minh = minh.min(-climb - bot);
+                        }
+                        for (j, ns) in ncol.iter().enumerate() {
+                            let nbot = ns.smax;
+                            let ntop = ncol.get(j + 1).map_or(MAX_HEIGHT, |n| n.smin);
+                            if top.min(ntop) - bot.max(nbot) > walkable_height {
+                                minh = minh.min(nbot - bot);
+                                if (nbot - bot).abs() <= climb {
+                                    asmin = asmin.min(nbot);
+                                    asmax = asmax.max(nbot);
+                                }
+                            }
+                        }
+                    }
+                    if minh < -climb || asmax - asmin > climb {
+                        ledges.push((z as usize * self.width + x as usize, i));
+                    }
+                }
+            }
+        }
+        for (c, i) in ledges {
+            self.cols[c][i].walkable = false;
+        }
+    }
+
+    /// Drop spans without enough headroom below the next span up.
+    fn filter_low_height(&mut self, walkable_height: i32) {
+        for col in &mut self.cols {
+            for i in 0..col.len() {
+                let top = col.get(i + 1).map_or(MAX_HEIGHT, |n| n.smin);
+                if top - col[i].smax < walkable_height {
+                    col[i].walkable = false;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn bounds(tris: &[Triangle]) -> Option<(Vec3, Vec3)> {
+    let mut bmin = Vec3::splat(f32::INFINITY);
+    let mut bmax = Vec3::splat(f32::NEG_INFINITY);
+    for t in tris {
+        for v in [t.a, t.b, t.c] {
+            bmin = bmin.min(v);
+            bmax = bmax.max(v);
+        }
+    }
+    (bmin.is_finite() && bmax.is_finite()).then_some((bmin, bmax))
+}
+
+/// Sutherland-Hodgman clip of a convex polygon against an axis-aligned plane.
+fn clip(poly: &[Vec3], axis: usize, v: f32, keep_above: bool) -> Vec<Vec3> {
+    let d = |p: Vec3| if keep_above { p[axis] - v } else { v - p[axis] };
+    let mut out = Vec::with_capacity(poly.len() + 2);
+    for i in 0..poly.len() {
+        let (a, b) = (poly[i], poly[(i + 1) % poly.len()]);
+        let (da, db) = (d(a), d(b));
+        if da >= 0.0 {
+            out.push(a);
+        }
+        if (da >= 0.0) != (db >= 0.0) {
+            out.push(a + (b - a) * (da / (da - db)));
+        }
+    }
+    out
+}
+
+#[derive(Clone, Debug)]
+struct OpenSpan {
+    x: i32,
+    z: i32,
+    /// Floor height in cells.
+    y: i32,
+    /// Clearance above the floor in cells.
+    h: i32,
+    con: [Option<usize>; 4],
+    walkable: bool,
+    reg: u32,
+    area: AreaType,
+}
+
+impl OpenSpan {
+    /// World position of the floor at the cell center.
+    fn floor(&self, hf: &Heightfield) -> Vec3 {
+        hf.bmin
+            + Vec3::new(
+                (self.x as f32 + 0.5) * hf.cs,
+                self.y as f32 * hf.ch,
+                (self.z as f32 + 0.5) * hf.cs,
+            )
+    }
+}
+
+/// The open space above walkable spans, with links to neighbouring open spans.
+struct CompactHeightfield {
+    width: usize,
+    depth: usize,
+    cells: Vec<(usize, usize)>,
+    spans: Vec<OpenSpan>,
+}
+
+impl CompactHeightfield {
+    fn build(hf: &Heightfield, walkable_height: i32, climb: i32) -> Self {
+        let mut cells = Vec::with_capacity(hf.cols.len());
+        let mut spans = vec![];
+        for z in 0..hf.depth {
+            for x in 0..hf.width {
+                let col = &hf.cols[z * hf.width + x];
+                let start = spans.len();
+                for (i, s) in col.iter().enumerate() {
+                    if !s.walkable {
+                        continue;
+                    }
+                    let top = col.get(i + 1).map_or(MAX_HEIGHT, |n| n.smin);
+                    spans.push(OpenSpan {
+                        x: x as i32,
+                        z: z as i32,
+                        y: s.smax,
+                        h: top - s.smax,
+                        con: [None; 4],
+                        walkable: true,
+                        reg: 0,
+                        area: AreaType::Ground,
+                    });
+                }
+                cells.push((start, spans.len() - start));
+            }
+        }
+        let mut chf = Self {
+            width: hf.width,
+            depth: hf.depth,
+            cells,
+            spans,
+        };
+        for i in 0..chf.spans.len() {
+            let (x, z, y, h) = {
+                let s = &chf.spans[i];
+                (s.x, s.z, s.y, s.h)
+            };
+            for d in 0..4 {
+                let Some((start, count)) = chf.cell(x + DX[d], z + DZ[d]) else {
+                    continue;
+                };
+                chf.spans[i].con[d] = (start..start + count).find(|&k| {
+                    let n = &chf.spans[k];
+                    let bot = y.max(n.y);
+                    let top = (y + h).min(n.y + n.h);
+                    top - bot >= walkable_height && (n.y - y).abs() <= climb
+                });
+            }
+        }
+        chf
+    }
+
+    fn cell(&self, x: i32, z: i32) -> Option<(usize, usize)> {
+        if x < 0 || z < 0 || x as usize >= self.width || z as usize >= self.depth {
+            return None;
+        }
+        Some(self.cells[z as usize * self.width + x as usize])
+    }
+
+    /// Neighbour across `dir`, only if it is still walkable.
+    fn walkable_con(&self, i: usize, dir: usize) -> Option<usize> {
+        self.spans[i].con[dir].filter(|&n| self.spans[n].walkable)
+    }
+
+    /// Remove spans closer than `radius` cells to a wall or ledge (chamfer distance).
+    fn erode(&mut self, radius: i32) {
+        if radius <= 0 {
+            return;
+        }
+        let n = self.spans.len();
+        let mut dist = vec![i32::MAX; n];
+        let mut heap = BinaryHeap::new();
+        for (i, d) in dist.iter_mut().enumerate() {
+            if !self.spans[i].walkable || (0..4).any(|dir| self.walkable_con(i, dir).is_none()) {
+                *d = 0;
+                heap.push(Reverse((0, i)));
+            }
+        }
+        while let Some(Reverse((d, i))) = heap.pop() {
+            if d > dist[i] {
+                continue;
+            }
+            for dir in 0..4 {
+                let Some(a) = self.spans[i].con[dir] else {
+                    continue;
+                };
+                let mut relax = |k: usize, nd: i32| {
+                    if nd < dist[k] {
+                        dist[k] = nd;
+                        heap.push(Reverse((nd, k)));
+                    }
+                };
+                relax(a, d + 2);
+                if let Some(b) = self.spans[a].con[(dir + 1) % 4] {
+                    relax(b, d + 3);
+                }
+            }
+        }
+        for (s, d) in self.spans.iter_mut().zip(dist) {
+            if d < radius * 2 {
+                s.walkable = false;
+            }
+        }
+    }
+
+    /// Partition walkable spans into monotone (hole-free) regions, row by row.
+    fn build_regions(&mut self, min_region_area: usize) {
+        const NULL_NEI: u32 = u32::MAX;
+        #[derive(Clone, Copy, Default)]
+        struct Sweep {
+            id: u32,
+            ns: u32,
+            nei: u32,
+        }
+        let mut next_id = 1u32;
+        let mut prev_count: Vec<u32> = vec![0];
+        let mut sweeps: Vec<Sweep> = vec![];
+        for z in 0..self.depth as i32 {
+            prev_count.resize(next_id as usize, 0);
+            prev_count.iter_mut().for_each(|c| *c = 0);
+            sweeps.clear();
+            sweeps.push(Sweep::default());
+            for x in 0..self.width as i32 {
+                let (start, count) = self.cell(x, z).unwrap();
+                for i in start..start + count {
+                    if !self.spans[i].walkable || self.spans[i].reg & BORDER_REG != 0 {
+                        continue;
+                    }
+                    // Continue the sweep of the -x neighbour in this row, if any.
+                    let mut sid = self.same_area_reg(i, 0);
+                    if sid == 0 {
+                        sid = sweeps.len() as u32;
+                        sweeps.push(Sweep::default());
+                    }
+                    // Track which region of the previous row this sweep touches.
+                    let nr = self.same_area_reg(i, 3);
+                    if nr != 0 {
+                        let sw = &mut sweeps[sid as usize];
+                        if sw.nei == 0 || sw.nei == nr {
+                            sw.nei = nr;
+                            sw.ns += 1;
+                            prev_count[nr as usize] += 1;
+                        } else {
+                            sw.nei = NULL_NEI;
+                        }
+                    }
+                    self.spans[i].reg = sid;
+                }
+            }
+            // A sweep continues a previous region only if it is that region's sole continuation.
+            for sw in sweeps.iter_mut().skip(1) {
+                if sw.nei != NULL_NEI && sw.nei != 0 && prev_count[sw.nei as usize] == sw.ns {
+                    sw.id = sw.nei;
+                } else {
+                    sw.id = next_id;
+                    next_id += 1;
+                }
+            }
+            for x in 0..self.width as i32 {
+                let (start, count) = self.cell(x, z).unwrap();
+                for s in &mut self.spans[start..start + count] {
+                    if s.walkable && s.reg != 0 && s.reg & BORDER_REG == 0 {
+                        s.reg = sweeps[s.reg as usize].id;
+                    }
+                }
+            }
+        }
+        self.remove_small_islands(min_region_area);
+    }
+
+    fn remove_small_islands(&mut self, min_area: usize) {
+        let mut seen = vec![false; self.spans.len()];
+        for seed in 0..self.spans.len() {
+            if seen[seed] || self.spans[seed].reg == 0 {
+                continue;
+            }
+            let mut island = vec![seed];
+            seen[seed] = true;
+            let mut k = 0;
+            while k < island.len() {
+                let i = island[k];
+                k += 1;
+                for dir in 0..4 {
+                    if let Some(n) = self.walkable_con(i, dir) {
+                        if !seen[n] && self.spans[n].reg != 0 {
+                            seen[n] = true;
+                            island.push(n);
+                        }
+                    }
+                }
+            }
+            // Islands reaching into the border continue in the next tile; count those cells too.
+            if island.len() < min_area {
+                for i in island {
+                    if self.spans[i].reg & BORDER_REG == 0 {
+                        self.spans[i].reg = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Region of the neighbour across `dir`, treating border spans as unassigned.
+    fn inner_reg(&self, i: usize, dir: usize) -> u32 {
+        match self.region_con(i, dir) {
+            r if r & BORDER_REG != 0 => 0,
+            r => r,
+        }
+    }
+
+    /// Block spans whose floor lies inside any obstacle volume.
+    fn mark_obstacles(&mut self, obstacles: &[NavObstacle], hf: &Heightfield) {
+        if obstacles.is_empty() {
+            return;
+        }
+        for s in &mut self.spans {
+            let p = s.floor(hf);
+            if obstacles.iter().any(|o| o.contains(p)) {
+                s.walkable = false;
+            }
+        }
+    }
+
+    fn mark_areas(&mut self, areas: &[AreaVolume], hf: &Heightfield) {
+        for s in self.spans.iter_mut().filter(|s| s.walkable) {
+            let p = s.floor(hf);
+            if let Some(v) = areas.iter().rev().find(|v| v.shape.contains(p)) {
+                s.area = v.area;
+            }
+        }
+    }
+
+    /// Assign walkable spans within `border` cells of each edge to that side's border region.
+    fn mark_border(&mut self, border: usize) {
+        let b = border as i32;
+        let (w, d) = (self.width as i32, self.depth as i32);
+        for s in &mut self.spans {
+            let side = if s.x < b {
+                1
+            } else if s.x >= w - b {
+                2
+            } else if s.z < b {
+                3
+            } else if s.z >= d - b {
+                4
+            } else {
+                continue;
+            };
+            if s.walkable {
+                s.reg = BORDER_REG | side;
+            }
+        }
+    }
+
+    /// Like [`Self::inner_reg`], but only across spans of the same area type.
+    fn same_area_reg(&self, i: usize, dir: usize) -> u32 {
+        match self.walkable_con(i, dir) {
+            Some(n) if self.spans[n].area == self.spans[i].area => self.inner_reg(i, dir),
+            _ => 0,
+        }
+    }
+
+    fn region_con(&self, i: usize, dir: usize) -> u32 {
+        self.walkable_con(i, dir).map_or(0, |n| self.spans[n].reg)
+    }
+
+    /// Highest floor among the (up to) four spans sharing the corner ahead of `dir`.
+    fn corner_height(&self, i: usize, dir: usize) -> i32 {
+        let dirp = (dir + 1) % 4;
+        let mut h = self.spans[i].y;
+        for (d1, d2) in [(dir, dirp), (dirp, dir)] {
+            if let Some(a) = self.spans[i].con[d1] {
+                h = h.max(self.spans[a].y);
+                if let Some(b) = self.spans[a].con[d2] {
+                    h = h.max(self.spans[b].y);
+                }
+            }
+        }
+        h
+    }
+
+    fn build_contours(&self, max_error: f32) -> Vec<Contour> {
+        let mut flags: Vec<u8> = self
+            .spans
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if s.reg == 0 || s.reg & BORDER_REG != 0 {
+                    return 0;
+                }
+                (0..4)
+                    .filter(|&d| self.region_con(i, d) != s.reg)
+                    .fold(0u8, |f, d| f | (1 << d))
+            })
+            .collect();
+        let mut out = vec![];
+        for i in 0..self.spans.len() {
+            if flags[i] == 0 {
+                continue;
+            }
+            let dir = flags[i].trailing_zeros() as usize;
+            let raw = self.walk_contour(&mut flags, i, dir);
+            let mut verts = simplify_contour(&raw, max_error);
+            verts.dedup_by(|a, b| a[0] == b[0] && a[2] == b[2]);
+            while verts.len() > 1
+                && verts[0][0] == verts[verts.len() - 1][0]
+                && verts[0][2] == verts[verts.len() - 1][2]
+            {
+                verts.pop();
+            }
+            // Outlines walk clockwise in (x, z); anything else is a hole or degenerate.
+            if verts.len() >= 3 && signed_area2(&verts) < 0 {
+                out.push(Contour {
+                    reg: self.spans[i].reg,
+                    area: self.spans[i].area,
+                    verts,
+                });
+            }
+        }
+        out
+    }
+
+    fn walk_contour(&self, flags: &mut [u8], start: usize, start_dir: usize) -> Vec<RawVert> {
+        let (mut i, mut dir) = (start, start_dir);
+        let mut pts = vec![];
+        for _ in 0..(self.spans.len() * 8).max(64) {
+            if flags[i] & (1 << dir) != 0 {
+                let s = &self.spans[i];
+                let (px, pz) = match dir {
+                    0 => (s.x, s.z + 1),
+                    1 => (s.x + 1, s.z + 1),
+                    2 => (s.x + 1, s.z),
+                    _ => (s.x, s.z),
+                };
+                pts.push(RawVert {
+                    p: [px, self.corner_height(i, dir), pz],
+                    nei: self.region_con(i, dir),
+                });
+                flags[i] &= !(1 << dir);
+                dir = (dir + 1) % 4;
+            } else {
+                let Some(n) = self.walkable_con(i, dir) else {
+                    break;
+                };
+                i = n;
+                dir = (dir + 3) % 4;
+            }
+            if i == start && dir == start_dir {
+                break;
+            }
+        }
+        pts
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RawVert {
+    /// Corner position in cells (x, y, z).
+    p: [i32; 3],
+    /// Region on the other side of the edge ending at this corner (0 = wall).
+    nei: u32,
+}
+
+struct Contour {
+    reg: u32,
+    area: AreaType,
+    verts: Vec<[i32; 3]>,
+}
+
+/// Keep corners where the neighbouring region changes, then refine wall edges
+/// (Douglas-Peucker) until no raw corner is further than `max_error` cells away.
+fn simplify_contour(raw: &[RawVert], max_error: f32) -> Vec<[i32; 3]> {
+    let n = raw.len();
+    if n == 0 {
+        return vec![];
+    }
+    let mut simp: Vec<usize> = (0..n)
+        .filter(|&i| raw[i].nei != raw[(i + 1) % n].nei)
+        .collect();
+    if simp.is_empty() {
+        // No portals at all: seed with the lower-left and upper-right corners.
+        let ll = (0..n).min_by_key(|&i| (raw[i].p[0], raw[i].p[2])).unwrap();
+        let ur = (0..n).max_by_key(|&i| (raw[i].p[0], raw[i].p[2])).unwrap();
+        simp = if ll < ur { vec![ll, ur] } else { vec![ur, ll] };
+        if ll == ur {
+            simp.truncate(1);
+        }
+    }
+    let max_err2 = max_error * max_error;
+    let mut k = 0;
+    while k < simp.len() {
+        let (ai, bi) = (simp[k], simp[(k + 1) % simp.len()]);
+        let mut worst: Option<(f32, usize)> = None;
+        let mut ci = (ai + 1) % n;
+        if raw[ci].nei == 0 {
+            while ci != bi {
+                let d = dist_pt_seg_xz(raw[ci].p, raw[ai].p, raw[bi].p);
+                if worst.is_none_or(|(w, _)| d > w) {
+                    worst = Some((d, ci));
+                }
+                ci = (ci + 1) % n;
+            }
+        }
+        match worst {
+            Some((d, ci)) if d > max_err2 || simp.len() < 3 => simp.insert(k + 1, ci),
+            _ => k += 1,
+        }
+    }
+    simp.into_iter().map(|i| raw[i].p).collect()
+}
+
+fn dist_pt_seg_xz(p: [i32; 3], a: [i32; 3], b: [i32; 3]) -> f32 {
+    let (px, pz) = (p[0] as f32, p[2] as f32);
+    let (ax, az) = (a[0] as f32, a[2] as f32);
+    let (dx, dz) = (b[0] as f32 - ax, b[2] as f32 - az);
+    let len2 = dx * dx + dz * dz;
+    let t = if len2 > 0.0 {
+        (((px - ax) * dx + (pz - az) * dz) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (ex, ez) = (ax + t * dx - px, az + t * dz - pz);
+    ex * ex + ez * ez
+}
+
+/// Twice the signed (x, z) area of a polygon.
+fn signed_area2(v: &[[i32; 3]]) -> i64 {
+    (0..v.len())
+        .map(|i| {
+            let (a, b) = (v[i], v[(i + 1) % v.len()]);
+            a[0] as i64 * b[2] as i64 - b[0] as i64 * a[2] as i64
+        })
+        .sum()
+}
+
+fn cross_xz(a: [i32; 3], b: [i32; 3], c: [i32; 3]) -> i64 {
+    let (ux, uz) = ((b[0] - a[0]) as i64, (b[2] - a[2]) as i64);
+    let (vx, vz) = ((c[0] - a[0]) as i64, (c[2] - a[2]) as i64);
+    ux * vz - uz * vx
+}
+
+fn in_tri_xz(p: [i32; 3], a: [i32; 3], b: [i32; 3], c: [i32; 3], sign: i64) -> bool {
+    cross_xz(a, b, p) * sign >= 0 && cross_xz(b, c, p) * sign >= 0 && cross_xz(c, a, p) * sign >= 0
+}
+
+/// Ear-clip a simple polygon; returns index triples into `v`.
+fn triangulate(v: &[[i32; 3]]) -> Vec<[usize; 3]> {
+    let sign = signed_area2(v).signum();
+    let mut idx: Vec<usize> = (0..v.len()).collect();
+    let mut out = vec![];
+    while idx.len() > 3 {
+        let n = idx.len();
+        let is_ear = |k: usize| {
+            let (a, b, c) = (idx[(k + n - 1) % n], idx[k], idx[(k + 1) % n]);
+            if cross_xz(v[a], v[b], v[c]) * sign <= 0 {
+                return false;
+            }
+            idx.iter().all(|&o| {
+                o == a
+                    || o == b
+                    || o == c
+                    || v[o] == v[a]
+                    || v[o] == v[b]
+                    || v[o] == v[c]
+                    || !in_tri_xz(v[o], v[a], v[b], v[c], sign)
+            })
+        };
+        // Fall back to the flattest corner if no proper ear exists (degenerate outline).
+        let k = (0..n).find(|&k| is_ear(k)).unwrap_or_else(|| {
+            (0..n)
+                .min_by_key(|&k| {
+                    cross_xz(v[idx[(k + n - 1) % n]], v[idx[k]], v[idx[(k + 1) % n]]).abs()
+                })
+                .unwrap()
+        });
+        out.push([idx[(k + n - 1) % n], idx[k], idx[(k + 1) % n]]);
+        idx.remove(k);
+    }
+    if idx.len() == 3 {
+        out.push([idx[0], idx[1], idx[2]]);
+    }
+    out
+}
+
+/// The (x, z) area one tile covers; `min.y` is the floor of the shared height grid so
+/// neighbouring tiles quantize heights identically.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TileBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+/// Cells baked around a tile so erosion and ledge filtering match across tile edges.
+pub(crate) fn tile_border(cfg: &BakeConfig) -> usize {
+    (cfg.agent_radius / cfg.cell_size).ceil() as usize + 3
+}
+
+pub(crate) fn bake(tris: &[Triangle], cfg: &BakeConfig) -> NavMesh {
+    let hf = Heightfield::new(tris, cfg.cell_size, cfg.cell_height);
+    build(tris, cfg, hf, 0, &[], &cfg.areas)
+}
+
+/// Bake one tile. Edges on the tile boundary stay straight so they line up with the
+/// neighbouring tile's outline.
+pub(crate) fn bake_tile(
+    tris: &[Triangle],
+    cfg: &BakeConfig,
+    tile: &TileBounds,
+    obstacles: &[NavObstacle],
+    areas: &[AreaVolume],
+) -> NavMesh {
+    let border = tile_border(cfg);
+    let hf = Heightfield::for_tile(tile, border, cfg.cell_size, cfg.cell_height);
+    build(tris, cfg, Some(hf), border, obstacles, areas)
+}
+
+fn build(
+    tris: &[Triangle],
+    cfg: &BakeConfig,
+    hf: Option<Heightfield>,
+    border: usize,
+    obstacles: &[NavObstacle],
+    areas: &[AreaVolume],
+) -> NavMesh {
+    let mut nav = NavMesh::empty(cfg.max_step, cfg.max_slope_deg);
+    let Some(mut hf) = hf else {
+        return nav;
+    };
+    let walkable_height = (cfg.agent_height / cfg.cell_height).ceil() as i32;
+    let climb = (cfg.max_step / cfg.cell_height).floor() as i32;
+    let radius = (cfg.agent_radius / cfg.cell_size).ceil() as i32;
+    let min_up = cfg.max_slope_deg.to_radians().cos();
+
+    for t in tris {
+        let n = (t.b - t.a).cross(t.c - t.a).normalize_or_zero();
+        // Accept either winding; vertical and degenerate triangles are never walkable.
+        let walkable = n.y.abs() >= min_up && n != Vec3::ZERO;
+        hf.rasterize(t, walkable, climb);
+    }
+    hf.filter_low_hanging_obstacles(climb);
+    hf.filter_ledges(walkable_height, climb);
+    hf.filter_low_height(walkable_height);
+
+    let mut chf = CompactHeightfield::build(&hf, walkable_height, climb);
+    chf.mark_obstacles(obstacles, &hf);
+    chf.erode(radius);
+    chf.mark_areas(areas, &hf);
+    chf.mark_border(border);
+    chf.build_regions(cfg.min_region_area);
+    let contours = chf.build_contours(cfg.max_edge_error / cfg.cell_size);
+
+    let to_world = |p: [i32; 3]| {
+        hf.bmin
+            + Vec3::new(
+                p[0] as f32 * hf.cs,
+                p[1] as f32 * hf.ch,
+                p[2] as f32 * hf.cs,
+            )
+    };
+    for c in contours {
+        let poly = nav.polys.len();
+        let mut tri_ids = vec![];
+        for [a, b, cc] in triangulate(&c.verts) {
+            let mut verts = [
+                to_world(c.verts[a]),
+                to_world(c.verts[b]),
+                to_world(c.verts[cc]),
+            ];
+            let mut normal = (verts[1] - verts[0]).cross(verts[2] - verts[0]);
+            if normal.length_squared() < 1e-12 {
+                continue;
+            }
+            if normal.y < 0.0 {
+                verts.swap(1, 2);
+                normal = -normal;
+            }
+            tri_ids.push(nav.tris.len());
+            nav.tris.push(NavTri {
+                idx: nav.tris.len(),
+                poly,
+                area: c.area,
+                verts,
+                normal: normal.normalize(),
+                center: (verts[0] + verts[1] + verts[2]) / 3.0,
+                neighbors: vec![],
+            });
+        }
+        nav.polys.push(NavPoly {
+            verts: c.verts.iter().map(|&p| to_world(p)).collect(),
+            tris: tri_ids,
+            region: c.reg,
+            area: c.area,
+        });
+    }
+    link_shared_edges(&mut nav.tris);
+    nav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn cuboid(min: Vec3, max: Vec3) -> Vec<Triangle> {
+        let mut out = quad(min.x, min.z, max.x, max.z, max.y);
+        let c = |x: f32, y: f32, z: f32| vec3(x, y, z);
+        let faces = [
+            [
+                c(min.x, min.y, min.z),
+                c(max.x, min.y, min.z),
+                c(max.x, max.y, min.z),
+                c(min.x, max.y, min.z),
+            ],
+            [
+                c(min.x, min.y, max.z),
+                c(max.x, min.y, max.z),
+                c(max.x, max.y, max.z),
+                c(min.x, max.y, max.z),
+            ],
+            [
+                c(min.x, min.y, min.z),
+                c(min.x, min.y, max.z),
+                c(min.x, max.y, max.z),
+                c(min.x, max.y, min.z),
+            ],
+            [
+                c(max.x, min.y, min.z),
+                c(max.x, min.y, max.z),
+                c(max.x, max.y, max.z),
+                c(max.x, max.y, min.z),
+            ],
+        ];
+        for f in faces {
+            out.push(Triangle {
+                a: f[0],
+                b: f[1],
+                c: f[2],
+            });
+            out.push(Triangle {
+                a: f[0],
+                b: f[2],
+                c: f[3],
+            });
+        }
+        out
+    }
+
+    fn area_xz(nav: &NavMesh) -> f32 {
+        nav.tris
+            .iter()
+            .map(|t| {
+                let (u, v) = (t.verts[1] - t.verts[0], t.verts[2] - t.verts[0]);
+                (u.x * v.z - u.z * v.x).abs() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn flat_floor_is_eroded_and_simplified() {
+        let nav = NavMesh::bake_with(&quad(-5.0, -5.0, 5.0, 5.0, 0.0), &BakeConfig::default());
+        assert!(
+            !nav.tris.is_empty() && nav.tris.len() <= 8,
+            "{} tris",
+            nav.tris.len()
+        );
+        let area = area_xz(&nav);
+        assert!(area > 60.0 && area < 100.0, "area {area}");
+        for t in &nav.tris {
+            for v in t.verts {
+                assert!(v.x.abs() < 4.7 && v.z.abs() < 4.7, "vertex {v} not eroded");
+            }
+        }
+        assert!(!nav
+            .find_path(vec3(-3.5, 0.0, -3.5), vec3(3.5, 0.0, 3.5))
+            .is_empty());
+    }
+
+    #[test]
+    fn low_ceiling_blocks_floor_underneath() {
+        let mut geo = quad(-5.0, -5.0, 5.0, 5.0, 0.0);
+        geo.extend(cuboid(vec3(0.0, 1.0, -5.0), vec3(5.0, 1.2, 5.0)));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        for t in nav.tris.iter().filter(|t| t.center.y < 0.6) {
+            assert!(
+                t.center.x < 0.0,
+                "floor under the slab is walkable at {}",
+                t.center
+            );
+        }
+        // The slab's roof is its own walkable area.
+        assert!(nav
+            .tris
+            .iter()
+            .any(|t| t.center.y > 1.1 && t.center.x > 0.5));
+    }
+
+    #[test]
+    fn overhang_keeps_both_layers() {
+        let mut geo = quad(-5.0, -5.0, 5.0, 5.0, 0.0);
+        geo.extend(cuboid(vec3(-2.0, 2.5, -2.0), vec3(2.0, 2.7, 2.0)));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let covers = |y0: f32, y1: f32| {
+            nav.polys.iter().any(|p| {
+                let (mn, mx) = p.verts.iter().fold(
+                    (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                    |(a, b), v| (a.min(*v), b.max(*v)),
+                );
+                mn.y >= y0 && mx.y <= y1 && mn.x <= 0.0 && mx.x >= 0.0 && mn.z <= 0.0 && mx.z >= 0.0
+            })
+        };
+        assert!(covers(-0.1, 0.5), "floor under the platform is missing");
+        for (i, t) in nav.tris.iter().enumerate() {
+            assert_eq!(t.idx, i);
+            assert!(nav.polys[t.poly].tris.contains(&i));
+        }
+        assert!(covers(2.5, 3.0), "platform top is missing");
+        let path = nav.find_path(vec3(-4.0, 0.0, 0.0), vec3(4.0, 0.0, 0.0));
+        assert!(!path.is_empty() && path.iter().all(|p| p.y < 1.0));
+    }
+
+    fn stairs(rise: f32) -> Vec<Triangle> {
+        let mut geo = quad(-4.0, -2.0, 0.0, 2.0, 0.0);
+        for k in 0..6 {
+            let x = k as f32 * 0.5;
+            geo.extend(cuboid(
+                vec3(x, 0.0, -2.0),
+                vec3(x + 0.5, rise * (k + 1) as f32, 2.0),
+            ));
+        }
+        geo.extend(cuboid(vec3(3.0, 0.0, -2.0), vec3(7.0, rise * 6.0, 2.0)));
+        geo
+    }
+
+    #[test]
+    fn climbable_stairs_connect_levels() {
+        let nav = NavMesh::bake_with(&stairs(0.2), &BakeConfig::default());
+        let path = nav.find_path(vec3(-3.0, 0.0, 0.0), vec3(6.0, 1.2, 0.0));
+        assert!(!path.is_empty());
+        assert!((path.last().unwrap().y - 1.2).abs() < 0.3);
+    }
+
+    #[test]
+    fn tall_steps_are_ledges() {
+        let nav = NavMesh::bake_with(&stairs(0.8), &BakeConfig::default());
+        assert!(nav
+            .find_path(vec3(-3.0, 0.0, 0.0), vec3(6.0, 4.8, 0.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn ramp_onto_plateau_is_walkable() {
+        let mut geo = quad(-4.0, -4.0, 4.0, 4.0, 0.0);
+        geo.push(Triangle {
+            a: vec3(1.5, 0.0, -1.0),
+            b: vec3(4.0, 0.6, 1.0),
+            c: vec3(4.0, 0.6, -1.0),
+        });
+        geo.push(Triangle {
+            a: vec3(1.5, 0.0, -1.0),
+            b: vec3(1.5, 0.0, 1.0),
+            c: vec3(4.0, 0.6, 1.0),
+        });
+        geo.extend(quad(4.0, -1.0, 6.5, 1.0, 0.6));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let path = nav.find_path(vec3(-3.0, 0.0, -3.0), vec3(5.8, 0.6, 0.0));
+        assert!(!path.is_empty());
+        assert!(path.last().unwrap().y > 0.5);
+    }
+}
@@ -0,0 +1,804 @@
+//! Crowds: agents that follow navmesh paths, keep clear of each other with optimal
+//! reciprocal collision avoidance (ORCA), keep a little personal space and can hold
+//! formation slots around a leader.
+//!
+//! Paths are planned through a [`PathQueue`], so a crowd replanning at once spreads the
+//! searches over several updates; agents keep following their old path meanwhile.
+//!
+//! The crowd integrates agent positions itself, so it works standalone. When characters are
+//! moved by physics instead, feed [`CrowdAgent::vel`] to the character controller and sync
+//! the resulting positions back with [`Crowd::set_position`] before the next update.
+
+use crate::path::{closest_point_on_tri, height_at};
+use crate::{LinkKind, NavMesh, NavPath, PathFilter, PathHandle, PathQueue, Traversal};
+use glam::{vec2, vec3, Vec2, Vec3};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CrowdAgentId(pub u32);
+
+#[derive(Clone, Debug)]
+pub struct CrowdAgentParams {
+    pub radius: f32,
+    pub max_speed: f32,
+    pub max_accel: f32,
+    /// Seconds ahead other agents are avoided; longer is smoother but more timid.
+    pub time_horizon: f32,
+    /// Agents farther than this are ignored.
+    pub neighbor_dist: f32,
+    /// Gap kept from other agents on top of both radii.
+    pub separation: f32,
+    /// Leaving the current path leg by more than this triggers a replan.
+    pub replan_dist: f32,
+    pub filter: PathFilter,
+}
+
+impl Default for CrowdAgentParams {
+    fn default() -> Self {
+        Self {
+            radius: 0.4,
+            max_speed: 3.5,
+            max_accel: 10.0,
+            time_horizon: 2.0,
+            neighbor_dist: 6.0,
+            separation: 0.2,
+            replan_dist: 2.0,
+            filter: PathFilter::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrowdAgentState {
+    /// No target.
+    Idle,
+    Moving,
+    Arrived,
+}
+
+#[derive(Clone, Debug)]
+pub struct CrowdAgent {
+    pub params: CrowdAgentParams,
+    pub pos: Vec3,
+    /// Velocity chosen by the last update, after avoidance.
+    pub vel: Vec3,
+    /// Velocity the agent wanted before avoidance.
+    pub preferred_vel: Vec3,
+    pub state: CrowdAgentState,
+    target: Option<Vec3>,
+    path: Option<NavPath>,
+    /// Index of the path point being walked to.
+    corner: usize,
+    tri: Option<usize>,
+    heading: Vec2,
+    replan: bool,
+}
+
+impl CrowdAgent {
+    pub fn target(&self) -> Option<Vec3> {
+        self.target
+    }
+
+    pub fn path(&self) -> Option<&NavPath> {
+        self.path.as_ref()
+    }
+
+    pub fn next_corner(&self) -> Option<Vec3> {
+        self.path.as_ref()?.points.get(self.corner).copied()
+    }
+
+    /// How the agent is getting to its next corner, so the controller can jump or climb.
+    pub fn traversal(&self) -> Traversal {
+        self.leg(self.corner)
+    }
+
+    /// Traversal of the leg ending at path point `i`.
+    fn leg(&self, i: usize) -> Traversal {
+        self.path
+            .as_ref()
+            .and_then(|p| p.segments.get(i.wrapping_sub(1)))
+            .map_or(Traversal::Walk, |s| s.traversal)
+    }
+
+    fn xz(&self) -> Vec2 {
+        vec2(self.pos.x, self.pos.z)
+    }
+
+    /// Follow the path: advance past reached corners, skip corners the agent can see past
+    /// and pick a preferred velocity toward the next one.
+    fn steer(&mut self, nav: &NavMesh) {
+        self.preferred_vel = Vec3::ZERO;
+        let Some(points) = self.path.as_ref().map(|p| p.points.clone()) else {
+            return;
+        };
+        let last = points.len() - 1;
+        let reach = self.params.radius * 0.5;
+        while self.corner <= last && flat(points[self.corner] - self.pos).length() < reach {
+            // Teleports happen on arrival at the entrance; other links are walked.
+            if let Traversal::Link {
+                kind: LinkKind::Teleport,
+                ..
+            } = self.leg(self.corner + 1)
+            {
+                self.pos = points[self.corner + 1];
+                self.tri = None;
+                self.corner += 1;
+            }
+            self.corner += 1;
+        }
+        if self.corner > last {
+            self.state = CrowdAgentState::Arrived;
+            return;
+        }
+
+        let walking = |a: &Self, i: usize| a.leg(i) == Traversal::Walk;
+        if walking(self, self.corner) {
+            let (a, b) = (points[self.corner - 1], points[self.corner]);
+            if distance_to_leg(self.pos, a, b) > self.params.replan_dist {
+                self.replan = true;
+            }
+        }
+        // Corridor optimization: head straight for a later corner when nothing is in the way.
+        for _ in 0..2 {
+            let next = self.corner + 1;
+            if next > last || !walking(self, self.corner) || !walking(self, next) {
+                break;
+            }
+            match self.tri {
+                Some(t) if straight_walkable(nav, t, self.pos, points[next]) => self.corner = next,
+                _ => break,
+            }
+        }
+
+        let to = flat(points[self.corner] - self.pos);
+        let mut speed = self.params.max_speed;
+        if self.corner == last {
+            // Ease in over the distance it takes to stop.
+            let stop =
+                self.params.max_speed * self.params.max_speed / (2.0 * self.params.max_accel);
+            speed *= (to.length() / stop.max(1e-3)).min(1.0);
+        }
+        self.preferred_vel = to.normalize_or_zero() * speed;
+    }
+}
+
+struct Formation {
+    leader: CrowdAgentId,
+    /// Each member with its slot: x to the leader's right, y ahead of it.
+    slots: Vec<(CrowdAgentId, Vec2)>,
+}
+
+/// Agents steering over one navmesh.
+pub struct Crowd {
+    agents: BTreeMap<CrowdAgentId, CrowdAgent>,
+    formations: Vec<Formation>,
+    next_id: u32,
+    revision: Option<u64>,
+    paths: PathQueue,
+    /// Path requests still being searched, by agent.
+    pending: BTreeMap<CrowdAgentId, PathHandle>,
+}
+
+impl Default for Crowd {
+    fn default() -> Self {
+        Self {
+            agents: BTreeMap::new(),
+            formations: vec![],
+            next_id: 0,
+            revision: None,
+            paths: PathQueue::new(512),
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl Crowd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The queue agents plan through.
+    pub fn path_queue(&self) -> &PathQueue {
+        &self.paths
+    }
+
+    /// Set the queue's `budget` to bound path search per update.
+    pub fn path_queue_mut(&mut self) -> &mut PathQueue {
+        &mut self.paths
+    }
+
+    pub fn add_agent(&mut self, pos: Vec3, params: CrowdAgentParams) -> CrowdAgentId {
+        self.next_id += 1;
+        let id = CrowdAgentId(self.next_id);
+        self.agents.insert(
+            id,
+            CrowdAgent {
+                params,
+                pos,
+                vel: Vec3::ZERO,
+                preferred_vel: Vec3::ZERO,
+                state: CrowdAgentState::Idle,
+                target: None,
+                path: None,
+                corner: 1,
+                tri: None,
+                heading: Vec2::Y,
+                replan: false,
+            },
+        );
+        id
+    }
+
+    pub fn remove_agent(&mut self, id: CrowdAgentId) -> Option<CrowdAgent> {
+        self.pending.remove(&id);
+        self.formations.retain(|f| f.leader != id);
+        for f in &mut self.formations {
+            f.slots.retain(|(m, _)| *m != id);
+        }
+        self.agents.remove(&id)
+    }
+
+    pub fn agent(&self, id: CrowdAgentId) -> Option<&CrowdAgent> {
+        self.agents.get(&id)
+    }
+
+    pub fn agent_mut(&mut self, id: CrowdAgentId) -> Option<&mut CrowdAgent> {
+        self.agents.get_mut(&id)
+    }
+
+    pub fn agents(&self) -> impl Iterator<Item = (CrowdAgentId, &CrowdAgent)> {
+        self.agents.iter().map(|(id, a)| (*id, a))
+    }
+
+    /// Walk to `target`; the path is requested on the next update.
+    pub fn set_target(&mut self, id: CrowdAgentId, target: Vec3) {
+        self.pending.remove(&id);
+        if let Some(a) = self.agents.get_mut(&id) {
+            a.target = Some(target);
+            a.path = None;
+            a.replan = true;
+        }
+    }
+
+    pub fn clear_target(&mut self, id: CrowdAgentId) {
+        self.pending.remove(&id);
+        if let Some(a) = self.agents.get_mut(&id) {
+            a.target = None;
+            a.path = None;
+            a.state = CrowdAgentState::Idle;
+        }
+    }
+
+    /// Where the character actually is, e.g. after the physics step.
+    pub fn set_position(&mut self, id: CrowdAgentId, pos: Vec3) {
+        if let Some(a) = self.agents.get_mut(&id) {
+            if a.pos.distance_squared(pos) > 1e-8 {
+                a.pos = pos;
+                a.tri = None;
+            }
+        }
+    }
+
+    /// Have `members` follow `leader`, each keeping to its slot (x to the leader's right,
+    /// y ahead of it). Replaces any formation the leader already had.
+    pub fn set_formation(&mut self, leader: CrowdAgentId, members: Vec<(CrowdAgentId, Vec2)>) {
+        self.formations.retain(|f| f.leader != leader);
+        self.formations.push(Formation {
+            leader,
+            slots: members,
+        });
+    }
+
+    pub fn clear_formation(&mut self, leader: CrowdAgentId) {
+        self.formations.retain(|f| f.leader != leader);
+    }
+
+    /// Plan, steer, avoid and move every agent by `dt` seconds.
+    pub fn update(&mut self, nav: &NavMesh, dt: f32) {
+        if self.revision != Some(nav.revision()) {
+            self.revision = Some(nav.revision());
+            for a in self.agents.values_mut() {
+                a.tri = None;
+                a.replan |= a.target.is_some();
+            }
+        }
+        for a in self.agents.values_mut() {
+            if a.tri.is_none() {
+                a.tri = nav.locate(a.pos).map(|(t, _)| t);
+            }
+        }
+        self.update_formations(nav);
+        self.plan(nav);
+        for a in self.agents.values_mut() {
+            a.steer(nav);
+        }
+
+        let snapshot: Vec<(CrowdAgentId, Vec2, Vec2, f32)> = self
+            .agents
+            .iter()
+            .map(|(id, a)| (*id, a.xz(), vec2(a.vel.x, a.vel.z), a.params.radius))
+            .collect();
+        for a in self.agents.values_mut() {
+            let (me, my_vel, p) = (a.xz(), vec2(a.vel.x, a.vel.z), &a.params);
+            let near: Vec<_> = snapshot
+                .iter()
+                .filter(|(_, pos, _, _)| {
+                    let d = pos.distance(me);
+                    d > 1e-4 && d < p.neighbor_dist
+                })
+                .collect();
+
+            let mut pref = vec2(a.preferred_vel.x, a.preferred_vel.z);
+            for (_, pos, _, r) in &near {
+                let (off, range) = (me - *pos, p.radius + r + p.separation);
+                let d = off.length();
+                if d < range {
+                    pref += off / d * (1.0 - d / range) * p.max_speed;
+                }
+            }
+
+            let mut lines = vec![];
+            for (_, pos, vel, r) in &near {
+                lines.push(orca_line(
+                    *pos - me,
+                    my_vel - *vel,
+                    p.radius + r,
+                    p.time_horizon,
+                    dt,
+                    my_vel,
+                ));
+            }
+            // Limit acceleration before avoidance, so the velocity ORCA picks is the one
+            // actually used.
+            let pref = my_vel + (pref - my_vel).clamp_length_max(p.max_accel * dt);
+            let mut v = Vec2::ZERO;
+            let failed = linear_program2(&lines, p.max_speed, pref, false, &mut v);
+            if failed < lines.len() {
+                linear_program3(&lines, failed, p.max_speed, &mut v);
+            }
+
+            a.vel = vec3(v.x, 0.0, v.y);
+            if v.length_squared() > 0.01 {
+                a.heading = v.normalize();
+            }
+            let (tri, pos) = constrain(nav, a.tri, a.pos + a.vel * dt);
+            a.tri = tri;
+            a.pos = pos;
+        }
+    }
+
+    /// Queue paths for agents that need one, run the queue and hand out finished paths.
+    fn plan(&mut self, nav: &NavMesh) {
+        for (id, a) in self.agents.iter_mut().filter(|(_, a)| a.replan) {
+            a.replan = false;
+            // A request already under way is for the current target; new targets drop it.
+            if self.pending.contains_key(id) {
+                continue;
+            }
+            let Some(target) = a.target else {
+                continue;
+            };
+            let handle = self.paths.request(a.pos, target, a.params.filter.clone());
+            self.pending.insert(*id, handle);
+            a.state = CrowdAgentState::Moving;
+        }
+        if self.pending.is_empty() {
+            return;
+        }
+        self.paths.tick(nav);
+        let agents = &mut self.agents;
+        self.pending.retain(|id, handle| {
+            let Some(path) = handle.try_take() else {
+                return true;
+            };
+            if let Some(a) = agents.get_mut(id) {
+                a.corner = 1;
+                a.state = match path {
+                    Some(_) => CrowdAgentState::Moving,
+                    None => CrowdAgentState::Idle,
+                };
+                a.path = path;
+            }
+            false
+        });
+    }
+
+    fn update_formations(&mut self, nav: &NavMesh) {
+        for f in &self.formations {
+            let Some(leader) = self.agents.get(&f.leader) else {
+                continue;
+            };
+            let (origin, ahead) = (leader.pos, leader.heading);
+            let right = vec2(-ahead.y, ahead.x);
+            for (id, off) in &f.slots {
+                let slot = right * off.x + ahead * off.y;
+                let slot = origin + vec3(slot.x, 0.0, slot.y);
+                let Some(a) = self.agents.get_mut(id) else {
+                    continue;
+                };
+                if a.target.is_none_or(|t| flat(t - slot).length() > 0.5) {
+                    if let Some((_, on_mesh)) = nav.locate(slot) {
+                        a.target = Some(on_mesh);
+                        a.replan = true;
+                        self.pending.remove(id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn flat(v: Vec3) -> Vec3 {
+    vec3(v.x, 0.0, v.z)
+}
+
+fn distance_to_leg(p: Vec3, a: Vec3, b: Vec3) -> f32 {
+    let (p, a, b) = (vec2(p.x, p.z), vec2(a.x, a.z), vec2(b.x, b.z));
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_squared().max(1e-6)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+/// The triangle and its neighbours, and theirs.
+fn ring(nav: &NavMesh, tri: usize) -> impl Iterator<Item = usize> + '_ {
+    let near = &nav.tris[tri].neighbors;
+    std::iter::once(tri).chain(near.iter().copied()).chain(
+        near.iter()
+            .flat_map(|&n| nav.tris[n].neighbors.iter().copied()),
+    )
+}
+
+/// Keep a moved agent on the mesh: on the surface if `to` is over nearby triangles,
+/// otherwise slid back to their closest point.
+fn constrain(nav: &NavMesh, tri: Option<usize>, to: Vec3) -> (Option<usize>, Vec3) {
+    let Some(tri) = tri.filter(|&t| t < nav.tris.len()) else {
+        return (None, to);
+    };
+    if let Some((t, y)) = ring(nav, tri).find_map(|t| Some((t, height_at(&nav.tris[t], to)?))) {
+        return (Some(t), vec3(to.x, y, to.z));
+    }
+    ring(nav, tri)
+        .map(|t| (t, closest_point_on_tri(to, nav.tris[t].verts)))
+        .min_by(|(_, a), (_, b)| a.distance_squared(to).total_cmp(&b.distance_squared(to)))
+        .map_or((Some(tri), to), |(t, p)| (Some(t), p))
+}
+
+/// Whether the straight line from `a` (on triangle `tri`) to `b` stays on the mesh.
+fn straight_walkable(nav: &NavMesh, tri: usize, a: Vec3, b: Vec3) -> bool {
+    const STEP: f32 = 0.25;
+    let steps = (flat(b - a).length() / STEP).ceil().max(1.0) as usize;
+    let mut cur = tri;
+    for k in 1..=steps {
+        let p = a.lerp(b, k as f32 / steps as f32);
+        if height_at(&nav.tris[cur], p).is_some() {
+            continue;
+        }
+        match ring(nav, cur).find(|&t| height_at(&nav.tris[t], p).is_some()) {
+            Some(t) => cur = t,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// A half-plane of allowed velocities: everything left of `dir` through `point`.
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    point: Vec2,
+    dir: Vec2,
+}
+
+fn det(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// ORCA half-plane for one neighbour at `rel_pos` (other minus self) with `rel_vel`
+/// (self minus other). Each side takes half the avoidance (van den Berg et al. 2011).
+fn orca_line(rel_pos: Vec2, rel_vel: Vec2, radius: f32, tau: f32, dt: f32, vel: Vec2) -> Line {
+    let dist_sq = rel_pos.length_squared();
+    let r_sq = radius * radius;
+    let (dir, u);
+    if dist_sq > r_sq {
+        // No collision yet: avoid the velocity obstacle truncated at `tau`.
+        let w = rel_vel - rel_pos / tau;
+        let w_len_sq = w.length_squared();
+        let dot = w.dot(rel_pos);
+        if dot < 0.0 && dot * dot > r_sq * w_len_sq {
+            // Closest to the cut-off circle.
+            let w_len = w_len_sq.sqrt();
+            let unit = w / w_len;
+            dir = vec2(unit.y, -unit.x);
+            u = unit * (radius / tau - w_len);
+        } else {
+            // Closest to one of the legs.
+            let leg = (dist_sq - r_sq).sqrt();
+            dir = if det(rel_pos, w) > 0.0 {
+                vec2(
+                    rel_pos.x * leg - rel_pos.y * radius,
+                    rel_pos.x * radius + rel_pos.y * leg,
+                ) / dist_sq
+            } else {
+                -vec2(
+                    rel_pos.x * leg + rel_pos.y * radius,
+                    -rel_pos.x * radius + rel_pos.y * leg,
+                ) / dist_sq
+            };
+            u = dir * rel_vel.dot(dir) - rel_vel;
+        }
+    } else {
+        // Already overlapping: separate within this step.
+        let w = rel_vel - rel_pos / dt;
+        let w_len = w.length().max(1e-6);
+        let unit = w / w_len;
+        dir = vec2(unit.y, -unit.x);
+        u = unit * (radius / dt - w_len);
+    }
+    Line {
+        point: vel + u * 0.5,
+        dir,
+    }
+}
+
+const LP_EPS: f32 = 1e-5;
+
+/// Best velocity on line `i` within `radius` that satisfies lines `0..i`.
+fn linear_program1(
+    lines: &[Line],
+    i: usize,
+    radius: f32,
+    opt: Vec2,
+    direction: bool,
+    out: &mut Vec2,
+) -> bool {
+    let l = lines[i];
+    let dot = l.point.dot(l.dir);
+    let disc = dot * dot + radius * radius - l.point.length_squared();
+    if disc < 0.0 {
+        return false;
+    }
+    let sq = disc.sqrt();
+    let (mut left, mut right) = (-dot - sq, -dot + sq);
+    for o in &lines[..i] {
+        let denom = det(l.dir, o.dir);
+        let numer = det(o.dir, l.point - o.point);
+        if denom.abs() <= LP_EPS {
+            if numer < 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = numer / denom;
+        if denom >= 0.0 {
+            right = right.min(t);
+        } else {
+            left = left.max(t);
+        }
+        if left > right {
+            return false;
+        }
+    }
+    let t = if direction {
+        if opt.dot(l.dir) > 0.0 {
+            right
+        } else {
+            left
+        }
+    } else {
+        l.dir.dot(opt - l.point).clamp(left, right)
+    };
+    *out = l.point + l.dir * t;
+    true
+}
+
+/// Velocity closest to `opt` (or furthest along it, if `direction`) within `radius` and
+/// all half-planes. Returns the index of the first line that could not be satisfied,
+/// or `lines.len()`.
+fn linear_program2(
+    lines: &[Line],
+    radius: f32,
+    opt: Vec2,
+    direction: bool,
+    out: &mut Vec2,
+) -> usize {
+    *out = if direction {
+        opt * radius
+    } else {
+        opt.clamp_length_max(radius)
+    };
+    for i in 0..lines.len() {
+        if det(lines[i].dir, lines[i].point - *out) > 0.0 {
+            let prev = *out;
+            if !linear_program1(lines, i, radius, opt, direction, out) {
+                *out = prev;
+                return i;
+            }
+        }
+    }
+    lines.len()
+}
+
+/// When the half-planes leave no room, the velocity that violates them least.
+fn linear_program3(lines: &[Line], begin: usize, radius: f32, out: &mut Vec2) {
+    let mut distance = 0.0;
+    for i in begin..lines.len() {
+        if det(lines[i].dir, lines[i].point - *out) <= distance {
+            continue;
+        }
+        let projected: Vec<Line> = lines[..i]
+            .iter()
+            .filter_map(|o| {
+                let denom = det(lines[i].dir, o.dir);
+                let point = if denom.abs() <= LP_EPS {
+                    if lines[i].dir.dot(o.dir) > 0.0 {
+                        return None;
+                    }
+                    (lines[i].point + o.point) * 0.5
+                } else {
+                    lines[i].point + lines[i].dir * (det(o.dir, lines[i].point - o.point) / denom)
+                };
+                Some(Line {
+                    point,
+                    dir: (o.dir - lines[i].dir).normalize_or_zero(),
+                })
+            })
+            .collect();
+        let prev = *out;
+        let opt = vec2(-lines[i].dir.y, lines[i].dir.x);
+        if linear_program2(&projected, radius, opt, true, out) < projected.len() {
+            *out = prev;
+        }
+        distance = det(lines[i].dir, lines[i].point - *out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BakeConfig, Triangle};
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn on_mesh(nav: &NavMesh, p: Vec3) -> bool {
+        nav.tris.iter().any(|t| height_at(t, p).is_some())
+    }
+
+    /// Run until everyone has arrived, returning the closest any two agents came.
+    fn simulate(crowd: &mut Crowd, nav: &NavMesh, seconds: f32) -> f32 {
+        let mut closest = f32::INFINITY;
+        for _ in 0..(seconds / 0.05) as usize {
+            crowd.update(nav, 0.05);
+            let pos: Vec<Vec3> = crowd.agents().map(|(_, a)| a.pos).collect();
+            for (i, a) in pos.iter().enumerate() {
+                assert!(on_mesh(nav, *a), "{a} left the mesh");
+                for b in &pos[i + 1..] {
+                    closest = closest.min(flat(*a - *b).length());
+                }
+            }
+            if crowd
+                .agents()
+                .all(|(_, a)| a.state != CrowdAgentState::Moving)
+            {
+                return closest;
+            }
+        }
+        panic!("crowd did not settle");
+    }
+
+    #[test]
+    fn head_on_agents_pass_without_touching() {
+        let nav = NavMesh::bake_with(&quad(-10.0, -5.0, 10.0, 5.0, 0.0), &BakeConfig::default());
+        let mut crowd = Crowd::new();
+        let a = crowd.add_agent(vec3(-6.0, 0.0, 0.0), CrowdAgentParams::default());
+        let b = crowd.add_agent(vec3(6.0, 0.0, 0.05), CrowdAgentParams::default());
+        crowd.set_target(a, vec3(6.0, 0.0, 0.0));
+        crowd.set_target(b, vec3(-6.0, 0.0, 0.0));
+        let closest = simulate(&mut crowd, &nav, 20.0);
+        assert!(closest > 0.75, "agents came within {closest}");
+        for (id, goal) in [(a, 6.0), (b, -6.0)] {
+            let agent = crowd.agent(id).unwrap();
+            assert_eq!(agent.state, CrowdAgentState::Arrived);
+            assert!((agent.pos.x - goal).abs() < 0.5, "{}", agent.pos);
+        }
+    }
+
+    #[test]
+    fn market_crowd_crosses_around_a_stall() {
+        // Square market with a stall in the middle; shoppers cross to the opposite side.
+        let mut geo = quad(-10.0, -10.0, 10.0, -2.0, 0.0);
+        geo.extend(quad(-10.0, 2.0, 10.0, 10.0, 0.0));
+        geo.extend(quad(-10.0, -2.0, -2.0, 2.0, 0.0));
+        geo.extend(quad(2.0, -2.0, 10.0, 2.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let mut crowd = Crowd::new();
+        let mut goals = vec![];
+        for k in 0..8 {
+            let angle = k as f32 * std::f32::consts::TAU / 8.0;
+            let at = vec3(angle.cos(), 0.0, angle.sin()) * 7.5;
+            let id = crowd.add_agent(at, CrowdAgentParams::default());
+            crowd.set_target(id, -at);
+            goals.push((id, -at));
+        }
+        let closest = simulate(&mut crowd, &nav, 60.0);
+        assert!(closest > 0.7, "shoppers came within {closest}");
+        for (id, goal) in goals {
+            let agent = crowd.agent(id).unwrap();
+            assert!(
+                flat(agent.pos - goal).length() < 1.0,
+                "{} vs {goal}",
+                agent.pos
+            );
+        }
+    }
+
+    #[test]
+    fn replans_are_spread_over_updates_by_the_path_budget() {
+        let mut geo = quad(-10.0, -10.0, 10.0, -2.0, 0.0);
+        geo.extend(quad(-10.0, 2.0, 10.0, 10.0, 0.0));
+        geo.extend(quad(-10.0, -2.0, -2.0, 2.0, 0.0));
+        geo.extend(quad(2.0, -2.0, 10.0, 2.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let mut crowd = Crowd::new();
+        crowd.path_queue_mut().budget = 4;
+        let mut goals = vec![];
+        for k in 0..6 {
+            let at = vec3(-8.0 + k as f32 * 3.0, 0.0, -8.0);
+            let id = crowd.add_agent(at, CrowdAgentParams::default());
+            crowd.set_target(id, -at);
+            goals.push((id, -at));
+        }
+
+        crowd.update(&nav, 0.05);
+        assert!(crowd.path_queue().pending() > 0);
+        let waiting = crowd.agents().filter(|(_, a)| a.path().is_none()).count();
+        assert!(waiting > 0);
+        // Still waiting for a path counts as on the way.
+        assert!(crowd
+            .agents()
+            .all(|(_, a)| a.state == CrowdAgentState::Moving));
+
+        simulate(&mut crowd, &nav, 60.0);
+        assert_eq!(crowd.path_queue().pending(), 0);
+        assert!(crowd.path_queue().stats().completed >= goals.len());
+        for (id, goal) in goals {
+            let p = crowd.agent(id).unwrap().pos;
+            assert!(flat(p - goal).length() < 1.0, "{p} vs {goal}");
+        }
+    }
+
+    #[test]
+    fn followers_hold_their_formation_slots() {
+        let nav = NavMesh::bake_with(&quad(-15.0, -6.0, 15.0, 6.0, 0.0), &BakeConfig::default());
+        let mut crowd = Crowd::new();
+        let leader = crowd.add_agent(vec3(-10.0, 0.0, 0.0), CrowdAgentParams::default());
+        let left = crowd.add_agent(vec3(-12.0, 0.0, -2.0), CrowdAgentParams::default());
+        let right = crowd.add_agent(vec3(-12.0, 0.0, 2.0), CrowdAgentParams::default());
+        let slots = [(left, vec2(-1.5, -1.5)), (right, vec2(1.5, -1.5))];
+        crowd.set_formation(leader, slots.to_vec());
+        crowd.set_target(leader, vec3(10.0, 0.0, 0.0));
+        simulate(&mut crowd, &nav, 30.0);
+        for _ in 0..40 {
+            crowd.update(&nav, 0.05);
+        }
+
+        let lead = crowd.agent(leader).unwrap();
+        assert!((lead.pos.x - 10.0).abs() < 0.5);
+        // Heading +x with y up: the right is +z, behind is -x.
+        for (id, expect) in [(left, vec3(8.5, 0.0, -1.5)), (right, vec3(8.5, 0.0, 1.5))] {
+            let p = crowd.agent(id).unwrap().pos;
+            assert!(flat(p - expect).length() < 1.0, "{p} vs {expect}");
+        }
+    }
+}
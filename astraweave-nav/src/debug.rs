@@ -0,0 +1,485 @@
+//! Debug views of a baked mesh: OBJ and glTF exports coloured by region, island or area
+//! type, and a connectivity report the asset pipeline can fail a level on.
+
+use crate::{AreaType, Capabilities, LinkKind, NavMesh};
+use base64::Engine;
+use glam::Vec3;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Write;
+
+/// What the polygons of an exported mesh are coloured by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugColoring {
+    #[default]
+    Region,
+    Island,
+    Area,
+}
+
+impl DebugColoring {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Region => "region",
+            Self::Island => "island",
+            Self::Area => "area type",
+        }
+    }
+}
+
+/// Walkable triangles that connect to each other but to nothing else, except by links.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Island {
+    pub tris: Vec<usize>,
+    /// Walkable surface area.
+    pub area: f32,
+    /// Area-weighted centre.
+    pub center: Vec3,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectivityReport {
+    pub islands: Vec<Island>,
+    /// Islands no spawn point can get to, walking or over links.
+    pub disconnected: Vec<usize>,
+    /// Spawn points too far from the mesh to stand on it.
+    pub off_mesh: Vec<usize>,
+    /// Spawn points on the mesh that cannot get to and back from the first one on it.
+    pub unreachable: Vec<usize>,
+}
+
+impl ConnectivityReport {
+    pub fn is_ok(&self) -> bool {
+        self.disconnected.is_empty() && self.off_mesh.is_empty() && self.unreachable.is_empty()
+    }
+}
+
+impl fmt::Display for ConnectivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} island(s)", self.islands.len())?;
+        for &i in &self.disconnected {
+            let isl = &self.islands[i];
+            write!(
+                f,
+                "\n  island {i} ({} tris, {:.1} m2 around {:.1?}) is cut off from every spawn",
+                isl.tris.len(),
+                isl.area,
+                isl.center.to_array()
+            )?;
+        }
+        for &s in &self.off_mesh {
+            write!(f, "\n  spawn {s} is off the mesh")?;
+        }
+        for &s in &self.unreachable {
+            write!(f, "\n  spawn {s} cannot reach the main spawn")?;
+        }
+        Ok(())
+    }
+}
+
+impl NavMesh {
+    /// Island of each triangle. Triangles share an island if one can walk between them;
+    /// off-mesh links do not join islands.
+    pub fn islands(&self) -> Vec<usize> {
+        let mut island = vec![usize::MAX; self.tris.len()];
+        let mut next = 0;
+        for seed in 0..self.tris.len() {
+            if island[seed] != usize::MAX {
+                continue;
+            }
+            island[seed] = next;
+            let mut stack = vec![seed];
+            while let Some(t) = stack.pop() {
+                for &nb in &self.tris[t].neighbors {
+                    if island[nb] == usize::MAX {
+                        island[nb] = next;
+                        stack.push(nb);
+                    }
+                }
+            }
+            next += 1;
+        }
+        island
+    }
+
+    /// Check that every spawn point is within `max_snap` of the mesh and can get to and
+    /// back from the first one, and that no island is cut off from all of them. Links
+    /// count if `caps` allows them. Without spawn points the largest island stands in.
+    pub fn connectivity_report(
+        &self,
+        spawns: &[Vec3],
+        caps: Capabilities,
+        max_snap: f32,
+    ) -> ConnectivityReport {
+        let island_of = self.islands();
+        let count = island_of.iter().map(|&i| i + 1).max().unwrap_or(0);
+        let mut islands = vec![
+            Island {
+                tris: vec![],
+                area: 0.0,
+                center: Vec3::ZERO,
+            };
+            count
+        ];
+        for (t, &i) in island_of.iter().enumerate() {
+            let [a, b, c] = self.tris[t].verts;
+            let area = (b - a).cross(c - a).length() * 0.5;
+            let isl = &mut islands[i];
+            isl.tris.push(t);
+            isl.area += area;
+            isl.center += self.tris[t].center * area;
+        }
+        for isl in &mut islands {
+            if isl.area > 0.0 {
+                isl.center /= isl.area;
+            }
+        }
+
+        let mut edges = vec![BTreeSet::new(); count];
+        for (from, links) in self.link_edges(caps) {
+            for l in links {
+                edges[island_of[from]].insert(island_of[l.to_tri]);
+            }
+        }
+        let reach = |from: usize| {
+            let mut seen = vec![false; count];
+            seen[from] = true;
+            let mut stack = vec![from];
+            while let Some(i) = stack.pop() {
+                for &j in &edges[i] {
+                    if !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+            seen
+        };
+
+        let mut report = ConnectivityReport::default();
+        let mut placed = vec![];
+        for (s, &p) in spawns.iter().enumerate() {
+            match self.locate(p).filter(|(_, q)| q.distance(p) <= max_snap) {
+                Some((t, _)) => placed.push((s, island_of[t])),
+                None => report.off_mesh.push(s),
+            }
+        }
+        if placed.is_empty() && spawns.is_empty() && count > 0 {
+            let largest = (0..count)
+                .max_by(|&a, &b| islands[a].area.total_cmp(&islands[b].area))
+                .unwrap();
+            placed.push((usize::MAX, largest));
+        }
+
+        let mut covered = vec![false; count];
+        if let Some(&(_, main)) = placed.first() {
+            let from_main = reach(main);
+            for &(s, i) in &placed {
+                let seen = reach(i);
+                if !from_main[i] || !seen[main] {
+                    report.unreachable.push(s);
+                }
+                for (c, r) in covered.iter_mut().zip(seen) {
+                    *c |= r;
+                }
+            }
+        }
+        if !spawns.is_empty() && placed.is_empty() {
+            // Every spawn is off the mesh; that is the problem to report, not the islands.
+            covered.fill(true);
+        }
+        report.disconnected = (0..count).filter(|&i| !covered[i]).collect();
+        report.islands = islands;
+        report
+    }
+
+    /// Wavefront OBJ of the mesh with per-vertex colours (`v x y z r g b`), plus the
+    /// off-mesh links as lines coloured by kind.
+    pub fn export_obj(&self, coloring: DebugColoring) -> String {
+        let colors = self.tri_colors(coloring);
+        let mut out = format!(
+            "# AstraWeave navmesh: {} triangles, {} links, coloured by {}\no navmesh\n",
+            self.tris.len(),
+            self.links.len(),
+            coloring.name()
+        );
+        let vertex = |out: &mut String, p: Vec3, c: [f32; 3]| {
+            let _ = writeln!(
+                out,
+                "v {} {} {} {:.3} {:.3} {:.3}",
+                p.x, p.y, p.z, c[0], c[1], c[2]
+            );
+        };
+        for (t, c) in self.tris.iter().zip(&colors) {
+            for &v in &t.verts {
+                vertex(&mut out, v, *c);
+            }
+        }
+        for i in 0..self.tris.len() {
+            let _ = writeln!(out, "f {} {} {}", 3 * i + 1, 3 * i + 2, 3 * i + 3);
+        }
+        if !self.links.is_empty() {
+            out.push_str("o links\n");
+            for l in &self.links {
+                vertex(&mut out, l.link.start, link_color(l.link.kind));
+                vertex(&mut out, l.link.end, link_color(l.link.kind));
+            }
+            let base = 3 * self.tris.len();
+            for i in 0..self.links.len() {
+                let _ = writeln!(out, "l {} {}", base + 2 * i + 1, base + 2 * i + 2);
+            }
+        }
+        out
+    }
+
+    /// Self-contained glTF 2.0 (`.gltf` with an embedded buffer) of the mesh with vertex
+    /// colours, plus the off-mesh links as a line primitive coloured by kind.
+    pub fn export_gltf(&self, coloring: DebugColoring) -> String {
+        let colors = self.tri_colors(coloring);
+        let mut gltf = GltfBuffers::default();
+        let mut primitives = vec![];
+        if !self.tris.is_empty() {
+            let pos: Vec<Vec3> = self.tris.iter().flat_map(|t| t.verts).collect();
+            let col: Vec<[f32; 3]> = colors.iter().flat_map(|&c| [c; 3]).collect();
+            primitives.push(json!({
+                "attributes": { "POSITION": gltf.positions(&pos), "COLOR_0": gltf.colors(&col) },
+                "mode": 4,
+            }));
+        }
+        if !self.links.is_empty() {
+            let pos: Vec<Vec3> = self
+                .links
+                .iter()
+                .flat_map(|l| [l.link.start, l.link.end])
+                .collect();
+            let col: Vec<[f32; 3]> = self
+                .links
+                .iter()
+                .flat_map(|l| [link_color(l.link.kind); 2])
+                .collect();
+            primitives.push(json!({
+                "attributes": { "POSITION": gltf.positions(&pos), "COLOR_0": gltf.colors(&col) },
+                "mode": 1,
+            }));
+        }
+        let (nodes, meshes) = if primitives.is_empty() {
+            (json!([]), json!([]))
+        } else {
+            (
+                json!([{ "name": "navmesh", "mesh": 0 }]),
+                json!([{ "name": "navmesh", "primitives": primitives }]),
+            )
+        };
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&gltf.bin)
+        );
+        let doc = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": format!("astraweave-nav (coloured by {})", coloring.name()),
+            },
+            "scene": 0,
+            "scenes": [{ "nodes": if primitives.is_empty() { json!([]) } else { json!([0]) } }],
+            "nodes": nodes,
+            "meshes": meshes,
+            "accessors": gltf.accessors,
+            "bufferViews": gltf.views,
+            "buffers": [{ "byteLength": gltf.bin.len(), "uri": uri }],
+        });
+        serde_json::to_string_pretty(&doc).expect("glTF document always encodes")
+    }
+
+    fn tri_colors(&self, coloring: DebugColoring) -> Vec<[f32; 3]> {
+        match coloring {
+            DebugColoring::Region => self
+                .tris
+                .iter()
+                .map(|t| palette(self.polys.get(t.poly).map_or(0, |p| p.region as usize)))
+                .collect(),
+            DebugColoring::Island => self.islands().into_iter().map(palette).collect(),
+            DebugColoring::Area => self.tris.iter().map(|t| area_color(t.area)).collect(),
+        }
+    }
+}
+
+/// Buffer, views and accessors of a glTF document being built.
+#[derive(Default)]
+struct GltfBuffers {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GltfBuffers {
+    fn push(&mut self, data: &[[f32; 3]], bounds: Option<([f32; 3], [f32; 3])>) -> usize {
+        let offset = self.bin.len();
+        for v in data {
+            for x in v {
+                self.bin.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.bin.len() - offset,
+            "target": 34962,
+        }));
+        let mut accessor = json!({
+            "bufferView": self.views.len() - 1,
+            "componentType": 5126,
+            "count": data.len(),
+            "type": "VEC3",
+        });
+        if let Some((min, max)) = bounds {
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn positions(&mut self, pos: &[Vec3]) -> usize {
+        let min = pos.iter().fold(Vec3::INFINITY, |m, &p| m.min(p));
+        let max = pos.iter().fold(Vec3::NEG_INFINITY, |m, &p| m.max(p));
+        let data: Vec<[f32; 3]> = pos.iter().map(|p| p.to_array()).collect();
+        self.push(&data, Some((min.to_array(), max.to_array())))
+    }
+
+    fn colors(&mut self, col: &[[f32; 3]]) -> usize {
+        self.push(col, None)
+    }
+}
+
+/// Distinct colour for an id: golden-ratio steps around the hue circle.
+fn palette(i: usize) -> [f32; 3] {
+    let h = (i as f32 * 0.618_034).fract() * 6.0;
+    let (s, v) = (0.65, 0.95);
+    let f = h.fract();
+    let (p, q, t) = (v * (1.0 - s), v * (1.0 - s * f), v * (1.0 - s * (1.0 - f)));
+    match h as u32 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
+
+fn area_color(area: AreaType) -> [f32; 3] {
+    match area {
+        AreaType::Ground => [0.55, 0.75, 0.55],
+        AreaType::Water => [0.25, 0.5, 0.95],
+        AreaType::Hazard => [0.95, 0.3, 0.2],
+        AreaType::Cover => [0.6, 0.45, 0.8],
+    }
+}
+
+fn link_color(kind: LinkKind) -> [f32; 3] {
+    match kind {
+        LinkKind::Jump => [1.0, 0.85, 0.1],
+        LinkKind::Climb => [1.0, 0.5, 0.0],
+        LinkKind::Teleport => [0.9, 0.2, 0.9],
+        LinkKind::Bridge => [0.1, 0.9, 0.9],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaVolume, BakeConfig, NavObstacle, OffMeshLink, Triangle};
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    /// Main floor, a ledge reachable only by a one-way jump down, and a sealed-off room.
+    fn level() -> NavMesh {
+        let mut geo = quad(-8.0, -4.0, 8.0, 4.0, 0.0);
+        geo.extend(quad(-8.0, 8.0, 0.0, 12.0, 3.0));
+        geo.extend(quad(20.0, 0.0, 24.0, 4.0, 0.0));
+        let cfg = BakeConfig {
+            areas: vec![AreaVolume {
+                shape: NavObstacle::Box {
+                    min: vec3(2.0, -1.0, -4.0),
+                    max: vec3(8.0, 1.0, 4.0),
+                },
+                area: AreaType::Water,
+            }],
+            ..BakeConfig::default()
+        };
+        let mut nav = NavMesh::bake_with(&geo, &cfg);
+        nav.add_link(OffMeshLink::jump(
+            vec3(-4.0, 3.0, 9.0),
+            vec3(-4.0, 0.0, 3.0),
+        ));
+        nav
+    }
+
+    #[test]
+    fn report_finds_cut_off_islands_and_spawns() {
+        let nav = level();
+        let report = nav.connectivity_report(&[vec3(0.0, 0.0, 0.0)], Capabilities::ALL, 1.0);
+        assert_eq!(report.islands.len(), 3);
+        // The ledge is only left by jumping down; the room cannot be reached at all.
+        assert_eq!(report.disconnected.len(), 2);
+        assert!(!report.is_ok());
+        assert!(report.to_string().contains("cut off"));
+
+        let spawns = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(-4.0, 3.0, 10.0),
+            vec3(22.0, 0.0, 2.0),
+            vec3(50.0, 0.0, 0.0),
+        ];
+        let report = nav.connectivity_report(&spawns, Capabilities::ALL, 1.0);
+        assert!(report.disconnected.is_empty());
+        assert_eq!(report.off_mesh, vec![3]);
+        // Spawn 1 can jump down but never climb back; spawn 2 is stranded.
+        assert_eq!(report.unreachable, vec![1, 2]);
+
+        let floor = nav.connectivity_report(
+            &[vec3(-6.0, 0.0, -2.0), vec3(6.0, 0.0, 2.0)],
+            Capabilities::ALL,
+            1.0,
+        );
+        assert!(floor.unreachable.is_empty() && floor.off_mesh.is_empty());
+    }
+
+    #[test]
+    fn exports_colour_every_triangle_and_link() {
+        let nav = level();
+        let obj = nav.export_obj(DebugColoring::Area);
+        let verts = obj.lines().filter(|l| l.starts_with("v ")).count();
+        assert_eq!(verts, nav.tris.len() * 3 + 2);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("l ")).count(), 1);
+        assert!(obj.contains(" 0.250 0.500 0.950"), "water is blue");
+
+        let gltf: Value = serde_json::from_str(&nav.export_gltf(DebugColoring::Island)).unwrap();
+        let prims = gltf["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(prims.len(), 2);
+        let count = gltf["accessors"][0]["count"].as_u64().unwrap();
+        assert_eq!(count as usize, nav.tris.len() * 3);
+        let len = gltf["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+        let uri = gltf["buffers"][0]["uri"].as_str().unwrap();
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(uri.split_once(',').unwrap().1)
+            .unwrap();
+        assert_eq!(data.len(), len);
+        assert_eq!(len, (nav.tris.len() * 3 + 2) * 2 * 12);
+    }
+}
@@ -0,0 +1,323 @@
+//! Polygon clusters over the navmesh. A path is first routed over the much smaller cluster
+//! graph, and the triangle search then only looks inside the clusters along that route.
+
+use crate::path::TriSearch;
+use crate::{Capabilities, NavMesh, NavPath, PathFilter};
+use glam::Vec3;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap};
+
+#[derive(Clone, Copy, Debug)]
+struct ClusterEdge {
+    to: usize,
+    cost: f32,
+    /// Capabilities needed when the edge is an off-mesh link.
+    requires: Capabilities,
+}
+
+/// Connected groups of triangles, one or more per `cluster_size` square of the floor plan.
+#[derive(Clone, Debug)]
+pub struct NavClusters {
+    cluster_size: f32,
+    revision: u64,
+    /// Cluster of each triangle.
+    pub cluster_of: Vec<usize>,
+    centers: Vec<Vec3>,
+    edges: Vec<Vec<ClusterEdge>>,
+}
+
+impl NavClusters {
+    pub fn build(nav: &NavMesh, cluster_size: f32) -> Self {
+        let cell = |p: Vec3| {
+            (
+                (p.x / cluster_size).floor() as i32,
+                (p.z / cluster_size).floor() as i32,
+            )
+        };
+        // Flood fill within each cell, so floors stacked over the same square or pieces
+        // split by a wall get clusters of their own.
+        let mut cluster_of = vec![usize::MAX; nav.tris.len()];
+        let mut members: Vec<Vec<usize>> = vec![];
+        for seed in 0..nav.tris.len() {
+            if cluster_of[seed] != usize::MAX {
+                continue;
+            }
+            let (id, key) = (members.len(), cell(nav.tris[seed].center));
+            let mut group = vec![seed];
+            cluster_of[seed] = id;
+            let mut i = 0;
+            while i < group.len() {
+                for &nb in &nav.tris[group[i]].neighbors {
+                    if cluster_of[nb] == usize::MAX && cell(nav.tris[nb].center) == key {
+                        cluster_of[nb] = id;
+                        group.push(nb);
+                    }
+                }
+                i += 1;
+            }
+            members.push(group);
+        }
+        let centers: Vec<Vec3> = members
+            .iter()
+            .map(|m| m.iter().map(|&t| nav.tris[t].center).sum::<Vec3>() / m.len() as f32)
+            .collect();
+
+        let mut walk = BTreeSet::new();
+        for (i, t) in nav.tris.iter().enumerate() {
+            for &nb in &t.neighbors {
+                if cluster_of[i] != cluster_of[nb] {
+                    walk.insert((cluster_of[i], cluster_of[nb]));
+                }
+            }
+        }
+        let mut edges = vec![vec![]; members.len()];
+        for (a, b) in walk {
+            edges[a].push(ClusterEdge {
+                to: b,
+                cost: centers[a].distance(centers[b]),
+                requires: Capabilities::NONE,
+            });
+        }
+        for l in &nav.links {
+            let Some([(a_tri, a), (b_tri, b)]) = l.ends else {
+                continue;
+            };
+            let (ca, cb) = (cluster_of[a_tri], cluster_of[b_tri]);
+            if ca == cb {
+                continue;
+            }
+            let cost = centers[ca].distance(a) + l.link.cost + b.distance(centers[cb]);
+            let requires = l.link.requires;
+            edges[ca].push(ClusterEdge {
+                to: cb,
+                cost,
+                requires,
+            });
+            if l.link.bidirectional {
+                edges[cb].push(ClusterEdge {
+                    to: ca,
+                    cost,
+                    requires,
+                });
+            }
+        }
+        Self {
+            cluster_size,
+            revision: nav.revision(),
+            cluster_of,
+            centers,
+            edges,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.centers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centers.is_empty()
+    }
+
+    pub fn cluster_size(&self) -> f32 {
+        self.cluster_size
+    }
+
+    /// Whether the clusters were built from `nav` as it is now.
+    pub fn is_current(&self, nav: &NavMesh) -> bool {
+        self.revision == nav.revision() && self.cluster_of.len() == nav.tris.len()
+    }
+
+    /// Clusters leading from `from`'s to `to`'s, by A* over cluster centers and the links
+    /// an agent with `caps` may take.
+    pub fn route(&self, from: usize, to: usize, caps: Capabilities) -> Option<Vec<usize>> {
+        #[derive(Copy, Clone, PartialEq)]
+        struct Node {
+            f: f32,
+            i: usize,
+        }
+        impl Eq for Node {}
+        impl Ord for Node {
+            fn cmp(&self, o: &Self) -> Ordering {
+                o.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
+            }
+        }
+        impl PartialOrd for Node {
+            fn partial_cmp(&self, o: &Self) -> Option<Ordering> {
+                Some(self.cmp(o))
+            }
+        }
+
+        let (start, goal) = (self.cluster_of[from], self.cluster_of[to]);
+        let h = |i: usize| self.centers[i].distance(self.centers[goal]);
+        let mut open = BinaryHeap::from([Node {
+            f: h(start),
+            i: start,
+        }]);
+        let mut came = vec![usize::MAX; self.len()];
+        let mut gscore = vec![f32::INFINITY; self.len()];
+        gscore[start] = 0.0;
+        while let Some(Node { i, .. }) = open.pop() {
+            if i == goal {
+                let mut route = vec![goal];
+                while *route.last().unwrap() != start {
+                    route.push(came[*route.last().unwrap()]);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for e in &self.edges[i] {
+                if !caps.contains(e.requires) {
+                    continue;
+                }
+                let ng = gscore[i] + e.cost;
+                if ng < gscore[e.to] {
+                    came[e.to] = i;
+                    gscore[e.to] = ng;
+                    open.push(Node {
+                        f: ng + h(e.to),
+                        i: e.to,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Triangles in the route's clusters or next to them. The neighbours leave the funnel
+    /// some room where the cluster centers cut a corner.
+    fn corridor_mask(&self, route: &[usize]) -> Vec<bool> {
+        let mut keep = vec![false; self.len()];
+        for &c in route {
+            keep[c] = true;
+            for e in &self.edges[c] {
+                if e.requires == Capabilities::NONE {
+                    keep[e.to] = true;
+                }
+            }
+        }
+        self.cluster_of.iter().map(|&c| keep[c]).collect()
+    }
+
+    /// Triangle search for a located start and goal, narrowed to the cluster route when
+    /// there is one.
+    pub(crate) fn search(
+        &self,
+        nav: &NavMesh,
+        (s, g): (usize, usize),
+        gp: Vec3,
+        filter: &PathFilter,
+    ) -> TriSearch {
+        let allowed = self
+            .route(s, g, filter.caps)
+            .map(|r| self.corridor_mask(&r));
+        TriSearch::new(nav, (s, g), gp, filter, allowed)
+    }
+
+    /// Same result as [`NavMesh::query_path_filtered`], but the triangle search only looks
+    /// along the cluster route. Falls back to the whole mesh when area costs or exclusions
+    /// close that corridor.
+    pub fn query_path(
+        &self,
+        nav: &NavMesh,
+        start: Vec3,
+        goal: Vec3,
+        filter: &PathFilter,
+    ) -> Option<NavPath> {
+        if !self.is_current(nav) {
+            return nav.query_path_filtered(start, goal, filter);
+        }
+        let (s, sp) = nav.locate(start)?;
+        let (g, gp) = nav.locate(goal)?;
+        let mut search = self.search(nav, (s, g), gp, filter);
+        search.step(&nav.tris, usize::MAX);
+        let (mut steps, mut complete) = search.corridor();
+        if !complete {
+            let mut full = TriSearch::new(nav, (s, g), gp, filter, None);
+            full.step(&nav.tris, usize::MAX);
+            (steps, complete) = full.corridor();
+        }
+        nav.path_along(sp, (goal, gp), &steps, complete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BakeConfig, Triangle};
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    /// Serpentine: three long east-west lanes joined at alternating ends.
+    fn serpentine() -> NavMesh {
+        let mut geo = quad(-20.0, -12.0, 20.0, -8.0, 0.0);
+        geo.extend(quad(16.0, -8.0, 20.0, -2.0, 0.0));
+        geo.extend(quad(-20.0, -2.0, 20.0, 2.0, 0.0));
+        geo.extend(quad(-20.0, 2.0, -16.0, 8.0, 0.0));
+        geo.extend(quad(-20.0, 8.0, 20.0, 12.0, 0.0));
+        NavMesh::bake_with(&geo, &BakeConfig::default())
+    }
+
+    #[test]
+    fn clustered_query_matches_the_full_search() {
+        let nav = serpentine();
+        let clusters = NavClusters::build(&nav, 6.0);
+        assert!(clusters.len() > 1 && clusters.len() < nav.tris.len());
+        let (a, b) = (vec3(-18.0, 0.0, -10.0), vec3(-18.0, 0.0, 10.0));
+        let full = nav.query_path(a, b).unwrap();
+        let fast = clusters
+            .query_path(&nav, a, b, &PathFilter::default())
+            .unwrap();
+        assert!(fast.complete);
+        let len = |p: &NavPath| {
+            p.points
+                .windows(2)
+                .map(|w| w[0].distance(w[1]))
+                .sum::<f32>()
+        };
+        assert!(
+            (len(&fast) - len(&full)).abs() < 0.5,
+            "{:?} vs {:?}",
+            fast.points,
+            full.points
+        );
+
+        let route = clusters.route(full.tris[0], *full.tris.last().unwrap(), Capabilities::ALL);
+        assert!(route.unwrap().len() >= 3);
+    }
+
+    #[test]
+    fn clusters_go_stale_when_links_change() {
+        let mut nav = serpentine();
+        let clusters = NavClusters::build(&nav, 6.0);
+        assert!(clusters.is_current(&nav));
+        nav.add_link(crate::OffMeshLink::teleport(
+            vec3(-18.0, 0.0, -10.0),
+            vec3(-18.0, 0.0, 10.0),
+        ));
+        assert!(!clusters.is_current(&nav));
+        let rebuilt = NavClusters::build(&nav, 6.0);
+        let path = rebuilt
+            .query_path(
+                &nav,
+                vec3(-17.0, 0.0, -10.0),
+                vec3(-17.0, 0.0, 10.0),
+                &PathFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(path.segments.len(), 3);
+    }
+}
@@ -0,0 +1,256 @@
+//! Versioned binary files for baked navmeshes, so levels can ship them instead of rebaking
+//! at every startup.
+//!
+//! Layout, little endian:
+//! `magic "AWNM" | version u16 | kind u8 | reserved u8 | source u64 | data_len u32 | crc32 u32 | data`,
+//! where `data` is postcard and `source` is the [`source_checksum`] the mesh was baked from.
+
+use crate::{BakeConfig, NavMesh, Triangle};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+pub const NAV_FORMAT_VERSION: u16 = 2;
+const MAGIC: &[u8; 4] = b"AWNM";
+const HEADER_LEN: usize = 24;
+
+/// What a navmesh file holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    Mesh = 0,
+    Tiled = 1,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NavFileError {
+    #[error("not a navmesh file")]
+    BadMagic,
+    #[error("navmesh format version {0} is not supported (expected {NAV_FORMAT_VERSION})")]
+    Version(u16),
+    #[error("file holds a tiled navmesh where a single mesh was expected, or the other way round")]
+    WrongKind,
+    #[error("navmesh data is truncated or corrupt")]
+    Corrupt,
+    #[error(
+        "navmesh was baked from other geometry (source {found:016x}, expected {expected:016x})"
+    )]
+    StaleSource { expected: u64, found: u64 },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Fingerprint of the level geometry and bake settings. A saved mesh whose checksum differs
+/// from the current level's is stale and should be rebaked.
+pub fn source_checksum(tris: &[Triangle], cfg: &BakeConfig) -> u64 {
+    let bytes = postcard::to_allocvec(&(tris, cfg)).expect("bake input always encodes");
+    // FNV-1a: stable across builds and platforms, unlike the std hasher.
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub(crate) fn encode<T: Serialize>(kind: FileKind, source: u64, value: &T) -> Vec<u8> {
+    let data = postcard::to_allocvec(value).expect("navmesh always encodes");
+    let mut out = Vec::with_capacity(HEADER_LEN + data.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&NAV_FORMAT_VERSION.to_le_bytes());
+    out.push(kind as u8);
+    out.push(0);
+    out.extend_from_slice(&source.to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc32fast::hash(&data).to_le_bytes());
+    out.extend_from_slice(&data);
+    out
+}
+
+/// Decode a file written by [`encode`]. With `expected`, a file baked from other geometry
+/// is rejected.
+pub(crate) fn decode<T: DeserializeOwned>(
+    kind: FileKind,
+    bytes: &[u8],
+    expected: Option<u64>,
+) -> Result<T, NavFileError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(if bytes.starts_with(MAGIC) {
+            NavFileError::Corrupt
+        } else {
+            NavFileError::BadMagic
+        });
+    }
+    if &bytes[0..4] != MAGIC {
+        return Err(NavFileError::BadMagic);
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != NAV_FORMAT_VERSION {
+        return Err(NavFileError::Version(version));
+    }
+    if bytes[6] != kind as u8 {
+        return Err(NavFileError::WrongKind);
+    }
+    let found = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    if let Some(expected) = expected.filter(|&e| e != found) {
+        return Err(NavFileError::StaleSource { expected, found });
+    }
+    let len = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(bytes[20..24].try_into().unwrap());
+    let data = bytes
+        .get(HEADER_LEN..HEADER_LEN + len)
+        .ok_or(NavFileError::Corrupt)?;
+    if crc32fast::hash(data) != crc {
+        return Err(NavFileError::Corrupt);
+    }
+    postcard::from_bytes(data).map_err(|_| NavFileError::Corrupt)
+}
+
+impl NavMesh {
+    /// Encode the mesh and its off-mesh links, tagged with the [`source_checksum`] of the
+    /// geometry it was baked from.
+    pub fn to_bytes(&self, source: u64) -> Vec<u8> {
+        encode(FileKind::Mesh, source, self)
+    }
+
+    /// Decode a mesh written by [`NavMesh::to_bytes`]. With `source`, a mesh baked from
+    /// other geometry fails with [`NavFileError::StaleSource`].
+    pub fn from_bytes(bytes: &[u8], source: Option<u64>) -> Result<Self, NavFileError> {
+        decode(FileKind::Mesh, bytes, source)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, source: u64) -> Result<(), NavFileError> {
+        Ok(std::fs::write(path, self.to_bytes(source))?)
+    }
+
+    pub fn load(path: impl AsRef<Path>, source: Option<u64>) -> Result<Self, NavFileError> {
+        Self::from_bytes(&std::fs::read(path)?, source)
+    }
+
+    /// The mesh saved at `path` if it was baked from `tris` with `cfg`; otherwise bake it
+    /// and save it there for next time.
+    pub fn load_or_bake(
+        path: impl AsRef<Path>,
+        tris: &[Triangle],
+        cfg: &BakeConfig,
+    ) -> Result<Self, NavFileError> {
+        let source = source_checksum(tris, cfg);
+        if let Ok(nav) = Self::load(&path, Some(source)) {
+            return Ok(nav);
+        }
+        let nav = Self::bake_with(tris, cfg);
+        nav.save(&path, source)?;
+        Ok(nav)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OffMeshLink, TiledNavMesh};
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn level() -> Vec<Triangle> {
+        let mut geo = quad(-8.0, -2.0, 8.0, 2.0, 0.0);
+        geo.extend(quad(-8.0, 6.0, 8.0, 10.0, 0.0));
+        geo
+    }
+
+    #[test]
+    fn mesh_round_trips_with_its_links() {
+        let cfg = BakeConfig::default();
+        let geo = level();
+        let mut nav = NavMesh::bake_with(&geo, &cfg);
+        let link = nav.add_link(OffMeshLink::jump(vec3(0.0, 0.0, 1.0), vec3(0.0, 0.0, 7.0)));
+        let source = source_checksum(&geo, &cfg);
+
+        let loaded = NavMesh::from_bytes(&nav.to_bytes(source), Some(source)).unwrap();
+        assert_eq!(loaded.tris.len(), nav.tris.len());
+        assert_eq!(loaded.polys.len(), nav.polys.len());
+        assert!(loaded.is_link_attached(link));
+        let (a, b) = (vec3(-6.0, 0.0, 0.0), vec3(6.0, 0.0, 8.0));
+        assert_eq!(loaded.find_path(a, b), nav.find_path(a, b));
+    }
+
+    #[test]
+    fn stale_and_damaged_files_are_rejected() {
+        let cfg = BakeConfig::default();
+        let geo = level();
+        let nav = NavMesh::bake_with(&geo, &cfg);
+        let bytes = nav.to_bytes(source_checksum(&geo, &cfg));
+
+        let mut moved = geo.clone();
+        moved[0].a.y += 0.5;
+        let moved = source_checksum(&moved, &cfg);
+        assert!(matches!(
+            NavMesh::from_bytes(&bytes, Some(moved)),
+            Err(NavFileError::StaleSource { .. })
+        ));
+        let coarse = BakeConfig {
+            cell_size: 0.5,
+            ..cfg.clone()
+        };
+        assert_ne!(source_checksum(&geo, &coarse), source_checksum(&geo, &cfg));
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            NavMesh::from_bytes(&flipped, None),
+            Err(NavFileError::Corrupt)
+        ));
+        assert!(matches!(
+            NavMesh::from_bytes(&bytes[..bytes.len() / 2], None),
+            Err(NavFileError::Corrupt)
+        ));
+        assert!(matches!(
+            NavMesh::from_bytes(b"glTF....", None),
+            Err(NavFileError::BadMagic)
+        ));
+        assert!(matches!(
+            TiledNavMesh::from_bytes(&bytes, None),
+            Err(NavFileError::WrongKind)
+        ));
+    }
+
+    #[test]
+    fn tiled_mesh_round_trips_with_obstacles_and_links() {
+        let mut tiled = TiledNavMesh::new(&level(), BakeConfig::default(), 4.0);
+        tiled.add_obstacle(crate::NavObstacle::Box {
+            min: vec3(-1.0, -1.0, -3.0),
+            max: vec3(1.0, 2.0, 3.0),
+        });
+        tiled.flush();
+        tiled.add_link(OffMeshLink::bridge(
+            vec3(-4.0, 0.0, 1.0),
+            vec3(-4.0, 0.0, 7.0),
+        ));
+
+        let bytes = tiled.to_bytes();
+        let mut loaded = TiledNavMesh::from_bytes(&bytes, Some(tiled.source_checksum())).unwrap();
+        assert_eq!(loaded.mesh().tris.len(), tiled.mesh().tris.len());
+        assert_eq!(loaded.obstacles().count(), 1);
+        assert_eq!(loaded.mesh().links().count(), 1);
+        let (a, b) = (vec3(-6.0, 0.0, 0.0), vec3(6.0, 0.0, 8.0));
+        assert_eq!(loaded.mesh().find_path(a, b), tiled.mesh().find_path(a, b));
+
+        // Loaded meshes keep working as tiled meshes.
+        let id = loaded.obstacles().next().unwrap().0;
+        loaded.remove_obstacle(id);
+        assert!(loaded.update() > 0);
+        assert!(!loaded
+            .mesh()
+            .find_path(vec3(-6.0, 0.0, 0.0), vec3(6.0, 0.0, 0.0))
+            .is_empty());
+    }
+}
@@ -1,193 +1,142 @@
 use glam::Vec3;
-
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub use astraweave_core::{AreaCosts, AreaType, Pathfinder};
+
+mod bake;
+mod crowd;
+mod debug;
+mod hierarchy;
+mod io;
+mod links;
+mod path;
+mod profiles;
+mod queue;
+mod tiles;
+pub use bake::BakeConfig;
+pub use crowd::{Crowd, CrowdAgent, CrowdAgentId, CrowdAgentParams, CrowdAgentState};
+pub use debug::{ConnectivityReport, DebugColoring, Island};
+pub use hierarchy::NavClusters;
+pub use io::{source_checksum, NavFileError, NAV_FORMAT_VERSION};
+pub use links::{Capabilities, LinkId, LinkKind, OffMeshLink};
+pub use path::{
+    build_portals, string_pull, NavPath, PathFilter, PathSegment, Portal, PortalGraph, Traversal,
+};
+pub use profiles::{AgentProfile, ProfileNavMeshes};
+pub use queue::{PathHandle, PathQueue, QueueStats};
+pub use tiles::{AreaVolume, NavEvent, NavObstacle, ObstacleId, PathTicket, TiledNavMesh};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Triangle {
     pub a: Vec3,
     pub b: Vec3,
     pub c: Vec3,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NavTri {
+    /// Index of this triangle in [`NavMesh::tris`].
     pub idx: usize,
+    /// Index of the polygon in [`NavMesh::polys`] this triangle was cut from.
+    pub poly: usize,
+    pub area: AreaType,
     pub verts: [Vec3; 3],
     pub normal: Vec3,
     pub center: Vec3,
     pub neighbors: Vec<usize>,
 }
 
-#[derive(Clone, Debug)]
+/// A simplified walkable region outline; its triangles are listed in `tris`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NavPoly {
+    pub verts: Vec<Vec3>,
+    pub tris: Vec<usize>,
+    pub region: u32,
+    pub area: AreaType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NavMesh {
     pub tris: Vec<NavTri>,
+    pub polys: Vec<NavPoly>,
     pub max_step: f32,
     pub max_slope_deg: f32,
+    links: Vec<links::AttachedLink>,
+    next_link: u32,
+    revision: u64,
 }
 
 impl NavMesh {
-    pub fn bake(tris: &[Triangle], max_step: f32, max_slope_deg: f32) -> Self {
-        let mut ntris: Vec<NavTri> = tris
-            .iter()
-            .enumerate()
-            .filter_map(|(i, t)| {
-                let n = (t.b - t.a).cross(t.c - t.a).normalize_or_zero();
-                let slope_ok = n.dot(Vec3::Y).acos().to_degrees() <= max_slope_deg;
-                if !slope_ok {
-                    return None;
-                }
-                let center = (t.a + t.b + t.c) / 3.0;
-                Some(NavTri {
-                    idx: i,
-                    verts: [t.a, t.b, t.c],
-                    normal: n,
-                    center,
-                    neighbors: vec![],
-                })
-            })
-            .collect();
-
-        // Build adjacency by shared edge (position‑based, epsilon)
-        let eps = 1e-3;
-        for i in 0..ntris.len() {
-            for j in i + 1..ntris.len() {
-                if share_edge(&ntris[i], &ntris[j], eps) {
-                    ntris[i].neighbors.push(j);
-                    ntris[j].neighbors.push(i);
-                }
-            }
-        }
-
+    pub(crate) fn empty(max_step: f32, max_slope_deg: f32) -> Self {
         Self {
-            tris: ntris,
+            tris: vec![],
+            polys: vec![],
             max_step,
             max_slope_deg,
+            links: vec![],
+            next_link: 0,
+            revision: 0,
         }
     }
 
-    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3> {
-        let s = closest_tri(&self.tris, start);
-        let g = closest_tri(&self.tris, goal);
-        if s.is_none() || g.is_none() {
-            return vec![];
-        }
-        let (s, g) = (s.unwrap(), g.unwrap());
-        let idx_path = astar_tri(&self.tris, s, g);
-        if idx_path.is_empty() {
-            return vec![];
-        }
-
-        // seed with start and goal
-        let mut pts = vec![start];
-        for ti in idx_path
-            .iter()
-            .skip(1)
-            .take(idx_path.len().saturating_sub(2))
-        {
-            pts.push(self.tris[*ti].center);
-        }
-        pts.push(goal);
-
-        // optional: simple smoothing
-        smooth(&mut pts, &self.tris);
-
-        pts
+    /// Bake with the default agent (see [`BakeConfig`]) and the given step and slope limits.
+    pub fn bake(tris: &[Triangle], max_step: f32, max_slope_deg: f32) -> Self {
+        Self::bake_with(
+            tris,
+            &BakeConfig {
+                max_step,
+                max_slope_deg,
+                ..BakeConfig::default()
+            },
+        )
     }
-}
 
-fn share_edge(a: &NavTri, b: &NavTri, eps: f32) -> bool {
-    let mut shared = 0;
-    for va in a.verts {
-        for vb in b.verts {
-            if va.distance(vb) <= eps {
-                shared += 1;
-            }
-        }
+    /// Voxelize arbitrary level geometry and build a navmesh for the configured agent.
+    pub fn bake_with(tris: &[Triangle], cfg: &BakeConfig) -> Self {
+        bake::bake(tris, cfg)
     }
-    shared >= 2
-}
-
-fn closest_tri(tris: &[NavTri], p: Vec3) -> Option<usize> {
-    tris.iter()
-        .enumerate()
-        .min_by(|(_, x), (_, y)| {
-            x.center
-                .distance_squared(p)
-                .total_cmp(&y.center.distance_squared(p))
-        })
-        .map(|(i, _)| i)
-}
-
-fn astar_tri(tris: &[NavTri], start: usize, goal: usize) -> Vec<usize> {
-    use std::cmp::Ordering;
-    use std::collections::{BinaryHeap, HashMap};
 
-    #[derive(Copy, Clone, PartialEq)]
-    struct Node {
-        f: f32,
-        i: usize,
+    /// Bumped whenever links change or the triangles are rebuilt; cached routes and
+    /// clusters from an older revision are stale.
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
-    impl Eq for Node {}
-    impl Ord for Node {
-        fn cmp(&self, o: &Self) -> Ordering {
-            o.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
-        }
-    }
-    impl PartialOrd for Node {
-        fn partial_cmp(&self, o: &Self) -> Option<Ordering> {
-            Some(self.cmp(o))
-        }
-    }
-
-    let mut open = BinaryHeap::new();
-    let mut came: HashMap<usize, usize> = HashMap::new();
-    let mut gscore: HashMap<usize, f32> = HashMap::new();
 
-    open.push(Node { f: 0.0, i: start });
-    gscore.insert(start, 0.0);
-
-    while let Some(Node { i, .. }) = open.pop() {
-        if i == goal {
-            break;
-        }
-        let gi = *gscore.get(&i).unwrap_or(&f32::INFINITY);
-        for &nb in &tris[i].neighbors {
-            let cost = tris[i].center.distance(tris[nb].center);
-            let ng = gi + cost;
-            if ng < *gscore.get(&nb).unwrap_or(&f32::INFINITY) {
-                came.insert(nb, i);
-                gscore.insert(nb, ng);
-                let f = ng + tris[nb].center.distance(tris[goal].center);
-                open.push(Node { f, i: nb });
-            }
+    /// Path from `start` to `goal` along the mesh and its off-mesh links, or empty if the
+    /// goal is unreachable.
+    /// Use [`NavMesh::query_path`] to get a partial path instead.
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3> {
+        match self.query_path(start, goal) {
+            Some(p) if p.complete => p.points,
+            _ => vec![],
         }
     }
+}
 
-    // reconstruct
-    let mut path = vec![];
-    let mut cur = goal;
-    path.push(cur);
-    while let Some(&prev) = came.get(&cur) {
-        cur = prev;
-        path.push(cur);
-        if cur == start {
-            break;
+/// Link triangles that share an edge (vertices matched on a 1mm grid).
+fn link_shared_edges(tris: &mut [NavTri]) {
+    let key = |v: Vec3| (v * 1000.0).round().as_ivec3().to_array();
+    let mut edges: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, t) in tris.iter().enumerate() {
+        for e in 0..3 {
+            let (a, b) = (key(t.verts[e]), key(t.verts[(e + 1) % 3]));
+            edges
+                .entry(if a < b { (a, b) } else { (b, a) })
+                .or_default()
+                .push(i);
         }
     }
-    path.reverse();
-    if path.first().copied() != Some(start) {
-        return vec![];
+    for t in tris.iter_mut() {
+        t.neighbors.clear();
     }
-    path
-}
-
-fn smooth(pts: &mut [Vec3], _tris: &[NavTri]) {
-    if pts.len() < 3 {
-        return;
-    }
-    for _ in 0..2 {
-        for i in 1..pts.len() - 1 {
-            let a = pts[i - 1];
-            let b = pts[i + 1];
-            pts[i] = a * 0.25 + pts[i] * 0.5 + b * 0.25;
+    for shared in edges.values() {
+        for &i in shared {
+            for &j in shared {
+                if i != j && !tris[i].neighbors.contains(&j) {
+                    tris[i].neighbors.push(j);
+                }
+            }
         }
     }
 }
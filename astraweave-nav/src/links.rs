@@ -0,0 +1,307 @@
+//! Off-mesh links: jumps, ladders, portals and bridges between points the mesh itself
+//! does not connect.
+
+use crate::NavMesh;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::BitOr;
+
+/// What an agent can do besides walking. Links list what they require.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    pub const JUMP: Self = Self(1);
+    pub const CLIMB: Self = Self(1 << 1);
+    pub const TELEPORT: Self = Self(1 << 2);
+    pub const ALL: Self = Self(u32::MAX);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+    fn bitor(self, o: Self) -> Self {
+        Self(self.0 | o.0)
+    }
+}
+
+/// How a link is traversed; the character controller picks its animation or state from this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LinkKind {
+    Jump,
+    Climb,
+    Teleport,
+    Bridge,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LinkId(pub u32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OffMeshLink {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub kind: LinkKind,
+    /// One-way links can only be taken from `start` to `end`.
+    pub bidirectional: bool,
+    /// Path cost of taking the link; walking costs its length.
+    pub cost: f32,
+    pub requires: Capabilities,
+    /// How far an endpoint may be from the mesh and still attach to it.
+    pub snap_radius: f32,
+    /// Seconds until the link disappears (see [`NavMesh::tick_links`]); `None` is permanent.
+    pub lifetime: Option<f32>,
+}
+
+impl OffMeshLink {
+    fn new(kind: LinkKind, start: Vec3, end: Vec3, bidirectional: bool, cost: f32) -> Self {
+        let requires = match kind {
+            LinkKind::Jump => Capabilities::JUMP,
+            LinkKind::Climb => Capabilities::CLIMB,
+            LinkKind::Teleport => Capabilities::TELEPORT,
+            LinkKind::Bridge => Capabilities::NONE,
+        };
+        Self {
+            start,
+            end,
+            kind,
+            bidirectional,
+            cost,
+            requires,
+            snap_radius: 1.0,
+            lifetime: None,
+        }
+    }
+
+    /// One-way jump, usually down a ledge.
+    pub fn jump(start: Vec3, end: Vec3) -> Self {
+        Self::new(LinkKind::Jump, start, end, false, start.distance(end) + 1.0)
+    }
+
+    /// Ladder or climbable wall, usable both ways.
+    pub fn climb(start: Vec3, end: Vec3) -> Self {
+        Self::new(LinkKind::Climb, start, end, true, start.distance(end) * 2.0)
+    }
+
+    /// One-way portal; costs the same regardless of distance.
+    pub fn teleport(start: Vec3, end: Vec3) -> Self {
+        Self::new(LinkKind::Teleport, start, end, false, 1.0)
+    }
+
+    /// Walkable span anyone can cross, both ways.
+    pub fn bridge(start: Vec3, end: Vec3) -> Self {
+        Self::new(LinkKind::Bridge, start, end, true, start.distance(end))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct AttachedLink {
+    pub id: LinkId,
+    pub link: OffMeshLink,
+    /// Triangle and on-mesh point for each end, if both ends are on the mesh.
+    pub ends: Option<[(usize, Vec3); 2]>,
+}
+
+/// A link as seen by the path search, oriented in the direction of travel.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LinkEdge {
+    pub id: LinkId,
+    pub kind: LinkKind,
+    pub from: Vec3,
+    pub to: Vec3,
+    pub to_tri: usize,
+    pub cost: f32,
+}
+
+impl NavMesh {
+    pub fn add_link(&mut self, link: OffMeshLink) -> LinkId {
+        self.next_link += 1;
+        let id = LinkId(self.next_link);
+        let ends = self.attach(&link);
+        self.links.push(AttachedLink { id, link, ends });
+        self.revision += 1;
+        id
+    }
+
+    pub fn remove_link(&mut self, id: LinkId) -> Option<OffMeshLink> {
+        let i = self.links.iter().position(|l| l.id == id)?;
+        self.revision += 1;
+        Some(self.links.remove(i).link)
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = (LinkId, &OffMeshLink)> {
+        self.links.iter().map(|l| (l.id, &l.link))
+    }
+
+    /// Whether both ends of the link found the mesh.
+    pub fn is_link_attached(&self, id: LinkId) -> bool {
+        self.links.iter().any(|l| l.id == id && l.ends.is_some())
+    }
+
+    /// Age links with a lifetime and drop the expired ones, returning their ids.
+    pub fn tick_links(&mut self, dt: f32) -> Vec<LinkId> {
+        let mut expired = vec![];
+        self.links.retain_mut(|l| match &mut l.link.lifetime {
+            Some(t) => {
+                *t -= dt;
+                if *t <= 0.0 {
+                    expired.push(l.id);
+                }
+                *t > 0.0
+            }
+            None => true,
+        });
+        if !expired.is_empty() {
+            self.revision += 1;
+        }
+        expired
+    }
+
+    /// Re-snap link ends after the triangles changed.
+    pub(crate) fn reattach_links(&mut self) {
+        for i in 0..self.links.len() {
+            self.links[i].ends = self.attach(&self.links[i].link);
+        }
+        self.revision += 1;
+    }
+
+    fn attach(&self, link: &OffMeshLink) -> Option<[(usize, Vec3); 2]> {
+        let snap = |p: Vec3| {
+            self.locate(p)
+                .filter(|(_, q)| q.distance(p) <= link.snap_radius)
+        };
+        Some([snap(link.start)?, snap(link.end)?])
+    }
+
+    /// Usable links for an agent with `caps`, keyed by the triangle they leave from.
+    pub(crate) fn link_edges(&self, caps: Capabilities) -> HashMap<usize, Vec<LinkEdge>> {
+        let mut out: HashMap<usize, Vec<LinkEdge>> = HashMap::new();
+        for l in &self.links {
+            let Some([(a_tri, a), (b_tri, b)]) = l.ends else {
+                continue;
+            };
+            if !caps.contains(l.link.requires) || a_tri == b_tri {
+                continue;
+            }
+            let edge = |from, to, to_tri| LinkEdge {
+                id: l.id,
+                kind: l.link.kind,
+                from,
+                to,
+                to_tri,
+                cost: l.link.cost,
+            };
+            out.entry(a_tri).or_default().push(edge(a, b, b_tri));
+            if l.link.bidirectional {
+                out.entry(b_tri).or_default().push(edge(b, a, a_tri));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BakeConfig, PathFilter, Traversal, Triangle};
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn islands() -> NavMesh {
+        let mut geo = quad(-6.0, -2.0, 0.0, 2.0, 0.0);
+        geo.extend(quad(3.0, -2.0, 9.0, 2.0, 0.0));
+        NavMesh::bake_with(&geo, &BakeConfig::default())
+    }
+
+    fn kinds(path: &crate::NavPath) -> Vec<Traversal> {
+        path.segments.iter().map(|s| s.traversal).collect()
+    }
+
+    #[test]
+    fn bridge_joins_islands_both_ways() {
+        let mut nav = islands();
+        let (a, b) = (vec3(-5.0, 0.0, 0.0), vec3(8.0, 0.0, 0.0));
+        assert!(!nav.query_path(a, b).unwrap().complete);
+
+        let id = nav.add_link(OffMeshLink::bridge(
+            vec3(-0.5, 0.0, 0.0),
+            vec3(3.5, 0.0, 0.0),
+        ));
+        assert!(nav.is_link_attached(id));
+        let there = nav.query_path(a, b).unwrap();
+        assert!(there.complete);
+        let bridge = Traversal::Link {
+            id,
+            kind: LinkKind::Bridge,
+        };
+        assert_eq!(
+            kinds(&there),
+            vec![Traversal::Walk, bridge, Traversal::Walk]
+        );
+        assert_eq!(nav.find_path(a, b), there.points);
+        assert!(nav.query_path(b, a).unwrap().complete);
+
+        nav.remove_link(id);
+        assert!(nav.find_path(a, b).is_empty());
+    }
+
+    #[test]
+    fn one_way_jump_needs_capability() {
+        let mut geo = quad(-6.0, -2.0, 0.0, 2.0, 2.0);
+        geo.extend(quad(0.0, -2.0, 6.0, 2.0, 0.0));
+        let mut nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let (top, bottom) = (vec3(-4.0, 2.0, 0.0), vec3(4.0, 0.0, 0.0));
+        nav.add_link(OffMeshLink::jump(vec3(-0.5, 2.0, 0.0), vec3(1.0, 0.0, 0.0)));
+
+        let down = nav.query_path(top, bottom).unwrap();
+        assert!(down.complete);
+        assert!(down.segments.iter().any(|s| matches!(
+            s.traversal,
+            Traversal::Link {
+                kind: LinkKind::Jump,
+                ..
+            }
+        )));
+        assert!(!nav.query_path(bottom, top).unwrap().complete);
+
+        let walker = PathFilter {
+            caps: Capabilities::CLIMB,
+            ..PathFilter::default()
+        };
+        assert!(
+            !nav.query_path_filtered(top, bottom, &walker)
+                .unwrap()
+                .complete
+        );
+    }
+
+    #[test]
+    fn temporary_links_expire() {
+        let mut nav = islands();
+        let mut bridge = OffMeshLink::bridge(vec3(-0.5, 0.0, 0.0), vec3(3.5, 0.0, 0.0));
+        bridge.lifetime = Some(1.0);
+        let id = nav.add_link(bridge);
+        assert!(nav.tick_links(0.6).is_empty());
+        assert_eq!(nav.tick_links(0.6), vec![id]);
+        assert_eq!(nav.links().count(), 0);
+    }
+}
@@ -0,0 +1,723 @@
+//! Point location on the mesh, portal extraction and funnel (string-pull) smoothing.
+
+use crate::links::LinkEdge;
+use crate::{AreaCosts, Capabilities, LinkId, LinkKind, NavMesh, NavTri, Pathfinder};
+use glam::{vec3, Vec3};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Per-query restrictions on what the path may use.
+#[derive(Clone, Debug)]
+pub struct PathFilter {
+    /// Off-mesh links requiring anything outside this set are skipped.
+    pub caps: Capabilities,
+    /// Cost multipliers and allowed areas for the triangles crossed.
+    pub areas: AreaCosts,
+}
+
+impl Default for PathFilter {
+    fn default() -> Self {
+        Self {
+            caps: Capabilities::ALL,
+            areas: AreaCosts::default(),
+        }
+    }
+}
+
+/// How the character gets along one [`PathSegment`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Traversal {
+    Walk,
+    Link { id: LinkId, kind: LinkKind },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathSegment {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub traversal: Traversal,
+}
+
+/// Result of [`NavMesh::query_path`].
+#[derive(Clone, Debug, Default)]
+pub struct NavPath {
+    /// Waypoints on the mesh, from the projected start to the projected goal.
+    pub points: Vec<Vec3>,
+    /// Triangle corridor the path runs through.
+    pub tris: Vec<usize>,
+    /// `false` when the goal is unreachable; `points` then ends as close to it as the mesh allows.
+    pub complete: bool,
+    /// `points` as consecutive legs, each tagged with how to traverse it.
+    pub segments: Vec<PathSegment>,
+}
+
+impl NavPath {
+    fn push(&mut self, to: Vec3, traversal: Traversal) {
+        let from = *self.points.last().unwrap();
+        if same_point(from, to) && traversal == Traversal::Walk {
+            return;
+        }
+        self.points.push(to);
+        self.segments.push(PathSegment {
+            from,
+            to,
+            traversal,
+        });
+    }
+}
+
+/// Edge shared by two adjacent triangles.
+#[derive(Clone, Debug)]
+pub struct Portal {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub left_tri: usize,
+    pub right_tri: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct PortalGraph {
+    pub portals: Vec<Portal>,
+    pub tri_to_portals: Vec<Vec<usize>>,
+}
+
+impl NavMesh {
+    /// Triangle under (or over) `p` and the point on it straight above/below `p`.
+    /// If `p` is outside every triangle's footprint, the nearest point on the mesh is used.
+    pub fn locate(&self, p: Vec3) -> Option<(usize, Vec3)> {
+        let mut best: Option<(f32, usize, f32)> = None;
+        for (i, t) in self.tris.iter().enumerate() {
+            if let Some(y) = height_at(t, p) {
+                let d = (y - p.y).abs();
+                if best.is_none_or(|(bd, _, _)| d < bd) {
+                    best = Some((d, i, y));
+                }
+            }
+        }
+        match best {
+            Some((_, i, y)) => Some((i, vec3(p.x, y, p.z))),
+            None => self.closest_point(p),
+        }
+    }
+
+    /// Nearest point on the mesh surface to `p`, with the triangle it lies on.
+    pub fn closest_point(&self, p: Vec3) -> Option<(usize, Vec3)> {
+        self.tris
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (i, closest_point_on_tri(p, t.verts)))
+            .min_by(|(_, a), (_, b)| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+    }
+
+    /// Find a path that stays on the mesh, taking any off-mesh link. Unlike
+    /// [`NavMesh::find_path`], an unreachable goal still yields a partial path to the
+    /// closest reachable point.
+    pub fn query_path(&self, start: Vec3, goal: Vec3) -> Option<NavPath> {
+        self.query_path_filtered(start, goal, &PathFilter::default())
+    }
+
+    pub fn query_path_filtered(
+        &self,
+        start: Vec3,
+        goal: Vec3,
+        filter: &PathFilter,
+    ) -> Option<NavPath> {
+        let (s, sp) = self.locate(start)?;
+        let (g, gp) = self.locate(goal)?;
+        let mut search = TriSearch::new(self, (s, g), gp, filter, None);
+        search.step(&self.tris, usize::MAX);
+        let (steps, complete) = search.corridor();
+        self.path_along(sp, (goal, gp), &steps, complete)
+    }
+
+    /// Funnel a path from `sp` along a searched corridor. `goal` is the requested goal and
+    /// `gp` its projection onto the goal triangle.
+    pub(crate) fn path_along(
+        &self,
+        sp: Vec3,
+        (goal, gp): (Vec3, Vec3),
+        steps: &[(usize, Option<LinkEdge>)],
+        complete: bool,
+    ) -> Option<NavPath> {
+        let end = if complete {
+            gp
+        } else {
+            closest_point_on_tri(goal, self.tris[steps.last()?.0].verts)
+        };
+
+        let mut path = NavPath {
+            points: vec![sp],
+            tris: steps.iter().map(|s| s.0).collect(),
+            complete,
+            segments: vec![],
+        };
+        // Funnel each stretch of walking between links separately.
+        let mut section = vec![steps[0].0];
+        for &(tri, via) in &steps[1..] {
+            match via {
+                Some(l) => {
+                    self.walk(&mut path, &section, l.from);
+                    path.push(
+                        l.to,
+                        Traversal::Link {
+                            id: l.id,
+                            kind: l.kind,
+                        },
+                    );
+                    section = vec![tri];
+                }
+                None => section.push(tri),
+            }
+        }
+        self.walk(&mut path, &section, end);
+        Some(path)
+    }
+
+    fn walk(&self, path: &mut NavPath, corridor: &[usize], to: Vec3) {
+        let portals: Vec<(Vec3, Vec3)> = corridor
+            .windows(2)
+            .filter_map(|w| {
+                let (a, b) = shared_edge(&self.tris[w[0]], &self.tris[w[1]])?;
+                Some(orient_portal(self.tris[w[0]].center, a, b))
+            })
+            .collect();
+        let from = *path.points.last().unwrap();
+        for p in funnel(from, &portals, to).into_iter().skip(1) {
+            path.push(p, Traversal::Walk);
+        }
+    }
+}
+
+impl Pathfinder for NavMesh {
+    /// Unlike [`NavMesh::find_path`], a goal outside the mesh footprint is unreachable
+    /// rather than snapped to the nearest point on the mesh.
+    fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3> {
+        if !self.tris.iter().any(|t| height_at(t, goal).is_some()) {
+            return vec![];
+        }
+        NavMesh::find_path(self, start, goal)
+    }
+
+    /// On the mesh footprint and within a step of its surface.
+    fn is_walkable(&self, p: Vec3) -> bool {
+        self.tris
+            .iter()
+            .any(|t| height_at(t, p).is_some_and(|y| (y - p.y).abs() <= self.max_step))
+    }
+}
+
+/// Height of the triangle at `p`'s (x, z), if `p` lies inside its footprint.
+pub(crate) fn height_at(t: &NavTri, p: Vec3) -> Option<f32> {
+    let [a, b, c] = t.verts;
+    let v0 = (c.x - a.x, c.z - a.z);
+    let v1 = (b.x - a.x, b.z - a.z);
+    let v2 = (p.x - a.x, p.z - a.z);
+    let det = v0.0 * v1.1 - v0.1 * v1.0;
+    if det.abs() < 1e-9 {
+        return None;
+    }
+    let u = (v2.0 * v1.1 - v2.1 * v1.0) / det;
+    let v = (v0.0 * v2.1 - v0.1 * v2.0) / det;
+    const EPS: f32 = 1e-4;
+    if u < -EPS || v < -EPS || u + v > 1.0 + EPS {
+        return None;
+    }
+    Some(a.y + u * (c.y - a.y) + v * (b.y - a.y))
+}
+
+/// Closest point on a triangle (Ericson, "Real-Time Collision Detection" 5.1.5).
+pub(crate) fn closest_point_on_tri(p: Vec3, [a, b, c]: [Vec3; 3]) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// The edge two triangles have in common, if any. Triangles from neighbouring tiles may
+/// only partly share an edge; the overlapping stretch is returned then.
+pub(crate) fn shared_edge(a: &NavTri, b: &NavTri) -> Option<(Vec3, Vec3)> {
+    let shared: Vec<Vec3> = a
+        .verts
+        .into_iter()
+        .filter(|va| b.verts.iter().any(|vb| va.distance_squared(*vb) <= 1e-6))
+        .collect();
+    if shared.len() >= 2 {
+        return Some((shared[0], shared[1]));
+    }
+    (0..3).find_map(|i| {
+        (0..3).find_map(|j| {
+            edge_overlap(
+                (a.verts[i], a.verts[(i + 1) % 3]),
+                (b.verts[j], b.verts[(j + 1) % 3]),
+            )
+        })
+    })
+}
+
+/// Stretch of edge `a` that collinear edge `b` also covers in (x, z), measured along `a`.
+pub(crate) fn edge_overlap((a0, a1): (Vec3, Vec3), (b0, b1): (Vec3, Vec3)) -> Option<(Vec3, Vec3)> {
+    const EPS: f32 = 1e-3;
+    let (dx, dz) = (a1.x - a0.x, a1.z - a0.z);
+    let len2 = dx * dx + dz * dz;
+    if len2 < EPS * EPS {
+        return None;
+    }
+    let off_line = |p: Vec3| ((p.x - a0.x) * dz - (p.z - a0.z) * dx).abs() / len2.sqrt() > EPS;
+    if off_line(b0) || off_line(b1) {
+        return None;
+    }
+    let t = |p: Vec3| ((p.x - a0.x) * dx + (p.z - a0.z) * dz) / len2;
+    let (t0, t1) = (t(b0), t(b1));
+    let lo = t0.min(t1).max(0.0);
+    let hi = t0.max(t1).min(1.0);
+    ((hi - lo) * len2.sqrt() > EPS).then(|| (a0.lerp(a1, lo), a0.lerp(a1, hi)))
+}
+
+/// Order a portal's endpoints as (left, right) when crossing it from `from`.
+fn orient_portal(from: Vec3, a: Vec3, b: Vec3) -> (Vec3, Vec3) {
+    let cross = (a.x - from.x) * (b.z - from.z) - (a.z - from.z) * (b.x - from.x);
+    if cross > 0.0 {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+fn tri_area2(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    let (abx, abz) = (b.x - a.x, b.z - a.z);
+    let (acx, acz) = (c.x - a.x, c.z - a.z);
+    acx * abz - abx * acz
+}
+
+fn same_point(a: Vec3, b: Vec3) -> bool {
+    a.distance_squared(b) < 1e-6
+}
+
+/// Simple stupid funnel algorithm over (left, right) portals.
+fn funnel(start: Vec3, portals: &[(Vec3, Vec3)], goal: Vec3) -> Vec<Vec3> {
+    let mut ps = Vec::with_capacity(portals.len() + 2);
+    ps.push((start, start));
+    ps.extend_from_slice(portals);
+    ps.push((goal, goal));
+
+    let mut path = vec![start];
+    let (mut apex, mut left, mut right) = (start, start, start);
+    let (mut left_i, mut right_i) = (0usize, 0usize);
+    let mut i = 1;
+    while i < ps.len() {
+        let (l, r) = ps[i];
+        if tri_area2(apex, right, r) <= 0.0 {
+            if same_point(apex, right) || tri_area2(apex, left, r) > 0.0 {
+                right = r;
+                right_i = i;
+            } else {
+                // Right crossed over left: the left corner becomes the new apex.
+                apex = left;
+                if !same_point(*path.last().unwrap(), apex) {
+                    path.push(apex);
+                }
+                (left, right, right_i) = (apex, apex, left_i);
+                i = left_i + 1;
+                continue;
+            }
+        }
+        if tri_area2(apex, left, l) >= 0.0 {
+            if same_point(apex, left) || tri_area2(apex, right, l) < 0.0 {
+                left = l;
+                left_i = i;
+            } else {
+                apex = right;
+                if !same_point(*path.last().unwrap(), apex) {
+                    path.push(apex);
+                }
+                (left, right, left_i) = (apex, apex, right_i);
+                i = right_i + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if !same_point(*path.last().unwrap(), goal) {
+        path.push(goal);
+    }
+    path
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct Node {
+    f: f32,
+    i: usize,
+}
+impl Eq for Node {}
+impl Ord for Node {
+    fn cmp(&self, o: &Self) -> Ordering {
+        o.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
+    }
+}
+impl PartialOrd for Node {
+    fn partial_cmp(&self, o: &Self) -> Option<Ordering> {
+        Some(self.cmp(o))
+    }
+}
+
+/// A* over triangle centers and off-mesh links, weighting each leg by the area it crosses.
+/// The search is resumable: [`TriSearch::step`] expands a bounded number of triangles, so
+/// one query can be spread over several frames.
+pub(crate) struct TriSearch {
+    start: usize,
+    goal: usize,
+    goal_pos: Vec3,
+    links: HashMap<usize, Vec<LinkEdge>>,
+    areas: AreaCosts,
+    h_scale: f32,
+    /// Triangles the search may enter besides the start; `None` allows all.
+    allowed: Option<Vec<bool>>,
+    open: BinaryHeap<Node>,
+    came: Vec<Option<(usize, Option<LinkEdge>)>>,
+    gscore: Vec<f32>,
+    best: (f32, usize),
+    done: bool,
+}
+
+impl TriSearch {
+    pub(crate) fn new(
+        nav: &NavMesh,
+        (start, goal): (usize, usize),
+        goal_pos: Vec3,
+        filter: &PathFilter,
+        allowed: Option<Vec<bool>>,
+    ) -> Self {
+        let h_scale = filter.areas.min_multiplier();
+        let h0 = nav.tris[start].center.distance(goal_pos) * h_scale;
+        let mut gscore = vec![f32::INFINITY; nav.tris.len()];
+        gscore[start] = 0.0;
+        Self {
+            start,
+            goal,
+            goal_pos,
+            links: nav.link_edges(filter.caps),
+            areas: filter.areas.clone(),
+            h_scale,
+            allowed,
+            open: BinaryHeap::from([Node { f: h0, i: start }]),
+            came: vec![None; nav.tris.len()],
+            gscore,
+            best: (h0, start),
+            done: false,
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    fn h(&self, tris: &[NavTri], i: usize) -> f32 {
+        tris[i].center.distance(self.goal_pos) * self.h_scale
+    }
+
+    fn cost(&self, tris: &[NavTri], i: usize) -> Option<f32> {
+        // The start triangle is always usable, even in an excluded area.
+        if i == self.start {
+            return Some(self.areas.cost(tris[i].area).unwrap_or(1.0));
+        }
+        if self.allowed.as_ref().is_some_and(|a| !a[i]) {
+            return None;
+        }
+        self.areas.cost(tris[i].area)
+    }
+
+    /// Expand up to `budget` triangles and return how many were expanded.
+    pub(crate) fn step(&mut self, tris: &[NavTri], budget: usize) -> usize {
+        let mut used = 0;
+        while used < budget {
+            let Some(Node { i, .. }) = self.open.pop() else {
+                self.done = true;
+                break;
+            };
+            used += 1;
+            if i == self.goal {
+                self.best = (0.0, i);
+                self.done = true;
+                break;
+            }
+            let hi = self.h(tris, i);
+            if hi < self.best.0 {
+                self.best = (hi, i);
+            }
+            let (c, mi) = (tris[i].center, self.cost(tris, i).unwrap_or(1.0));
+            let walks = tris[i].neighbors.iter().filter_map(|&nb| {
+                let d = c.distance(tris[nb].center);
+                Some((nb, d * 0.5 * (mi + self.cost(tris, nb)?), None))
+            });
+            let jumps = self.links.get(&i).into_iter().flatten().filter_map(|l| {
+                let mn = self.cost(tris, l.to_tri)?;
+                let d = c.distance(l.from) * mi + l.to.distance(tris[l.to_tri].center) * mn;
+                Some((l.to_tri, d + l.cost, Some(*l)))
+            });
+            let steps: Vec<_> = walks.chain(jumps).collect();
+            for (nb, step, via) in steps {
+                let ng = self.gscore[i] + step;
+                if ng < self.gscore[nb] {
+                    self.came[nb] = Some((i, via));
+                    self.gscore[nb] = ng;
+                    let f = ng + self.h(tris, nb);
+                    self.open.push(Node { f, i: nb });
+                }
+            }
+        }
+        used
+    }
+
+    /// The corridor found so far, each step with the link used to enter it, and whether it
+    /// reaches the goal; if not, it ends at the explored triangle closest to the goal.
+    pub(crate) fn corridor(&self) -> (Vec<(usize, Option<LinkEdge>)>, bool) {
+        let mut path = vec![];
+        let mut cur = self.best.1;
+        loop {
+            match self.came[cur] {
+                Some((prev, via)) if cur != self.start => {
+                    path.push((cur, via));
+                    cur = prev;
+                }
+                _ => break,
+            }
+        }
+        path.push((self.start, None));
+        path.reverse();
+        (path, self.best.1 == self.goal)
+    }
+}
+
+/// Collect every shared edge of the mesh, indexed by triangle.
+pub fn build_portals(nav: &NavMesh) -> PortalGraph {
+    let mut portals = vec![];
+    let mut tri_to_portals = vec![vec![]; nav.tris.len()];
+    for (i, t) in nav.tris.iter().enumerate() {
+        for &j in &t.neighbors {
+            if j < i {
+                continue;
+            }
+            if let Some((a, b)) = shared_edge(t, &nav.tris[j]) {
+                let pid = portals.len();
+                portals.push(Portal {
+                    a,
+                    b,
+                    left_tri: i,
+                    right_tri: j,
+                });
+                tri_to_portals[i].push(pid);
+                tri_to_portals[j].push(pid);
+            }
+        }
+    }
+    PortalGraph {
+        portals,
+        tri_to_portals,
+    }
+}
+
+/// Funnel / string-pull through the portals along a triangle corridor.
+pub fn string_pull(
+    nav: &NavMesh,
+    pg: &PortalGraph,
+    tri_path: &[usize],
+    start: Vec3,
+    goal: Vec3,
+) -> Vec<Vec3> {
+    let portals: Vec<(Vec3, Vec3)> = tri_path
+        .windows(2)
+        .filter_map(|w| {
+            let (t0, t1) = (w[0], w[1]);
+            let pid = pg.tri_to_portals.get(t0)?.iter().find(|&&pid| {
+                let p = &pg.portals[pid];
+                (p.left_tri == t0 && p.right_tri == t1) || (p.left_tri == t1 && p.right_tri == t0)
+            })?;
+            let p = &pg.portals[*pid];
+            Some(orient_portal(nav.tris[t0].center, p.a, p.b))
+        })
+        .collect();
+    funnel(start, &portals, goal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BakeConfig, NavMesh, Triangle};
+    use glam::{vec3, Vec3};
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn on_mesh(nav: &NavMesh, p: Vec3) -> bool {
+        nav.tris.iter().any(|t| super::height_at(t, p).is_some())
+    }
+
+    #[test]
+    fn open_floor_path_is_straight() {
+        let nav = NavMesh::bake_with(&quad(-5.0, -5.0, 5.0, 5.0, 0.0), &BakeConfig::default());
+        let path = nav.find_path(vec3(-3.0, 0.0, -3.0), vec3(3.0, 0.0, 3.0));
+        assert_eq!(path.len(), 2, "{path:?}");
+    }
+
+    #[test]
+    fn corner_path_hugs_the_corner_and_stays_on_mesh() {
+        // L-shaped corridor: along x at the bottom, then up along z on the right.
+        let mut geo = quad(-6.0, -6.0, 6.0, -2.0, 0.0);
+        geo.extend(quad(2.0, -2.0, 6.0, 6.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let path = nav.find_path(vec3(-5.0, 0.0, -4.0), vec3(4.0, 0.0, 5.0));
+        assert!(path.len() >= 3, "{path:?}");
+        for w in path.windows(2) {
+            for k in 0..=20 {
+                let p = w[0].lerp(w[1], k as f32 / 20.0);
+                assert!(on_mesh(&nav, p), "{p} leaves the mesh on {path:?}");
+            }
+        }
+        // The bend sits near the inner corner, not at some triangle center.
+        let bend = path[1];
+        assert!(
+            bend.distance(vec3(2.0, bend.y, -2.0)) < 1.5,
+            "bend at {bend}"
+        );
+    }
+
+    #[test]
+    fn unreachable_goal_gives_partial_path() {
+        let mut geo = quad(-5.0, -2.0, 0.0, 2.0, 0.0);
+        geo.extend(quad(3.0, -2.0, 8.0, 2.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let goal = vec3(6.0, 0.0, 0.0);
+        assert!(nav.find_path(vec3(-4.0, 0.0, 0.0), goal).is_empty());
+
+        let partial = nav.query_path(vec3(-4.0, 0.0, 0.0), goal).unwrap();
+        assert!(!partial.complete);
+        let end = *partial.points.last().unwrap();
+        assert!(end.x > -1.0 && end.x < 0.0, "ends at {end}");
+    }
+
+    #[test]
+    fn grid_and_navmesh_answer_through_the_same_trait() {
+        use astraweave_core::{GridMapping, GridPathfinder, Pathfinder, World};
+
+        // Two rooms joined by a doorway at z = 0, on a grid with half-metre cells.
+        let mut geo = quad(-6.0, -6.0, -1.0, 6.0, 0.0);
+        geo.extend(quad(-1.0, -1.0, 1.0, 1.0, 0.0));
+        geo.extend(quad(1.0, -6.0, 6.0, 6.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let mut w = World::new();
+        w.grid = GridMapping {
+            origin: Vec3::ZERO,
+            cell_size: 0.5,
+        };
+        for y in -12i32..=12 {
+            if y.abs() > 2 {
+                w.obstacles.extend((-2..=2).map(|x| (x, y)));
+            }
+        }
+        let grid = GridPathfinder::new(&w, (-12, -12, 12, 12));
+        assert_eq!(w.grid.to_cell(vec3(-0.26, 0.0, 0.74)).x, -1);
+        assert_eq!(w.grid.to_cell(vec3(-0.26, 0.0, 0.74)).y, 1);
+
+        let layers: [&dyn Pathfinder; 2] = [&grid, &nav];
+        let (a, b) = (vec3(-4.0, 0.0, -4.0), vec3(4.0, 0.0, 4.0));
+        for p in layers {
+            let path = p.find_path(a, b);
+            assert!(path.first().unwrap().distance(a) < 0.5);
+            assert!(path.last().unwrap().distance(b) < 0.5);
+            // Crosses the wall line through the doorway.
+            let cross = path
+                .windows(2)
+                .find(|s| s[0].x < 0.0 && s[1].x >= 0.0)
+                .unwrap();
+            let t = -cross[0].x / (cross[1].x - cross[0].x);
+            assert!(
+                (cross[0].z + t * (cross[1].z - cross[0].z)).abs() < 1.3,
+                "{path:?}"
+            );
+            assert!(p.is_walkable(vec3(0.0, 0.0, 0.0)));
+            assert!(!p.is_walkable(vec3(0.0, 0.0, 4.0)));
+            assert!(!p.path_exists(a, vec3(0.0, 0.0, 4.0)));
+        }
+    }
+
+    #[test]
+    fn plans_are_checked_against_the_navmesh() {
+        use astraweave_core::{
+            validate_and_execute_with, ActionStep, EngineError, IVec2, PlanIntent, Team,
+            ValidateCfg, World,
+        };
+
+        // Two rooms with no way between them; the grid has no walls at all.
+        let mut geo = quad(-6.0, -6.0, -1.0, 6.0, 0.0);
+        geo.extend(quad(1.0, -6.0, 6.0, 6.0, 0.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let mut w = World::new();
+        let me = w.spawn("Comp", IVec2 { x: -4, y: 0 }, Team { id: 1 }, 80, 30);
+        let cfg = ValidateCfg {
+            world_bounds: (-8, -8, 8, 8),
+        };
+        let go = |w: &mut World, x, y| {
+            let intent = PlanIntent {
+                plan_id: "p".into(),
+                steps: vec![ActionStep::MoveTo { x, y }],
+            };
+            validate_and_execute_with(w, me, &intent, &cfg, Some(&nav), None, &mut |_| {})
+        };
+
+        go(&mut w, -2, 3).unwrap();
+        assert_eq!(w.pos_of(me), Some(IVec2 { x: -2, y: 3 }));
+        assert!(matches!(go(&mut w, 4, 0), Err(EngineError::NoPath)));
+        assert_eq!(w.pos_of(me), Some(IVec2 { x: -2, y: 3 }));
+        // Past the room's edge is off the mesh, not snapped back onto it.
+        assert!(matches!(go(&mut w, -2, 7), Err(EngineError::NoPath)));
+        assert_eq!(w.pos_of(me), Some(IVec2 { x: -2, y: 3 }));
+    }
+
+    #[test]
+    fn locate_projects_onto_the_surface() {
+        let mut geo = quad(-5.0, -5.0, 5.0, 5.0, 0.0);
+        geo.extend(quad(-2.0, -2.0, 2.0, 2.0, 3.0));
+        let nav = NavMesh::bake_with(&geo, &BakeConfig::default());
+        let (_, low) = nav.locate(vec3(0.0, 0.5, 0.0)).unwrap();
+        let (_, high) = nav.locate(vec3(0.0, 2.9, 0.0)).unwrap();
+        assert!(low.y < 0.5 && high.y > 2.9, "{low} {high}");
+        let (_, outside) = nav.locate(vec3(20.0, 0.0, 0.0)).unwrap();
+        assert!(outside.x < 5.0);
+    }
+}
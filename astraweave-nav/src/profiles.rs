@@ -0,0 +1,237 @@
+//! Agent profiles: per-agent size limits, capabilities and area preferences, with one
+//! baked mesh per distinct agent size.
+
+use crate::{AreaCosts, BakeConfig, Capabilities, NavMesh, NavPath, PathFilter, Triangle};
+use glam::Vec3;
+
+#[derive(Clone, Debug)]
+pub struct AgentProfile {
+    pub name: String,
+    pub radius: f32,
+    pub height: f32,
+    pub max_step: f32,
+    pub max_slope_deg: f32,
+    pub caps: Capabilities,
+    pub areas: AreaCosts,
+}
+
+impl AgentProfile {
+    /// Profile with the default agent size from [`BakeConfig::default`].
+    pub fn new(name: &str) -> Self {
+        let cfg = BakeConfig::default();
+        Self {
+            name: name.to_string(),
+            radius: cfg.agent_radius,
+            height: cfg.agent_height,
+            max_step: cfg.max_step,
+            max_slope_deg: cfg.max_slope_deg,
+            caps: Capabilities::ALL,
+            areas: AreaCosts::default(),
+        }
+    }
+
+    /// `base` with this agent's size limits.
+    pub fn bake_config(&self, base: &BakeConfig) -> BakeConfig {
+        BakeConfig {
+            agent_radius: self.radius,
+            agent_height: self.height,
+            max_step: self.max_step,
+            max_slope_deg: self.max_slope_deg,
+            ..base.clone()
+        }
+    }
+
+    pub fn filter(&self) -> PathFilter {
+        PathFilter {
+            caps: self.caps,
+            areas: self.areas.clone(),
+        }
+    }
+
+    fn size_key(&self) -> [u32; 4] {
+        [
+            self.radius.to_bits(),
+            self.height.to_bits(),
+            self.max_step.to_bits(),
+            self.max_slope_deg.to_bits(),
+        ]
+    }
+}
+
+/// Navmeshes for a set of agent profiles over the same level. Profiles of the same size
+/// share a mesh and differ only in their path filter.
+pub struct ProfileNavMeshes {
+    profiles: Vec<AgentProfile>,
+    mesh_of: Vec<usize>,
+    meshes: Vec<NavMesh>,
+}
+
+impl ProfileNavMeshes {
+    pub fn bake(tris: &[Triangle], base: &BakeConfig, profiles: Vec<AgentProfile>) -> Self {
+        let mut keys: Vec<[u32; 4]> = vec![];
+        let mut meshes = vec![];
+        let mut mesh_of = vec![];
+        for p in &profiles {
+            let key = p.size_key();
+            let i = match keys.iter().position(|k| *k == key) {
+                Some(i) => i,
+                None => {
+                    keys.push(key);
+                    meshes.push(NavMesh::bake_with(tris, &p.bake_config(base)));
+                    meshes.len() - 1
+                }
+            };
+            mesh_of.push(i);
+        }
+        Self {
+            profiles,
+            mesh_of,
+            meshes,
+        }
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.profiles.iter().position(|p| p.name == name)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&AgentProfile> {
+        self.index(name).map(|i| &self.profiles[i])
+    }
+
+    pub fn mesh(&self, name: &str) -> Option<&NavMesh> {
+        self.index(name).map(|i| &self.meshes[self.mesh_of[i]])
+    }
+
+    /// Meshes for every distinct agent size, e.g. to add the same off-mesh link to all.
+    pub fn meshes_mut(&mut self) -> impl Iterator<Item = &mut NavMesh> {
+        self.meshes.iter_mut()
+    }
+
+    /// Path for the named profile, on its mesh and with its filter.
+    pub fn query_path(&self, name: &str, start: Vec3, goal: Vec3) -> Option<NavPath> {
+        let i = self.index(name)?;
+        self.meshes[self.mesh_of[i]].query_path_filtered(start, goal, &self.profiles[i].filter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaType, AreaVolume, NavObstacle};
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    /// Open floor with a river across the middle, leaving a dry crossing at the top.
+    fn river_config() -> BakeConfig {
+        BakeConfig {
+            areas: vec![AreaVolume {
+                shape: NavObstacle::Box {
+                    min: vec3(-2.0, -1.0, -6.0),
+                    max: vec3(2.0, 1.0, 2.5),
+                },
+                area: AreaType::Water,
+            }],
+            ..BakeConfig::default()
+        }
+    }
+
+    #[test]
+    fn area_costs_change_the_route() {
+        let nav = NavMesh::bake_with(&quad(-10.0, -5.0, 10.0, 5.0, 0.0), &river_config());
+        assert!(nav.tris.iter().any(|t| t.area == AreaType::Water));
+        let (a, b) = (vec3(-8.0, 0.0, 0.0), vec3(8.0, 0.0, 0.0));
+
+        let wade = nav.query_path(a, b).unwrap();
+        assert_eq!(wade.points.len(), 2, "{:?}", wade.points);
+
+        let dry = PathFilter {
+            areas: AreaCosts::default().excluding(AreaType::Water),
+            ..PathFilter::default()
+        };
+        let around = nav.query_path_filtered(a, b, &dry).unwrap();
+        assert!(around.complete);
+        assert!(around
+            .tris
+            .iter()
+            .all(|&t| nav.tris[t].area != AreaType::Water));
+        assert!(
+            around.points.iter().any(|p| p.z >= 2.4),
+            "{:?}",
+            around.points
+        );
+
+        let mut nowhere = AreaCosts::default();
+        nowhere.include.insert(AreaType::Cover);
+        let stuck = nav
+            .query_path_filtered(
+                a,
+                b,
+                &PathFilter {
+                    areas: nowhere,
+                    ..PathFilter::default()
+                },
+            )
+            .unwrap();
+        assert!(!stuck.complete);
+    }
+
+    #[test]
+    fn profiles_route_differently_over_the_same_level() {
+        // Two rooms joined by a 2m corridor, and a wide detour through the river.
+        let mut geo = quad(-10.0, -5.0, -3.0, 5.0, 0.0);
+        geo.extend(quad(3.0, -5.0, 10.0, 5.0, 0.0));
+        geo.extend(quad(-3.0, 3.0, 3.0, 5.0, 0.0));
+        geo.extend(quad(-3.0, -5.0, 3.0, -1.0, 0.0));
+        let base = BakeConfig {
+            areas: vec![AreaVolume {
+                shape: NavObstacle::Box {
+                    min: vec3(-3.5, -1.0, -6.0),
+                    max: vec3(3.5, 1.0, -0.5),
+                },
+                area: AreaType::Water,
+            }],
+            ..BakeConfig::default()
+        };
+        let mut brute = AgentProfile::new("brute");
+        brute.radius = 1.2;
+        let mut scout = AgentProfile::new("scout");
+        scout.radius = 0.3;
+        scout.areas = AreaCosts::soldier().excluding(AreaType::Water);
+        let mut swimmer = AgentProfile::new("swimmer");
+        swimmer.radius = 0.3;
+        let nav = ProfileNavMeshes::bake(&geo, &base, vec![brute, scout, swimmer]);
+        assert!(std::ptr::eq(
+            nav.mesh("scout").unwrap(),
+            nav.mesh("swimmer").unwrap()
+        ));
+
+        let (a, b) = (vec3(-7.0, 0.0, 3.5), vec3(7.0, 0.0, 3.5));
+        // The brute is too wide for the corridor and has to wade.
+        let brute_path = nav.query_path("brute", a, b).unwrap();
+        assert!(brute_path.complete);
+        assert!(brute_path.points.iter().any(|p| p.z < 0.0));
+        // The scout fits through the corridor and avoids the water.
+        let scout_path = nav.query_path("scout", a, b).unwrap();
+        assert!(scout_path.complete);
+        assert!(
+            scout_path.points.iter().all(|p| p.z > 3.0),
+            "{:?}",
+            scout_path.points
+        );
+        assert!(nav.query_path("nobody", a, b).is_none());
+    }
+}
@@ -0,0 +1,368 @@
+//! Time-sliced path requests. Searches are spread over frames under a per-tick expansion
+//! budget, narrowed by [`NavClusters`], and recent corridors are reused.
+
+use crate::hierarchy::NavClusters;
+use crate::links::LinkEdge;
+use crate::path::TriSearch;
+use crate::{NavMesh, NavPath, PathFilter};
+use glam::Vec3;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Default)]
+struct Slot {
+    result: Option<Option<NavPath>>,
+    waker: Option<Waker>,
+}
+
+/// Pending result of [`PathQueue::request`]. Either `.await` it or check
+/// [`PathHandle::try_take`] each frame. Dropping the handle cancels the request.
+pub struct PathHandle {
+    slot: Arc<Mutex<Slot>>,
+}
+
+impl PathHandle {
+    pub fn is_ready(&self) -> bool {
+        self.slot.lock().unwrap().result.is_some()
+    }
+
+    /// The finished query, once; `Some(None)` if the start or goal is off the mesh.
+    pub fn try_take(&mut self) -> Option<Option<NavPath>> {
+        self.slot.lock().unwrap().result.take()
+    }
+}
+
+impl Future for PathHandle {
+    type Output = Option<NavPath>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(path) => Poll::Ready(path),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub completed: usize,
+    pub cache_hits: usize,
+    /// Triangles expanded over all ticks.
+    pub expanded: usize,
+}
+
+struct Request {
+    start: Vec3,
+    goal: Vec3,
+    filter: PathFilter,
+    slot: Arc<Mutex<Slot>>,
+}
+
+impl Request {
+    fn cancelled(&self) -> bool {
+        Arc::strong_count(&self.slot) == 1
+    }
+
+    fn fulfill(self, path: Option<NavPath>) {
+        let mut slot = self.slot.lock().unwrap();
+        slot.result = Some(path);
+        if let Some(w) = slot.waker.take() {
+            w.wake();
+        }
+    }
+}
+
+struct Active {
+    req: Request,
+    located: (usize, usize),
+    sp: Vec3,
+    gp: Vec3,
+    key: CacheKey,
+    search: TriSearch,
+    narrowed: bool,
+}
+
+/// Start triangle, goal triangle and a hash of the filter.
+type CacheKey = (usize, usize, u64);
+type Corridor = Vec<(usize, Option<LinkEdge>)>;
+
+/// Path requests for one navmesh, answered a slice at a time from [`PathQueue::tick`].
+pub struct PathQueue {
+    /// Triangle expansions per tick, shared by all requests in turn.
+    pub budget: usize,
+    /// Floor-plan size of a [`NavClusters`] cell.
+    pub cluster_size: f32,
+    /// Most complete corridors kept for reuse.
+    pub cache_capacity: usize,
+    clusters: Option<NavClusters>,
+    requests: VecDeque<Request>,
+    active: Option<Active>,
+    cache: HashMap<CacheKey, Corridor>,
+    cache_order: VecDeque<CacheKey>,
+    stats: QueueStats,
+}
+
+impl PathQueue {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            cluster_size: 8.0,
+            cache_capacity: 64,
+            clusters: None,
+            requests: VecDeque::new(),
+            active: None,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+            stats: QueueStats::default(),
+        }
+    }
+
+    pub fn request(&mut self, start: Vec3, goal: Vec3, filter: PathFilter) -> PathHandle {
+        let slot = Arc::new(Mutex::new(Slot::default()));
+        self.requests.push_back(Request {
+            start,
+            goal,
+            filter,
+            slot: slot.clone(),
+        });
+        PathHandle { slot }
+    }
+
+    /// Requests not answered yet, including the one being searched.
+    pub fn pending(&self) -> usize {
+        self.requests.len() + usize::from(self.active.is_some())
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+
+    /// Work through the queue until the budget runs out, returning how much was used.
+    /// Locating a request's ends or answering it from the cache costs one unit, each
+    /// expanded triangle another.
+    pub fn tick(&mut self, nav: &NavMesh) -> usize {
+        let current = self
+            .clusters
+            .as_ref()
+            .is_some_and(|c| c.is_current(nav) && c.cluster_size() == self.cluster_size);
+        if !current {
+            self.clusters = Some(NavClusters::build(nav, self.cluster_size));
+            self.cache.clear();
+            self.cache_order.clear();
+            if let Some(a) = self.active.take() {
+                self.requests.push_front(a.req);
+            }
+        }
+
+        let mut left = self.budget;
+        while left > 0 {
+            let Some(active) = self.active.as_mut() else {
+                let Some(req) = self.requests.pop_front() else {
+                    break;
+                };
+                left -= 1;
+                self.begin(nav, req);
+                continue;
+            };
+            if active.req.cancelled() {
+                self.active = None;
+                continue;
+            }
+            let used = active.search.step(&nav.tris, left);
+            left -= used;
+            self.stats.expanded += used;
+            if active.search.is_done() {
+                self.finish(nav);
+            }
+        }
+        self.budget - left
+    }
+
+    fn begin(&mut self, nav: &NavMesh, req: Request) {
+        if req.cancelled() {
+            return;
+        }
+        let (Some((s, sp)), Some((g, gp))) = (nav.locate(req.start), nav.locate(req.goal)) else {
+            self.stats.completed += 1;
+            req.fulfill(None);
+            return;
+        };
+        let key = (s, g, filter_key(&req.filter));
+        if let Some(steps) = self.cache.get(&key) {
+            let path = nav.path_along(sp, (req.goal, gp), steps, true);
+            self.cache_order.retain(|k| *k != key);
+            self.cache_order.push_back(key);
+            self.stats.cache_hits += 1;
+            self.stats.completed += 1;
+            req.fulfill(path);
+            return;
+        }
+        let clusters = self.clusters.as_ref().unwrap();
+        self.active = Some(Active {
+            search: clusters.search(nav, (s, g), gp, &req.filter),
+            req,
+            located: (s, g),
+            sp,
+            gp,
+            key,
+            narrowed: true,
+        });
+    }
+
+    fn finish(&mut self, nav: &NavMesh) {
+        let mut a = self.active.take().unwrap();
+        let (steps, complete) = a.search.corridor();
+        if !complete && a.narrowed {
+            // The cluster corridor did not work out for this filter; search everywhere.
+            a.search = TriSearch::new(nav, a.located, a.gp, &a.req.filter, None);
+            a.narrowed = false;
+            self.active = Some(a);
+            return;
+        }
+        let path = nav.path_along(a.sp, (a.req.goal, a.gp), &steps, complete);
+        if complete && self.cache_capacity > 0 {
+            if self.cache.len() >= self.cache_capacity {
+                if let Some(old) = self.cache_order.pop_front() {
+                    self.cache.remove(&old);
+                }
+            }
+            self.cache.insert(a.key, steps);
+            self.cache_order.push_back(a.key);
+        }
+        self.stats.completed += 1;
+        a.req.fulfill(path);
+    }
+}
+
+fn filter_key(f: &PathFilter) -> u64 {
+    let mut h = DefaultHasher::new();
+    f.caps.hash(&mut h);
+    for (area, m) in &f.areas.multipliers {
+        area.hash(&mut h);
+        m.to_bits().hash(&mut h);
+    }
+    f.areas.include.hash(&mut h);
+    f.areas.exclude.hash(&mut h);
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BakeConfig, OffMeshLink, Triangle};
+    use glam::vec3;
+    use std::task::Waker;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn corridor() -> NavMesh {
+        let mut geo = quad(-12.0, -12.0, 12.0, -8.0, 0.0);
+        geo.extend(quad(8.0, -8.0, 12.0, 12.0, 0.0));
+        NavMesh::bake_with(&geo, &BakeConfig::default())
+    }
+
+    fn run(queue: &mut PathQueue, nav: &NavMesh, handle: &mut PathHandle) -> (usize, NavPath) {
+        for ticks in 1..1000 {
+            queue.tick(nav);
+            if let Some(path) = handle.try_take() {
+                return (ticks, path.unwrap());
+            }
+        }
+        panic!("request never finished");
+    }
+
+    #[test]
+    fn small_budget_spreads_the_search_over_ticks() {
+        let nav = corridor();
+        let (a, b) = (vec3(-10.0, 0.0, -10.0), vec3(10.0, 0.0, 10.0));
+        let mut queue = PathQueue::new(2);
+        let mut handle = queue.request(a, b, PathFilter::default());
+        let (ticks, path) = run(&mut queue, &nav, &mut handle);
+        assert!(ticks > 1);
+        assert!(path.complete);
+        assert_eq!(path.points, nav.query_path(a, b).unwrap().points);
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn repeated_routes_come_from_the_cache_until_the_mesh_changes() {
+        let mut nav = corridor();
+        let (a, b) = (vec3(-10.0, 0.0, -10.0), vec3(10.0, 0.0, 10.0));
+        let mut queue = PathQueue::new(1000);
+        let mut first = queue.request(a, b, PathFilter::default());
+        let mut second = queue.request(a, b, PathFilter::default());
+        queue.tick(&nav);
+        let (first, second) = (first.try_take().unwrap(), second.try_take().unwrap());
+        assert_eq!(first.unwrap().points, second.unwrap().points);
+        assert_eq!(queue.stats().cache_hits, 1);
+
+        nav.add_link(OffMeshLink::teleport(a, b));
+        let mut third = queue.request(a, b, PathFilter::default());
+        queue.tick(&nav);
+        let third = third.try_take().unwrap().unwrap();
+        assert_eq!(queue.stats().cache_hits, 1);
+        assert_eq!(third.segments.len(), 1);
+    }
+
+    #[test]
+    fn handles_are_futures_and_dropping_one_cancels_it() {
+        let nav = corridor();
+        let mut queue = PathQueue::new(3);
+        let dropped = queue.request(
+            vec3(-10.0, 0.0, -10.0),
+            vec3(10.0, 0.0, 10.0),
+            PathFilter::default(),
+        );
+        drop(dropped);
+        let mut handle = queue.request(
+            vec3(10.0, 0.0, 10.0),
+            vec3(-10.0, 0.0, -10.0),
+            PathFilter::default(),
+        );
+        let mut off_mesh = queue.request(
+            vec3(10.0, 0.0, 10.0),
+            vec3(50.0, 0.0, 50.0),
+            PathFilter::default(),
+        );
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut handle).poll(&mut cx).is_pending());
+        let mut ready = None;
+        for _ in 0..1000 {
+            queue.tick(&nav);
+            if let Poll::Ready(path) = Pin::new(&mut handle).poll(&mut cx) {
+                ready = path;
+                break;
+            }
+        }
+        assert!(ready.unwrap().complete);
+        while queue.pending() > 0 {
+            queue.tick(&nav);
+        }
+        // The far goal snaps to the nearest point on the mesh.
+        assert!(off_mesh.try_take().unwrap().is_some());
+        assert_eq!(queue.stats().completed, 2);
+    }
+}
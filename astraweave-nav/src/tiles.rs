@@ -0,0 +1,909 @@
+//! Tiled navmesh: obstacles dirty only the tiles they touch, and those tiles are rebaked
+//! on the caller's thread or a background worker while the rest of the mesh stays usable.
+
+use crate::bake::{bake_tile, bounds, tile_border, TileBounds};
+use crate::io::{decode, encode, source_checksum, FileKind, NavFileError};
+use crate::links::AttachedLink;
+use crate::path::edge_overlap;
+use crate::{
+    link_shared_edges, AreaType, BakeConfig, LinkId, NavMesh, NavPath, OffMeshLink, Traversal,
+    Triangle,
+};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+/// A volume that blocks walking on any surface whose floor lies inside it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NavObstacle {
+    /// Axis-aligned box, e.g. a barricade or fortification.
+    Box { min: Vec3, max: Vec3 },
+    /// Upright cylinder standing on `base`.
+    Cylinder {
+        base: Vec3,
+        radius: f32,
+        height: f32,
+    },
+    /// Everything within `radius` of the segment `a`-`b`, e.g. a collapsed bridge.
+    Line { a: Vec3, b: Vec3, radius: f32 },
+}
+
+impl NavObstacle {
+    pub fn contains(&self, p: Vec3) -> bool {
+        match *self {
+            Self::Box { min, max } => p.cmpge(min).all() && p.cmple(max).all(),
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => {
+                let (dx, dz) = (p.x - base.x, p.z - base.z);
+                dx * dx + dz * dz <= radius * radius && p.y >= base.y && p.y <= base.y + height
+            }
+            Self::Line { a, b, radius } => {
+                let ab = b - a;
+                let len2 = ab.length_squared();
+                let t = if len2 > 0.0 {
+                    ((p - a).dot(ab) / len2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                p.distance_squared(a + ab * t) <= radius * radius
+            }
+        }
+    }
+
+    /// World-space bounding box.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        match *self {
+            Self::Box { min, max } => (min, max),
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => (
+                base - Vec3::new(radius, 0.0, radius),
+                base + Vec3::new(radius, height, radius),
+            ),
+            Self::Line { a, b, radius } => (
+                a.min(b) - Vec3::splat(radius),
+                a.max(b) + Vec3::splat(radius),
+            ),
+        }
+    }
+}
+
+/// Tags the walkable surface inside `shape` with an area type (water, hazard, cover, ...).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AreaVolume {
+    pub shape: NavObstacle,
+    pub area: AreaType,
+}
+
+/// Id of an obstacle or area volume added to a [`TiledNavMesh`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ObstacleId(pub u32);
+
+/// Handle for a path registered with [`TiledNavMesh::watch_path`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathTicket(pub u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NavEvent {
+    /// These tiles (x, z) were rebaked and swapped into the mesh.
+    TilesRebuilt(Vec<(i32, i32)>),
+    /// Sent with [`NavEvent::TilesRebuilt`]: the merged mesh was renumbered, and entry `i`
+    /// is the new index of what was triangle `i`, or `None` if its tile was rebaked.
+    /// Triangle and polygon indices held from before must be mapped through it.
+    TrisRemapped(Vec<Option<usize>>),
+    /// A temporary off-mesh link ran out and was removed.
+    LinkExpired(LinkId),
+    /// A watched path crosses a rebuilt tile or a removed link, or was partial and may now
+    /// reach its goal. The watch is dropped; the owner should replan and watch the new path.
+    PathInvalidated(PathTicket),
+}
+
+struct Watch {
+    /// Tiles the path crosses; `None` for partial paths.
+    tiles: Option<BTreeSet<usize>>,
+    links: BTreeSet<LinkId>,
+}
+
+struct TileJob {
+    tile: usize,
+    generation: u64,
+    bounds: TileBounds,
+    tris: Vec<Triangle>,
+    obstacles: Vec<NavObstacle>,
+    areas: Vec<AreaVolume>,
+}
+
+struct TileResult {
+    tile: usize,
+    generation: u64,
+    mesh: NavMesh,
+}
+
+impl TileJob {
+    fn run(self, cfg: &BakeConfig) -> TileResult {
+        TileResult {
+            tile: self.tile,
+            generation: self.generation,
+            mesh: bake_tile(&self.tris, cfg, &self.bounds, &self.obstacles, &self.areas),
+        }
+    }
+}
+
+struct Worker {
+    jobs: Option<Sender<TileJob>>,
+    results: Receiver<TileResult>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    fn spawn(cfg: BakeConfig) -> Self {
+        let (jobs, job_rx) = channel::<TileJob>();
+        let (result_tx, results) = channel();
+        let handle = std::thread::spawn(move || {
+            for job in job_rx {
+                if result_tx.send(job.run(&cfg)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            jobs: Some(jobs),
+            results,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Closing the job channel ends the worker loop.
+        self.jobs.take();
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+/// A navmesh baked in square tiles so runtime obstacles only rebake what they touch.
+///
+/// Call [`TiledNavMesh::update`] once per frame: it queues dirty tiles, swaps in finished
+/// ones and reports what changed through [`TiledNavMesh::drain_events`].
+pub struct TiledNavMesh {
+    cfg: BakeConfig,
+    source: Vec<Triangle>,
+    origin: Vec3,
+    tile_size: f32,
+    tiles_x: i32,
+    tiles_z: i32,
+    tiles: Vec<NavMesh>,
+    generation: Vec<u64>,
+    /// Tiles whose latest generation has not been queued yet.
+    dirty: BTreeSet<usize>,
+    /// Tiles queued on the worker, with the generation they were queued at.
+    pending: BTreeMap<usize, u64>,
+    obstacles: BTreeMap<ObstacleId, NavObstacle>,
+    /// Runtime area volumes, applied after the static ones in the bake config.
+    areas: BTreeMap<ObstacleId, AreaVolume>,
+    next_obstacle: u32,
+    mesh: NavMesh,
+    tri_tile: Vec<usize>,
+    watched: BTreeMap<PathTicket, Watch>,
+    next_ticket: u64,
+    events: Vec<NavEvent>,
+    worker: Option<Worker>,
+}
+
+impl TiledNavMesh {
+    /// Bake every tile of `tris` up front. `tile_size` is rounded to whole cells.
+    pub fn new(tris: &[Triangle], cfg: BakeConfig, tile_size: f32) -> Self {
+        let cs = cfg.cell_size;
+        let tile_size = (tile_size / cs).round().max(1.0) * cs;
+        let (origin, tiles_x, tiles_z) = match bounds(tris) {
+            Some((bmin, bmax)) => {
+                let origin = Vec3::new(
+                    (bmin.x / cs).floor() * cs,
+                    bmin.y,
+                    (bmin.z / cs).floor() * cs,
+                );
+                let count = |span: f32| ((span / tile_size).ceil() as i32).max(1);
+                (origin, count(bmax.x - origin.x), count(bmax.z - origin.z))
+            }
+            None => (Vec3::ZERO, 0, 0),
+        };
+        let n = (tiles_x * tiles_z) as usize;
+        let mut nav = Self {
+            mesh: NavMesh::empty(cfg.max_step, cfg.max_slope_deg),
+            cfg,
+            source: tris.to_vec(),
+            origin,
+            tile_size,
+            tiles_x,
+            tiles_z,
+            tiles: Vec::with_capacity(n),
+            generation: vec![0; n],
+            dirty: BTreeSet::new(),
+            pending: BTreeMap::new(),
+            obstacles: BTreeMap::new(),
+            areas: BTreeMap::new(),
+            next_obstacle: 1,
+            tri_tile: vec![],
+            watched: BTreeMap::new(),
+            next_ticket: 1,
+            events: vec![],
+            worker: None,
+        };
+        for t in 0..n {
+            let mesh = nav.job(t).run(&nav.cfg).mesh;
+            nav.tiles.push(mesh);
+        }
+        nav.rebuild_mesh();
+        nav
+    }
+
+    /// Rebake dirty tiles on a background thread from now on.
+    pub fn spawn_worker(&mut self) {
+        if self.worker.is_none() {
+            self.worker = Some(Worker::spawn(self.cfg.clone()));
+        }
+    }
+
+    /// The merged mesh of all tiles, for path queries.
+    pub fn mesh(&self) -> &NavMesh {
+        &self.mesh
+    }
+
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    /// Tile (x, z) containing `p`, if it is inside the baked area.
+    pub fn tile_of(&self, p: Vec3) -> Option<(i32, i32)> {
+        let tx = ((p.x - self.origin.x) / self.tile_size).floor() as i32;
+        let tz = ((p.z - self.origin.z) / self.tile_size).floor() as i32;
+        (tx >= 0 && tz >= 0 && tx < self.tiles_x && tz < self.tiles_z).then_some((tx, tz))
+    }
+
+    pub fn add_obstacle(&mut self, obstacle: NavObstacle) -> ObstacleId {
+        let id = ObstacleId(self.next_obstacle);
+        self.next_obstacle += 1;
+        self.mark_dirty(obstacle.bounds());
+        self.obstacles.insert(id, obstacle);
+        id
+    }
+
+    pub fn remove_obstacle(&mut self, id: ObstacleId) -> bool {
+        match self.obstacles.remove(&id) {
+            Some(o) => {
+                self.mark_dirty(o.bounds());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tag part of the mesh at runtime, e.g. spreading fire or a flooded street.
+    pub fn add_area(&mut self, volume: AreaVolume) -> ObstacleId {
+        let id = ObstacleId(self.next_obstacle);
+        self.next_obstacle += 1;
+        self.mark_dirty(volume.shape.bounds());
+        self.areas.insert(id, volume);
+        id
+    }
+
+    pub fn remove_area(&mut self, id: ObstacleId) -> bool {
+        match self.areas.remove(&id) {
+            Some(v) => {
+                self.mark_dirty(v.shape.bounds());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn obstacles(&self) -> impl Iterator<Item = (ObstacleId, &NavObstacle)> {
+        self.obstacles.iter().map(|(id, o)| (*id, o))
+    }
+
+    /// True while any tile is waiting to be rebaked or swapped in.
+    pub fn is_busy(&self) -> bool {
+        !self.dirty.is_empty() || !self.pending.is_empty()
+    }
+
+    /// Queue dirty tiles and swap in finished ones. Without a worker the dirty tiles are
+    /// baked right here. Returns how many tiles were replaced.
+    pub fn update(&mut self) -> usize {
+        let mut done = vec![];
+        if let Some(w) = &self.worker {
+            done.extend(w.results.try_iter());
+        }
+        for tile in std::mem::take(&mut self.dirty) {
+            let job = self.job(tile);
+            match self.worker.as_ref().and_then(|w| w.jobs.as_ref()) {
+                Some(jobs) => {
+                    self.pending.insert(tile, job.generation);
+                    // The worker only hangs up when dropped along with `self`.
+                    let _ = jobs.send(job);
+                }
+                None => done.push(job.run(&self.cfg)),
+            }
+        }
+        self.apply(done)
+    }
+
+    /// Block until every dirty tile has been rebaked and swapped in.
+    pub fn flush(&mut self) {
+        self.update();
+        while self.is_busy() {
+            let next = self.worker.as_ref().and_then(|w| w.results.recv().ok());
+            match next {
+                Some(r) => {
+                    self.apply(vec![r]);
+                }
+                None => self.pending.clear(),
+            }
+            self.update();
+        }
+    }
+
+    /// Add an off-mesh link; it stays attached across tile rebuilds.
+    pub fn add_link(&mut self, link: OffMeshLink) -> LinkId {
+        let id = self.mesh.add_link(link);
+        self.invalidate(|w| w.tiles.is_none());
+        id
+    }
+
+    pub fn remove_link(&mut self, id: LinkId) -> Option<OffMeshLink> {
+        let link = self.mesh.remove_link(id)?;
+        self.invalidate(|w| w.links.contains(&id));
+        Some(link)
+    }
+
+    /// Age temporary links, dropping expired ones and the paths that used them.
+    pub fn tick_links(&mut self, dt: f32) {
+        for id in self.mesh.tick_links(dt) {
+            self.events.push(NavEvent::LinkExpired(id));
+            self.invalidate(|w| w.links.contains(&id));
+        }
+    }
+
+    /// Track a path so a [`NavEvent::PathInvalidated`] is raised when a tile or link under
+    /// it changes.
+    pub fn watch_path(&mut self, path: &NavPath) -> PathTicket {
+        let ticket = PathTicket(self.next_ticket);
+        self.next_ticket += 1;
+        let tiles = path.complete.then(|| {
+            path.tris
+                .iter()
+                .filter_map(|&t| self.tri_tile.get(t).copied())
+                .collect()
+        });
+        let links = path
+            .segments
+            .iter()
+            .filter_map(|s| match s.traversal {
+                Traversal::Link { id, .. } => Some(id),
+                Traversal::Walk => None,
+            })
+            .collect();
+        self.watched.insert(ticket, Watch { tiles, links });
+        ticket
+    }
+
+    pub fn unwatch_path(&mut self, ticket: PathTicket) {
+        self.watched.remove(&ticket);
+    }
+
+    pub fn drain_events(&mut self) -> Vec<NavEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn coords(&self, tile: usize) -> (i32, i32) {
+        (tile as i32 % self.tiles_x, tile as i32 / self.tiles_x)
+    }
+
+    fn tile_bounds(&self, tile: usize) -> TileBounds {
+        let (tx, tz) = self.coords(tile);
+        let min =
+            self.origin + Vec3::new(tx as f32 * self.tile_size, 0.0, tz as f32 * self.tile_size);
+        TileBounds {
+            min,
+            max: min + Vec3::new(self.tile_size, 0.0, self.tile_size),
+        }
+    }
+
+    /// Width of the skirt each tile bakes around itself, in world units.
+    fn border(&self) -> f32 {
+        tile_border(&self.cfg) as f32 * self.cfg.cell_size
+    }
+
+    fn job(&self, tile: usize) -> TileJob {
+        let b = self.tile_bounds(tile);
+        let pad = self.border();
+        let (lo, hi) = (b.min.x - pad, b.max.x + pad);
+        let (zlo, zhi) = (b.min.z - pad, b.max.z + pad);
+        let overlaps =
+            |min: Vec3, max: Vec3| max.x >= lo && min.x <= hi && max.z >= zlo && min.z <= zhi;
+        TileJob {
+            tile,
+            generation: self.generation[tile],
+            bounds: b,
+            tris: self
+                .source
+                .iter()
+                .filter(|t| overlaps(t.a.min(t.b).min(t.c), t.a.max(t.b).max(t.c)))
+                .cloned()
+                .collect(),
+            obstacles: self
+                .obstacles
+                .values()
+                .filter(|o| {
+                    let (min, max) = o.bounds();
+                    overlaps(min, max)
+                })
+                .cloned()
+                .collect(),
+            areas: self
+                .cfg
+                .areas
+                .iter()
+                .chain(self.areas.values())
+                .filter(|v| {
+                    let (min, max) = v.shape.bounds();
+                    overlaps(min, max)
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn mark_dirty(&mut self, (min, max): (Vec3, Vec3)) {
+        let pad = self.border();
+        let cell =
+            |v: f32, o: f32, n: i32| (((v - o) / self.tile_size).floor() as i32).clamp(0, n - 1);
+        if self.tiles.is_empty() {
+            return;
+        }
+        let (x0, x1) = (
+            cell(min.x - pad, self.origin.x, self.tiles_x),
+            cell(max.x + pad, self.origin.x, self.tiles_x),
+        );
+        let (z0, z1) = (
+            cell(min.z - pad, self.origin.z, self.tiles_z),
+            cell(max.z + pad, self.origin.z, self.tiles_z),
+        );
+        for tz in z0..=z1 {
+            for tx in x0..=x1 {
+                let t = (tz * self.tiles_x + tx) as usize;
+                self.generation[t] += 1;
+                self.dirty.insert(t);
+            }
+        }
+    }
+
+    fn apply(&mut self, results: Vec<TileResult>) -> usize {
+        let old_start = self.tile_starts();
+        let mut changed = BTreeSet::new();
+        for r in results {
+            if self.pending.get(&r.tile) == Some(&r.generation) {
+                self.pending.remove(&r.tile);
+            }
+            // Results for a tile that was dirtied again since are stale; a newer job follows.
+            if r.generation == self.generation[r.tile] {
+                self.tiles[r.tile] = r.mesh;
+                changed.insert(r.tile);
+            }
+        }
+        if changed.is_empty() {
+            return 0;
+        }
+        let old_tile = std::mem::take(&mut self.tri_tile);
+        self.rebuild_mesh();
+        let new_start = self.tile_starts();
+        let remap = old_tile
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| (!changed.contains(&t)).then(|| new_start[t] + i - old_start[t]))
+            .collect();
+        self.events.push(NavEvent::TrisRemapped(remap));
+        self.events.push(NavEvent::TilesRebuilt(
+            changed.iter().map(|&t| self.coords(t)).collect(),
+        ));
+        self.invalidate(|w| w.tiles.as_ref().is_none_or(|ts| !ts.is_disjoint(&changed)));
+        changed.len()
+    }
+
+    /// Index of each tile's first triangle in the merged mesh.
+    fn tile_starts(&self) -> Vec<usize> {
+        self.tiles
+            .iter()
+            .scan(0, |n, t| {
+                let start = *n;
+                *n += t.tris.len();
+                Some(start)
+            })
+            .collect()
+    }
+
+    fn invalidate(&mut self, stale: impl Fn(&Watch) -> bool) {
+        let tickets: Vec<PathTicket> = self
+            .watched
+            .iter()
+            .filter(|(_, w)| stale(w))
+            .map(|(t, _)| *t)
+            .collect();
+        for ticket in tickets {
+            self.watched.remove(&ticket);
+            self.events.push(NavEvent::PathInvalidated(ticket));
+        }
+    }
+
+    fn rebuild_mesh(&mut self) {
+        self.mesh.tris.clear();
+        self.mesh.polys.clear();
+        self.tri_tile.clear();
+        for (t, tile) in self.tiles.iter().enumerate() {
+            let (tri_off, poly_off) = (self.mesh.tris.len(), self.mesh.polys.len());
+            self.mesh
+                .tris
+                .extend(tile.tris.iter().cloned().map(|mut nt| {
+                    nt.idx += tri_off;
+                    nt.poly += poly_off;
+                    nt
+                }));
+            self.mesh
+                .polys
+                .extend(tile.polys.iter().cloned().map(|mut p| {
+                    p.tris.iter_mut().for_each(|i| *i += tri_off);
+                    p
+                }));
+            self.tri_tile
+                .extend(std::iter::repeat_n(t, tile.tris.len()));
+        }
+        link_shared_edges(&mut self.mesh.tris);
+        self.link_tile_edges();
+        self.mesh.reattach_links();
+    }
+
+    /// Link triangles across tile boundaries, where outlines meet edge to edge but do not
+    /// necessarily share vertices.
+    fn link_tile_edges(&mut self) {
+        let on_line = |a: f32, b: f32, o: f32| {
+            let k = ((a - o) / self.tile_size).round();
+            let line = o + k * self.tile_size;
+            ((a - line).abs() < 1e-3 && (b - line).abs() < 1e-3).then_some(k as i32)
+        };
+        // (triangle, edge start, edge end), keyed by (axis, grid line).
+        type LineEdges = Vec<(usize, Vec3, Vec3)>;
+        let mut lines: HashMap<(u8, i32), LineEdges> = HashMap::new();
+        for (i, t) in self.mesh.tris.iter().enumerate() {
+            for e in 0..3 {
+                let (a, b) = (t.verts[e], t.verts[(e + 1) % 3]);
+                if let Some(k) = on_line(a.x, b.x, self.origin.x) {
+                    lines.entry((0, k)).or_default().push((i, a, b));
+                }
+                if let Some(k) = on_line(a.z, b.z, self.origin.z) {
+                    lines.entry((1, k)).or_default().push((i, a, b));
+                }
+            }
+        }
+        let max_step = self.cfg.max_step;
+        for edges in lines.values() {
+            for (n, &(i, a0, a1)) in edges.iter().enumerate() {
+                for &(j, b0, b1) in &edges[n + 1..] {
+                    if self.tri_tile[i] == self.tri_tile[j] {
+                        continue;
+                    }
+                    let (Some((p, q)), Some((r, s))) = (
+                        edge_overlap((a0, a1), (b0, b1)),
+                        edge_overlap((b0, b1), (a0, a1)),
+                    ) else {
+                        continue;
+                    };
+                    if ((p.y + q.y) - (r.y + s.y)).abs() * 0.5 > max_step {
+                        continue;
+                    }
+                    let tris = &mut self.mesh.tris;
+                    if !tris[i].neighbors.contains(&j) {
+                        tris[i].neighbors.push(j);
+                        tris[j].neighbors.push(i);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What a saved [`TiledNavMesh`] keeps: enough to carry on rebaking tiles after loading.
+#[derive(Serialize, Deserialize)]
+struct TiledFile {
+    cfg: BakeConfig,
+    source: Vec<Triangle>,
+    origin: Vec3,
+    tile_size: f32,
+    tiles_x: i32,
+    tiles_z: i32,
+    tiles: Vec<NavMesh>,
+    /// Tiles still waiting to be rebaked when the mesh was saved.
+    dirty: BTreeSet<usize>,
+    obstacles: BTreeMap<ObstacleId, NavObstacle>,
+    areas: BTreeMap<ObstacleId, AreaVolume>,
+    next_obstacle: u32,
+    links: Vec<AttachedLink>,
+    next_link: u32,
+}
+
+impl TiledNavMesh {
+    /// [`source_checksum`] of the geometry and settings the tiles are baked from.
+    pub fn source_checksum(&self) -> u64 {
+        source_checksum(&self.source, &self.cfg)
+    }
+
+    /// Encode every tile with the source geometry, runtime obstacles and areas, and
+    /// off-mesh links. Tiles not rebaked yet are rebaked after loading.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let file = TiledFile {
+            cfg: self.cfg.clone(),
+            source: self.source.clone(),
+            origin: self.origin,
+            tile_size: self.tile_size,
+            tiles_x: self.tiles_x,
+            tiles_z: self.tiles_z,
+            tiles: self.tiles.clone(),
+            dirty: self
+                .dirty
+                .iter()
+                .chain(self.pending.keys())
+                .copied()
+                .collect(),
+            obstacles: self.obstacles.clone(),
+            areas: self.areas.clone(),
+            next_obstacle: self.next_obstacle,
+            links: self.mesh.links.clone(),
+            next_link: self.mesh.next_link,
+        };
+        encode(FileKind::Tiled, self.source_checksum(), &file)
+    }
+
+    /// Decode a mesh written by [`TiledNavMesh::to_bytes`]. With `source`, a mesh baked
+    /// from other geometry fails with [`NavFileError::StaleSource`].
+    pub fn from_bytes(bytes: &[u8], source: Option<u64>) -> Result<Self, NavFileError> {
+        let f: TiledFile = decode(FileKind::Tiled, bytes, source)?;
+        let n = f.tiles.len();
+        if n != (f.tiles_x.max(0) * f.tiles_z.max(0)) as usize || f.dirty.iter().any(|&t| t >= n) {
+            return Err(NavFileError::Corrupt);
+        }
+        let mut mesh = NavMesh::empty(f.cfg.max_step, f.cfg.max_slope_deg);
+        mesh.links = f.links;
+        mesh.next_link = f.next_link;
+        let mut nav = Self {
+            mesh,
+            cfg: f.cfg,
+            source: f.source,
+            origin: f.origin,
+            tile_size: f.tile_size,
+            tiles_x: f.tiles_x,
+            tiles_z: f.tiles_z,
+            tiles: f.tiles,
+            generation: vec![0; n],
+            dirty: f.dirty,
+            pending: BTreeMap::new(),
+            obstacles: f.obstacles,
+            areas: f.areas,
+            next_obstacle: f.next_obstacle,
+            tri_tile: vec![],
+            watched: BTreeMap::new(),
+            next_ticket: 1,
+            events: vec![],
+            worker: None,
+        };
+        nav.rebuild_mesh();
+        Ok(nav)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), NavFileError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn load(path: impl AsRef<Path>, source: Option<u64>) -> Result<Self, NavFileError> {
+        Self::from_bytes(&std::fs::read(path)?, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, y: f32) -> Vec<Triangle> {
+        vec![
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x1, y, z1),
+                c: vec3(x1, y, z0),
+            },
+            Triangle {
+                a: vec3(x0, y, z0),
+                b: vec3(x0, y, z1),
+                c: vec3(x1, y, z1),
+            },
+        ]
+    }
+
+    fn wall(z0: f32) -> NavObstacle {
+        NavObstacle::Box {
+            min: vec3(1.0, -1.0, z0),
+            max: vec3(2.0, 2.0, 4.0),
+        }
+    }
+
+    #[test]
+    fn paths_cross_tile_edges() {
+        let nav = TiledNavMesh::new(
+            &quad(-10.0, -3.0, 10.0, 3.0, 0.0),
+            BakeConfig::default(),
+            4.0,
+        );
+        let mesh = nav.mesh();
+        let tiles: BTreeSet<_> = nav.tri_tile.iter().collect();
+        assert_eq!(tiles.len(), 10);
+        for (i, t) in mesh.tris.iter().enumerate() {
+            assert_eq!(t.idx, i);
+            assert!(mesh.polys[t.poly].tris.contains(&i));
+        }
+        let path = mesh
+            .query_path(vec3(-9.0, 0.0, 0.5), vec3(9.0, 0.0, -0.5))
+            .unwrap();
+        assert!(path.complete);
+        assert_eq!(path.points.len(), 2, "{:?}", path.points);
+    }
+
+    #[test]
+    fn obstacle_rebakes_touched_tiles_and_invalidates_paths() {
+        let mut nav = TiledNavMesh::new(
+            &quad(-10.0, -3.0, 10.0, 3.0, 0.0),
+            BakeConfig::default(),
+            4.0,
+        );
+        let (start, goal) = (vec3(-9.0, 0.0, 1.5), vec3(9.0, 0.0, 1.5));
+        let crossing = nav.mesh().query_path(start, goal).unwrap();
+        let far = nav
+            .mesh()
+            .query_path(vec3(-9.0, 0.0, 0.0), vec3(-7.0, 0.0, 0.0))
+            .unwrap();
+        let crossing_ticket = nav.watch_path(&crossing);
+        let far_ticket = nav.watch_path(&far);
+        // A triangle east of the wall, numbered after the tiles that get rebaked.
+        let east_tri = nav.mesh().locate(vec3(9.0, 0.0, 0.0)).unwrap().0;
+        let east_center = nav.mesh().tris[east_tri].center;
+        let near_tri = nav.mesh().locate(vec3(1.5, 0.0, 1.5)).unwrap().0;
+
+        let id = nav.add_obstacle(wall(-1.0));
+        assert!(nav.is_busy());
+        let rebuilt = nav.update();
+        assert!(rebuilt > 0 && rebuilt < 10, "{rebuilt} tiles rebuilt");
+        let events = nav.drain_events();
+        assert!(events.contains(&NavEvent::PathInvalidated(crossing_ticket)));
+        assert!(!events.contains(&NavEvent::PathInvalidated(far_ticket)));
+        // Triangles of untouched tiles can be followed to their new index.
+        let remap = events
+            .iter()
+            .find_map(|e| match e {
+                NavEvent::TrisRemapped(m) => Some(m),
+                _ => None,
+            })
+            .unwrap();
+        let moved = remap[east_tri].unwrap();
+        assert_eq!(nav.mesh().tris[moved].center, east_center);
+        assert_eq!(remap[near_tri], None);
+
+        // The new path squeezes through the gap below the wall.
+        let detour = nav.mesh().query_path(start, goal).unwrap();
+        assert!(detour.complete);
+        for w in detour.points.windows(2) {
+            for k in 0..=20 {
+                let p = w[0].lerp(w[1], k as f32 / 20.0);
+                assert!(
+                    !(p.x > 1.0 && p.x < 2.0 && p.z > -1.0),
+                    "{p} crosses the wall"
+                );
+            }
+        }
+
+        assert!(nav.remove_obstacle(id));
+        nav.update();
+        let clear = nav.mesh().query_path(start, goal).unwrap();
+        assert_eq!(clear.points.len(), 2, "{:?}", clear.points);
+    }
+
+    #[test]
+    fn sealed_corridor_leaves_a_partial_path() {
+        let mut nav = TiledNavMesh::new(
+            &quad(-10.0, -3.0, 10.0, 3.0, 0.0),
+            BakeConfig::default(),
+            4.0,
+        );
+        nav.add_obstacle(NavObstacle::Line {
+            a: vec3(1.5, 0.0, -4.0),
+            b: vec3(1.5, 0.0, 4.0),
+            radius: 0.5,
+        });
+        nav.update();
+        let path = nav
+            .mesh()
+            .query_path(vec3(-9.0, 0.0, 0.0), vec3(9.0, 0.0, 0.0))
+            .unwrap();
+        assert!(!path.complete);
+        let ticket = nav.watch_path(&path);
+        nav.add_obstacle(NavObstacle::Cylinder {
+            base: vec3(-8.0, -1.0, 0.0),
+            radius: 0.5,
+            height: 3.0,
+        });
+        nav.update();
+        // Partial paths are invalidated by any rebuild, since a way through may have opened.
+        assert!(nav
+            .drain_events()
+            .contains(&NavEvent::PathInvalidated(ticket)));
+    }
+
+    #[test]
+    fn links_survive_rebuilds_and_expiry_invalidates_paths() {
+        let mut geo = quad(-10.0, -3.0, -1.0, 3.0, 0.0);
+        geo.extend(quad(2.0, -3.0, 10.0, 3.0, 0.0));
+        let mut nav = TiledNavMesh::new(&geo, BakeConfig::default(), 4.0);
+        let mut bridge = OffMeshLink::bridge(vec3(-1.5, 0.0, 0.0), vec3(2.5, 0.0, 0.0));
+        bridge.lifetime = Some(5.0);
+        nav.add_link(bridge);
+        let (start, goal) = (vec3(-8.0, 0.0, 0.0), vec3(8.0, 0.0, 0.0));
+
+        // Rebake the tiles under both ends of the bridge.
+        nav.add_obstacle(wall(2.0));
+        nav.update();
+        let path = nav.mesh().query_path(start, goal).unwrap();
+        assert!(path.complete);
+        let ticket = nav.watch_path(&path);
+
+        nav.tick_links(6.0);
+        let events = nav.drain_events();
+        assert!(
+            events.contains(&NavEvent::PathInvalidated(ticket)),
+            "{events:?}"
+        );
+        assert!(!nav.mesh().query_path(start, goal).unwrap().complete);
+    }
+
+    #[test]
+    fn background_worker_matches_inline_bake() {
+        let geo = quad(-10.0, -3.0, 10.0, 3.0, 0.0);
+        let mut inline = TiledNavMesh::new(&geo, BakeConfig::default(), 4.0);
+        let mut threaded = TiledNavMesh::new(&geo, BakeConfig::default(), 4.0);
+        threaded.spawn_worker();
+        inline.add_obstacle(wall(-1.0));
+        threaded.add_obstacle(wall(-1.0));
+        // Dirty the same tiles twice; the first result is stale and must be dropped.
+        threaded.update();
+        threaded.add_obstacle(wall(0.0));
+        inline.add_obstacle(wall(0.0));
+        inline.update();
+        threaded.flush();
+        assert!(!threaded.is_busy());
+        assert_eq!(inline.mesh().tris.len(), threaded.mesh().tris.len());
+        let a = inline
+            .mesh()
+            .query_path(vec3(-9.0, 0.0, 1.0), vec3(9.0, 0.0, 1.0))
+            .unwrap();
+        let b = threaded
+            .mesh()
+            .query_path(vec3(-9.0, 0.0, 1.0), vec3(9.0, 0.0, 1.0))
+            .unwrap();
+        assert_eq!(a.points, b.points);
+    }
+}
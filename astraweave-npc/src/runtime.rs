@@ -3,18 +3,30 @@ use glam::{vec3, Vec3};
 use std::collections::HashMap;
 
 use astraweave_audio::AudioEngine;
+use astraweave_nav::{Crowd, CrowdAgentId, CrowdAgentParams, CrowdAgentState, NavMesh};
 use astraweave_physics::{BodyId, PhysicsWorld};
 
 use crate::{llm::LlmAdapter, profile::NpcProfile, NpcAction, NpcMode, NpcWorldView};
 
 pub type NpcId = u64;
 
+/// Half extents of an NPC's character capsule.
+pub const NPC_HALF_EXTENTS: Vec3 = vec3(0.4, 0.9, 0.4);
+
+/// From an NPC's feet up to its body centre. Bodies, profile homes and
+/// [`CommandSink::position_of`] are centres; the crowd and navmesh work with feet.
+pub const NPC_FEET_TO_CENTRE: Vec3 = vec3(0.0, NPC_HALF_EXTENTS.y + NPC_HALF_EXTENTS.x, 0.0);
+
 pub trait CommandSink {
     fn move_character(&mut self, body: BodyId, dir: Vec3, speed: f32);
     fn say(&mut self, speaker: &str, text: &str);
     fn open_shop(&mut self, npc_id: NpcId);
     fn call_guards(&mut self, pos: Vec3, reason: &str);
     fn give_quest(&mut self, npc_id: NpcId, quest_id: &str);
+    /// Where the body's centre is now, if the sink can tell.
+    fn position_of(&self, _body: BodyId) -> Option<Vec3> {
+        None
+    }
 }
 
 pub struct EngineCommandSink<'a> {
@@ -52,12 +64,18 @@ impl<'a> CommandSink for EngineCommandSink<'a> {
     fn give_quest(&mut self, _npc_id: NpcId, quest_id: &str) {
         println!("[Quest] Offered quest {}", quest_id);
     }
+
+    fn position_of(&self, body: BodyId) -> Option<Vec3> {
+        self.phys.body_transform(body).map(|m| m.w_axis.truncate())
+    }
 }
 
 pub struct Npc {
     pub id: NpcId,
     pub profile: NpcProfile,
     pub body: BodyId,
+    /// Steering agent in the manager's crowd.
+    pub agent: CrowdAgentId,
     pub mode: NpcMode,
     pub pending: Vec<NpcAction>,
     pub cooldown_talk: f32,
@@ -67,6 +85,8 @@ pub struct NpcManager {
     next_id: NpcId,
     npcs: HashMap<NpcId, Npc>,
     planner: Box<dyn LlmAdapter>,
+    crowd: Crowd,
+    nav: Option<NavMesh>,
 }
 
 impl NpcManager {
@@ -75,13 +95,35 @@ impl NpcManager {
             next_id: 1,
             npcs: HashMap::new(),
             planner,
+            crowd: Crowd::new(),
+            nav: None,
         }
     }
 
-    pub fn spawn_from_profile(&mut self, phys: &mut PhysicsWorld, prof: NpcProfile) -> NpcId {
-        // convert home to spawn pos; capsule half extents similar to character
+    /// Walkable area for `MoveTo`; without one NPCs head straight for their target.
+    pub fn set_navmesh(&mut self, nav: NavMesh) {
+        self.nav = Some(nav);
+    }
+
+    pub fn crowd(&self) -> &Crowd {
+        &self.crowd
+    }
+
+    pub fn spawn_from_profile(
+        &mut self,
+        phys: &mut PhysicsWorld,
+        prof: NpcProfile,
+    ) -> Result<NpcId> {
+        // home is where the body's centre starts; the crowd agent stands at its feet
         let pos = prof.home_vec3();
-        let body = phys.add_character(pos, vec3(0.4, 0.9, 0.4));
+        let body = phys.add_character(pos, NPC_HALF_EXTENTS, "character")?;
+        let agent = self.crowd.add_agent(
+            pos - NPC_FEET_TO_CENTRE,
+            CrowdAgentParams {
+                radius: 0.4,
+                ..CrowdAgentParams::default()
+            },
+        );
         let id = self.alloc_id();
         self.npcs.insert(
             id,
@@ -89,12 +131,13 @@ impl NpcManager {
                 id,
                 profile: prof,
                 body,
+                agent,
                 mode: NpcMode::Idle,
                 pending: vec![],
                 cooldown_talk: 0.0,
             },
         );
-        id
+        Ok(id)
     }
 
     pub fn update(
@@ -136,9 +179,35 @@ impl NpcManager {
             }
         }
 
-        // Execute collected actions
+        // Execute collected actions; walking goes through the crowd when there is a navmesh
         for (npc_id, body, display_name, act) in actions_to_execute {
-            Self::execute_action(glue, npc_id, body, &display_name, &act);
+            if let (NpcAction::MoveTo { pos, speed }, Some(_)) = (&act, &self.nav) {
+                let agent = self.npcs[&npc_id].agent;
+                if let Some(a) = self.crowd.agent_mut(agent) {
+                    a.params.max_speed = *speed;
+                }
+                self.crowd.set_target(agent, *pos - NPC_FEET_TO_CENTRE);
+                continue;
+            }
+            let home = self.npcs[&npc_id].profile.home_vec3();
+            Self::execute_action(glue, npc_id, body, home, &display_name, &act);
+        }
+
+        if let Some(nav) = &self.nav {
+            for npc in self.npcs.values() {
+                if let Some(p) = glue.position_of(npc.body) {
+                    self.crowd.set_position(npc.agent, p - NPC_FEET_TO_CENTRE);
+                }
+            }
+            self.crowd.update(nav, dt);
+            for npc in self.npcs.values() {
+                let Some(a) = self.crowd.agent(npc.agent) else {
+                    continue;
+                };
+                if a.state == CrowdAgentState::Moving || a.vel.length_squared() > 1e-4 {
+                    glue.move_character(npc.body, a.vel, a.vel.length());
+                }
+            }
         }
     }
 
@@ -162,20 +231,22 @@ impl NpcManager {
         Ok(())
     }
 
+    /// Carry out `act` directly; `home` stands in for the body's position when the sink
+    /// cannot report it.
     fn execute_action(
         glue: &mut dyn CommandSink,
         npc_id: NpcId,
         body: BodyId,
+        home: Vec3,
         display_name: &str,
         act: &NpcAction,
     ) {
+        let here = glue.position_of(body).unwrap_or(home);
         match act {
             NpcAction::Say { text } => glue.say(display_name, text),
             NpcAction::MoveTo { pos, speed } => {
-                // move in direct line toward pos (simple demo; pathfinding can be added)
-                // direction = (pos - current). normalized
-                // For now, we cannot query position from CommandSink, so move toward target directly
-                let dir = Vec3::new(pos.x - 0.0, 0.0, pos.z - 0.0); // placeholder calculation
+                // Without a navmesh, head straight for the target.
+                let dir = vec3(pos.x - here.x, 0.0, pos.z - here.z);
                 glue.move_character(body, dir, *speed);
             }
             NpcAction::Emote { kind } => {
@@ -183,21 +254,10 @@ impl NpcManager {
             }
             NpcAction::OpenShop => glue.open_shop(npc_id),
             NpcAction::GiveQuest { id } => glue.give_quest(npc_id, id),
-            NpcAction::CallGuards { reason } => {
-                // For now, use a placeholder position since we can't query body position via CommandSink
-                let placeholder_pos = Vec3::new(0.0, 0.0, 0.0);
-                glue.call_guards(placeholder_pos, reason);
-            }
+            NpcAction::CallGuards { reason } => glue.call_guards(here, reason),
         }
     }
 
-    #[allow(dead_code)]
-    fn body_pos(&self, _glue: &dyn CommandSink, _body: BodyId) -> Option<Vec3> {
-        // For now, we cannot query position via CommandSink.
-        // In your integration, pull from PhysicsWorld or World. For demo, return None to skip direction calc (handled by move_character).
-        None
-    }
-
     fn alloc_id(&mut self) -> NpcId {
         let id = self.next_id;
         self.next_id += 1;
@@ -1,75 +1,326 @@
 use anyhow::{anyhow, Result};
-use astraweave_memory::{CompanionProfile, Fact, Persona, Skill};
-use serde::Deserialize;
-use std::io::Read;
-
-#[derive(Deserialize)]
-struct Manifest {
-    tone: String,
-    risk: String,
-    humor: String,
-    voice: String,
-    #[serde(default)]
-    prefs_json: Option<String>,
-    #[serde(default)]
-    skills: Option<Vec<SkillEntry>>,
+use astraweave_memory::{canonical_json, CompanionProfile, ProfileSigningKey, TrustedKeys};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+mod pack;
+pub use pack::{
+    build_persona_pack, PackAssets, PackDependency, PackInfo, PackManifest, PersonaPack,
+    VoiceBankSpec, VoiceSpecEntry, PACK_SCHEMA_VERSION,
+};
+
+const MANIFEST_FILE: &str = "persona_manifest.toml";
+const SIGNATURE_FILE: &str = "persona_signature.toml";
+
+/// Detached signature stored next to the manifest. `signature` covers the canonical
+/// profile built from the pack (see `CompanionProfile::canonical_bytes`); `files`
+/// covers the SHA-256 of every other entry so assets cannot be swapped either. Packs
+/// signed before `files` existed carry only the profile signature; see
+/// `PackPolicy::allow_legacy_signatures`.
+#[derive(Serialize, Deserialize)]
+struct PackSignature {
+    key_id: String,
+    signature: String,
     #[serde(default)]
-    facts: Option<Vec<FactEntry>>,
+    files: Option<String>,
 }
-#[derive(Deserialize)]
-struct SkillEntry {
-    name: String,
-    level: u8,
-    notes: String,
+
+/// How strictly pack loading treats signatures.
+#[derive(Clone, Debug, Default)]
+pub struct PackPolicy {
+    /// Refuse packs that are unsigned, signed by an unknown key, or tampered with.
+    pub require_trusted: bool,
+    pub trusted: TrustedKeys,
+    /// Accept signatures without a file digest on schema 1 packs that hold nothing but
+    /// the manifest. Any other pack missing the digest is refused under `require_trusted`.
+    pub allow_legacy_signatures: bool,
 }
-#[derive(Deserialize)]
-struct FactEntry {
-    k: String,
-    v: String,
-    t: String,
+
+impl PackPolicy {
+    pub fn require(trusted: TrustedKeys) -> Self {
+        Self {
+            require_trusted: true,
+            trusted,
+            allow_legacy_signatures: false,
+        }
+    }
 }
 
+/// Load a pack without signature enforcement. A signature shipped in the pack is
+/// attached to the profile so callers can still `verify` it themselves.
 pub fn load_persona_zip(path: &str) -> Result<CompanionProfile> {
+    load_persona_zip_with(path, &PackPolicy::default())
+}
+
+pub fn load_persona_zip_with(path: &str, policy: &PackPolicy) -> Result<CompanionProfile> {
+    Ok(load_persona_pack(path, policy)?.profile)
+}
+
+/// Load every asset in a pack (schema 1 or 2) and apply the signature policy.
+pub fn load_persona_pack(path: &str, policy: &PackPolicy) -> Result<PersonaPack> {
     let file = std::fs::File::open(path)?;
     let mut zip = zip::ZipArchive::new(file)?;
-    let mut manifest_txt = String::new();
-    {
-        let mut mf = zip
-            .by_name("persona_manifest.toml")
-            .map_err(|_| anyhow!("persona_manifest.toml missing"))?;
-        mf.read_to_string(&mut manifest_txt)?;
+    let mut pack = PersonaPack::from_zip(&mut zip)?;
+    let sig = read_signature(&pack.files)?;
+    if let Some(sig) = &sig {
+        pack.profile
+            .attach_signature(sig.key_id.clone(), sig.signature.clone());
+    }
+    if policy.require_trusted {
+        let reject = |e| anyhow!("persona pack {path} rejected: {e}");
+        pack.profile.verify(&policy.trusted).map_err(reject)?;
+        let sig = sig.expect("verified profiles carry a signature");
+        match &sig.files {
+            Some(files) => policy
+                .trusted
+                .verify_bytes(&sig.key_id, &files_digest(&pack.files), files)
+                .map_err(reject)?,
+            None if policy.allow_legacy_signatures && pack.is_legacy() => {}
+            None => {
+                return Err(anyhow!(
+                    "persona pack {path} rejected: signature does not cover the pack files"
+                ))
+            }
+        }
     }
-    let m: Manifest = toml::from_str(&manifest_txt)?;
-    let mut p = CompanionProfile::new_default();
-    p.persona = Persona {
-        tone: m.tone,
-        risk: m.risk,
-        humor: m.humor,
-        voice: m.voice,
+    Ok(pack)
+}
+
+/// Sign a pack in place, replacing any previous signature.
+pub fn sign_persona_zip(path: &str, key: &ProfileSigningKey) -> Result<()> {
+    let mut pack = {
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        PersonaPack::from_zip(&mut zip)?
     };
-    if let Some(js) = m.prefs_json {
-        p.player_prefs = serde_json::from_str(&js)?;
+    pack.files.remove(SIGNATURE_FILE);
+    pack.profile.sign(key);
+    let sig = PackSignature {
+        key_id: key.key_id(),
+        signature: pack.profile.signature.clone().unwrap_or_default(),
+        files: Some(key.sign_bytes(&files_digest(&pack.files))),
+    };
+    pack.files.insert(
+        SIGNATURE_FILE.to_string(),
+        toml::to_string(&sig)?.into_bytes(),
+    );
+
+    let tmp = format!("{path}.tmp");
+    {
+        let mut w = zip::ZipWriter::new(std::fs::File::create(&tmp)?);
+        let opts = zip::write::FileOptions::default();
+        for (name, bytes) in &pack.files {
+            w.start_file(name.as_str(), opts)?;
+            w.write_all(bytes)?;
+        }
+        w.finish()?;
     }
-    if let Some(sk) = m.skills {
-        p.skills = sk
-            .into_iter()
-            .map(|s| Skill {
-                name: s.name,
-                level: s.level,
-                notes: s.notes,
-            })
-            .collect();
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn read_signature(files: &BTreeMap<String, Vec<u8>>) -> Result<Option<PackSignature>> {
+    match files.get(SIGNATURE_FILE) {
+        Some(bytes) => Ok(Some(toml::from_str(std::str::from_utf8(bytes)?)?)),
+        None => Ok(None),
     }
-    if let Some(fs) = m.facts {
-        p.facts = fs
-            .into_iter()
-            .map(|f| Fact {
-                k: f.k,
-                v: f.v,
-                t: f.t,
-            })
-            .collect();
+}
+
+/// Canonical `{path: sha256}` map of every entry except the signature itself.
+fn files_digest(files: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let m: serde_json::Map<String, serde_json::Value> = files
+        .iter()
+        .filter(|(name, _)| name.as_str() != SIGNATURE_FILE)
+        .map(|(name, bytes)| (name.clone(), hex::encode(Sha256::digest(bytes)).into()))
+        .collect();
+    canonical_json(&serde_json::Value::Object(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pack(path: &std::path::Path) {
+        let mut w = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        w.start_file(MANIFEST_FILE, zip::write::FileOptions::default())
+            .unwrap();
+        w.write_all(b"tone = \"laconic\"\nrisk = \"high\"\nhumor = \"dry\"\nvoice = \"v02\"\n")
+            .unwrap();
+        w.finish().unwrap();
+    }
+
+    #[test]
+    fn strict_policy_accepts_only_trusted_signed_packs() {
+        let path = std::env::temp_dir().join(format!("aw_persona_{}.zip", std::process::id()));
+        let path_s = path.to_str().unwrap();
+        write_pack(&path);
+
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+        let strict = PackPolicy::require(trusted);
+
+        assert!(load_persona_zip_with(path_s, &strict).is_err());
+        assert!(load_persona_zip(path_s).is_ok());
+
+        sign_persona_zip(path_s, &key).unwrap();
+        let p = load_persona_zip_with(path_s, &strict).unwrap();
+        assert_eq!(p.key_id, Some(key.key_id()));
+
+        sign_persona_zip(path_s, &ProfileSigningKey::generate()).unwrap();
+        assert!(load_persona_zip_with(path_s, &strict).is_err());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn legacy_signatures_verify_only_when_allowed() {
+        let path = std::env::temp_dir().join(format!("aw_persona_old_{}.zip", std::process::id()));
+        let path_s = path.to_str().unwrap();
+        write_pack(&path);
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+
+        // The earlier format: only the profile signature, no `files` entry.
+        let mut profile = load_persona_zip(path_s).unwrap();
+        profile.sign(&key);
+        let old = format!(
+            "key_id = \"{}\"\nsignature = \"{}\"\n",
+            key.key_id(),
+            profile.signature.unwrap()
+        );
+        {
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+            let mut w = zip::ZipWriter::new_append(file).unwrap();
+            w.start_file(SIGNATURE_FILE, zip::write::FileOptions::default())
+                .unwrap();
+            w.write_all(old.as_bytes()).unwrap();
+            w.finish().unwrap();
+        }
+        // Refused unless the policy opts in to legacy signatures.
+        assert!(load_persona_zip_with(path_s, &PackPolicy::require(trusted.clone())).is_err());
+        let legacy = PackPolicy {
+            allow_legacy_signatures: true,
+            ..PackPolicy::require(trusted)
+        };
+        let p = load_persona_zip_with(path_s, &legacy).unwrap();
+        assert_eq!(p.key_id, Some(key.key_id()));
+        let untrusted = PackPolicy {
+            allow_legacy_signatures: true,
+            ..PackPolicy::require(TrustedKeys::new())
+        };
+        assert!(load_persona_zip_with(path_s, &untrusted).is_err());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn v2_pack_round_trips_assets_and_signs_them() {
+        let src = std::env::temp_dir().join(format!("aw_persona_v2_{}", std::process::id()));
+        std::fs::create_dir_all(src.join("voice/Sniper")).unwrap();
+        std::fs::create_dir_all(src.join("prompts")).unwrap();
+        std::fs::write(
+            src.join(MANIFEST_FILE),
+            r#"
+schema_version = 2
+[pack]
+id = "sniper"
+version = "1.2.0"
+[persona]
+tone = "laconic"
+risk = "high"
+humor = "dry"
+voice = "v02"
+[[dependencies]]
+id = "base_voices"
+version = "^1.0"
+[assets]
+voice_bank = "voice/bank.toml"
+prompts = { system = "prompts/system.txt" }
+[behavior_presets.overwatch]
+stealth_bias = 0.9
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("voice/bank.toml"),
+            "[speakers.Sniper]\nfolder = \"voice/Sniper\"\nfiles = [\"hi.ogg\"]\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("voice/Sniper/hi.ogg"), b"OggS").unwrap();
+        std::fs::write(src.join("prompts/system.txt"), "You are {name}.").unwrap();
+
+        let zip_path = src.with_extension("zip");
+        let zip_s = zip_path.to_str().unwrap();
+        build_persona_pack(&src, &zip_path).unwrap();
+        let pack = load_persona_pack(zip_s, &PackPolicy::default()).unwrap();
+        assert!(pack.validate().is_empty(), "{:?}", pack.validate());
+        assert_eq!(pack.prompts["system"], "You are {name}.");
+        assert_eq!(
+            pack.manifest.behavior_presets["overwatch"]["stealth_bias"],
+            0.9
+        );
+        let available = [("base_voices".to_string(), "1.4.0".to_string())].into();
+        assert!(pack.missing_dependencies(&available).is_empty());
+
+        let key = ProfileSigningKey::generate();
+        let mut trusted = TrustedKeys::new();
+        trusted.add(&key);
+        sign_persona_zip(zip_s, &key).unwrap();
+        assert!(load_persona_pack(zip_s, &PackPolicy::require(trusted.clone())).is_ok());
+
+        // Swap a voice clip after signing: the profile still matches, the file digest does not.
+        std::fs::write(src.join("voice/Sniper/hi.ogg"), b"OggS-evil").unwrap();
+        let signed = load_persona_pack(zip_s, &PackPolicy::default()).unwrap();
+        build_persona_pack(&src, &zip_path).unwrap();
+        {
+            let mut w = zip::ZipWriter::new_append(
+                std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&zip_path)
+                    .unwrap(),
+            )
+            .unwrap();
+            w.start_file(SIGNATURE_FILE, zip::write::FileOptions::default())
+                .unwrap();
+            w.write_all(&signed.files[SIGNATURE_FILE]).unwrap();
+            w.finish().unwrap();
+        }
+        assert!(load_persona_pack(zip_s, &PackPolicy::require(trusted.clone())).is_err());
+
+        // Strip the file digest from the original signature as well: a v2 pack never
+        // falls back to the profile signature, even when legacy signatures are allowed.
+        let mut stripped: PackSignature =
+            toml::from_str(std::str::from_utf8(&signed.files[SIGNATURE_FILE]).unwrap()).unwrap();
+        stripped.files = None;
+        build_persona_pack(&src, &zip_path).unwrap();
+        {
+            let mut w = zip::ZipWriter::new_append(
+                std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&zip_path)
+                    .unwrap(),
+            )
+            .unwrap();
+            w.start_file(SIGNATURE_FILE, zip::write::FileOptions::default())
+                .unwrap();
+            w.write_all(toml::to_string(&stripped).unwrap().as_bytes())
+                .unwrap();
+            w.finish().unwrap();
+        }
+        let legacy = PackPolicy {
+            allow_legacy_signatures: true,
+            ..PackPolicy::require(trusted.clone())
+        };
+        assert!(load_persona_pack(zip_s, &PackPolicy::require(trusted)).is_err());
+        assert!(load_persona_pack(zip_s, &legacy).is_err());
+        std::fs::remove_dir_all(&src).ok();
+        std::fs::remove_file(zip_path).ok();
     }
-    p.sign();
-    Ok(p)
 }
@@ -0,0 +1,453 @@
+use anyhow::{anyhow, bail, Result};
+use astraweave_core::dialogue::Dialogue;
+use astraweave_memory::{CompanionProfile, Fact, Persona, Skill};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Latest persona manifest schema understood by this crate.
+pub const PACK_SCHEMA_VERSION: u32 = 2;
+
+/// `persona_manifest.toml`. Schema 1 manifests (no `schema_version`, persona fields at
+/// the top level) are still accepted; see [`PackManifest::parse`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub schema_version: u32,
+    pub pack: PackInfo,
+    pub persona: Persona,
+    #[serde(default)]
+    pub prefs_json: Option<String>,
+    #[serde(default)]
+    pub skills: Vec<Skill>,
+    #[serde(default)]
+    pub facts: Vec<Fact>,
+    #[serde(default)]
+    pub dependencies: Vec<PackDependency>,
+    #[serde(default)]
+    pub assets: PackAssets,
+    /// Named tuning presets (e.g. `stealth_bias`, `aggro_risk`) the companion can switch between.
+    #[serde(default)]
+    pub behavior_presets: BTreeMap<String, BTreeMap<String, f32>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
+
+/// Another pack this one builds on, e.g. a shared voice bank.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackDependency {
+    pub id: String,
+    /// Requirement such as `"1.2.0"`, `">=1.0"`, `"^2.1"` or `"*"`.
+    #[serde(default = "any_version")]
+    pub version: String,
+}
+
+fn any_version() -> String {
+    "*".into()
+}
+
+/// Paths inside the pack, relative to the archive root.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PackAssets {
+    #[serde(default)]
+    pub voice_bank: Option<String>,
+    #[serde(default)]
+    pub dialogue: Vec<String>,
+    /// portrait name (e.g. "neutral", "angry") -> PNG path
+    #[serde(default)]
+    pub portraits: BTreeMap<String, String>,
+    /// template name (e.g. "system", "banter") -> text path
+    #[serde(default)]
+    pub prompts: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct SchemaProbe {
+    #[serde(default = "legacy_schema")]
+    schema_version: u32,
+}
+
+fn legacy_schema() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct ManifestV1 {
+    tone: String,
+    risk: String,
+    humor: String,
+    voice: String,
+    #[serde(default)]
+    prefs_json: Option<String>,
+    #[serde(default)]
+    skills: Vec<Skill>,
+    #[serde(default)]
+    facts: Vec<Fact>,
+}
+
+impl PackManifest {
+    /// Parse any supported schema version and upgrade it to the current layout.
+    pub fn parse(txt: &str) -> Result<Self> {
+        let probe: SchemaProbe = toml::from_str(txt)?;
+        match probe.schema_version {
+            1 => {
+                let v1: ManifestV1 = toml::from_str(txt)?;
+                Ok(Self {
+                    schema_version: PACK_SCHEMA_VERSION,
+                    pack: PackInfo {
+                        id: "legacy".into(),
+                        name: String::new(),
+                        version: "1.0.0".into(),
+                        authors: vec![],
+                    },
+                    persona: Persona {
+                        tone: v1.tone,
+                        risk: v1.risk,
+                        humor: v1.humor,
+                        voice: v1.voice,
+                    },
+                    prefs_json: v1.prefs_json,
+                    skills: v1.skills,
+                    facts: v1.facts,
+                    dependencies: vec![],
+                    assets: PackAssets::default(),
+                    behavior_presets: BTreeMap::new(),
+                })
+            }
+            PACK_SCHEMA_VERSION => Ok(toml::from_str(txt)?),
+            v => bail!("unsupported persona manifest schema_version {v}"),
+        }
+    }
+
+    pub fn to_profile(&self) -> Result<CompanionProfile> {
+        let mut p = CompanionProfile::new_default();
+        p.persona = self.persona.clone();
+        if let Some(js) = &self.prefs_json {
+            p.player_prefs = serde_json::from_str(js)?;
+        }
+        p.skills = self.skills.clone();
+        p.facts = self.facts.clone();
+        Ok(p)
+    }
+
+    /// Every file the manifest points at.
+    pub fn referenced_files(&self) -> Vec<&str> {
+        let a = &self.assets;
+        a.voice_bank
+            .iter()
+            .chain(a.dialogue.iter())
+            .chain(a.portraits.values())
+            .chain(a.prompts.values())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Speaker table with the same TOML layout as `astraweave_audio::VoiceBank`, so a
+/// bank shipped in a pack can be fed straight to `load_voice_bank` once extracted.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VoiceBankSpec {
+    pub speakers: HashMap<String, VoiceSpecEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VoiceSpecEntry {
+    pub folder: String,
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub tts_voice: Option<String>,
+}
+
+/// A fully loaded persona pack.
+#[derive(Clone, Debug)]
+pub struct PersonaPack {
+    pub manifest: PackManifest,
+    pub profile: CompanionProfile,
+    pub voice_bank: Option<VoiceBankSpec>,
+    pub dialogues: Vec<Dialogue>,
+    pub portraits: BTreeMap<String, Vec<u8>>,
+    pub prompts: BTreeMap<String, String>,
+    /// Raw bytes of every archive entry, keyed by path (voice clips included).
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl PersonaPack {
+    pub(crate) fn from_zip<R: Read + Seek>(zip: &mut zip::ZipArchive<R>) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut f = zip.by_index(i)?;
+            if f.is_dir() {
+                continue;
+            }
+            let mut bytes = vec![];
+            f.read_to_end(&mut bytes)?;
+            files.insert(f.name().to_string(), bytes);
+        }
+        let manifest_txt = text_entry(&files, crate::MANIFEST_FILE)?;
+        let manifest = PackManifest::parse(&manifest_txt)?;
+        let profile = manifest.to_profile()?;
+
+        let voice_bank = match &manifest.assets.voice_bank {
+            Some(p) => Some(toml::from_str(&text_entry(&files, p)?)?),
+            None => None,
+        };
+        let mut dialogues = vec![];
+        for p in &manifest.assets.dialogue {
+            let d: Dialogue = toml::from_str(&text_entry(&files, p)?)
+                .map_err(|e| anyhow!("dialogue {p}: {e}"))?;
+            dialogues.push(d);
+        }
+        let mut portraits = BTreeMap::new();
+        for (name, p) in &manifest.assets.portraits {
+            let bytes = files
+                .get(p)
+                .ok_or_else(|| anyhow!("portrait {name} missing: {p}"))?;
+            portraits.insert(name.clone(), bytes.clone());
+        }
+        let mut prompts = BTreeMap::new();
+        for (name, p) in &manifest.assets.prompts {
+            prompts.insert(name.clone(), text_entry(&files, p)?);
+        }
+
+        Ok(Self {
+            manifest,
+            profile,
+            voice_bank,
+            dialogues,
+            portraits,
+            prompts,
+            files,
+        })
+    }
+
+    /// True for schema 1 packs holding nothing but the manifest: the profile signature
+    /// alone covers everything such a pack can carry.
+    pub(crate) fn is_legacy(&self) -> bool {
+        let schema = std::str::from_utf8(&self.files[crate::MANIFEST_FILE])
+            .ok()
+            .and_then(|txt| toml::from_str::<SchemaProbe>(txt).ok())
+            .map(|p| p.schema_version);
+        schema == Some(1)
+            && self
+                .files
+                .keys()
+                .all(|name| name == crate::MANIFEST_FILE || name == crate::SIGNATURE_FILE)
+    }
+
+    /// Check the pack for problems that loading alone does not catch.
+    /// Returns human-readable issues; an empty list means the pack is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = vec![];
+        let m = &self.manifest;
+        if m.pack.id.trim().is_empty() {
+            issues.push("pack.id is empty".to_string());
+        }
+        if parse_version(&m.pack.version).is_none() {
+            issues.push(format!(
+                "pack.version '{}' is not major.minor.patch",
+                m.pack.version
+            ));
+        }
+        for dep in &m.dependencies {
+            if dep.id == m.pack.id {
+                issues.push(format!("pack depends on itself ({})", dep.id));
+            }
+            if VersionReq::parse(&dep.version).is_none() {
+                issues.push(format!(
+                    "dependency {} has bad version '{}'",
+                    dep.id, dep.version
+                ));
+            }
+        }
+        if let Some(bank) = &self.voice_bank {
+            for (speaker, spec) in &bank.speakers {
+                let folder = spec.folder.trim_end_matches('/');
+                for f in &spec.files {
+                    let path = format!("{folder}/{f}");
+                    if !self.files.contains_key(&path) {
+                        issues.push(format!("voice {speaker}: missing clip {path}"));
+                    }
+                }
+                if spec.files.is_empty()
+                    && spec.tts_voice.is_none()
+                    && !self
+                        .files
+                        .keys()
+                        .any(|k| k.starts_with(&format!("{folder}/")))
+                {
+                    issues.push(format!("voice {speaker}: no clips and no tts_voice"));
+                }
+            }
+        }
+        for d in &self.dialogues {
+            let ids: Vec<&str> = d.nodes.iter().map(|n| n.id.as_str()).collect();
+            if !ids.contains(&d.start.as_str()) {
+                issues.push(format!("dialogue {}: start node {} missing", d.id, d.start));
+            }
+            for n in &d.nodes {
+                for c in &n.choices {
+                    if !ids.contains(&c.go_to.as_str()) {
+                        issues.push(format!(
+                            "dialogue {}: node {} jumps to unknown {}",
+                            d.id, n.id, c.go_to
+                        ));
+                    }
+                }
+            }
+        }
+        for (name, bytes) in &self.portraits {
+            if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+                issues.push(format!("portrait {name} is not a PNG"));
+            }
+        }
+        for (name, text) in &self.prompts {
+            if text.trim().is_empty() {
+                issues.push(format!("prompt template {name} is empty"));
+            }
+        }
+        issues
+    }
+
+    /// Dependencies not satisfied by `available` (pack id -> version).
+    pub fn missing_dependencies(&self, available: &HashMap<String, String>) -> Vec<PackDependency> {
+        self.manifest
+            .dependencies
+            .iter()
+            .filter(|d| {
+                let have = available.get(&d.id).and_then(|v| parse_version(v));
+                match (have, VersionReq::parse(&d.version)) {
+                    (Some(v), Some(req)) => !req.matches(v),
+                    _ => true,
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Write every pack file under `dir` and return the voice bank with speaker
+    /// folders rewritten to point there.
+    pub fn extract_to(&self, dir: &Path) -> Result<Option<VoiceBankSpec>> {
+        for (name, bytes) in &self.files {
+            let out = safe_join(dir, name)?;
+            if let Some(parent) = out.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(out, bytes)?;
+        }
+        Ok(self.voice_bank.clone().map(|mut bank| {
+            for spec in bank.speakers.values_mut() {
+                spec.folder = dir.join(&spec.folder).to_string_lossy().into_owned();
+            }
+            bank
+        }))
+    }
+}
+
+fn text_entry(files: &BTreeMap<String, Vec<u8>>, name: &str) -> Result<String> {
+    let bytes = files
+        .get(name)
+        .ok_or_else(|| anyhow!("{name} missing from pack"))?;
+    Ok(String::from_utf8(bytes.clone())?)
+}
+
+fn safe_join(dir: &Path, name: &str) -> Result<PathBuf> {
+    let rel = Path::new(name);
+    if rel.is_absolute()
+        || rel
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        bail!("refusing to extract {name} outside of the target directory");
+    }
+    Ok(dir.join(rel))
+}
+
+/// Zip a pack source directory. The manifest must sit at the directory root.
+pub fn build_persona_pack(src_dir: &Path, out: &Path) -> Result<()> {
+    let manifest = src_dir.join(crate::MANIFEST_FILE);
+    PackManifest::parse(&std::fs::read_to_string(&manifest)?)?;
+    let mut entries = vec![];
+    collect_files(src_dir, src_dir, &mut entries)?;
+    entries.sort();
+
+    let mut w = zip::ZipWriter::new(std::fs::File::create(out)?);
+    let opts = zip::write::FileOptions::default();
+    for (name, path) in entries {
+        if name == crate::SIGNATURE_FILE {
+            continue;
+        }
+        w.start_file(name, opts)?;
+        w.write_all(&std::fs::read(path)?)?;
+    }
+    w.finish()?;
+    Ok(())
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for e in std::fs::read_dir(dir)? {
+        let path = e?.path();
+        if path.is_dir() {
+            collect_files(root, &path, out)?;
+        } else {
+            let rel = path.strip_prefix(root)?;
+            let name = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+fn parse_version(s: &str) -> Option<(u32, u32, u32)> {
+    let mut it = s.trim().split('.');
+    let major = it.next()?.parse().ok()?;
+    let minor = it.next().map_or(Some(0), |x| x.parse().ok())?;
+    let patch = it.next().map_or(Some(0), |x| x.parse().ok())?;
+    if it.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+enum VersionReq {
+    Any,
+    Exact((u32, u32, u32)),
+    AtLeast((u32, u32, u32)),
+    Caret((u32, u32, u32)),
+}
+
+impl VersionReq {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s == "*" {
+            Some(Self::Any)
+        } else if let Some(v) = s.strip_prefix(">=") {
+            parse_version(v).map(Self::AtLeast)
+        } else if let Some(v) = s.strip_prefix('^') {
+            parse_version(v).map(Self::Caret)
+        } else {
+            parse_version(s.strip_prefix('=').unwrap_or(s)).map(Self::Exact)
+        }
+    }
+
+    fn matches(&self, v: (u32, u32, u32)) -> bool {
+        match *self {
+            Self::Any => true,
+            Self::Exact(r) => v == r,
+            Self::AtLeast(r) => v >= r,
+            Self::Caret(r) => v >= r && v.0 == r.0,
+        }
+    }
+}
@@ -0,0 +1,527 @@
+//! Kinematic character controller on top of rapier's shape casts: slides along walls,
+//! steps up ledges, refuses steep slopes, sticks to the ground on the way down, and falls,
+//! jumps, climbs, swims and rides moving platforms.
+
+use crate::{BodyId, PhysicsWorld};
+use glam::{vec3, Vec3};
+use rapier3d::control::{CharacterAutostep, CharacterLength, KinematicCharacterController};
+use rapier3d::parry::query::ShapeCastOptions;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CharState {
+    Grounded,
+    /// Rising after a jump.
+    Jumping,
+    /// Airborne and not rising: walked off a ledge or past the top of a jump.
+    Falling,
+    /// Holding onto a wall or ladder.
+    Climbing,
+    /// In water deeper than `swim_depth`.
+    Swimming,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CharacterController {
+    pub state: CharState,
+    /// Steepest slope the character walks up; anything steeper is a wall.
+    pub max_climb_angle_deg: f32,
+    /// Tallest ledge stepped onto without jumping.
+    pub max_step: f32,
+    /// How far below its feet a walking character looks for ground to stay on, so it
+    /// follows ramps and stairs down instead of skipping off them.
+    pub snap_distance: f32,
+    pub jump_speed: f32,
+    pub climb_speed: f32,
+    /// Water depth at the feet where wading turns into swimming. Swimmers float with
+    /// their feet this deep unless they dive.
+    pub swim_depth: f32,
+    /// Fastest a swimmer rises or dives.
+    pub swim_speed: f32,
+    /// Fraction of the air's speed (see [`PhysicsWorld::wind_at`]) added to the character's
+    /// own; 0 ignores wind and force fields.
+    pub wind_push: f32,
+    /// Vertical speed from gravity, jumps and climbing.
+    pub vertical_speed: f32,
+    /// Body the character stands on while grounded; moving platforms carry it along,
+    /// dynamic ones by their velocity at its feet.
+    pub ground: Option<BodyId>,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            state: CharState::Grounded,
+            max_climb_angle_deg: 70.0,
+            max_step: 0.4,
+            snap_distance: 0.3,
+            jump_speed: 5.0,
+            climb_speed: 2.0,
+            swim_depth: 1.0,
+            swim_speed: 1.5,
+            wind_push: 0.0,
+            vertical_speed: 0.0,
+            ground: None,
+        }
+    }
+}
+
+/// Gap kept between the character and everything it touches.
+const SKIN: f32 = 0.02;
+/// How far ahead a climbing character feels for the wall.
+const CLIMB_REACH: f32 = 0.3;
+
+impl PhysicsWorld {
+    /// Walk a character at `desired_move` (m/s) for `dt` seconds. Gravity, stepping and
+    /// ground snapping are applied here; the vertical part of `desired_move` only counts
+    /// while climbing or swimming, to dive and surface. With `climb`, walking into a wall steeper than
+    /// `max_climb_angle_deg` climbs it.
+    ///
+    /// The body moves on the next [`PhysicsWorld::step`], pushing dynamic bodies aside.
+    pub fn control_character(&mut self, id: BodyId, desired_move: Vec3, dt: f32, climb: bool) {
+        let (Some(mut ctrl), Some(h)) = (self.char_map.get(&id).copied(), self.handle_of(id))
+        else {
+            return;
+        };
+        let Some(rb) = self.bodies.get(h) else {
+            return;
+        };
+        let Some(&ch) = rb.colliders().first() else {
+            return;
+        };
+        let pos = *rb.position();
+        let collider = &self.colliders[ch];
+        let shape = collider.shape();
+        let filter = QueryFilter::new()
+            .exclude_rigid_body(h)
+            .exclude_sensors()
+            .groups(collider.collision_groups());
+
+        let kcc = KinematicCharacterController {
+            offset: CharacterLength::Absolute(SKIN),
+            autostep: Some(CharacterAutostep {
+                max_height: CharacterLength::Absolute(ctrl.max_step),
+                min_width: CharacterLength::Absolute(0.1),
+                include_dynamic_bodies: false,
+            }),
+            max_slope_climb_angle: ctrl.max_climb_angle_deg.to_radians(),
+            min_slope_slide_angle: ctrl.max_climb_angle_deg.to_radians(),
+            snap_to_ground: (ctrl.state == CharState::Grounded)
+                .then_some(CharacterLength::Absolute(ctrl.snap_distance)),
+            ..KinematicCharacterController::default()
+        };
+
+        let mut walk = vector![desired_move.x, 0.0, desired_move.z];
+        let on_wall = climb
+            && walk.norm() > 1e-3
+            && self
+                .query_pipeline
+                .cast_shape(
+                    &self.bodies,
+                    &self.colliders,
+                    &pos,
+                    &walk.normalize(),
+                    shape,
+                    ShapeCastOptions::with_max_time_of_impact(CLIMB_REACH),
+                    filter,
+                )
+                .is_some_and(|(_, hit)| {
+                    hit.normal1.y.clamp(-1.0, 1.0).acos() >= kcc.max_slope_climb_angle
+                });
+        let half_height = shape.compute_local_aabb().half_extents().y;
+        let feet = vec3(
+            pos.translation.x,
+            pos.translation.y - half_height,
+            pos.translation.z,
+        );
+        // Stay afloat a little above `swim_depth` too, so treading water does not flicker
+        // between swimming and falling.
+        let afloat = if ctrl.state == CharState::Swimming {
+            ctrl.swim_depth - 0.2
+        } else {
+            ctrl.swim_depth
+        };
+        let swim_depth = self
+            .water_depth(feet)
+            .filter(|&d| !on_wall && ctrl.state != CharState::Jumping && d >= afloat);
+        if on_wall {
+            ctrl.vertical_speed = if desired_move.y > 0.0 {
+                desired_move.y
+            } else {
+                ctrl.climb_speed
+            };
+        } else if let Some(depth) = swim_depth {
+            // Drift back up to floating depth unless diving; swimming can't leave the water,
+            // jumping can.
+            let below = depth - ctrl.swim_depth;
+            let rise = if below > 0.0 { ctrl.swim_speed } else { 0.0 };
+            ctrl.vertical_speed = if desired_move.y != 0.0 {
+                desired_move.y.clamp(-ctrl.swim_speed, rise)
+            } else {
+                (below * 2.0).clamp(-ctrl.swim_speed, ctrl.swim_speed)
+            };
+        } else {
+            ctrl.vertical_speed += self.gravity.y * dt;
+        }
+        if ctrl.wind_push > 0.0 {
+            let centre = pos.translation.vector;
+            let air = self.wind_at(vec3(centre.x, centre.y, centre.z)) * ctrl.wind_push;
+            walk += vector![air.x, 0.0, air.z];
+            if !on_wall && swim_depth.is_none() && air.y > 0.0 {
+                // Updrafts carry the character up at the pushed air speed.
+                ctrl.vertical_speed = ctrl.vertical_speed.max(air.y);
+            }
+        }
+
+        // Rapier's controller already drags characters along with kinematic platforms;
+        // dynamic ground such as a floating raft carries them by its velocity here.
+        let carry = ctrl
+            .ground
+            .filter(|_| ctrl.state == CharState::Grounded)
+            .and_then(|g| self.bodies.get(self.handle_of(g)?))
+            .filter(|g| g.is_dynamic())
+            .map_or(Vector::zeros(), |g| {
+                g.velocity_at_point(&point![feet.x, feet.y, feet.z])
+            });
+        walk += vector![carry.x, 0.0, carry.z];
+
+        let sweep = |walk: Vector<Real>| {
+            kcc.move_shape(
+                dt,
+                &self.bodies,
+                &self.colliders,
+                &self.query_pipeline,
+                shape,
+                &pos,
+                (walk + Vector::y() * ctrl.vertical_speed) * dt,
+                filter,
+                |_| {},
+            )
+        };
+        let mut moved = sweep(walk);
+        if !on_wall && swim_depth.is_none() && moved.translation.y > 0.0 {
+            // Rapier lets any walk into a slope climb it, however steep. Ground under the
+            // character that is past the limit is a wall: only walk along it.
+            let reach = half_height + ctrl.max_step;
+            let ray = Ray::new(
+                (pos.translation.vector + moved.translation).into(),
+                -Vector::y(),
+            );
+            let steep = self
+                .query_pipeline
+                .cast_ray_and_get_normal(&self.bodies, &self.colliders, &ray, reach, true, filter)
+                .map(|(_, hit)| hit.normal)
+                .filter(|n| n.y.clamp(-1.0, 1.0).acos() > kcc.max_slope_climb_angle);
+            if let Some(away) = steep.and_then(|n| vector![n.x, 0.0, n.z].try_normalize(1e-5)) {
+                moved = sweep(walk - away * walk.dot(&away).min(0.0));
+            }
+        }
+        let wanted = ctrl.vertical_speed * dt;
+
+        let mut next = pos.translation.vector + moved.translation;
+        let below = self.query_pipeline.cast_shape(
+            &self.bodies,
+            &self.colliders,
+            &Isometry::from_parts(next.into(), pos.rotation),
+            &-Vector::y(),
+            shape,
+            ShapeCastOptions::with_max_time_of_impact(ctrl.snap_distance),
+            filter,
+        );
+        // Rapier stops reporting ground once the capsule rolls over a ledge's edge; a
+        // character that was walking keeps to the ground below it.
+        let snap = match below {
+            Some((_, hit)) if !moved.grounded && ctrl.state == CharState::Grounded => {
+                Some((hit.time_of_impact - SKIN).max(0.0))
+            }
+            _ => None,
+        };
+
+        if on_wall {
+            ctrl.state = CharState::Climbing;
+        } else if swim_depth.is_some() {
+            ctrl.state = CharState::Swimming;
+        } else if (moved.grounded || snap.is_some()) && ctrl.vertical_speed <= 0.0 {
+            ctrl.state = CharState::Grounded;
+            ctrl.vertical_speed = 0.0;
+            next.y -= snap.unwrap_or(0.0);
+        } else {
+            if ctrl.vertical_speed > 0.0 && moved.translation.y < wanted * 0.5 {
+                // Bumped a ceiling.
+                ctrl.vertical_speed = 0.0;
+            }
+            ctrl.state = if ctrl.vertical_speed > 0.0 {
+                CharState::Jumping
+            } else {
+                CharState::Falling
+            };
+        }
+
+        ctrl.ground = below
+            .filter(|_| ctrl.state == CharState::Grounded)
+            .and_then(|(c, _)| self.colliders[c].parent())
+            .and_then(|b| self.id_of(b));
+        self.bodies[h].set_next_kinematic_translation(next);
+        self.char_map.insert(id, ctrl);
+    }
+
+    /// Jump if the character stands on the ground, holds onto a wall or swims.
+    pub fn jump_character(&mut self, id: BodyId) -> bool {
+        let Some(ctrl) = self.char_map.get_mut(&id) else {
+            return false;
+        };
+        if !matches!(
+            ctrl.state,
+            CharState::Grounded | CharState::Climbing | CharState::Swimming
+        ) {
+            return false;
+        }
+        ctrl.vertical_speed = ctrl.jump_speed;
+        ctrl.state = CharState::Jumping;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ActorKind, Layers};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn world() -> PhysicsWorld {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        w
+    }
+
+    fn fixed(w: &mut PhysicsWorld, collider: Collider, at: Vec3) -> RigidBodyHandle {
+        let h = w
+            .bodies
+            .insert(RigidBodyBuilder::fixed().translation(vector![at.x, at.y, at.z]));
+        w.colliders.insert_with_parent(collider, h, &mut w.bodies);
+        h
+    }
+
+    /// Steps of `rise` and 0.5 deep climbing along +x from x = 1.
+    fn stairs(w: &mut PhysicsWorld, rise: f32, count: usize) {
+        for i in 0..count {
+            let top = rise * (i + 1) as f32;
+            let x = 1.0 + 0.5 * i as f32;
+            let half = vector![0.25, top * 0.5, 2.0];
+            fixed(
+                w,
+                ColliderBuilder::cuboid(half.x, half.y, half.z).build(),
+                vec3(x + 0.25, top * 0.5, 0.0),
+            );
+        }
+        // Landing at the top.
+        let top = rise * count as f32;
+        let x = 1.0 + 0.5 * count as f32;
+        fixed(
+            w,
+            ColliderBuilder::cuboid(3.0, top * 0.5, 2.0).build(),
+            vec3(x + 3.0, top * 0.5, 0.0),
+        );
+    }
+
+    /// Ramp rising along +x from x = 1 at `deg` degrees to height 2.
+    fn ramp(w: &mut PhysicsWorld, deg: f32) {
+        let angle = deg.to_radians();
+        let len = 2.0 / angle.sin();
+        let thick = 0.2;
+        let run = len * angle.cos();
+        let centre = vec3(1.0 + run * 0.5, 1.0, 0.0) + vec3(angle.sin(), -angle.cos(), 0.0) * thick;
+        fixed(
+            w,
+            ColliderBuilder::cuboid(len * 0.5, thick, 2.0)
+                .rotation(vector![0.0, 0.0, angle])
+                .build(),
+            centre,
+        );
+        fixed(
+            w,
+            ColliderBuilder::cuboid(3.0, 1.0, 2.0).build(),
+            vec3(1.0 + run + 3.0, 1.0, 0.0),
+        );
+    }
+
+    fn character(w: &mut PhysicsWorld, at: Vec3) -> BodyId {
+        let id = w
+            .add_character(at, vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.step();
+        id
+    }
+
+    fn run(w: &mut PhysicsWorld, id: BodyId, vel: Vec3, frames: usize, climb: bool) {
+        for _ in 0..frames {
+            w.control_character(id, vel, DT, climb);
+            w.step();
+        }
+    }
+
+    fn pos(w: &PhysicsWorld, id: BodyId) -> Vec3 {
+        w.body_transform(id).unwrap().w_axis.truncate()
+    }
+
+    fn state(w: &PhysicsWorld, id: BodyId) -> CharState {
+        w.char_map[&id].state
+    }
+
+    /// Height of the capsule centre above the surface it stands on.
+    const STAND: f32 = 0.6 + 0.3;
+
+    #[test]
+    fn dropped_character_falls_lands_and_jumps() {
+        let mut w = world();
+        let id = character(&mut w, vec3(0.0, 5.0, 0.0));
+        run(&mut w, id, Vec3::ZERO, 5, false);
+        assert_eq!(state(&w, id), CharState::Falling);
+        run(&mut w, id, Vec3::ZERO, 120, false);
+        assert_eq!(state(&w, id), CharState::Grounded);
+        let landed = pos(&w, id).y;
+        assert!((landed - (0.1 + STAND)).abs() < 0.1, "stands at {landed}");
+
+        assert!(w.jump_character(id));
+        assert!(!w.jump_character(id), "no double jump");
+        run(&mut w, id, Vec3::ZERO, 10, false);
+        assert_eq!(state(&w, id), CharState::Jumping);
+        assert!(pos(&w, id).y > landed + 0.3);
+        run(&mut w, id, Vec3::ZERO, 120, false);
+        assert_eq!(state(&w, id), CharState::Grounded);
+        assert!((pos(&w, id).y - landed).abs() < 0.05);
+    }
+
+    #[test]
+    fn walks_up_and_down_stairs_but_not_tall_ledges() {
+        let mut w = world();
+        stairs(&mut w, 0.25, 6);
+        let id = character(&mut w, vec3(-1.0, 3.0, 0.0));
+        run(&mut w, id, Vec3::ZERO, 90, false);
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 180, false);
+        let p = pos(&w, id);
+        assert!(p.x > 5.0, "stuck at {p}");
+        assert!((p.y - (1.5 + STAND)).abs() < 0.15, "on top at {p}");
+        assert_eq!(state(&w, id), CharState::Grounded);
+
+        // Back down without leaving the ground.
+        for _ in 0..150 {
+            w.control_character(id, vec3(-3.0, 0.0, 0.0), DT, false);
+            w.step();
+            assert_ne!(state(&w, id), CharState::Falling, "at {}", pos(&w, id));
+        }
+        assert!(pos(&w, id).x < 0.5 && pos(&w, id).y < 0.1 + STAND + 0.1);
+
+        let mut w = world();
+        stairs(&mut w, 0.8, 1);
+        let id = character(&mut w, vec3(-1.0, 1.5, 0.0));
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 120, false);
+        let p = pos(&w, id);
+        assert!(
+            p.x < 1.0 && p.y < 0.1 + STAND + 0.1,
+            "climbed the ledge to {p}"
+        );
+    }
+
+    #[test]
+    fn ramps_are_walkable_up_to_the_slope_limit() {
+        let mut w = world();
+        ramp(&mut w, 25.0);
+        let id = character(&mut w, vec3(-1.0, 1.5, 0.0));
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 180, false);
+        let p = pos(&w, id);
+        assert!(p.x > 6.0 && p.y > 2.0 + STAND - 0.15, "stopped at {p}");
+        assert_eq!(state(&w, id), CharState::Grounded);
+
+        // The default limit is 70 degrees.
+        let mut w = world();
+        ramp(&mut w, 60.0);
+        let id = character(&mut w, vec3(-1.0, 1.5, 0.0));
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 180, false);
+        let p = pos(&w, id);
+        assert!(p.y > 2.0 + STAND - 0.15, "stopped at {p} on a 60 degree slope");
+
+        let mut w = world();
+        ramp(&mut w, 80.0);
+        let id = character(&mut w, vec3(-1.0, 1.5, 0.0));
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 180, false);
+        let p = pos(&w, id);
+        assert!(p.y < 1.0 + STAND, "walked up an 80 degree slope to {p}");
+
+        let mut w = world();
+        ramp(&mut w, 60.0);
+        let id = character(&mut w, vec3(-1.0, 1.5, 0.0));
+        w.char_map.get_mut(&id).unwrap().max_climb_angle_deg = 45.0;
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 180, false);
+        let p = pos(&w, id);
+        assert!(p.y < 1.0 + STAND, "walked up a 60 degree slope to {p}");
+
+        // The same slope can be climbed.
+        run(&mut w, id, vec3(3.0, 0.0, 0.0), 90, true);
+        let p = pos(&w, id);
+        assert!(p.x > 2.5 && p.y > 2.0 + STAND - 0.15, "climbed to {p}");
+    }
+
+    #[test]
+    fn characters_ride_moving_platforms() {
+        let mut w = world();
+        let rb = RigidBodyBuilder::kinematic_position_based().translation(vector![0.0, 1.0, 0.0]);
+        let h = w.bodies.insert(rb);
+        w.colliders.insert_with_parent(
+            ColliderBuilder::cuboid(1.5, 0.2, 1.5)
+                .collision_groups(InteractionGroups::new(
+                    Group::from_bits_truncate(Layers::DEFAULT.bits()),
+                    Group::ALL,
+                ))
+                .build(),
+            h,
+            &mut w.bodies,
+        );
+        let platform = w.tag_body(h, ActorKind::Other);
+        let id = character(&mut w, vec3(0.0, 3.0, 0.0));
+        run(&mut w, id, Vec3::ZERO, 90, false);
+        assert_eq!(state(&w, id), CharState::Grounded);
+        assert_eq!(w.char_map[&id].ground, Some(platform));
+
+        for i in 1..=120 {
+            let t = i as f32 * DT;
+            w.bodies[h].set_next_kinematic_translation(vector![2.0 * t, 1.0 + 0.5 * t, 0.0]);
+            w.control_character(id, Vec3::ZERO, DT, false);
+            w.step();
+        }
+        let (p, top) = (pos(&w, id), w.bodies[h].translation());
+        assert!(
+            (p.x - top.x).abs() < 0.2,
+            "left behind at {p}, platform at {top}"
+        );
+        assert!((p.y - (top.y + 0.2 + STAND)).abs() < 0.15, "{p} vs {top}");
+        assert_eq!(state(&w, id), CharState::Grounded);
+    }
+
+    #[test]
+    fn characters_ride_dynamic_bodies() {
+        let mut w = world();
+        let raft = w
+            .add_dynamic_box(vec3(0.0, 0.3, 0.0), vec3(1.5, 0.2, 1.5), 500.0, "default")
+            .unwrap();
+        let h = w.handle_of(raft).unwrap();
+        let id = character(&mut w, vec3(0.0, 2.0, 0.0));
+        run(&mut w, id, Vec3::ZERO, 60, false);
+        assert_eq!(w.char_map[&id].ground, Some(raft));
+
+        for _ in 0..120 {
+            w.bodies[h].set_linvel(vector![2.0, 0.0, 0.0], true);
+            w.control_character(id, Vec3::ZERO, DT, false);
+            w.step();
+        }
+        let (p, top) = (pos(&w, id), w.bodies[h].translation());
+        assert!(top.x > 3.5, "raft stuck at {top}");
+        assert!(
+            (p.x - top.x).abs() < 0.2,
+            "left behind at {p}, raft at {top}"
+        );
+        assert_eq!(state(&w, id), CharState::Grounded);
+    }
+}
@@ -0,0 +1,401 @@
+//! Breakable boxes. Each has health for gameplay damage and an impulse threshold for
+//! impacts, read from the step's contact force events. Breaking swaps the box for chunks that
+//! keep its motion and queues a [`BreakEvent`] for loot, effects and navmesh updates.
+
+use crate::{ActorKind, BodyId, LayerRef, Layers, PhysicsWorld};
+use glam::{Quat, Vec3};
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One piece of a pre-fractured box, in the box's local frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub offset: Vec3,
+    pub half: Vec3,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Fracture {
+    /// Split the box into an `x × y × z` grid of equal chunks.
+    Grid([u32; 3]),
+    /// Authored chunks, for boxes fractured ahead of time.
+    Chunks(Vec<Chunk>),
+}
+
+impl Default for Fracture {
+    fn default() -> Self {
+        Fracture::Grid([2, 2, 2])
+    }
+}
+
+impl Fracture {
+    fn chunks(&self, half: Vec3) -> Vec<Chunk> {
+        match self {
+            Fracture::Chunks(chunks) => chunks.clone(),
+            Fracture::Grid(n) => {
+                let [nx, ny, nz] = n.map(|n| n.max(1));
+                let size = 2.0 * half / Vec3::new(nx as f32, ny as f32, nz as f32);
+                let mut chunks = Vec::with_capacity((nx * ny * nz) as usize);
+                for x in 0..nx {
+                    for y in 0..ny {
+                        for z in 0..nz {
+                            let cell = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+                            chunks.push(Chunk {
+                                offset: -half + size * cell,
+                                half: size * 0.5,
+                            });
+                        }
+                    }
+                }
+                chunks
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Destructible {
+    /// Breaks when gameplay damage brings this to zero.
+    pub health: f32,
+    /// Impact impulse (N·s) that breaks it outright.
+    pub break_impulse: f32,
+    pub fracture: Fracture,
+}
+
+impl Destructible {
+    pub fn new(health: f32, break_impulse: f32) -> Self {
+        Self {
+            health,
+            break_impulse,
+            fracture: Fracture::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BreakCause {
+    /// Hit harder than its `break_impulse`.
+    Impact { impulse: f32 },
+    /// Ran out of health.
+    Damage,
+    /// Broken by [`PhysicsWorld::break_destructible`].
+    Scripted,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakEvent {
+    /// The box that broke; it no longer exists.
+    pub id: BodyId,
+    pub cause: BreakCause,
+    pub position: Vec3,
+    /// World-space bounds of the box when it broke, e.g. for the navmesh region to rebake.
+    pub min: Vec3,
+    pub max: Vec3,
+    pub chunks: Vec<BodyId>,
+}
+
+impl PhysicsWorld {
+    /// A breakable box split into a 2×2×2 grid when it breaks.
+    pub fn add_destructible_box<'a>(
+        &mut self,
+        pos: Vec3,
+        half: Vec3,
+        mass: f32,
+        health: f32,
+        break_impulse: f32,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let destructible = Destructible::new(health, break_impulse);
+        self.add_destructible(pos, half, mass, destructible, layer)
+    }
+
+    /// A breakable box on `layer`. Its chunks go on the debris layer.
+    pub fn add_destructible<'a>(
+        &mut self,
+        pos: Vec3,
+        half: Vec3,
+        mass: f32,
+        destructible: Destructible,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let id = self.add_dynamic_box(pos, half, mass, layer)?;
+        self.report_impacts(id, destructible.break_impulse);
+        self.destructibles.insert(id, destructible);
+        Ok(id)
+    }
+
+    pub fn destructible(&self, id: BodyId) -> Option<&Destructible> {
+        self.destructibles.get(&id)
+    }
+
+    pub fn destructible_mut(&mut self, id: BodyId) -> Option<&mut Destructible> {
+        self.destructibles.get_mut(&id)
+    }
+
+    /// Take `amount` off a destructible's health, breaking it at zero. Returns whether it
+    /// broke.
+    pub fn damage_destructible(&mut self, id: BodyId, amount: f32) -> bool {
+        let Some(d) = self.destructibles.get_mut(&id) else {
+            return false;
+        };
+        d.health -= amount;
+        d.health <= 0.0 && self.shatter(id, BreakCause::Damage)
+    }
+
+    /// Break a destructible now, whatever its health.
+    pub fn break_destructible(&mut self, id: BodyId) -> bool {
+        self.shatter(id, BreakCause::Scripted)
+    }
+
+    /// Destructibles broken since the last call, oldest first; at most
+    /// [`crate::MAX_QUEUED_EVENTS`] are kept.
+    pub fn drain_break_events(&mut self) -> Vec<BreakEvent> {
+        std::mem::take(&mut self.break_events)
+    }
+
+    /// Break destructibles hit harder than their threshold during the last step.
+    pub(crate) fn process_destructible_hits(&mut self, hits: &[ContactForceEvent]) {
+        let dt = self.integration.dt;
+        for hit in hits {
+            let impulse = hit.total_force_magnitude * dt;
+            for ch in [hit.collider1, hit.collider2] {
+                let Some(id) = self
+                    .colliders
+                    .get(ch)
+                    .and_then(|c| c.parent())
+                    .and_then(|b| self.id_of(b))
+                else {
+                    continue;
+                };
+                let breaks = self
+                    .destructibles
+                    .get(&id)
+                    .is_some_and(|d| impulse >= d.break_impulse);
+                if breaks {
+                    self.shatter(id, BreakCause::Impact { impulse });
+                }
+            }
+        }
+    }
+
+    fn shatter(&mut self, id: BodyId, cause: BreakCause) -> bool {
+        let Some(h) = self.handle_of(id) else {
+            return false;
+        };
+        let Some(d) = self.destructibles.remove(&id) else {
+            return false;
+        };
+        let rb = &self.bodies[h];
+        let (pose, linvel, angvel, com) = (
+            *rb.position(),
+            *rb.linvel(),
+            *rb.angvel(),
+            *rb.center_of_mass(),
+        );
+        let mass = rb.mass();
+        let Some(co) = rb.colliders().first().map(|&c| &self.colliders[c]) else {
+            return false;
+        };
+        let friction = co.friction();
+        let aabb = co.compute_aabb();
+        let half = co
+            .shape()
+            .as_cuboid()
+            .map(|c| Vec3::new(c.half_extents.x, c.half_extents.y, c.half_extents.z))
+            .unwrap_or_else(|| {
+                let e = aabb.half_extents();
+                Vec3::new(e.x, e.y, e.z)
+            });
+        self.remove_body(id);
+
+        let chunks = d.fracture.chunks(half);
+        let volume: f32 = chunks.iter().map(|c| c.half.x * c.half.y * c.half.z).sum();
+        let rotation = Quat::from_xyzw(
+            pose.rotation.i,
+            pose.rotation.j,
+            pose.rotation.k,
+            pose.rotation.w,
+        );
+        let mut ids = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let offset = rotation * chunk.offset;
+            let at = pose.translation.vector + vector![offset.x, offset.y, offset.z];
+            // Each chunk keeps the velocity its part of the box had.
+            let spin = angvel.cross(&(at - com.coords));
+            let rb = RigidBodyBuilder::dynamic()
+                .position(Isometry::from_parts(at.into(), pose.rotation))
+                .linvel(linvel + spin)
+                .angvel(angvel)
+                .build();
+            let ch = self.bodies.insert(rb);
+            let share = chunk.half.x * chunk.half.y * chunk.half.z / volume.max(f32::EPSILON);
+            let coll = ColliderBuilder::cuboid(chunk.half.x, chunk.half.y, chunk.half.z)
+                .mass(mass * share)
+                .collision_groups(self.layers.groups(Layers::DEBRIS))
+                .friction(friction)
+                .build();
+            self.colliders
+                .insert_with_parent(coll, ch, &mut self.bodies);
+            ids.push(self.tag_body(ch, ActorKind::Dynamic));
+        }
+
+        self.break_events.push(BreakEvent {
+            id,
+            cause,
+            position: Vec3::new(pose.translation.x, pose.translation.y, pose.translation.z),
+            min: Vec3::new(aabb.mins.x, aabb.mins.y, aabb.mins.z),
+            max: Vec3::new(aabb.maxs.x, aabb.maxs.y, aabb.maxs.z),
+            chunks: ids,
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layers;
+    use glam::vec3;
+
+    fn world() -> PhysicsWorld {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        w
+    }
+
+    fn settle(w: &mut PhysicsWorld, frames: usize) {
+        for _ in 0..frames {
+            w.step();
+        }
+    }
+
+    #[test]
+    fn hard_impacts_break_boxes_and_gentle_ones_do_not() {
+        let mut w = world();
+        let crate_ = w
+            .add_destructible_box(
+                vec3(0.0, 0.6, 0.0),
+                Vec3::splat(0.5),
+                10.0,
+                100.0,
+                20.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        settle(&mut w, 60);
+        assert!(
+            w.drain_break_events().is_empty(),
+            "resting on the ground is fine"
+        );
+
+        // A light, slow box bumps into it.
+        let pebble = w
+            .add_dynamic_box(vec3(-2.0, 0.6, 0.0), Vec3::splat(0.2), 1.0, Layers::DEFAULT)
+            .unwrap();
+        let h = w.handle_of(pebble).unwrap();
+        w.bodies[h].set_linvel(vector![3.0, 0.0, 0.0], true);
+        settle(&mut w, 60);
+        assert!(w.drain_break_events().is_empty());
+        assert!(w.destructible(crate_).is_some());
+
+        // A heavy, fast one smashes it.
+        let ram = w
+            .add_dynamic_box(
+                vec3(-3.0, 0.6, 0.0),
+                Vec3::splat(0.4),
+                50.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let h = w.handle_of(ram).unwrap();
+        w.bodies[h].set_linvel(vector![12.0, 0.0, 0.0], true);
+        settle(&mut w, 60);
+        let events = w.drain_break_events();
+        assert_eq!(events.len(), 1);
+        let ev = &events[0];
+        assert_eq!(ev.id, crate_);
+        assert!(matches!(ev.cause, BreakCause::Impact { impulse } if impulse >= 20.0));
+        assert_eq!(ev.chunks.len(), 8);
+        assert!(w.handle_of(crate_).is_none(), "the whole box is gone");
+        assert!(w.destructible(crate_).is_none());
+        let downrange = ev
+            .chunks
+            .iter()
+            .filter(|&&c| w.body_transform(c).unwrap().w_axis.x > 0.6)
+            .count();
+        assert!(downrange > 0, "chunks fly on with the hit");
+    }
+
+    #[test]
+    fn damage_breaks_boxes_when_health_runs_out() {
+        let mut w = world();
+        let id = w
+            .add_destructible_box(
+                vec3(0.0, 0.6, 0.0),
+                Vec3::splat(0.5),
+                10.0,
+                50.0,
+                1000.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        assert!(!w.damage_destructible(id, 30.0));
+        assert_eq!(w.destructible(id).unwrap().health, 20.0);
+        assert!(w.damage_destructible(id, 30.0));
+        assert!(!w.damage_destructible(id, 30.0), "already broken");
+        let events = w.drain_break_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].cause, BreakCause::Damage);
+        assert!((events[0].min - vec3(-0.5, 0.1, -0.5)).length() < 0.01);
+        assert!((events[0].max - vec3(0.5, 1.1, 0.5)).length() < 0.01);
+    }
+
+    #[test]
+    fn chunks_fill_the_box_and_inherit_its_motion() {
+        let mut w = PhysicsWorld::new(Vec3::ZERO);
+        let id = w
+            .add_destructible(
+                vec3(0.0, 5.0, 0.0),
+                vec3(1.0, 0.5, 0.5),
+                12.0,
+                Destructible {
+                    fracture: Fracture::Chunks(vec![
+                        Chunk {
+                            offset: vec3(-0.5, 0.0, 0.0),
+                            half: vec3(0.5, 0.5, 0.5),
+                        },
+                        Chunk {
+                            offset: vec3(0.5, 0.0, 0.0),
+                            half: vec3(0.5, 0.5, 0.5),
+                        },
+                    ]),
+                    ..Destructible::new(10.0, 1000.0)
+                },
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let h = w.handle_of(id).unwrap();
+        w.bodies[h].set_linvel(vector![0.0, 0.0, 2.0], true);
+        w.bodies[h].set_angvel(vector![0.0, 1.0, 0.0], true);
+        assert!(w.break_destructible(id));
+        let ev = w.drain_break_events().remove(0);
+        assert_eq!(ev.cause, BreakCause::Scripted);
+
+        let (left, right) = (
+            w.handle_of(ev.chunks[0]).unwrap(),
+            w.handle_of(ev.chunks[1]).unwrap(),
+        );
+        assert_eq!(w.bodies[left].translation().x, -0.5);
+        assert_eq!(w.bodies[right].translation().x, 0.5);
+        assert!((w.bodies[left].mass() - 6.0).abs() < 1e-3);
+        // Spinning about y: the left half moves forward faster than the right.
+        assert!(w.bodies[left].linvel().z > 2.0 && w.bodies[right].linvel().z < 2.0);
+        assert_eq!(*w.bodies[left].angvel(), vector![0.0, 1.0, 0.0]);
+        assert_eq!(w.body_layer(ev.chunks[0]), Some(Layers::DEBRIS));
+
+        let grid = Fracture::Grid([3, 1, 2]).chunks(vec3(1.5, 0.5, 1.0));
+        assert_eq!(grid.len(), 6);
+        assert!(grid.iter().all(|c| c.half == vec3(0.5, 0.5, 0.5)));
+        assert_eq!(grid[0].offset, vec3(-1.0, 0.0, -0.5));
+    }
+}
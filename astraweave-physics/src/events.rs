@@ -0,0 +1,357 @@
+//! Contacts, trigger overlaps, impacts and broken joints from each step, reported by [`BodyId`].
+
+use crate::{ActorKind, BodyId, JointId, LayerRef, PhysicsWorld};
+use glam::Vec3;
+use rapier3d::crossbeam::channel::{unbounded, Receiver};
+use rapier3d::prelude::*;
+
+/// Most events of each kind kept between drains; past it the oldest are dropped, so a
+/// world nobody drains does not grow without bound.
+pub const MAX_QUEUED_EVENTS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhysicsEvent {
+    /// Two solid bodies started touching.
+    ContactStarted { a: BodyId, b: BodyId },
+    /// Two solid bodies stopped touching, or one of them was removed.
+    ContactStopped { a: BodyId, b: BodyId },
+    /// `body` moved into a trigger volume.
+    TriggerEntered { trigger: BodyId, body: BodyId },
+    /// `body` left a trigger volume, or one of them was removed.
+    TriggerExited { trigger: BodyId, body: BodyId },
+    /// Two bodies hit each other with at least the impulse one of them reports (see
+    /// [`PhysicsWorld::report_impacts`]). `direction` is that of the strongest contact force.
+    Impact {
+        a: BodyId,
+        b: BodyId,
+        impulse: f32,
+        direction: Vec3,
+    },
+    /// A joint between `a` and `b` gave way under load or was broken on purpose.
+    JointBroken {
+        joint: JointId,
+        a: BodyId,
+        b: BodyId,
+    },
+}
+
+/// Receiving ends of the channels rapier's event collector writes to during a step.
+pub(crate) struct EventChannels {
+    pub(crate) collector: ChannelEventCollector,
+    collisions: Receiver<CollisionEvent>,
+    forces: Receiver<ContactForceEvent>,
+}
+
+impl Default for EventChannels {
+    fn default() -> Self {
+        let (collision_send, collisions) = unbounded();
+        let (force_send, forces) = unbounded();
+        Self {
+            collector: ChannelEventCollector::new(collision_send, force_send),
+            collisions,
+            forces,
+        }
+    }
+}
+
+impl PhysicsWorld {
+    /// A fixed sensor box reporting bodies and characters that enter and leave it. Only
+    /// layers that collide with `layer` set it off.
+    pub fn add_trigger_box<'a>(
+        &mut self,
+        center: Vec3,
+        half: Vec3,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let shape = ColliderBuilder::cuboid(half.x, half.y, half.z);
+        self.add_trigger(center, shape, layer.into())
+    }
+
+    pub fn add_trigger_sphere<'a>(
+        &mut self,
+        center: Vec3,
+        radius: f32,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        self.add_trigger(center, ColliderBuilder::ball(radius), layer.into())
+    }
+
+    fn add_trigger(
+        &mut self,
+        center: Vec3,
+        shape: ColliderBuilder,
+        layer: LayerRef,
+    ) -> anyhow::Result<BodyId> {
+        let groups = self.layer_groups(layer)?;
+        let rb = RigidBodyBuilder::fixed()
+            .translation(vector![center.x, center.y, center.z])
+            .build();
+        let h = self.bodies.insert(rb);
+        let coll = shape
+            .sensor(true)
+            // Characters are kinematic, which fixed sensors ignore by default.
+            .active_collision_types(
+                ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_FIXED,
+            )
+            .collision_groups(groups)
+            .build();
+        self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+        let id = self.tag_body(h, ActorKind::Static);
+        self.triggers.insert(id);
+        Ok(id)
+    }
+
+    pub fn is_trigger(&self, id: BodyId) -> bool {
+        self.triggers.contains(&id)
+    }
+
+    /// Report [`PhysicsEvent::Impact`]s on `id` of at least `min_impulse` (N·s).
+    pub fn report_impacts(&mut self, id: BodyId, min_impulse: f32) {
+        let Some(h) = self.handle_of(id) else {
+            return;
+        };
+        let threshold = min_impulse / self.integration.dt;
+        for &ch in self.bodies[h].colliders() {
+            let co = &mut self.colliders[ch];
+            co.set_active_events(co.active_events() | ActiveEvents::CONTACT_FORCE_EVENTS);
+            co.set_contact_force_event_threshold(threshold);
+        }
+    }
+
+    /// Events from every step since the last call, oldest first. Drain once per frame;
+    /// at most [`MAX_QUEUED_EVENTS`] are kept.
+    pub fn drain_events(&mut self) -> Vec<PhysicsEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Drop the oldest undrained events past [`MAX_QUEUED_EVENTS`]; run at the end of a step.
+    pub(crate) fn trim_event_queues(&mut self) {
+        fn keep_newest<T>(queue: &mut Vec<T>) {
+            let over = queue.len().saturating_sub(MAX_QUEUED_EVENTS);
+            queue.drain(..over);
+        }
+        keep_newest(&mut self.events);
+        keep_newest(&mut self.break_events);
+        keep_newest(&mut self.projectile_events);
+    }
+
+    fn body_of_collider(&self, ch: ColliderHandle) -> Option<BodyId> {
+        self.colliders
+            .get(ch)
+            .and_then(|c| c.parent())
+            .and_then(|b| self.id_of(b))
+            .or_else(|| self.removed_colliders.get(&ch).copied())
+    }
+
+    /// Turn what rapier reported during the step into [`PhysicsEvent`]s, then let
+    /// destructibles react to the impacts.
+    pub(crate) fn collect_events(&mut self) {
+        let collisions: Vec<CollisionEvent> = self.channels.collisions.try_iter().collect();
+        for ev in collisions {
+            let (Some(a), Some(b)) = (
+                self.body_of_collider(ev.collider1()),
+                self.body_of_collider(ev.collider2()),
+            ) else {
+                continue;
+            };
+            let trigger = if self.triggers.contains(&a) {
+                Some((a, b))
+            } else if self.triggers.contains(&b) {
+                Some((b, a))
+            } else {
+                None
+            };
+            self.events.push(match (trigger, ev.started()) {
+                (Some((trigger, body)), true) => PhysicsEvent::TriggerEntered { trigger, body },
+                (Some((trigger, body)), false) => PhysicsEvent::TriggerExited { trigger, body },
+                (None, true) => PhysicsEvent::ContactStarted { a, b },
+                (None, false) => PhysicsEvent::ContactStopped { a, b },
+            });
+        }
+        // Removed colliders only report their last contacts during the step after removal.
+        self.removed_colliders.clear();
+
+        let dt = self.integration.dt;
+        let forces: Vec<ContactForceEvent> = self.channels.forces.try_iter().collect();
+        for ev in &forces {
+            if let (Some(a), Some(b)) = (
+                self.body_of_collider(ev.collider1),
+                self.body_of_collider(ev.collider2),
+            ) {
+                let d = ev.max_force_direction;
+                self.events.push(PhysicsEvent::Impact {
+                    a,
+                    b,
+                    impulse: ev.total_force_magnitude * dt,
+                    direction: Vec3::new(d.x, d.y, d.z),
+                });
+            }
+        }
+        self.process_destructible_hits(&forces);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layers;
+    use glam::vec3;
+
+    fn world() -> (PhysicsWorld, BodyId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        let ground = w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        (w, ground)
+    }
+
+    fn run(w: &mut PhysicsWorld, frames: usize) -> Vec<PhysicsEvent> {
+        for _ in 0..frames {
+            w.step();
+        }
+        w.drain_events()
+    }
+
+    fn touches(ev: &PhysicsEvent, x: BodyId, y: BodyId) -> bool {
+        match *ev {
+            PhysicsEvent::ContactStarted { a, b } | PhysicsEvent::ContactStopped { a, b } => {
+                (a, b) == (x, y) || (a, b) == (y, x)
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn contacts_start_and_stop_by_body() {
+        let (mut w, ground) = world();
+        let a = w
+            .add_dynamic_box(vec3(0.0, 1.0, 0.0), Vec3::splat(0.5), 1.0, Layers::DEFAULT)
+            .unwrap();
+        let b = w
+            .add_dynamic_box(vec3(0.0, 3.0, 0.0), Vec3::splat(0.5), 1.0, Layers::DEFAULT)
+            .unwrap();
+        let events = run(&mut w, 90);
+        let started = |x, y| {
+            events
+                .iter()
+                .any(|e| matches!(e, PhysicsEvent::ContactStarted { .. }) && touches(e, x, y))
+        };
+        assert!(started(a, ground));
+        assert!(started(a, b), "stacked");
+        assert!(w.drain_events().is_empty(), "drained");
+
+        // Removing the top box ends its contact.
+        w.remove_body(b);
+        let events = run(&mut w, 1);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, PhysicsEvent::ContactStopped { .. }) && touches(e, a, b)));
+    }
+
+    #[test]
+    fn triggers_report_bodies_and_characters_passing_through() {
+        let (mut w, _) = world();
+        let zone = w
+            .add_trigger_box(vec3(3.0, 1.0, 0.0), vec3(1.0, 1.0, 1.0), Layers::TRIGGER)
+            .unwrap();
+        let orb = w
+            .add_trigger_sphere(vec3(-5.0, 1.0, 0.0), 1.0, Layers::TRIGGER)
+            .unwrap();
+        assert!(w.is_trigger(zone) && !w.is_trigger(orb + 1));
+
+        let hero = w
+            .add_character(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        let rock = w
+            .add_dynamic_box(vec3(-5.0, 4.0, 0.0), Vec3::splat(0.2), 1.0, Layers::DEFAULT)
+            .unwrap();
+        w.step();
+        let mut events = Vec::new();
+        for _ in 0..150 {
+            w.control_character(hero, vec3(3.0, 0.0, 0.0), 1.0 / 60.0, false);
+            w.step();
+            events.extend(w.drain_events());
+        }
+        let entered = events.iter().position(|e| {
+            *e == PhysicsEvent::TriggerEntered {
+                trigger: zone,
+                body: hero,
+            }
+        });
+        let exited = events.iter().position(|e| {
+            *e == PhysicsEvent::TriggerExited {
+                trigger: zone,
+                body: hero,
+            }
+        });
+        assert!(
+            matches!((entered, exited), (Some(i), Some(o)) if i < o),
+            "{events:?}"
+        );
+        assert!(events.contains(&PhysicsEvent::TriggerEntered {
+            trigger: orb,
+            body: rock
+        }));
+        assert!(
+            !events.iter().any(|e| touches(e, orb, rock)),
+            "sensors don't collide"
+        );
+
+        // The rock rests inside the orb; removing it counts as leaving.
+        w.remove_body(rock);
+        assert!(run(&mut w, 1).contains(&PhysicsEvent::TriggerExited {
+            trigger: orb,
+            body: rock
+        }));
+    }
+
+    #[test]
+    fn impacts_are_reported_above_the_threshold() {
+        let (mut w, ground) = world();
+        let soft = w
+            .add_dynamic_box(vec3(0.0, 0.7, 0.0), Vec3::splat(0.5), 2.0, Layers::DEFAULT)
+            .unwrap();
+        let hard = w
+            .add_dynamic_box(vec3(3.0, 6.0, 0.0), Vec3::splat(0.5), 2.0, Layers::DEFAULT)
+            .unwrap();
+        w.report_impacts(soft, 5.0);
+        w.report_impacts(hard, 5.0);
+        let impacts: Vec<_> = run(&mut w, 90)
+            .into_iter()
+            .filter_map(|e| match e {
+                PhysicsEvent::Impact {
+                    a,
+                    b,
+                    impulse,
+                    direction,
+                } => Some(((a, b), impulse, direction)),
+                _ => None,
+            })
+            .collect();
+        assert!(!impacts.is_empty());
+        assert!(impacts
+            .iter()
+            .all(
+                |&(pair, impulse, _)| (pair == (hard, ground) || pair == (ground, hard))
+                    && impulse >= 5.0
+            ));
+        assert!(
+            impacts.iter().all(|&(_, _, d)| d.y.abs() > 0.9),
+            "vertical hit"
+        );
+    }
+
+    #[test]
+    fn undrained_events_are_capped() {
+        let (mut w, ground) = world();
+        let broken = |joint| PhysicsEvent::JointBroken {
+            joint,
+            a: ground,
+            b: ground,
+        };
+        w.events
+            .extend((0..MAX_QUEUED_EVENTS as JointId + 10).map(broken));
+        w.step();
+        let events = w.drain_events();
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(events[0], broken(10), "oldest dropped first");
+    }
+}
@@ -0,0 +1,502 @@
+//! Joints between bodies, addressed by [`JointId`]: hinges for doors, ball joints for chains
+//! and limbs, ropes, and rope bridges built from them. A joint can be given a force it
+//! breaks at, and gameplay can break joints outright, e.g. to collapse a bridge.
+
+use crate::{ActorKind, BodyId, LayerRef, PhysicsEvent, PhysicsWorld};
+use glam::{Quat, Vec3};
+use rapier3d::na::{Quaternion, UnitQuaternion};
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type JointId = u64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JointKind {
+    /// Holds the bodies together as they are.
+    Fixed,
+    /// Hinge turning about `axis`, within `limits` radians of where it started if given.
+    Revolute {
+        axis: Vec3,
+        limits: Option<[f32; 2]>,
+    },
+    /// Ball joint twisting up to `twist` radians either way about `axis`, e.g. along a limb,
+    /// and swinging up to `swing` about the two axes across it.
+    Spherical { axis: Vec3, swing: f32, twist: f32 },
+    /// Keeps the anchors at most `length` apart but lets them come closer.
+    Rope { length: f32 },
+}
+
+impl JointKind {
+    pub(crate) fn rotated(self, rot: Quat) -> Self {
+        match self {
+            JointKind::Revolute { axis, limits } => JointKind::Revolute {
+                axis: rot * axis,
+                limits,
+            },
+            JointKind::Spherical { axis, swing, twist } => JointKind::Spherical {
+                axis: rot * axis,
+                swing,
+                twist,
+            },
+            other => other,
+        }
+    }
+
+    fn axis(&self) -> Vec3 {
+        match *self {
+            JointKind::Revolute { axis, .. } | JointKind::Spherical { axis, .. } => {
+                axis.try_normalize().unwrap_or(Vec3::X)
+            }
+            JointKind::Fixed | JointKind::Rope { .. } => Vec3::X,
+        }
+    }
+}
+
+/// Bodies strung together end to end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chain {
+    pub links: Vec<BodyId>,
+    /// From the first link's anchor to the last link, in order.
+    pub joints: Vec<JointId>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct Joint {
+    handle: ImpulseJointHandle,
+    a: BodyId,
+    b: BodyId,
+    /// Force (N) the joint gives way at.
+    break_force: Option<f32>,
+}
+
+fn to_na(q: Quat) -> UnitQuaternion<Real> {
+    UnitQuaternion::from_quaternion(Quaternion::new(q.w, q.x, q.y, q.z))
+}
+
+impl PhysicsWorld {
+    /// Join `a` and `b` at the world point `anchor`, as they are now posed.
+    pub fn add_joint(
+        &mut self,
+        a: BodyId,
+        b: BodyId,
+        anchor: Vec3,
+        kind: JointKind,
+    ) -> Option<JointId> {
+        let (ha, hb) = (self.handle_of(a)?, self.handle_of(b)?);
+        let frame = Isometry::from_parts(
+            vector![anchor.x, anchor.y, anchor.z].into(),
+            to_na(Quat::from_rotation_arc(Vec3::X, kind.axis())),
+        );
+        let frame1 = self.bodies[ha].position().inv_mul(&frame);
+        let frame2 = self.bodies[hb].position().inv_mul(&frame);
+        let joint: GenericJoint = match kind {
+            JointKind::Fixed => GenericJointBuilder::new(JointAxesMask::LOCKED_FIXED_AXES).build(),
+            JointKind::Revolute { limits, .. } => {
+                let mut builder = GenericJointBuilder::new(JointAxesMask::LOCKED_REVOLUTE_AXES);
+                if let Some(limits) = limits {
+                    builder = builder.limits(JointAxis::AngX, limits);
+                }
+                builder.build()
+            }
+            JointKind::Spherical { swing, twist, .. } => {
+                GenericJointBuilder::new(JointAxesMask::LOCKED_SPHERICAL_AXES)
+                    .limits(JointAxis::AngX, [-twist, twist])
+                    .limits(JointAxis::AngY, [-swing, swing])
+                    .limits(JointAxis::AngZ, [-swing, swing])
+                    .build()
+            }
+            JointKind::Rope { length } => RopeJointBuilder::new(length).build().into(),
+        };
+        let joint = GenericJointBuilder(joint)
+            .local_frame1(frame1)
+            .local_frame2(frame2)
+            // Jointed bodies overlap where they meet.
+            .contacts_enabled(false)
+            .build();
+        let handle = self.joints.insert(ha, hb, joint, true);
+        let id = self.next_joint_id;
+        self.next_joint_id += 1;
+        self.joint_map.insert(
+            id,
+            Joint {
+                handle,
+                a,
+                b,
+                break_force: None,
+            },
+        );
+        Some(id)
+    }
+
+    /// Break the joint once it has to hold more than `force` (N); `None` never breaks it.
+    pub fn set_joint_break_force(&mut self, id: JointId, force: Option<f32>) -> bool {
+        let Some(j) = self.joint_map.get_mut(&id) else {
+            return false;
+        };
+        j.break_force = force;
+        true
+    }
+
+    /// The bodies a joint holds together.
+    pub fn joint_bodies(&self, id: JointId) -> Option<(BodyId, BodyId)> {
+        self.joint_map.get(&id).map(|j| (j.a, j.b))
+    }
+
+    /// Where the joint currently sits, as seen from its first body.
+    pub fn joint_anchor(&self, id: JointId) -> Option<Vec3> {
+        let j = self.joints.get(self.joint_map.get(&id)?.handle)?;
+        let p = self.bodies.get(j.body1)?.position() * j.data.local_anchor1();
+        Some(Vec3::new(p.x, p.y, p.z))
+    }
+
+    pub fn joints_of(&self, body: BodyId) -> Vec<JointId> {
+        self.joint_map
+            .iter()
+            .filter(|(_, j)| j.a == body || j.b == body)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Break a joint now, reporting [`PhysicsEvent::JointBroken`].
+    pub fn break_joint(&mut self, id: JointId) -> bool {
+        let Some(j) = self.joint_map.remove(&id) else {
+            return false;
+        };
+        self.joints.remove(j.handle, true);
+        self.events.push(PhysicsEvent::JointBroken {
+            joint: id,
+            a: j.a,
+            b: j.b,
+        });
+        true
+    }
+
+    /// Break every joint sitting within `radius` of the segment from `a` to `b`.
+    pub fn break_joints_near(&mut self, a: Vec3, b: Vec3, radius: f32) -> Vec<JointId> {
+        let ab = b - a;
+        let near: Vec<JointId> = self
+            .joint_map
+            .keys()
+            .copied()
+            .filter(|&id| {
+                self.joint_anchor(id).is_some_and(|p| {
+                    let t = ((p - a).dot(ab) / ab.length_squared().max(1e-6)).clamp(0.0, 1.0);
+                    p.distance(a + ab * t) <= radius
+                })
+            })
+            .collect();
+        for &id in &near {
+            self.break_joint(id);
+        }
+        near
+    }
+
+    /// Drop the joints of a body that is being removed; rapier removes them with it.
+    pub(crate) fn forget_joints_of(&mut self, body: BodyId) {
+        self.joint_map.retain(|_, j| j.a != body && j.b != body);
+    }
+
+    /// Break joints that held more than their break force during the last step.
+    pub(crate) fn break_overloaded_joints(&mut self) {
+        let dt = self.integration.dt;
+        let overloaded: Vec<JointId> = self
+            .joint_map
+            .iter()
+            .filter(|(_, j)| {
+                j.break_force.is_some_and(|limit| {
+                    self.joints
+                        .get(j.handle)
+                        .is_some_and(|joint| joint.impulses.fixed_rows::<3>(0).norm() / dt >= limit)
+                })
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for id in overloaded {
+            self.break_joint(id);
+        }
+    }
+
+    /// Hang `links` capsules of `radius` end to end from `from` to `to`, the first from
+    /// `anchor` at `from`, joined by ball joints.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_chain<'a>(
+        &mut self,
+        anchor: BodyId,
+        from: Vec3,
+        to: Vec3,
+        links: usize,
+        radius: f32,
+        mass: f32,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<Chain> {
+        let groups = self.layer_groups(layer.into())?;
+        let step = (to - from) / links.max(1) as f32;
+        let swing = JointKind::Spherical {
+            axis: step,
+            swing: std::f32::consts::PI,
+            twist: std::f32::consts::PI,
+        };
+        let mut chain = Chain::default();
+        let mut prev = anchor;
+        for i in 0..links.max(1) {
+            let head = from + step * i as f32;
+            let centre = head + step * 0.5;
+            let rb = RigidBodyBuilder::dynamic()
+                .translation(vector![centre.x, centre.y, centre.z])
+                .build();
+            let h = self.bodies.insert(rb);
+            let (p, q) = (-step * 0.5, step * 0.5);
+            let coll = ColliderBuilder::capsule_from_endpoints(
+                point![p.x, p.y, p.z],
+                point![q.x, q.y, q.z],
+                radius,
+            )
+            .mass(mass / links.max(1) as f32)
+            .collision_groups(groups)
+            .friction(0.6)
+            .build();
+            self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+            let link = self.tag_body(h, ActorKind::Dynamic);
+            if let Some(j) = self.add_joint(prev, link, head, swing) {
+                chain.joints.push(j);
+            }
+            chain.links.push(link);
+            prev = link;
+        }
+        Ok(chain)
+    }
+
+    /// A bridge of `planks` boards from `from` to `to`, hinged to each other across the
+    /// bridge and to `ends` (e.g. the cliffs either side) at the ends, so it sags under
+    /// load. Breaking its joints drops it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_rope_bridge<'a>(
+        &mut self,
+        ends: [BodyId; 2],
+        from: Vec3,
+        to: Vec3,
+        planks: usize,
+        half_width: f32,
+        mass: f32,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<Chain> {
+        let groups = self.layer_groups(layer.into())?;
+        let planks = planks.max(1);
+        let along = (to - from).try_normalize().unwrap_or(Vec3::X);
+        let side = along.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::Z);
+        let up = side.cross(along);
+        let rot = Quat::from_mat3(&glam::Mat3::from_cols(along, up, side));
+        let step = (to - from) / planks as f32;
+        let half_len = (step.length() * 0.5 - 0.02).max(0.01);
+        let hinge = JointKind::Revolute {
+            axis: side,
+            limits: None,
+        };
+
+        let mut bridge = Chain::default();
+        let mut prev = ends[0];
+        for i in 0..planks {
+            let centre = from + step * (i as f32 + 0.5);
+            let rb = RigidBodyBuilder::dynamic()
+                .position(Isometry::from_parts(
+                    vector![centre.x, centre.y, centre.z].into(),
+                    to_na(rot),
+                ))
+                .build();
+            let h = self.bodies.insert(rb);
+            let coll = ColliderBuilder::cuboid(half_len, 0.05, half_width)
+                .mass(mass / planks as f32)
+                .collision_groups(groups)
+                .friction(0.8)
+                .build();
+            self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+            let plank = self.tag_body(h, ActorKind::Dynamic);
+            if let Some(j) = self.add_joint(prev, plank, from + step * i as f32, hinge) {
+                bridge.joints.push(j);
+            }
+            bridge.links.push(plank);
+            prev = plank;
+        }
+        if let Some(j) = self.add_joint(prev, ends[1], to, hinge) {
+            bridge.joints.push(j);
+        }
+        Ok(bridge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layers;
+    use glam::vec3;
+
+    fn world() -> (PhysicsWorld, BodyId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        let ground = w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        (w, ground)
+    }
+
+    fn run(w: &mut PhysicsWorld, frames: usize) {
+        for _ in 0..frames {
+            w.step();
+        }
+    }
+
+    /// A 6 m high cliff top with its edge a metre from `x`.
+    fn cliff(w: &mut PhysicsWorld, x: f32) -> BodyId {
+        let rb = RigidBodyBuilder::fixed().translation(vector![x, 3.0, 0.0]);
+        let h = w.bodies.insert(rb);
+        let coll = ColliderBuilder::cuboid(1.0, 3.0, 2.0).build();
+        w.colliders.insert_with_parent(coll, h, &mut w.bodies);
+        w.tag_body(h, ActorKind::Static)
+    }
+
+    fn pos(w: &PhysicsWorld, id: BodyId) -> Vec3 {
+        w.body_transform(id).unwrap().w_axis.truncate()
+    }
+
+    #[test]
+    fn hinged_door_swings_about_its_hinge_within_limits() {
+        let (mut w, ground) = world();
+        let door = w
+            .add_dynamic_box(
+                vec3(0.5, 1.2, 0.0),
+                vec3(0.5, 1.0, 0.05),
+                20.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let hinge = w
+            .add_joint(
+                ground,
+                door,
+                vec3(0.0, 1.2, 0.0),
+                JointKind::Revolute {
+                    axis: Vec3::Y,
+                    limits: Some([0.0, 1.5]),
+                },
+            )
+            .unwrap();
+        assert_eq!(w.joint_bodies(hinge), Some((ground, door)));
+        assert_eq!(w.joints_of(door), vec![hinge]);
+
+        // Shove the free edge open.
+        let h = w.handle_of(door).unwrap();
+        w.bodies[h].apply_impulse_at_point(vector![0.0, 0.0, 30.0], point![1.0, 1.2, 0.0], true);
+        run(&mut w, 120);
+        let p = pos(&w, door);
+        assert!((p.y - 1.2).abs() < 0.05, "sagged to {p}");
+        assert!(
+            (vec3(p.x, 0.0, p.z).length() - 0.5).abs() < 0.05,
+            "left the hinge: {p}"
+        );
+        // Opening towards +z is past the limit; it stops shut instead.
+        assert!(p.z.abs() < 0.1 && p.x > 0.45, "{p}");
+
+        w.bodies[h].apply_impulse_at_point(vector![0.0, 0.0, -30.0], point![1.0, 1.2, 0.0], true);
+        run(&mut w, 120);
+        let p = pos(&w, door);
+        assert!(p.z < -0.3, "opened to {p}");
+    }
+
+    #[test]
+    fn chains_hang_and_overloaded_joints_break() {
+        let (mut w, _) = world();
+        let beam = w
+            .add_static_trimesh(
+                &[
+                    vec3(-1.0, 5.0, -1.0),
+                    vec3(1.0, 5.0, -1.0),
+                    vec3(0.0, 5.0, 1.0),
+                ],
+                &[[0, 1, 2]],
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        // Starts out sideways and swings down under the beam.
+        let chain = w
+            .add_chain(
+                beam,
+                vec3(0.0, 4.9, 0.0),
+                vec3(2.0, 4.9, 0.0),
+                4,
+                0.05,
+                2.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        assert_eq!((chain.links.len(), chain.joints.len()), (4, 4));
+        run(&mut w, 300);
+        // Still swinging, but in one piece below the beam.
+        let mut prev = vec3(0.0, 4.9, 0.0);
+        for (i, &link) in chain.links.iter().enumerate() {
+            let t = w.body_transform(link).unwrap();
+            let (head, tail) = (
+                t.transform_point3(vec3(-0.25, 0.0, 0.0)),
+                t.transform_point3(vec3(0.25, 0.0, 0.0)),
+            );
+            assert!(head.distance(prev) < 0.05, "link {i} came off at {head}");
+            assert!(head.y < 4.91 && tail.y < 4.9);
+            prev = tail;
+        }
+
+        // A weight too heavy for the top link tears it off the beam.
+        let last = *chain.links.last().unwrap();
+        let weight = w
+            .add_dynamic_box(
+                prev - vec3(0.0, 0.3, 0.0),
+                Vec3::splat(0.2),
+                50.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let tie = w
+            .add_joint(last, weight, prev, JointKind::Rope { length: 0.3 })
+            .unwrap();
+        assert!(w.set_joint_break_force(chain.joints[0], Some(200.0)));
+        w.drain_events();
+        run(&mut w, 60);
+        assert!(w.drain_events().contains(&PhysicsEvent::JointBroken {
+            joint: chain.joints[0],
+            a: beam,
+            b: chain.links[0],
+        }));
+        assert!(w.joint_bodies(chain.joints[0]).is_none());
+        run(&mut w, 120);
+        assert!(pos(&w, weight).y < 0.5, "fell with the chain");
+
+        // Removing a body takes its joints with it.
+        w.remove_body(last);
+        assert!(w.joint_bodies(tie).is_none());
+    }
+
+    #[test]
+    fn rope_bridge_holds_a_load_until_collapsed() {
+        let (mut w, _) = world();
+        let cliffs = [cliff(&mut w, -4.0), cliff(&mut w, 4.0)];
+        let bridge = w
+            .add_rope_bridge(
+                cliffs,
+                vec3(-3.0, 6.0, 0.0),
+                vec3(3.0, 6.0, 0.0),
+                6,
+                0.8,
+                30.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        assert_eq!((bridge.links.len(), bridge.joints.len()), (6, 7));
+        let crate_ = w
+            .add_dynamic_box(vec3(0.0, 6.6, 0.0), Vec3::splat(0.3), 20.0, Layers::DEFAULT)
+            .unwrap();
+        run(&mut w, 180);
+        let p = pos(&w, crate_);
+        assert!(p.y > 4.5 && p.y < 6.5, "sagged to {p}");
+
+        let broken = w.break_joints_near(vec3(-3.0, 6.0, 0.0), vec3(3.0, 6.0, 0.0), 1.5);
+        assert_eq!(broken.len(), bridge.joints.len());
+        run(&mut w, 120);
+        assert!(pos(&w, crate_).y < 1.0, "bridge collapsed");
+        assert!(bridge.links.iter().all(|&l| pos(&w, l).y < 1.0));
+    }
+}
@@ -0,0 +1,298 @@
+//! Named collision layers and the matrix deciding which of them collide, loaded from TOML.
+//! The built-in table is `layers.toml` at the crate root.
+
+use anyhow::{bail, Context};
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct Layers: u32 {
+        const DEFAULT     = 1 << 0;
+        const CHARACTER   = 1 << 1;
+        const PLAYER      = 1 << 2;
+        const COMPANION   = 1 << 3;
+        const ENEMY       = 1 << 4;
+        const PROJECTILE  = 1 << 5;
+        const DEBRIS      = 1 << 6;
+        const TRIGGER     = 1 << 7;
+        const WATER       = 1 << 8;
+        const NAV_BLOCKER = 1 << 9;
+        const SMOKE       = 1 << 10;
+        // Bits above these are handed out to layers named in a game's table.
+        const _ = !0;
+    }
+}
+
+const BUILT_IN: [(&str, Layers); 11] = [
+    ("default", Layers::DEFAULT),
+    ("character", Layers::CHARACTER),
+    ("player", Layers::PLAYER),
+    ("companion", Layers::COMPANION),
+    ("enemy", Layers::ENEMY),
+    ("projectile", Layers::PROJECTILE),
+    ("debris", Layers::DEBRIS),
+    ("trigger", Layers::TRIGGER),
+    ("water", Layers::WATER),
+    ("nav_blocker", Layers::NAV_BLOCKER),
+    ("smoke", Layers::SMOKE),
+];
+
+/// A layer given either by name, looked up in the world's [`LayerMatrix`], or as bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerRef<'a> {
+    Name(&'a str),
+    Bits(Layers),
+}
+
+impl<'a> From<&'a str> for LayerRef<'a> {
+    fn from(name: &'a str) -> Self {
+        LayerRef::Name(name)
+    }
+}
+
+impl From<Layers> for LayerRef<'_> {
+    fn from(layers: Layers) -> Self {
+        LayerRef::Bits(layers)
+    }
+}
+
+#[derive(Deserialize)]
+struct LayerDef {
+    #[serde(default)]
+    collides: Vec<String>,
+    #[serde(default)]
+    queries: Vec<String>,
+}
+
+/// Which layers collide with which, and what each layer's queries hit by default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayerMatrix {
+    names: BTreeMap<String, Layers>,
+    /// Indexed by bit.
+    collides: [Layers; 32],
+    queries: [Layers; 32],
+}
+
+impl Default for LayerMatrix {
+    fn default() -> Self {
+        Self::from_toml(include_str!("../layers.toml")).expect("built-in layer table is valid")
+    }
+}
+
+impl LayerMatrix {
+    /// Parse a `[layers.<name>]` table. Layers the table leaves out collide with nothing.
+    pub fn from_toml(toml_txt: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct File {
+            layers: BTreeMap<String, LayerDef>,
+        }
+        let f: File = toml::from_str(toml_txt)?;
+
+        let mut names: BTreeMap<String, Layers> = BUILT_IN
+            .iter()
+            .map(|&(name, bits)| (name.to_string(), bits))
+            .collect();
+        let mut next_bit = BUILT_IN.len() as u32;
+        for name in f.layers.keys() {
+            if !names.contains_key(name) {
+                if next_bit == 32 {
+                    bail!("too many physics layers; `{name}` needs a 33rd bit");
+                }
+                names.insert(name.clone(), Layers::from_bits_retain(1 << next_bit));
+                next_bit += 1;
+            }
+        }
+
+        let mask = |list: &[String], layer: &str| -> anyhow::Result<Layers> {
+            let mut out = Layers::empty();
+            for entry in list {
+                let (remove, name) = match entry.strip_prefix('!') {
+                    Some(name) => (true, name),
+                    None => (false, entry.as_str()),
+                };
+                let bits = if name == "all" {
+                    Layers::all()
+                } else {
+                    *names
+                        .get(name)
+                        .with_context(|| format!("layer `{layer}` lists unknown layer `{name}`"))?
+                };
+                out.set(bits, !remove);
+            }
+            Ok(out)
+        };
+        let mut collides = [Layers::empty(); 32];
+        let mut queries = [Layers::empty(); 32];
+        for (name, def) in &f.layers {
+            let bit = names[name].bits().trailing_zeros() as usize;
+            collides[bit] = mask(&def.collides, name)?;
+            queries[bit] = mask(&def.queries, name)?;
+        }
+        Ok(Self {
+            names,
+            collides,
+            queries,
+        })
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let txt = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+        Self::from_toml(&txt).with_context(|| format!("parsing {path}"))
+    }
+
+    pub fn layer(&self, name: &str) -> Option<Layers> {
+        self.names.get(name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&str, Layers)> {
+        self.names.iter().map(|(n, &l)| (n.as_str(), l))
+    }
+
+    /// Bits for `layer`; an error if it names a layer the matrix doesn't have.
+    pub fn resolve(&self, layer: LayerRef) -> anyhow::Result<Layers> {
+        match layer {
+            LayerRef::Bits(bits) => Ok(bits),
+            LayerRef::Name(name) => self
+                .layer(name)
+                .with_context(|| format!("unknown physics layer `{name}`")),
+        }
+    }
+
+    fn union(table: &[Layers; 32], layers: Layers) -> Layers {
+        layers
+            .iter()
+            .map(|l| table[l.bits().trailing_zeros() as usize])
+            .fold(Layers::empty(), |a, b| a | b)
+    }
+
+    /// Layers a body on `layers` is allowed to collide with.
+    pub fn collision_mask(&self, layers: Layers) -> Layers {
+        Self::union(&self.collides, layers)
+    }
+
+    /// What queries made for a body on `layers` should hit by default.
+    pub fn query_mask(&self, layer: LayerRef) -> anyhow::Result<Layers> {
+        Ok(Self::union(&self.queries, self.resolve(layer)?))
+    }
+
+    /// Whether bodies on `a` and `b` collide: each has to allow the other.
+    pub fn collides(&self, a: Layers, b: Layers) -> bool {
+        self.collision_mask(a).intersects(b) && self.collision_mask(b).intersects(a)
+    }
+
+    pub(crate) fn groups(&self, layers: Layers) -> InteractionGroups {
+        InteractionGroups::new(
+            Group::from_bits_retain(layers.bits()),
+            Group::from_bits_retain(self.collision_mask(layers).bits()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PhysicsWorld;
+    use glam::{vec3, Vec3};
+
+    #[test]
+    fn built_in_table_keeps_allies_and_debris_out_of_the_way() {
+        let m = LayerMatrix::default();
+        assert_eq!(m.layer("nav_blocker"), Some(Layers::NAV_BLOCKER));
+        assert_eq!(m.names().count(), BUILT_IN.len());
+        assert!(m.collides(Layers::PROJECTILE, Layers::ENEMY));
+        assert!(!m.collides(Layers::PROJECTILE, Layers::COMPANION));
+        assert!(!m.collides(Layers::DEBRIS, Layers::PLAYER));
+        assert!(m.collides(Layers::DEBRIS, Layers::DEFAULT));
+        assert!(m.collides(Layers::NAV_BLOCKER, Layers::ENEMY));
+        assert!(!m.collides(Layers::NAV_BLOCKER, Layers::DEFAULT));
+        assert!(!m
+            .query_mask("enemy".into())
+            .unwrap()
+            .intersects(Layers::DEBRIS | Layers::NAV_BLOCKER));
+    }
+
+    #[test]
+    fn custom_tables_add_layers_and_reject_unknown_names() {
+        let m = LayerMatrix::from_toml(
+            r#"
+            [layers.default]
+            collides = ["all"]
+            [layers.enemy]
+            collides = ["all"]
+            [layers.enemy_projectile]
+            collides = ["all", "!enemy", "!enemy_projectile"]
+            queries = ["default", "player"]
+            "#,
+        )
+        .unwrap();
+        let bolt = m.layer("enemy_projectile").unwrap();
+        assert_eq!(bolt.bits(), 1 << BUILT_IN.len());
+        assert!(m.collides(bolt, Layers::DEFAULT));
+        assert!(!m.collides(bolt, Layers::ENEMY));
+        assert!(
+            !m.collides(bolt, Layers::PLAYER),
+            "player isn't in this table"
+        );
+        assert_eq!(
+            m.query_mask(LayerRef::Bits(bolt)).unwrap(),
+            Layers::DEFAULT | Layers::PLAYER
+        );
+
+        let err = LayerMatrix::from_toml("[layers.enemy]\ncollides = [\"ghost\"]").unwrap_err();
+        assert!(err.to_string().contains("ghost"));
+    }
+
+    #[test]
+    fn builders_refuse_unknown_layer_names() {
+        let mut w = PhysicsWorld::new(Vec3::ZERO);
+        let err = w
+            .add_dynamic_box(Vec3::ZERO, Vec3::ONE, 1.0, "ghost")
+            .unwrap_err();
+        assert!(err.to_string().contains("ghost"), "{err}");
+        assert_eq!(w.bodies.len(), 0, "nothing half-built");
+        assert!(w.layer_matrix().query_mask("ghost".into()).is_err());
+
+        let crate_ = w
+            .add_dynamic_box(Vec3::ZERO, Vec3::ONE, 1.0, "default")
+            .unwrap();
+        assert!(w.set_body_layer(crate_, "ghost").is_err());
+        assert_eq!(w.body_layer(crate_), Some(Layers::DEFAULT));
+    }
+
+    #[test]
+    fn debris_falls_past_characters_but_lands_on_the_ground() {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        let hero = w
+            .add_character(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), "player")
+            .unwrap();
+        let rubble = w
+            .add_dynamic_box(vec3(0.0, 3.0, 0.0), Vec3::splat(0.2), 1.0, "debris")
+            .unwrap();
+        let rock = w
+            .add_dynamic_box(vec3(5.0, 3.0, 0.0), Vec3::splat(0.2), 1.0, "debris")
+            .unwrap();
+        for _ in 0..120 {
+            w.step();
+        }
+        let y = |id| w.body_transform(id).unwrap().w_axis.y;
+        assert!((y(rubble) - 0.3).abs() < 0.05, "fell through the player");
+        assert!((y(rock) - 0.3).abs() < 0.05);
+        assert_eq!(w.body_layer(hero), Some(Layers::PLAYER));
+
+        // The player's own queries look past debris too.
+        let mask = w.layer_matrix().query_mask("player".into()).unwrap();
+        let down = |w: &PhysicsWorld| {
+            w.raycast(vec3(5.0, 5.0, 0.0), -Vec3::Y, 10.0, mask, &[])
+                .map(|h| h.body)
+        };
+        assert_ne!(down(&w), Some(rock));
+        assert!(w.set_body_layer(rock, "default").unwrap());
+        assert_eq!(w.body_layer(rock), Some(Layers::DEFAULT));
+        w.step();
+        assert_eq!(down(&w), Some(rock));
+    }
+}
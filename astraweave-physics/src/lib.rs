@@ -1,10 +1,34 @@
 use glam::{vec3, Mat4, Vec3};
 use rapier3d::prelude::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+mod character;
+mod destructible;
+mod events;
+mod joints;
+mod layers;
+mod projectile;
+mod query;
+mod ragdoll;
+mod snapshot;
+mod water;
+mod wind;
+pub use character::{CharState, CharacterController};
+pub use destructible::{BreakCause, BreakEvent, Chunk, Destructible, Fracture};
+pub use events::{PhysicsEvent, MAX_QUEUED_EVENTS};
+pub use joints::{Chain, JointId, JointKind};
+pub use layers::{LayerMatrix, LayerRef, Layers};
+pub use projectile::{launch_velocities, Projectile, ProjectileEvent, ProjectileKind};
+pub use query::QueryHit;
+pub use ragdoll::{Bone, Ragdoll, Skeleton};
+pub use snapshot::SNAPSHOT_VERSION;
+pub use water::{WaterId, WaterVolume};
+pub use wind::{Falloff, ForceField, ForceFieldId, ForceFieldKind, DEFAULT_DRAG};
 
 pub type BodyId = u64;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ActorKind {
     Static,
     Dynamic,
@@ -12,24 +36,6 @@ pub enum ActorKind {
     Other,
 }
 
-bitflags::bitflags! {
-    pub struct Layers: u32 {
-        const DEFAULT   = 0b00000001;
-        const CHARACTER = 0b00000010;
-    }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum CharState {
-    Grounded,
-}
-
-#[derive(Clone, Copy, Debug)]
-pub struct CharacterController {
-    pub state: CharState,
-    pub max_climb_angle_deg: f32,
-}
-
 pub struct PhysicsWorld {
     pub bodies: RigidBodySet,
     pub colliders: ColliderSet,
@@ -44,9 +50,31 @@ pub struct PhysicsWorld {
     pub query_pipeline: QueryPipeline,
     pub ccd: CCDSolver,
     body_ids: HashMap<RigidBodyHandle, BodyId>,
+    /// Reverse of `body_ids`.
+    handles: HashMap<BodyId, RigidBodyHandle>,
     body_kinds: HashMap<RigidBodyHandle, ActorKind>,
     next_body_id: BodyId,
+    layers: LayerMatrix,
     pub char_map: HashMap<BodyId, CharacterController>,
+    water: BTreeMap<WaterId, WaterVolume>,
+    next_water_id: WaterId,
+    wind: Vec3,
+    force_fields: BTreeMap<ForceFieldId, ForceField>,
+    next_field_id: ForceFieldId,
+    body_drag: HashMap<BodyId, f32>,
+    destructibles: HashMap<BodyId, Destructible>,
+    break_events: Vec<BreakEvent>,
+    projectiles: BTreeMap<BodyId, Projectile>,
+    /// Smoke clouds and the seconds each has left.
+    smoke: BTreeMap<BodyId, f32>,
+    projectile_events: Vec<ProjectileEvent>,
+    channels: events::EventChannels,
+    events: Vec<PhysicsEvent>,
+    triggers: HashSet<BodyId>,
+    joint_map: BTreeMap<JointId, joints::Joint>,
+    next_joint_id: JointId,
+    /// Owners of colliders removed since the last step, whose final events still need them.
+    removed_colliders: HashMap<ColliderHandle, BodyId>,
 }
 
 impl PhysicsWorld {
@@ -65,9 +93,28 @@ impl PhysicsWorld {
             query_pipeline: QueryPipeline::new(),
             ccd: CCDSolver::new(),
             body_ids: HashMap::new(),
+            handles: HashMap::new(),
             body_kinds: HashMap::new(),
             next_body_id: 1,
+            layers: LayerMatrix::default(),
             char_map: HashMap::new(),
+            water: BTreeMap::new(),
+            next_water_id: 1,
+            wind: Vec3::ZERO,
+            force_fields: BTreeMap::new(),
+            next_field_id: 1,
+            body_drag: HashMap::new(),
+            destructibles: HashMap::new(),
+            break_events: Vec::new(),
+            projectiles: BTreeMap::new(),
+            smoke: BTreeMap::new(),
+            projectile_events: Vec::new(),
+            channels: events::EventChannels::default(),
+            events: Vec::new(),
+            triggers: HashSet::new(),
+            joint_map: BTreeMap::new(),
+            next_joint_id: 1,
+            removed_colliders: HashMap::new(),
         }
     }
 
@@ -78,7 +125,8 @@ impl PhysicsWorld {
     }
 
     pub fn step(&mut self) {
-        let events = ();
+        self.apply_water();
+        self.apply_wind();
         self.pipeline.step(
             &self.gravity,
             &self.integration,
@@ -91,9 +139,14 @@ impl PhysicsWorld {
             &mut self.multibody_joints,
             &mut self.ccd,
             Some(&mut self.query_pipeline),
-            &events,
             &(),
+            &self.channels.collector,
         );
+        let first_new = self.events.len();
+        self.collect_events();
+        self.break_overloaded_joints();
+        self.update_projectiles(first_new);
+        self.trim_event_queues();
     }
 
     pub fn create_ground_plane(&mut self, half: Vec3, friction: f32) -> BodyId {
@@ -101,96 +154,145 @@ impl PhysicsWorld {
         let h = self.bodies.insert(rb);
         let shape = ColliderBuilder::cuboid(half.x, 0.1, half.z)
             .friction(friction)
-            .collision_groups(InteractionGroups::new(
-                Group::from_bits_truncate(Layers::DEFAULT.bits()),
-                Group::ALL,
-            ))
+            .collision_groups(self.layers.groups(Layers::DEFAULT))
             .build();
         self.colliders
             .insert_with_parent(shape, h, &mut self.bodies);
         self.tag_body(h, ActorKind::Static)
     }
 
-    pub fn add_static_trimesh(
+    pub fn add_static_trimesh<'a>(
         &mut self,
         vertices: &[Vec3],
         indices: &[[u32; 3]],
-        groups: Layers,
-    ) -> BodyId {
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let groups = self.layer_groups(layer.into())?;
         let rb = RigidBodyBuilder::fixed().build();
         let h = self.bodies.insert(rb);
         let v: Vec<Point<Real>> = vertices.iter().map(|p| point![p.x, p.y, p.z]).collect();
         let i: Vec<[u32; 3]> = indices.to_vec();
         let coll = ColliderBuilder::trimesh(v, i)
-            .collision_groups(InteractionGroups::new(
-                Group::from_bits_truncate(groups.bits()),
-                Group::ALL,
-            ))
+            .collision_groups(groups)
             .friction(0.9)
             .build();
         self.colliders.insert_with_parent(coll, h, &mut self.bodies);
-        self.tag_body(h, ActorKind::Static)
+        Ok(self.tag_body(h, ActorKind::Static))
     }
 
-    pub fn add_dynamic_box(&mut self, pos: Vec3, half: Vec3, mass: f32, groups: Layers) -> BodyId {
+    pub fn add_dynamic_box<'a>(
+        &mut self,
+        pos: Vec3,
+        half: Vec3,
+        mass: f32,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let groups = self.layer_groups(layer.into())?;
         let rb = RigidBodyBuilder::dynamic()
             .translation(vector![pos.x, pos.y, pos.z])
             .build();
         let h = self.bodies.insert(rb);
         let coll = ColliderBuilder::cuboid(half.x, half.y, half.z)
             .mass(mass)
-            .collision_groups(InteractionGroups::new(
-                Group::from_bits_truncate(groups.bits()),
-                Group::ALL,
-            ))
+            .collision_groups(groups)
             .friction(0.8)
             .build();
         self.colliders.insert_with_parent(coll, h, &mut self.bodies);
-        self.tag_body(h, ActorKind::Dynamic)
+        Ok(self.tag_body(h, ActorKind::Dynamic))
     }
 
-    pub fn add_character(&mut self, pos: Vec3, half: Vec3) -> BodyId {
+    pub fn add_character<'a>(
+        &mut self,
+        pos: Vec3,
+        half: Vec3,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let groups = self.layer_groups(layer.into())?;
         let rb = RigidBodyBuilder::kinematic_position_based()
             .translation(vector![pos.x, pos.y, pos.z])
             .build();
         let h = self.bodies.insert(rb);
         let coll = ColliderBuilder::capsule_y(half.y, half.x.max(half.z))
-            .collision_groups(InteractionGroups::new(
-                Group::from_bits_truncate(Layers::CHARACTER.bits()),
-                Group::ALL,
-            ))
+            .collision_groups(groups)
             .friction(0.6)
             .build();
         self.colliders.insert_with_parent(coll, h, &mut self.bodies);
         let id = self.tag_body(h, ActorKind::Character);
-        self.char_map.insert(
-            id,
-            CharacterController {
-                state: CharState::Grounded,
-                max_climb_angle_deg: 70.0,
-            },
+        self.char_map.insert(id, CharacterController::default());
+        Ok(id)
+    }
+
+    /// Remove a body with its colliders and joints.
+    pub fn remove_body(&mut self, id: BodyId) -> bool {
+        let Some(h) = self.handle_of(id) else {
+            return false;
+        };
+        for &ch in self.bodies[h].colliders() {
+            self.removed_colliders.insert(ch, id);
+        }
+        self.bodies.remove(
+            h,
+            &mut self.island_mgr,
+            &mut self.colliders,
+            &mut self.joints,
+            &mut self.multibody_joints,
+            true,
         );
-        id
+        self.body_ids.remove(&h);
+        self.handles.remove(&id);
+        self.body_kinds.remove(&h);
+        self.char_map.remove(&id);
+        self.body_drag.remove(&id);
+        self.destructibles.remove(&id);
+        self.triggers.remove(&id);
+        self.projectiles.remove(&id);
+        self.smoke.remove(&id);
+        self.forget_joints_of(id);
+        true
+    }
+
+    pub fn layer_matrix(&self) -> &LayerMatrix {
+        &self.layers
     }
 
-    pub fn control_character(&mut self, id: BodyId, desired_move: Vec3, dt: f32, _climb: bool) {
-        if let Some(_ctrl) = self.char_map.get_mut(&id) {
-            if let Some(h) = self.handle_of(id) {
-                if let Some(rb) = self.bodies.get_mut(h) {
-                    let mut p = *rb.position();
-                    p.translation.x += desired_move.x * dt;
-                    p.translation.y += desired_move.y * dt;
-                    p.translation.z += desired_move.z * dt;
-                    rb.set_position(p, true);
-                }
-            }
+    /// Switch to another layer matrix, regrouping existing colliders by their layers.
+    pub fn set_layer_matrix(&mut self, layers: LayerMatrix) {
+        self.layers = layers;
+        for (_, co) in self.colliders.iter_mut() {
+            let member = Layers::from_bits_retain(co.collision_groups().memberships.bits());
+            co.set_collision_groups(self.layers.groups(member));
         }
     }
 
+    fn layer_groups(&self, layer: LayerRef) -> anyhow::Result<InteractionGroups> {
+        Ok(self.layers.groups(self.layers.resolve(layer)?))
+    }
+
+    /// Move all of a body's colliders onto `layer`. Returns whether the body exists.
+    pub fn set_body_layer<'a>(
+        &mut self,
+        id: BodyId,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<bool> {
+        let groups = self.layer_groups(layer.into())?;
+        let Some(h) = self.handle_of(id) else {
+            return Ok(false);
+        };
+        for &ch in self.bodies[h].colliders() {
+            self.colliders[ch].set_collision_groups(groups);
+        }
+        Ok(true)
+    }
+
+    pub fn body_layer(&self, id: BodyId) -> Option<Layers> {
+        let &ch = self.bodies.get(self.handle_of(id)?)?.colliders().first()?;
+        Some(Layers::from_bits_retain(
+            self.colliders[ch].collision_groups().memberships.bits(),
+        ))
+    }
+
     pub fn handle_of(&self, id: BodyId) -> Option<RigidBodyHandle> {
-        self.body_ids
-            .iter()
-            .find_map(|(h, bid)| if *bid == id { Some(*h) } else { None })
+        self.handles.get(&id).copied()
     }
 
     pub fn id_of(&self, handle: RigidBodyHandle) -> Option<BodyId> {
@@ -213,28 +315,16 @@ impl PhysicsWorld {
         ))
     }
 
+    /// Give a body an id and have its colliders report contacts.
     fn tag_body(&mut self, h: RigidBodyHandle, kind: ActorKind) -> BodyId {
+        for &ch in self.bodies[h].colliders() {
+            let co = &mut self.colliders[ch];
+            co.set_active_events(co.active_events() | ActiveEvents::COLLISION_EVENTS);
+        }
         let id = self.alloc_id();
         self.body_ids.insert(h, id);
+        self.handles.insert(id, h);
         self.body_kinds.insert(h, kind);
         id
     }
-
-    pub fn add_water_aabb(&mut self, _min: Vec3, _max: Vec3, _density: f32, _linear_damp: f32) {}
-    pub fn set_wind(&mut self, _dir: Vec3, _strength: f32) {}
-    pub fn clear_water(&mut self) {}
-    pub fn add_destructible_box(
-        &mut self,
-        pos: Vec3,
-        half: Vec3,
-        mass: f32,
-        _health: f32,
-        _break_impulse: f32,
-    ) -> BodyId {
-        self.add_dynamic_box(pos, half, mass, Layers::DEFAULT)
-    }
-    pub fn break_destructible(&mut self, _id: BodyId) {}
-
-    #[allow(dead_code)]
-    fn process_destructible_hits(&mut self) {}
 }
@@ -0,0 +1,493 @@
+//! Thrown and fired objects: ballistic shots that stop at the first thing they hit, grenades
+//! that bounce until their fuse runs out, and smoke that leaves a cloud blocking line of
+//! sight. [`PhysicsWorld::solve_throw`] finds an arc to a target that clears the scenery.
+
+use crate::{ActorKind, BodyId, LayerRef, Layers, PhysicsEvent, PhysicsWorld};
+use glam::Vec3;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Arcs are checked for obstacles in this many straight pieces.
+const ARC_SEGMENTS: usize = 32;
+/// How close to the target an arc has to come down to count as reaching it.
+const LANDING_SLACK: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProjectileKind {
+    /// Stops at the first thing it hits.
+    Ballistic,
+    /// Bounces around until `fuse` seconds after launch, then goes off.
+    Grenade { fuse: f32, blast_radius: f32 },
+    /// Bounces around until `fuse`, then leaves a cloud of `radius` for `duration` seconds.
+    Smoke {
+        fuse: f32,
+        radius: f32,
+        duration: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Projectile {
+    pub kind: ProjectileKind,
+    /// Seconds since launch.
+    pub age: f32,
+    /// Velocity at the start of the last step, i.e. before any hit during it.
+    pub velocity: Vec3,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectileEvent {
+    /// `projectile` hit `body`. Ballistic projectiles are removed after their first hit.
+    Impact {
+        projectile: BodyId,
+        body: BodyId,
+        position: Vec3,
+        velocity: Vec3,
+    },
+    /// A grenade went off, catching `bodies` in the blast.
+    Exploded {
+        projectile: BodyId,
+        position: Vec3,
+        radius: f32,
+        bodies: Vec<BodyId>,
+    },
+    /// A smoke projectile burst into `cloud`.
+    SmokeReleased {
+        projectile: BodyId,
+        cloud: BodyId,
+        position: Vec3,
+        radius: f32,
+    },
+    SmokeCleared {
+        cloud: BodyId,
+    },
+}
+
+/// Launch velocities of magnitude `speed` that carry a point from `from` to `to` under
+/// `gravity` (m/s², pointing down), the flat arc first. Empty if `to` is out of range.
+pub fn launch_velocities(from: Vec3, to: Vec3, speed: f32, gravity: f32) -> Vec<Vec3> {
+    let d = to - from;
+    let flat = Vec3::new(d.x, 0.0, d.z);
+    let dist = flat.length();
+    if dist < 1e-4 {
+        return if d.y <= 0.0 || speed * speed >= 2.0 * gravity * d.y {
+            vec![Vec3::Y * speed.copysign(d.y)]
+        } else {
+            Vec::new()
+        };
+    }
+    let v2 = speed * speed;
+    let disc = v2 * v2 - gravity * (gravity * dist * dist + 2.0 * d.y * v2);
+    if disc < 0.0 {
+        return Vec::new();
+    }
+    let dir = flat / dist;
+    let mut out = Vec::with_capacity(2);
+    for tan in [
+        (v2 - disc.sqrt()) / (gravity * dist),
+        (v2 + disc.sqrt()) / (gravity * dist),
+    ] {
+        let angle = tan.atan();
+        let v = dir * speed * angle.cos() + Vec3::Y * speed * angle.sin();
+        if !out.contains(&v) {
+            out.push(v);
+        }
+    }
+    out
+}
+
+impl PhysicsWorld {
+    /// Launch a ball of `radius` from `pos` at `velocity`. Projectiles use continuous collision
+    /// detection so fast ones can't tunnel, and no air drag so they follow
+    /// [`launch_velocities`]' arcs; give them drag with [`PhysicsWorld::set_body_drag`] to let
+    /// the wind push them.
+    pub fn spawn_projectile<'a>(
+        &mut self,
+        pos: Vec3,
+        velocity: Vec3,
+        radius: f32,
+        mass: f32,
+        kind: ProjectileKind,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<BodyId> {
+        let groups = self.layer_groups(layer.into())?;
+        let rb = RigidBodyBuilder::dynamic()
+            .translation(vector![pos.x, pos.y, pos.z])
+            .linvel(vector![velocity.x, velocity.y, velocity.z])
+            .ccd_enabled(true)
+            .build();
+        let h = self.bodies.insert(rb);
+        let restitution = match kind {
+            ProjectileKind::Ballistic => 0.1,
+            _ => 0.4,
+        };
+        let coll = ColliderBuilder::ball(radius)
+            .mass(mass)
+            .restitution(restitution)
+            .friction(0.6)
+            .collision_groups(groups)
+            .build();
+        self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+        let id = self.tag_body(h, ActorKind::Dynamic);
+        self.set_body_drag(id, 0.0);
+        self.projectiles.insert(
+            id,
+            Projectile {
+                kind,
+                age: 0.0,
+                velocity,
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn projectile(&self, id: BodyId) -> Option<&Projectile> {
+        self.projectiles.get(&id)
+    }
+
+    /// Smoke clouds still hanging around, with the seconds each has left.
+    pub fn smoke_clouds(&self) -> impl Iterator<Item = (BodyId, f32)> + '_ {
+        self.smoke.iter().map(|(&id, &left)| (id, left))
+    }
+
+    /// Impacts, blasts and smoke since the last call, oldest first; at most
+    /// [`crate::MAX_QUEUED_EVENTS`] are kept.
+    pub fn drain_projectile_events(&mut self) -> Vec<ProjectileEvent> {
+        std::mem::take(&mut self.projectile_events)
+    }
+
+    /// Launch velocity for throwing something of `radius` at `speed` from `from` to land at
+    /// `to`, trying the flat arc before the high one. Arcs that run into anything in `mask`
+    /// (other than `exclude`) before coming down at the target are rejected.
+    pub fn solve_throw(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        speed: f32,
+        radius: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Option<Vec3> {
+        let g = -self.gravity.y;
+        if g <= 0.0 {
+            return None;
+        }
+        launch_velocities(from, to, speed, g)
+            .into_iter()
+            .find(|&v| {
+                // Time to reach the target: across, or on the way down for straight throws.
+                let flat = Vec3::new(v.x, 0.0, v.z).length();
+                let t_end = if flat > 1e-4 {
+                    Vec3::new(to.x - from.x, 0.0, to.z - from.z).length() / flat
+                } else {
+                    (v.y + (v.y * v.y - 2.0 * g * (to.y - from.y)).max(0.0).sqrt()) / g
+                };
+                let at = |t: f32| from + v * t - Vec3::Y * (0.5 * g * t * t);
+                (0..ARC_SEGMENTS).all(|i| {
+                    let t0 = t_end * i as f32 / ARC_SEGMENTS as f32;
+                    let t1 = t_end * (i + 1) as f32 / ARC_SEGMENTS as f32;
+                    let (a, b) = (at(t0), at(t1));
+                    match self.sphere_cast(a, b - a, radius, a.distance(b), mask, exclude) {
+                        Some(hit) => hit.point.distance(to) <= radius + LANDING_SLACK,
+                        None => true,
+                    }
+                })
+            })
+    }
+
+    /// Age projectiles and smoke, and turn this step's contacts into projectile events.
+    /// `first_new` is where this step's entries start in the physics event queue.
+    pub(crate) fn update_projectiles(&mut self, first_new: usize) {
+        let dt = self.integration.dt;
+        let hits: Vec<(BodyId, BodyId)> = self.events[first_new..]
+            .iter()
+            .filter_map(|e| match *e {
+                PhysicsEvent::ContactStarted { a, b } if self.projectiles.contains_key(&a) => {
+                    Some((a, b))
+                }
+                PhysicsEvent::ContactStarted { a, b } if self.projectiles.contains_key(&b) => {
+                    Some((b, a))
+                }
+                _ => None,
+            })
+            .collect();
+        for (id, body) in hits {
+            let Some(p) = self.projectiles.get(&id).copied() else {
+                continue;
+            };
+            let position = self.body_position(id);
+            self.projectile_events.push(ProjectileEvent::Impact {
+                projectile: id,
+                body,
+                position,
+                velocity: p.velocity,
+            });
+            if p.kind == ProjectileKind::Ballistic {
+                self.remove_body(id);
+            }
+        }
+
+        let ids: Vec<BodyId> = self.projectiles.keys().copied().collect();
+        for id in ids {
+            let position = self.body_position(id);
+            let velocity = self
+                .handle_of(id)
+                .and_then(|h| self.bodies.get(h))
+                .map(|rb| Vec3::new(rb.linvel().x, rb.linvel().y, rb.linvel().z));
+            let Some(p) = self.projectiles.get_mut(&id) else {
+                continue;
+            };
+            p.age += dt;
+            p.velocity = velocity.unwrap_or(p.velocity);
+            match p.kind {
+                ProjectileKind::Grenade { fuse, blast_radius } if p.age >= fuse => {
+                    self.remove_body(id);
+                    let bodies = self.overlap_sphere(position, blast_radius, Layers::all(), &[]);
+                    self.projectile_events.push(ProjectileEvent::Exploded {
+                        projectile: id,
+                        position,
+                        radius: blast_radius,
+                        bodies,
+                    });
+                }
+                ProjectileKind::Smoke {
+                    fuse,
+                    radius,
+                    duration,
+                } if p.age >= fuse => {
+                    self.remove_body(id);
+                    let cloud = self.add_smoke_cloud(position, radius, duration);
+                    self.projectile_events.push(ProjectileEvent::SmokeReleased {
+                        projectile: id,
+                        cloud,
+                        position,
+                        radius,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let mut cleared = Vec::new();
+        for (&cloud, left) in self.smoke.iter_mut() {
+            *left -= dt;
+            if *left <= 0.0 {
+                cleared.push(cloud);
+            }
+        }
+        for cloud in cleared {
+            self.remove_body(cloud);
+            self.projectile_events
+                .push(ProjectileEvent::SmokeCleared { cloud });
+        }
+    }
+
+    /// A ball on the smoke layer: queries that include smoke can't see through it, but
+    /// nothing collides with it.
+    fn add_smoke_cloud(&mut self, center: Vec3, radius: f32, duration: f32) -> BodyId {
+        let rb = RigidBodyBuilder::fixed()
+            .translation(vector![center.x, center.y, center.z])
+            .build();
+        let h = self.bodies.insert(rb);
+        let coll = ColliderBuilder::ball(radius)
+            .collision_groups(self.layers.groups(Layers::SMOKE))
+            .build();
+        self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+        let id = self.tag_body(h, ActorKind::Static);
+        self.smoke.insert(id, duration);
+        id
+    }
+
+    fn body_position(&self, id: BodyId) -> Vec3 {
+        self.body_transform(id)
+            .map(|m| m.w_axis.truncate())
+            .unwrap_or(Vec3::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    const G: f32 = 9.81;
+
+    fn world() -> (PhysicsWorld, BodyId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -G, 0.0));
+        let ground = w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        w.step();
+        (w, ground)
+    }
+
+    fn wall(w: &mut PhysicsWorld, x: f32, height: f32) -> BodyId {
+        let id = w
+            .add_static_trimesh(
+                &[
+                    vec3(x, 0.0, -10.0),
+                    vec3(x, 0.0, 10.0),
+                    vec3(x, height, 10.0),
+                    vec3(x, height, -10.0),
+                ],
+                &[[0, 1, 2], [0, 2, 3]],
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        w.step();
+        id
+    }
+
+    #[test]
+    fn launch_velocities_reach_the_target() {
+        let (from, to) = (vec3(0.0, 1.5, 0.0), vec3(6.0, 0.0, 8.0));
+        let arcs = launch_velocities(from, to, 12.0, G);
+        assert_eq!(arcs.len(), 2);
+        assert!(arcs[0].y < arcs[1].y, "flat arc first");
+        for v in arcs {
+            assert!((v.length() - 12.0).abs() < 1e-3);
+            let t = 10.0 / Vec3::new(v.x, 0.0, v.z).length();
+            let end = from + v * t - Vec3::Y * (0.5 * G * t * t);
+            assert!(end.distance(to) < 1e-2, "lands at {end}");
+        }
+        assert!(launch_velocities(from, vec3(30.0, 0.0, 0.0), 12.0, G).is_empty());
+        assert_eq!(
+            launch_velocities(from, vec3(0.0, 4.0, 0.0), 12.0, G),
+            vec![Vec3::Y * 12.0]
+        );
+    }
+
+    #[test]
+    fn thrown_objects_land_on_target_and_stop() {
+        let (mut w, ground) = world();
+        let (from, to) = (vec3(0.0, 1.5, 0.0), vec3(8.0, 0.1, 0.0));
+        let v = w
+            .solve_throw(from, to, 12.0, 0.1, Layers::all(), &[])
+            .unwrap();
+        let rock = w
+            .spawn_projectile(from, v, 0.1, 0.5, ProjectileKind::Ballistic, "projectile")
+            .unwrap();
+        let mut impact = None;
+        for _ in 0..180 {
+            w.step();
+            if let Some(ProjectileEvent::Impact { body, position, .. }) =
+                w.drain_projectile_events().into_iter().next()
+            {
+                impact = Some((body, position));
+                break;
+            }
+        }
+        let (body, position) = impact.expect("hit something");
+        assert_eq!(body, ground);
+        assert!(position.distance(to) < 0.5, "landed at {position}");
+        assert!(w.handle_of(rock).is_none() && w.projectile(rock).is_none());
+    }
+
+    #[test]
+    fn throws_arc_over_walls_they_can_clear() {
+        let (mut w, _) = world();
+        let (from, to) = (vec3(0.0, 1.5, 0.0), vec3(8.0, 0.1, 0.0));
+        let flat = launch_velocities(from, to, 12.0, G)[0];
+        wall(&mut w, 4.0, 2.5);
+        let v = w
+            .solve_throw(from, to, 12.0, 0.1, Layers::all(), &[])
+            .unwrap();
+        assert!(v.y > flat.y, "lobbed over the wall");
+        let lob = w
+            .spawn_projectile(from, v, 0.1, 0.5, ProjectileKind::Ballistic, "projectile")
+            .unwrap();
+        let mut hit_at = None;
+        for _ in 0..240 {
+            w.step();
+            if let Some(ProjectileEvent::Impact { position, .. }) =
+                w.drain_projectile_events().into_iter().next()
+            {
+                hit_at = Some(position);
+                break;
+            }
+        }
+        assert!(hit_at.unwrap().x > 4.0, "cleared the wall");
+        assert!(w.projectile(lob).is_none());
+
+        wall(&mut w, 5.0, 30.0);
+        assert_eq!(w.solve_throw(from, to, 12.0, 0.1, Layers::all(), &[]), None);
+    }
+
+    #[test]
+    fn grenades_bounce_then_explode_and_smoke_blocks_sight() {
+        let (mut w, ground) = world();
+        let crate_ = w
+            .add_dynamic_box(vec3(3.0, 0.5, 0.0), Vec3::splat(0.4), 5.0, "default")
+            .unwrap();
+        let grenade = ProjectileKind::Grenade {
+            fuse: 2.0,
+            blast_radius: 3.0,
+        };
+        let g = w
+            .spawn_projectile(
+                vec3(0.0, 1.0, 0.0),
+                vec3(2.0, 3.0, 0.0),
+                0.1,
+                0.4,
+                grenade,
+                "projectile",
+            )
+            .unwrap();
+        let mut events = Vec::new();
+        for _ in 0..115 {
+            w.step();
+            events.extend(w.drain_projectile_events());
+        }
+        assert!(w.projectile(g).is_some(), "fuse still burning");
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, ProjectileEvent::Impact { body, .. } if *body == ground)));
+        for _ in 0..10 {
+            w.step();
+        }
+        let boom = w.drain_projectile_events();
+        let Some(ProjectileEvent::Exploded { bodies, .. }) = boom.last() else {
+            panic!("no explosion in {boom:?}");
+        };
+        assert!(bodies.contains(&crate_) && bodies.contains(&ground));
+        assert!(w.handle_of(g).is_none());
+
+        let smoke = ProjectileKind::Smoke {
+            fuse: 0.5,
+            radius: 2.0,
+            duration: 1.0,
+        };
+        let (eye, target) = (vec3(-6.0, 1.5, 6.0), vec3(6.0, 1.5, 6.0));
+        assert!(w.segment_clear(eye, target, Layers::all(), &[]));
+        w.spawn_projectile(
+            vec3(0.0, 1.5, 6.0),
+            Vec3::ZERO,
+            0.1,
+            0.4,
+            smoke,
+            "projectile",
+        )
+        .unwrap();
+        let mut cloud = None;
+        for _ in 0..60 {
+            w.step();
+            for e in w.drain_projectile_events() {
+                if let ProjectileEvent::SmokeReleased { cloud: c, .. } = e {
+                    cloud = Some(c);
+                }
+            }
+        }
+        let cloud = cloud.expect("smoke released");
+        assert_eq!(w.smoke_clouds().count(), 1);
+        assert!(!w.segment_clear(eye, target, Layers::all(), &[]));
+        let player_view = w.layer_matrix().query_mask("player".into()).unwrap();
+        assert!(!w.segment_clear(eye, target, player_view, &[]));
+        // Nothing bumps into the cloud, and it drifts away after a while.
+        assert!(!w.layer_matrix().collides(Layers::SMOKE, Layers::PLAYER));
+        for _ in 0..60 {
+            w.step();
+        }
+        assert!(w
+            .drain_projectile_events()
+            .contains(&ProjectileEvent::SmokeCleared { cloud }));
+        assert!(w.segment_clear(eye, target, Layers::all(), &[]));
+    }
+}
@@ -0,0 +1,348 @@
+//! Ray, shape-cast and overlap queries by [`BodyId`], filtered by [`Layers`]. Queries see
+//! the world as of the last [`PhysicsWorld::step`] and never hit trigger volumes.
+
+use crate::{BodyId, Layers, PhysicsWorld};
+use glam::Vec3;
+use rapier3d::parry::query::{ShapeCastOptions, ShapeCastStatus};
+use rapier3d::prelude::*;
+
+/// The first thing a ray or cast ran into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryHit {
+    pub body: BodyId,
+    pub point: Vec3,
+    /// Surface normal at `point`, facing out of `body`.
+    pub normal: Vec3,
+    /// How far the ray or shape travelled before hitting; 0 if it started inside.
+    pub distance: f32,
+}
+
+fn to_vec3(v: &Vector<Real>) -> Vec3 {
+    Vec3::new(v.x, v.y, v.z)
+}
+
+impl PhysicsWorld {
+    /// Run `query` with a filter for solid colliders on layers in `mask` not belonging to
+    /// `exclude`. Layers are matched by membership alone, so queries can hit colliders that
+    /// collide with nothing.
+    fn with_filter<R>(
+        &self,
+        mask: Layers,
+        exclude: &[BodyId],
+        query: impl FnOnce(QueryFilter) -> R,
+    ) -> R {
+        let excluded: Vec<RigidBodyHandle> = exclude
+            .iter()
+            .filter_map(|&id| self.handle_of(id))
+            .collect();
+        let wanted = |_, co: &Collider| {
+            co.collision_groups().memberships.bits() & mask.bits() != 0
+                && co.parent().is_none_or(|h| !excluded.contains(&h))
+        };
+        query(QueryFilter::new().exclude_sensors().predicate(&wanted))
+    }
+
+    fn owner(&self, ch: ColliderHandle) -> Option<BodyId> {
+        self.colliders.get(ch)?.parent().and_then(|h| self.id_of(h))
+    }
+
+    /// First body in `mask` along `dir` from `origin`, up to `max_dist` away. Bodies in
+    /// `exclude`, such as the caster, are ignored.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        dir: Vec3,
+        max_dist: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Option<QueryHit> {
+        let dir = dir.try_normalize()?;
+        let ray = Ray::new(
+            point![origin.x, origin.y, origin.z],
+            vector![dir.x, dir.y, dir.z],
+        );
+        let (ch, hit) = self.with_filter(mask, exclude, |filter| {
+            self.query_pipeline.cast_ray_and_get_normal(
+                &self.bodies,
+                &self.colliders,
+                &ray,
+                max_dist,
+                true,
+                filter,
+            )
+        })?;
+        Some(QueryHit {
+            body: self.owner(ch)?,
+            point: origin + dir * hit.time_of_impact,
+            normal: to_vec3(&hit.normal),
+            distance: hit.time_of_impact,
+        })
+    }
+
+    /// Sweep a ball of `radius` along `dir` from `origin`.
+    pub fn sphere_cast(
+        &self,
+        origin: Vec3,
+        dir: Vec3,
+        radius: f32,
+        max_dist: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Option<QueryHit> {
+        self.shape_cast(&Ball::new(radius), origin, dir, max_dist, mask, exclude)
+    }
+
+    /// Sweep an upright capsule from `from` to `to`, e.g. a weapon swing or a character's
+    /// path. `half_height` is that of the capsule's straight section.
+    pub fn capsule_sweep(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        half_height: f32,
+        radius: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Option<QueryHit> {
+        let shape = Capsule::new_y(half_height, radius);
+        self.shape_cast(&shape, from, to - from, (to - from).length(), mask, exclude)
+    }
+
+    fn shape_cast(
+        &self,
+        shape: &dyn Shape,
+        origin: Vec3,
+        dir: Vec3,
+        max_dist: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Option<QueryHit> {
+        let dir = dir.try_normalize()?;
+        let (ch, hit) = self.with_filter(mask, exclude, |filter| {
+            self.query_pipeline.cast_shape(
+                &self.bodies,
+                &self.colliders,
+                &Isometry::translation(origin.x, origin.y, origin.z),
+                &vector![dir.x, dir.y, dir.z],
+                shape,
+                ShapeCastOptions::with_max_time_of_impact(max_dist),
+                filter,
+            )
+        })?;
+        // Contact geometry is meaningless when the shape starts inside something; report
+        // the hit where the cast began, facing back along it.
+        let (point, normal) = if hit.status == ShapeCastStatus::PenetratingOrWithinTargetDist {
+            (origin, -dir)
+        } else {
+            (
+                Vec3::new(hit.witness1.x, hit.witness1.y, hit.witness1.z),
+                to_vec3(&hit.normal1),
+            )
+        };
+        Some(QueryHit {
+            body: self.owner(ch)?,
+            point,
+            normal,
+            distance: hit.time_of_impact,
+        })
+    }
+
+    /// Bodies in `mask` touching an axis-aligned box, in no particular order.
+    pub fn overlap_box(
+        &self,
+        center: Vec3,
+        half: Vec3,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Vec<BodyId> {
+        self.overlap(
+            &Cuboid::new(vector![half.x, half.y, half.z]),
+            center,
+            mask,
+            exclude,
+        )
+    }
+
+    pub fn overlap_sphere(
+        &self,
+        center: Vec3,
+        radius: f32,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Vec<BodyId> {
+        self.overlap(&Ball::new(radius), center, mask, exclude)
+    }
+
+    fn overlap(
+        &self,
+        shape: &dyn Shape,
+        center: Vec3,
+        mask: Layers,
+        exclude: &[BodyId],
+    ) -> Vec<BodyId> {
+        let mut found = Vec::new();
+        self.with_filter(mask, exclude, |filter| {
+            self.query_pipeline.intersections_with_shape(
+                &self.bodies,
+                &self.colliders,
+                &Isometry::translation(center.x, center.y, center.z),
+                shape,
+                filter,
+                |ch| {
+                    if let Some(id) = self.owner(ch) {
+                        if !found.contains(&id) {
+                            found.push(id);
+                        }
+                    }
+                    true
+                },
+            )
+        });
+        found
+    }
+
+    /// Whether nothing in `mask` other than `exclude` lies between two points.
+    pub fn segment_clear(&self, from: Vec3, to: Vec3, mask: Layers, exclude: &[BodyId]) -> bool {
+        self.raycast(from, to - from, from.distance(to), mask, exclude)
+            .is_none()
+    }
+
+    /// Whether `viewer` can see `target`, looking between their centres past anything not in
+    /// `mask`.
+    pub fn line_of_sight(&self, viewer: BodyId, target: BodyId, mask: Layers) -> bool {
+        let (Some(a), Some(b)) = (self.body_transform(viewer), self.body_transform(target)) else {
+            return false;
+        };
+        self.segment_clear(
+            a.w_axis.truncate(),
+            b.w_axis.truncate(),
+            mask,
+            &[viewer, target],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    /// Ground, a wall at x = 5, a character at the origin and a trigger between them.
+    fn scene() -> (PhysicsWorld, BodyId, BodyId, BodyId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        let ground = w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        let wall = w
+            .add_static_trimesh(
+                &[
+                    vec3(5.0, 0.0, -5.0),
+                    vec3(5.0, 0.0, 5.0),
+                    vec3(5.0, 4.0, 5.0),
+                    vec3(5.0, 4.0, -5.0),
+                ],
+                &[[0, 1, 2], [0, 2, 3]],
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let hero = w
+            .add_character(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.add_trigger_box(vec3(2.0, 1.0, 0.0), Vec3::splat(1.0), Layers::TRIGGER)
+            .unwrap();
+        w.step();
+        (w, ground, wall, hero)
+    }
+
+    #[test]
+    fn rays_report_the_first_body_with_its_surface() {
+        let (w, ground, wall, hero) = scene();
+        let hit = w
+            .raycast(vec3(-3.0, 1.0, 0.0), Vec3::X, 20.0, Layers::all(), &[])
+            .unwrap();
+        assert_eq!(hit.body, hero);
+        assert!((hit.distance - 2.7).abs() < 1e-3);
+        assert!(hit.normal.abs_diff_eq(-Vec3::X, 1e-3));
+
+        // Characters filtered out or excluded: the ray passes the trigger and hits the wall.
+        for (mask, exclude) in [(Layers::DEFAULT, &[][..]), (Layers::all(), &[hero][..])] {
+            let hit = w
+                .raycast(vec3(-3.0, 1.0, 0.0), Vec3::X, 20.0, mask, exclude)
+                .unwrap();
+            assert_eq!(hit.body, wall);
+            assert!(hit.point.abs_diff_eq(vec3(5.0, 1.0, 0.0), 1e-3));
+        }
+        assert!(w
+            .raycast(vec3(-3.0, 1.0, 0.0), Vec3::X, 7.0, Layers::DEFAULT, &[])
+            .is_none());
+
+        let down = w
+            .raycast(vec3(-3.0, 5.0, 0.0), -Vec3::Y, 20.0, Layers::all(), &[])
+            .unwrap();
+        assert_eq!(down.body, ground);
+        assert!((down.point.y - 0.1).abs() < 1e-3 && down.normal.y > 0.99);
+    }
+
+    #[test]
+    fn shape_casts_hit_what_a_ray_would_miss() {
+        let (w, _, wall, hero) = scene();
+        // Passes just over the character's head.
+        let from = vec3(-3.0, 2.0, 0.0);
+        assert!(w
+            .raycast(from, Vec3::X, 20.0, Layers::CHARACTER, &[])
+            .is_none());
+        let hit = w
+            .sphere_cast(from, Vec3::X, 0.3, 20.0, Layers::CHARACTER, &[])
+            .unwrap();
+        assert_eq!(hit.body, hero);
+        assert!(hit.distance > 2.0 && hit.distance < 3.0, "{}", hit.distance);
+        assert!(
+            hit.normal.y > 0.0 && hit.normal.x < 0.0,
+            "top of the capsule"
+        );
+
+        let sweep = w
+            .capsule_sweep(from, vec3(8.0, 2.0, 0.0), 0.5, 0.2, Layers::DEFAULT, &[])
+            .unwrap();
+        assert_eq!(sweep.body, wall);
+        assert!((sweep.distance - 7.8).abs() < 1e-3);
+        assert!((sweep.point.x - 5.0).abs() < 1e-3);
+
+        let inside = w
+            .sphere_cast(vec3(0.0, 1.0, 0.0), Vec3::Z, 0.1, 5.0, Layers::all(), &[])
+            .unwrap();
+        assert_eq!((inside.body, inside.distance), (hero, 0.0));
+    }
+
+    #[test]
+    fn overlaps_and_line_of_sight_respect_layers() {
+        let (mut w, ground, wall, hero) = scene();
+        let mut near = w.overlap_sphere(vec3(0.0, 0.5, 0.0), 1.0, Layers::all(), &[]);
+        near.sort();
+        assert_eq!(near, vec![ground, hero]);
+        assert_eq!(
+            w.overlap_box(vec3(5.0, 2.0, 0.0), vec3(0.5, 0.5, 0.5), Layers::all(), &[]),
+            vec![wall]
+        );
+        assert!(w
+            .overlap_box(vec3(2.0, 1.0, 0.0), vec3(0.5, 0.5, 0.5), Layers::all(), &[])
+            .is_empty());
+
+        let guard = w
+            .add_character(vec3(8.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        let buddy = w
+            .add_character(vec3(0.0, 1.0, 4.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.step();
+        assert!(
+            !w.line_of_sight(hero, guard, Layers::DEFAULT),
+            "behind the wall"
+        );
+        assert!(w.line_of_sight(hero, buddy, Layers::all()));
+        // Another character in the way blocks only when characters are in the mask.
+        w.add_character(vec3(0.0, 1.0, 2.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.step();
+        assert!(!w.line_of_sight(hero, buddy, Layers::all()));
+        assert!(w.line_of_sight(hero, buddy, Layers::DEFAULT));
+        assert!(w.segment_clear(vec3(0.0, 3.0, 0.0), vec3(0.0, 3.0, 4.0), Layers::all(), &[]));
+    }
+}
@@ -0,0 +1,269 @@
+//! Ragdolls: capsule bones joined by limited ball joints and hinges, built from a
+//! [`Skeleton`]. A defeated character's kinematic capsule is swapped for one, keeping its
+//! motion.
+
+use crate::{ActorKind, BodyId, JointId, JointKind, LayerRef, PhysicsWorld};
+use glam::{vec3, Quat, Vec3};
+use rapier3d::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bone {
+    pub name: String,
+    /// Index of the bone this one hangs from; `None` for the root. Parents come first.
+    pub parent: Option<usize>,
+    /// Ends of the bone's capsule, relative to the character's centre as it stands. The
+    /// joint to the parent sits at `head`.
+    pub head: Vec3,
+    pub tail: Vec3,
+    pub radius: f32,
+    pub mass: f32,
+    /// How the bone turns against its parent, with axes as the character stands. Ignored
+    /// for the root.
+    pub joint: JointKind,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+}
+
+impl Skeleton {
+    /// Pelvis, chest, head, arms and legs for a character `height` tall weighing `mass`,
+    /// facing +z.
+    pub fn humanoid(height: f32, mass: f32) -> Self {
+        let s = height / 1.8;
+        let shoulder = JointKind::Spherical {
+            axis: Vec3::Y,
+            swing: 1.3,
+            twist: 0.6,
+        };
+        let elbow = JointKind::Revolute {
+            axis: Vec3::X,
+            limits: Some([-2.5, 0.0]),
+        };
+        let hip = JointKind::Spherical {
+            axis: Vec3::Y,
+            swing: 1.0,
+            twist: 0.3,
+        };
+        let knee = JointKind::Revolute {
+            axis: Vec3::X,
+            limits: Some([0.0, 2.4]),
+        };
+        let root = JointKind::Fixed;
+        let spine = JointKind::Spherical {
+            axis: Vec3::Y,
+            swing: 0.5,
+            twist: 0.4,
+        };
+        let neck = JointKind::Spherical {
+            axis: Vec3::Y,
+            swing: 0.6,
+            twist: 1.2,
+        };
+        // Upright capsule at `x` from `head` down or up to `tail` height, with its share of
+        // the mass.
+        let bone = |name: &str, parent, x, head, tail, radius, share: f32, joint| Bone {
+            name: name.into(),
+            parent,
+            head: vec3(x, head, 0.0) * s,
+            tail: vec3(x, tail, 0.0) * s,
+            radius: radius * s,
+            mass: share * mass,
+            joint,
+        };
+        Self {
+            bones: vec![
+                bone("pelvis", None, 0.0, -0.05, 0.1, 0.12, 0.19, root),
+                bone("chest", Some(0), 0.0, 0.1, 0.45, 0.14, 0.25, spine),
+                bone("head", Some(1), 0.0, 0.55, 0.75, 0.1, 0.08, neck),
+                bone("arm_l", Some(1), -0.25, 0.4, 0.12, 0.05, 0.035, shoulder),
+                bone("forearm_l", Some(3), -0.25, 0.1, -0.18, 0.045, 0.025, elbow),
+                bone("arm_r", Some(1), 0.25, 0.4, 0.12, 0.05, 0.035, shoulder),
+                bone("forearm_r", Some(5), 0.25, 0.1, -0.18, 0.045, 0.025, elbow),
+                bone("thigh_l", Some(0), -0.1, -0.1, -0.46, 0.07, 0.12, hip),
+                bone("shin_l", Some(7), -0.1, -0.5, -0.82, 0.06, 0.06, knee),
+                bone("thigh_r", Some(0), 0.1, -0.1, -0.46, 0.07, 0.12, hip),
+                bone("shin_r", Some(9), 0.1, -0.5, -0.82, 0.06, 0.06, knee),
+            ],
+        }
+    }
+
+    pub fn bone(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|b| b.name == name)
+    }
+}
+
+/// A spawned ragdoll: one body per skeleton bone, in the skeleton's order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ragdoll {
+    pub bones: Vec<BodyId>,
+    pub joints: Vec<JointId>,
+}
+
+impl Ragdoll {
+    pub fn root(&self) -> Option<BodyId> {
+        self.bones.first().copied()
+    }
+}
+
+impl PhysicsWorld {
+    /// Build `skeleton` centred on `position`, turned by `rotation`, with every bone moving
+    /// at `velocity`.
+    pub fn spawn_ragdoll<'a>(
+        &mut self,
+        skeleton: &Skeleton,
+        position: Vec3,
+        rotation: Quat,
+        velocity: Vec3,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<Ragdoll> {
+        let groups = self.layer_groups(layer.into())?;
+        let mut ragdoll = Ragdoll::default();
+        for bone in &skeleton.bones {
+            let (head, tail) = (rotation * bone.head, rotation * bone.tail);
+            let mid = position + (head + tail) * 0.5;
+            let rb = RigidBodyBuilder::dynamic()
+                .translation(vector![mid.x, mid.y, mid.z])
+                .linvel(vector![velocity.x, velocity.y, velocity.z])
+                .build();
+            let h = self.bodies.insert(rb);
+            let (a, b) = ((head - tail) * 0.5, (tail - head) * 0.5);
+            let coll = ColliderBuilder::capsule_from_endpoints(
+                point![a.x, a.y, a.z],
+                point![b.x, b.y, b.z],
+                bone.radius,
+            )
+            .mass(bone.mass)
+            .collision_groups(groups)
+            .friction(0.7)
+            .build();
+            self.colliders.insert_with_parent(coll, h, &mut self.bodies);
+            let id = self.tag_body(h, ActorKind::Dynamic);
+            let parent = bone.parent.and_then(|p| ragdoll.bones.get(p).copied());
+            if let Some(parent) = parent {
+                let joint = bone.joint.rotated(rotation);
+                if let Some(j) = self.add_joint(parent, id, position + head, joint) {
+                    ragdoll.joints.push(j);
+                }
+            }
+            ragdoll.bones.push(id);
+        }
+        Ok(ragdoll)
+    }
+
+    /// Swap a character's kinematic body for a ragdoll of `skeleton` moving as it was, e.g.
+    /// when it is defeated. The character's id stops existing. `None` if `id` is not a
+    /// character.
+    pub fn ragdoll_character<'a>(
+        &mut self,
+        id: BodyId,
+        skeleton: &Skeleton,
+        layer: impl Into<LayerRef<'a>>,
+    ) -> anyhow::Result<Option<Ragdoll>> {
+        let layer = LayerRef::Bits(self.layers.resolve(layer.into())?);
+        let Some(rb) = self
+            .handle_of(id)
+            .filter(|_| self.char_map.contains_key(&id))
+            .and_then(|h| self.bodies.get(h))
+        else {
+            return Ok(None);
+        };
+        let pose = *rb.position();
+        // Kinematic bodies get their velocity from the last move.
+        let v = *rb.linvel();
+        self.remove_body(id);
+        let t = pose.translation;
+        let r = pose.rotation;
+        self.spawn_ragdoll(
+            skeleton,
+            vec3(t.x, t.y, t.z),
+            Quat::from_xyzw(r.i, r.j, r.k, r.w),
+            vec3(v.x, v.y, v.z),
+            layer,
+        )
+        .map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layers;
+
+    fn world() -> PhysicsWorld {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        w
+    }
+
+    fn pos(w: &PhysicsWorld, id: BodyId) -> Vec3 {
+        w.body_transform(id).unwrap().w_axis.truncate()
+    }
+
+    #[test]
+    fn humanoid_ragdoll_collapses_in_one_piece() {
+        let mut w = world();
+        let skeleton = Skeleton::humanoid(1.8, 70.0);
+        assert_eq!(skeleton.bone("head"), Some(2));
+        let total: f32 = skeleton.bones.iter().map(|b| b.mass).sum();
+        assert!((total - 70.0).abs() < 0.5);
+
+        let rag = w
+            .spawn_ragdoll(
+                &skeleton,
+                vec3(0.0, 1.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::ZERO,
+                Layers::DEBRIS,
+            )
+            .unwrap();
+        assert_eq!(rag.bones.len(), skeleton.bones.len());
+        assert_eq!(rag.joints.len(), skeleton.bones.len() - 1);
+        // Give it a nudge so it falls over rather than balancing.
+        let chest = w.handle_of(rag.bones[1]).unwrap();
+        w.bodies[chest].apply_impulse(vector![0.0, 0.0, 20.0], true);
+        for _ in 0..240 {
+            w.step();
+        }
+        let head = pos(&w, rag.bones[2]);
+        assert!(head.y < 0.5, "still standing with its head at {head}");
+        // Limbs stay attached.
+        for (bone, &id) in skeleton.bones.iter().zip(&rag.bones).skip(1) {
+            let parent = rag.bones[bone.parent.unwrap()];
+            let apart = pos(&w, id).distance(pos(&w, parent));
+            assert!(apart < 0.8, "{} came off ({apart})", bone.name);
+        }
+    }
+
+    #[test]
+    fn defeated_character_keeps_its_momentum() {
+        let mut w = world();
+        let hero = w
+            .add_character(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), "enemy")
+            .unwrap();
+        for _ in 0..30 {
+            w.control_character(hero, vec3(4.0, 0.0, 0.0), 1.0 / 60.0, false);
+            w.step();
+        }
+        let at = pos(&w, hero);
+        let rag = w
+            .ragdoll_character(hero, &Skeleton::humanoid(1.8, 70.0), "debris")
+            .unwrap()
+            .unwrap();
+        assert!(w.handle_of(hero).is_none() && !w.char_map.contains_key(&hero));
+        assert!(w
+            .ragdoll_character(rag.root().unwrap(), &Skeleton::default(), "debris")
+            .unwrap()
+            .is_none());
+        let root = w.handle_of(rag.root().unwrap()).unwrap();
+        assert!((w.bodies[root].linvel().x - 4.0).abs() < 0.5);
+        assert_eq!(w.body_layer(rag.bones[0]), Some(Layers::DEBRIS));
+
+        for _ in 0..120 {
+            w.step();
+        }
+        let p = pos(&w, rag.root().unwrap());
+        assert!(p.x > at.x + 0.5, "stopped dead at {p}");
+    }
+}
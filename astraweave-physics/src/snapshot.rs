@@ -0,0 +1,284 @@
+//! Saving and rolling back the whole simulation, for save games and network
+//! reconciliation, and a hash of the bodies' motion for spotting divergence between peers.
+
+use crate::{
+    joints::Joint, ActorKind, BodyId, CharacterController, Destructible, ForceField, ForceFieldId,
+    JointId, LayerMatrix, PhysicsWorld, Projectile, WaterId, WaterVolume,
+};
+use anyhow::{bail, Context};
+use glam::Vec3;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Bump when [`State`] changes shape; older snapshots are refused.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// Everything a step depends on. Events not yet drained are left out: they belong to the
+/// timeline being replaced.
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u16,
+    gravity: Vector<Real>,
+    integration: IntegrationParameters,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    island_mgr: IslandManager,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    query_pipeline: QueryPipeline,
+    ccd: CCDSolver,
+    body_ids: HashMap<RigidBodyHandle, BodyId>,
+    body_kinds: HashMap<RigidBodyHandle, ActorKind>,
+    next_body_id: BodyId,
+    layers: LayerMatrix,
+    char_map: HashMap<BodyId, CharacterController>,
+    water: BTreeMap<WaterId, WaterVolume>,
+    next_water_id: WaterId,
+    wind: Vec3,
+    force_fields: BTreeMap<ForceFieldId, ForceField>,
+    next_field_id: ForceFieldId,
+    body_drag: HashMap<BodyId, f32>,
+    destructibles: HashMap<BodyId, Destructible>,
+    projectiles: BTreeMap<BodyId, Projectile>,
+    smoke: BTreeMap<BodyId, f32>,
+    triggers: HashSet<BodyId>,
+    joint_map: BTreeMap<JointId, Joint>,
+    next_joint_id: JointId,
+}
+
+impl PhysicsWorld {
+    /// Encode the simulation, e.g. for `WorldState::ecs_blob` in a save or a rollback
+    /// buffer. [`PhysicsWorld::restore`] brings it back exactly.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let state = State {
+            version: SNAPSHOT_VERSION,
+            gravity: self.gravity,
+            integration: self.integration,
+            bodies: self.bodies.clone(),
+            colliders: self.colliders.clone(),
+            joints: self.joints.clone(),
+            multibody_joints: self.multibody_joints.clone(),
+            island_mgr: self.island_mgr.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            query_pipeline: self.query_pipeline.clone(),
+            ccd: self.ccd.clone(),
+            body_ids: self.body_ids.clone(),
+            body_kinds: self.body_kinds.clone(),
+            next_body_id: self.next_body_id,
+            layers: self.layers.clone(),
+            char_map: self.char_map.clone(),
+            water: self.water.clone(),
+            next_water_id: self.next_water_id,
+            wind: self.wind,
+            force_fields: self.force_fields.clone(),
+            next_field_id: self.next_field_id,
+            body_drag: self.body_drag.clone(),
+            destructibles: self.destructibles.clone(),
+            projectiles: self.projectiles.clone(),
+            smoke: self.smoke.clone(),
+            triggers: self.triggers.clone(),
+            joint_map: self.joint_map.clone(),
+            next_joint_id: self.next_joint_id,
+        };
+        postcard::to_allocvec(&state).expect("physics state always encodes")
+    }
+
+    /// Replace the simulation with one from [`PhysicsWorld::snapshot`]. Undrained events
+    /// are dropped. On error the world is left as it was.
+    pub fn restore(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        let state: State = postcard::from_bytes(bytes).context("decoding physics snapshot")?;
+        if state.version != SNAPSHOT_VERSION {
+            bail!(
+                "physics snapshot version {} is not {SNAPSHOT_VERSION}",
+                state.version
+            );
+        }
+        self.gravity = state.gravity;
+        self.integration = state.integration;
+        self.bodies = state.bodies;
+        self.colliders = state.colliders;
+        self.joints = state.joints;
+        self.multibody_joints = state.multibody_joints;
+        self.island_mgr = state.island_mgr;
+        self.broad_phase = state.broad_phase;
+        self.narrow_phase = state.narrow_phase;
+        self.query_pipeline = state.query_pipeline;
+        self.ccd = state.ccd;
+        self.handles = state.body_ids.iter().map(|(&h, &id)| (id, h)).collect();
+        self.body_ids = state.body_ids;
+        self.body_kinds = state.body_kinds;
+        self.next_body_id = state.next_body_id;
+        self.layers = state.layers;
+        self.char_map = state.char_map;
+        self.water = state.water;
+        self.next_water_id = state.next_water_id;
+        self.wind = state.wind;
+        self.force_fields = state.force_fields;
+        self.next_field_id = state.next_field_id;
+        self.body_drag = state.body_drag;
+        self.destructibles = state.destructibles;
+        self.projectiles = state.projectiles;
+        self.smoke = state.smoke;
+        self.triggers = state.triggers;
+        self.joint_map = state.joint_map;
+        self.next_joint_id = state.next_joint_id;
+
+        // Drop whatever the replaced timeline left queued.
+        self.channels = Default::default();
+        self.events.clear();
+        self.break_events.clear();
+        self.projectile_events.clear();
+        self.removed_colliders.clear();
+        Ok(())
+    }
+
+    /// Hash of every body's pose and velocity, by id. Worlds that hash alike are in step;
+    /// it is stable across builds and platforms, so peers can compare theirs.
+    pub fn state_hash(&self) -> u64 {
+        let mut ids: Vec<(BodyId, RigidBodyHandle)> =
+            self.body_ids.iter().map(|(&h, &id)| (id, h)).collect();
+        ids.sort_unstable_by_key(|&(id, _)| id);
+        // FNV-1a: stable across builds and platforms, unlike the std hasher.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for &b in bytes {
+                hash = (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        for (id, h) in ids {
+            let Some(rb) = self.bodies.get(h) else {
+                continue;
+            };
+            let (pos, rot) = (rb.translation(), rb.rotation());
+            let (lin, ang) = (rb.linvel(), rb.angvel());
+            feed(&id.to_le_bytes());
+            for v in [
+                pos.x, pos.y, pos.z, rot.i, rot.j, rot.k, rot.w, lin.x, lin.y, lin.z, ang.x, ang.y,
+                ang.z,
+            ] {
+                feed(&v.to_bits().to_le_bytes());
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JointKind, Layers, ProjectileKind};
+    use glam::vec3;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// A pile of boxes, a walking character, a thrown grenade and a hinged door.
+    fn scene() -> (PhysicsWorld, BodyId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        let ground = w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        for i in 0..6 {
+            let at = vec3(0.1 * i as f32, 0.5 + 1.05 * i as f32, 0.0);
+            w.add_dynamic_box(at, Vec3::splat(0.5), 2.0, Layers::DEFAULT)
+                .unwrap();
+        }
+        w.add_destructible_box(
+            vec3(3.0, 4.0, 0.0),
+            Vec3::splat(0.4),
+            3.0,
+            40.0,
+            10.0,
+            "default",
+        )
+        .unwrap();
+        let hero = w
+            .add_character(vec3(-3.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), "player")
+            .unwrap();
+        w.spawn_projectile(
+            vec3(-3.0, 2.0, 0.0),
+            vec3(4.0, 5.0, 0.0),
+            0.1,
+            0.5,
+            ProjectileKind::Grenade {
+                fuse: 1.5,
+                blast_radius: 3.0,
+            },
+            "projectile",
+        )
+        .unwrap();
+        let door = w
+            .add_dynamic_box(vec3(0.5, 1.2, 4.0), vec3(0.5, 1.0, 0.05), 20.0, "default")
+            .unwrap();
+        w.add_joint(
+            ground,
+            door,
+            vec3(0.0, 1.2, 4.0),
+            JointKind::Revolute {
+                axis: Vec3::Y,
+                limits: None,
+            },
+        );
+        w.set_wind(vec3(1.0, 0.0, 0.0), 3.0);
+        (w, hero)
+    }
+
+    /// Hashes after each of `frames` steps with the character walking.
+    fn play(w: &mut PhysicsWorld, hero: BodyId, frames: usize) -> Vec<u64> {
+        (0..frames)
+            .map(|_| {
+                w.control_character(hero, vec3(2.0, 0.0, 0.5), DT, false);
+                w.step();
+                w.state_hash()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restored_worlds_step_bit_identically() {
+        let (mut w, hero) = scene();
+        play(&mut w, hero, 30);
+        let saved = w.snapshot();
+        let before = w.state_hash();
+        let expected = play(&mut w, hero, 120);
+        let end = w.body_transform(hero).unwrap();
+
+        // Rolling back the same world...
+        w.restore(&saved).unwrap();
+        assert_eq!(w.state_hash(), before);
+        assert_eq!(play(&mut w, hero, 120), expected);
+        assert_eq!(w.body_transform(hero).unwrap(), end);
+
+        // ...and loading into a fresh one both replay exactly.
+        let mut loaded = PhysicsWorld::new(Vec3::ZERO);
+        loaded.restore(&saved).unwrap();
+        assert_eq!(loaded.handle_of(hero), w.handle_of(hero));
+        assert_eq!(play(&mut loaded, hero, 120), expected);
+    }
+
+    #[test]
+    fn hash_tracks_divergence_and_bad_snapshots_are_refused() {
+        let (mut a, hero) = scene();
+        let (mut b, _) = scene();
+        assert_eq!(a.state_hash(), b.state_hash());
+        play(&mut a, hero, 10);
+        play(&mut b, hero, 10);
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        let crate_ = b
+            .body_ids
+            .values()
+            .copied()
+            .find(|&id| b.destructible(id).is_some());
+        b.remove_body(crate_.unwrap());
+        assert_ne!(a.state_hash(), b.state_hash());
+
+        let before = a.state_hash();
+        assert!(a.restore(&[1, 2, 3]).is_err());
+        let mut stale = b.snapshot();
+        stale[0] = stale[0].wrapping_add(1);
+        assert!(a.restore(&stale).is_err());
+        assert_eq!(a.state_hash(), before, "left alone");
+    }
+}
@@ -0,0 +1,324 @@
+//! Water volumes: buoyancy and drag for dynamic bodies, swimming for characters, and depth
+//! queries. Levels can change at runtime, so weaving can flood or drain an area.
+
+use crate::PhysicsWorld;
+use glam::Vec3;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type WaterId = u64;
+
+/// A box of water. Water fills the box from `min.y` up to `surface`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WaterVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Height of the water surface, between `min.y` and `max.y`.
+    pub surface: f32,
+    /// kg/m³; 1000 for fresh water.
+    pub density: f32,
+    /// Drag on fully submerged bodies, as a rate per second.
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+}
+
+impl WaterVolume {
+    /// A full box of water, with enough drag that floating bodies stop bobbing within a
+    /// few seconds.
+    pub fn new(min: Vec3, max: Vec3, density: f32) -> Self {
+        Self {
+            min,
+            max,
+            surface: max.y,
+            density,
+            linear_damping: 2.0,
+            angular_damping: 2.0,
+        }
+    }
+
+    /// How far below the surface `p` is, if it is in the water.
+    pub fn depth_at(&self, p: Vec3) -> Option<f32> {
+        let inside = p.x >= self.min.x
+            && p.x <= self.max.x
+            && p.z >= self.min.z
+            && p.z <= self.max.z
+            && p.y >= self.min.y
+            && p.y < self.surface;
+        inside.then_some(self.surface - p.y)
+    }
+
+    fn covers(&self, p: Vec3) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x && p.z >= self.min.z && p.z <= self.max.z
+    }
+
+    /// Part of `aabb` under water, if any.
+    fn submerged(&self, aabb: &Aabb) -> Option<Aabb> {
+        let min = aabb
+            .mins
+            .coords
+            .sup(&vector![self.min.x, self.min.y, self.min.z]);
+        let max = aabb
+            .maxs
+            .coords
+            .inf(&vector![self.max.x, self.surface, self.max.z]);
+        (min.x < max.x && min.y < max.y && min.z < max.z).then(|| Aabb::new(min.into(), max.into()))
+    }
+}
+
+impl PhysicsWorld {
+    pub fn add_water(&mut self, volume: WaterVolume) -> WaterId {
+        let id = self.next_water_id;
+        self.next_water_id += 1;
+        self.water.insert(id, volume);
+        id
+    }
+
+    /// Fresh or salt water filling `min..max`, with the same drag on movement and spin.
+    pub fn add_water_aabb(
+        &mut self,
+        min: Vec3,
+        max: Vec3,
+        density: f32,
+        linear_damp: f32,
+    ) -> WaterId {
+        self.add_water(WaterVolume {
+            linear_damping: linear_damp,
+            angular_damping: linear_damp,
+            ..WaterVolume::new(min, max, density)
+        })
+    }
+
+    pub fn water(&self, id: WaterId) -> Option<&WaterVolume> {
+        self.water.get(&id)
+    }
+
+    pub fn water_mut(&mut self, id: WaterId) -> Option<&mut WaterVolume> {
+        self.water.get_mut(&id)
+    }
+
+    pub fn water_volumes(&self) -> impl Iterator<Item = (WaterId, &WaterVolume)> {
+        self.water.iter().map(|(&id, v)| (id, v))
+    }
+
+    /// Move the surface of `id` to `surface`, kept within its box.
+    pub fn set_water_level(&mut self, id: WaterId, surface: f32) -> bool {
+        let Some(v) = self.water.get_mut(&id) else {
+            return false;
+        };
+        v.surface = surface.clamp(v.min.y, v.max.y);
+        true
+    }
+
+    /// Raise (or, with a negative `dy`, lower) every volume over `p`'s footprint. Returns
+    /// how many volumes changed.
+    pub fn change_water_level_at(&mut self, p: Vec3, dy: f32) -> usize {
+        let mut changed = 0;
+        for v in self.water.values_mut().filter(|v| v.covers(p)) {
+            v.surface = (v.surface + dy).clamp(v.min.y, v.max.y);
+            changed += 1;
+        }
+        changed
+    }
+
+    pub fn remove_water(&mut self, id: WaterId) -> bool {
+        self.water.remove(&id).is_some()
+    }
+
+    pub fn clear_water(&mut self) {
+        self.water.clear();
+    }
+
+    /// How far below the water surface `p` is, or `None` if it is not in water.
+    pub fn water_depth(&self, p: Vec3) -> Option<f32> {
+        self.water
+            .values()
+            .filter_map(|v| v.depth_at(p))
+            .reduce(f32::max)
+    }
+
+    pub fn in_water(&self, p: Vec3) -> bool {
+        self.water_depth(p).is_some()
+    }
+
+    /// Push dynamic bodies up by the weight of the water they displace, at the centre of
+    /// their submerged part so they right themselves, and drag them in proportion to how
+    /// deep they are. Submerged volume is estimated from each collider's bounding box.
+    pub(crate) fn apply_water(&mut self) {
+        if self.water.is_empty() {
+            return;
+        }
+        let dt = self.integration.dt;
+        for (_, rb) in self.bodies.iter_mut() {
+            if !rb.is_dynamic() {
+                continue;
+            }
+            let mut wet = 0.0f32;
+            let mut damping = (0.0f32, 0.0f32);
+            for i in 0..rb.colliders().len() {
+                let co = &self.colliders[rb.colliders()[i]];
+                let aabb = co.compute_aabb();
+                let box_volume = aabb.volume();
+                if box_volume <= 0.0 {
+                    continue;
+                }
+                let volume = co.shape().mass_properties(1.0).mass();
+                for water in self.water.values() {
+                    let Some(under) = water.submerged(&aabb) else {
+                        continue;
+                    };
+                    let fraction = under.volume() / box_volume;
+                    let lift = -self.gravity * water.density * volume * fraction;
+                    rb.apply_impulse_at_point(lift * dt, under.center(), true);
+                    if fraction > wet {
+                        wet = fraction;
+                        damping = (water.linear_damping, water.angular_damping);
+                    }
+                }
+            }
+            if wet > 0.0 {
+                let linvel = *rb.linvel() / (1.0 + dt * damping.0 * wet);
+                let angvel = *rb.angvel() / (1.0 + dt * damping.1 * wet);
+                rb.set_linvel(linvel, true);
+                rb.set_angvel(angvel, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharState, Layers};
+    use glam::vec3;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn pool(surface: f32) -> (PhysicsWorld, WaterId) {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        let id = w.add_water(WaterVolume {
+            surface,
+            ..WaterVolume::new(vec3(-5.0, 0.1, -5.0), vec3(5.0, 4.0, 5.0), 1000.0)
+        });
+        (w, id)
+    }
+
+    fn y(w: &PhysicsWorld, id: u64) -> f32 {
+        w.body_transform(id).unwrap().w_axis.y
+    }
+
+    fn settle(w: &mut PhysicsWorld, frames: usize) {
+        for _ in 0..frames {
+            w.step();
+        }
+    }
+
+    #[test]
+    fn light_bodies_float_and_heavy_ones_sink() {
+        let (mut w, _) = pool(3.0);
+        // One cubic metre each: half as dense as water, and twice as dense.
+        let light = w
+            .add_dynamic_box(
+                vec3(-2.0, 5.0, 0.0),
+                Vec3::splat(0.5),
+                500.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let heavy = w
+            .add_dynamic_box(
+                vec3(2.0, 5.0, 0.0),
+                Vec3::splat(0.5),
+                2000.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        settle(&mut w, 600);
+        assert!(
+            (y(&w, light) - 3.0).abs() < 0.1,
+            "floats half under at {}",
+            y(&w, light)
+        );
+        assert!(
+            (y(&w, heavy) - 0.6).abs() < 0.05,
+            "rests on the bottom at {}",
+            y(&w, heavy)
+        );
+        let h = w.handle_of(light).unwrap();
+        assert!(w.bodies[h].linvel().norm() < 0.1);
+    }
+
+    #[test]
+    fn floating_bodies_follow_the_water_level() {
+        let (mut w, water) = pool(3.0);
+        let crate_ = w
+            .add_dynamic_box(
+                vec3(0.0, 3.0, 0.0),
+                Vec3::splat(0.5),
+                500.0,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        settle(&mut w, 300);
+
+        assert_eq!(w.change_water_level_at(vec3(0.0, 0.0, 0.0), -1.5), 1);
+        assert_eq!(w.change_water_level_at(vec3(20.0, 0.0, 0.0), -1.5), 0);
+        assert_eq!(w.water(water).unwrap().surface, 1.5);
+        settle(&mut w, 300);
+        assert!((y(&w, crate_) - 1.5).abs() < 0.1, "at {}", y(&w, crate_));
+
+        // Drained: the crate sits on the floor.
+        assert!(w.set_water_level(water, -10.0));
+        assert_eq!(w.water(water).unwrap().surface, 0.1);
+        settle(&mut w, 300);
+        assert!((y(&w, crate_) - 0.6).abs() < 0.05, "at {}", y(&w, crate_));
+    }
+
+    #[test]
+    fn depth_queries_report_the_deepest_volume() {
+        let (mut w, water) = pool(3.0);
+        assert_eq!(w.water_depth(vec3(0.0, 1.0, 0.0)), Some(2.0));
+        assert!(w.in_water(vec3(4.9, 0.2, -4.9)));
+        assert!(!w.in_water(vec3(0.0, 3.5, 0.0)), "above the surface");
+        assert!(!w.in_water(vec3(6.0, 1.0, 0.0)), "outside the pool");
+
+        w.add_water_aabb(vec3(-1.0, 0.0, -1.0), vec3(1.0, 5.0, 1.0), 1000.0, 0.5);
+        assert_eq!(w.water_depth(vec3(0.0, 1.0, 0.0)), Some(4.0));
+        assert!(w.remove_water(water));
+        assert!(!w.in_water(vec3(3.0, 1.0, 0.0)));
+        w.clear_water();
+        assert!(!w.in_water(vec3(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn characters_swim_in_deep_water_and_wade_in_shallows() {
+        let (mut w, water) = pool(3.0);
+        let id = w
+            .add_character(vec3(0.0, 5.0, 0.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.step();
+        for _ in 0..300 {
+            w.control_character(id, Vec3::ZERO, DT, false);
+            w.step();
+        }
+        let ctrl = w.char_map[&id];
+        assert_eq!(ctrl.state, CharState::Swimming);
+        // Treads water with its feet `swim_depth` under the surface.
+        let feet = y(&w, id) - 0.9;
+        assert!((3.0 - feet - ctrl.swim_depth).abs() < 0.1, "feet at {feet}");
+
+        for _ in 0..60 {
+            w.control_character(id, vec3(0.0, -1.0, 0.0), DT, false);
+            w.step();
+        }
+        assert!(y(&w, id) - 0.9 < feet - 0.5, "dives");
+
+        w.set_water_level(water, 0.6);
+        for _ in 0..120 {
+            w.control_character(id, Vec3::ZERO, DT, false);
+            w.step();
+        }
+        assert_eq!(w.char_map[&id].state, CharState::Grounded);
+        assert!((y(&w, id) - 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,362 @@
+//! Global wind and local force fields. Both describe how the air moves; bodies are dragged
+//! towards that air speed every step, and anything else (particles, audio, AI) can sample
+//! it with [`PhysicsWorld::wind_at`].
+
+use crate::{BodyId, PhysicsWorld};
+use glam::Vec3;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type ForceFieldId = u64;
+
+/// kg/m³ at sea level.
+const AIR_DENSITY: f32 = 1.225;
+/// Drag coefficient of bodies without [`PhysicsWorld::set_body_drag`]; about a cube's.
+pub const DEFAULT_DRAG: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ForceFieldKind {
+    /// Air blowing along `dir`.
+    Directional { dir: Vec3 },
+    /// Air swirling counter-clockwise around the field's axis (seen from above), drawn
+    /// towards it by `inflow` times the swirl speed.
+    Vortex { inflow: f32 },
+    /// Air rising up the column.
+    Updraft,
+}
+
+/// How a field weakens from its axis to its edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Falloff {
+    /// Full strength right up to the edge.
+    Constant,
+    #[default]
+    Linear,
+    /// Eases out towards the edge, for fields without a visible boundary.
+    Smooth,
+}
+
+/// Moving air in an upright cylinder standing on `base`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForceField {
+    pub kind: ForceFieldKind,
+    pub base: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    /// Air speed on the axis, m/s.
+    pub strength: f32,
+    pub falloff: Falloff,
+}
+
+impl ForceField {
+    pub fn directional(base: Vec3, radius: f32, height: f32, dir: Vec3, strength: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Directional { dir },
+            base,
+            radius,
+            height,
+            strength,
+            falloff: Falloff::default(),
+        }
+    }
+
+    pub fn vortex(base: Vec3, radius: f32, height: f32, strength: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Vortex { inflow: 0.25 },
+            ..Self::directional(base, radius, height, Vec3::ZERO, strength)
+        }
+    }
+
+    pub fn updraft(base: Vec3, radius: f32, height: f32, strength: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Updraft,
+            ..Self::directional(base, radius, height, Vec3::ZERO, strength)
+        }
+    }
+
+    /// Air velocity this field adds at `p`.
+    pub fn velocity_at(&self, p: Vec3) -> Vec3 {
+        let offset = (p - self.base).with_y(0.0);
+        let dist = offset.length();
+        if dist > self.radius || p.y < self.base.y || p.y > self.base.y + self.height {
+            return Vec3::ZERO;
+        }
+        let t = dist / self.radius.max(f32::EPSILON);
+        let speed = self.strength
+            * match self.falloff {
+                Falloff::Constant => 1.0,
+                Falloff::Linear => 1.0 - t,
+                Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+            };
+        match self.kind {
+            ForceFieldKind::Directional { dir } => dir.normalize_or_zero() * speed,
+            ForceFieldKind::Vortex { inflow } => {
+                let Some(out) = offset.try_normalize() else {
+                    return Vec3::ZERO;
+                };
+                (Vec3::Y.cross(out) - out * inflow) * speed
+            }
+            ForceFieldKind::Updraft => Vec3::Y * speed,
+        }
+    }
+}
+
+impl PhysicsWorld {
+    /// Blow air along `dir` at `strength` m/s everywhere; zero strength calms it.
+    pub fn set_wind(&mut self, dir: Vec3, strength: f32) {
+        self.wind = dir.normalize_or_zero() * strength;
+    }
+
+    /// The global wind velocity, without force fields.
+    pub fn wind(&self) -> Vec3 {
+        self.wind
+    }
+
+    pub fn add_force_field(&mut self, field: ForceField) -> ForceFieldId {
+        let id = self.next_field_id;
+        self.next_field_id += 1;
+        self.force_fields.insert(id, field);
+        id
+    }
+
+    pub fn force_field_mut(&mut self, id: ForceFieldId) -> Option<&mut ForceField> {
+        self.force_fields.get_mut(&id)
+    }
+
+    pub fn force_fields(&self) -> impl Iterator<Item = (ForceFieldId, &ForceField)> {
+        self.force_fields.iter().map(|(&id, f)| (id, f))
+    }
+
+    pub fn remove_force_field(&mut self, id: ForceFieldId) -> bool {
+        self.force_fields.remove(&id).is_some()
+    }
+
+    pub fn clear_force_fields(&mut self) {
+        self.force_fields.clear();
+    }
+
+    /// Drag coefficient of a body; zero makes it ignore wind. Defaults to [`DEFAULT_DRAG`].
+    pub fn set_body_drag(&mut self, id: BodyId, drag: f32) {
+        self.body_drag.insert(id, drag.max(0.0));
+    }
+
+    /// Air velocity at `p`: the global wind plus every force field around it.
+    pub fn wind_at(&self, p: Vec3) -> Vec3 {
+        self.force_fields
+            .values()
+            .fold(self.wind, |v, f| v + f.velocity_at(p))
+    }
+
+    /// Quadratic air drag on dynamic bodies, towards the air's velocity at their centre of
+    /// mass. Cross-section is estimated from each body's bounding box. Bodies in still air
+    /// are left alone, so a local field only affects what is inside it.
+    pub(crate) fn apply_wind(&mut self) {
+        if self.wind == Vec3::ZERO && self.force_fields.is_empty() {
+            return;
+        }
+        let dt = self.integration.dt;
+        let handles: Vec<RigidBodyHandle> = self
+            .bodies
+            .iter()
+            .filter(|(_, rb)| rb.is_dynamic())
+            .map(|(h, _)| h)
+            .collect();
+        for h in handles {
+            let drag = self
+                .id_of(h)
+                .and_then(|id| self.body_drag.get(&id).copied())
+                .unwrap_or(DEFAULT_DRAG);
+            if drag <= 0.0 {
+                continue;
+            }
+            let rb = &self.bodies[h];
+            let centre = *rb.center_of_mass();
+            let air = self.wind_at(Vec3::new(centre.x, centre.y, centre.z));
+            if air == Vec3::ZERO {
+                continue;
+            }
+            let rel = vector![air.x, air.y, air.z] - rb.linvel();
+            let speed = rel.norm();
+            if speed < 1e-3 {
+                continue;
+            }
+            let Some(extent) = rb
+                .colliders()
+                .iter()
+                .map(|&c| self.colliders[c].compute_aabb())
+                .reduce(|a, b| a.merged(&b))
+                .map(|aabb| aabb.extents())
+            else {
+                continue;
+            };
+            // Area of the bounding box seen from the direction the air comes from.
+            let dir = rel / speed;
+            let area = dir.x.abs() * extent.y * extent.z
+                + dir.y.abs() * extent.x * extent.z
+                + dir.z.abs() * extent.x * extent.y;
+            let force = rel * (0.5 * AIR_DENSITY * drag * area * speed);
+            // Never push a body past the air's own speed in one step.
+            let impulse = force * dt;
+            let most = rel * self.bodies[h].mass();
+            let impulse = if impulse.norm() > most.norm() {
+                most
+            } else {
+                impulse
+            };
+            self.bodies[h].apply_impulse(impulse, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharState, Layers};
+    use glam::vec3;
+
+    fn world() -> PhysicsWorld {
+        let mut w = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+        w.create_ground_plane(vec3(50.0, 0.0, 50.0), 0.9);
+        w
+    }
+
+    fn pos(w: &PhysicsWorld, id: BodyId) -> Vec3 {
+        w.body_transform(id).unwrap().w_axis.truncate()
+    }
+
+    #[test]
+    fn fields_shape_the_sampled_wind() {
+        let mut w = world();
+        w.set_wind(vec3(2.0, 0.0, 0.0), 3.0);
+        assert_eq!(w.wind_at(vec3(40.0, 5.0, 0.0)), vec3(3.0, 0.0, 0.0));
+
+        let up = w.add_force_field(ForceField::updraft(Vec3::ZERO, 2.0, 10.0, 8.0));
+        assert_eq!(w.wind_at(vec3(0.0, 1.0, 0.0)), vec3(3.0, 8.0, 0.0));
+        assert_eq!(
+            w.wind_at(vec3(1.0, 1.0, 0.0)),
+            vec3(3.0, 4.0, 0.0),
+            "linear falloff"
+        );
+        assert_eq!(
+            w.wind_at(vec3(0.0, 11.0, 0.0)),
+            vec3(3.0, 0.0, 0.0),
+            "above the column"
+        );
+        w.force_field_mut(up).unwrap().falloff = Falloff::Constant;
+        assert_eq!(w.wind_at(vec3(1.9, 1.0, 0.0)).y, 8.0);
+        assert!(w.remove_force_field(up));
+
+        w.set_wind(Vec3::ZERO, 0.0);
+        let mut vortex = ForceField::vortex(vec3(10.0, 0.0, 0.0), 4.0, 5.0, 6.0);
+        vortex.falloff = Falloff::Smooth;
+        w.add_force_field(vortex);
+        let v = w.wind_at(vec3(12.0, 1.0, 0.0));
+        assert!(v.z < 0.0, "swirls counter-clockwise: {v}");
+        assert!(v.x < 0.0, "draws air inwards: {v}");
+        assert!(v.length() < 6.0);
+        assert_eq!(w.wind_at(vec3(10.0, 1.0, 0.0)), Vec3::ZERO, "calm eye");
+    }
+
+    #[test]
+    fn wind_drags_bodies_by_their_drag_coefficient() {
+        let mut w = world();
+        let ball = |w: &mut PhysicsWorld, z: f32| {
+            w.add_dynamic_box(vec3(0.0, 5.0, z), Vec3::splat(0.25), 1.0, Layers::DEFAULT)
+                .unwrap()
+        };
+        let (plain, sail, still) = (ball(&mut w, 0.0), ball(&mut w, 3.0), ball(&mut w, 6.0));
+        w.set_body_drag(sail, 4.0);
+        w.set_body_drag(still, 0.0);
+        w.set_wind(Vec3::X, 10.0);
+        for _ in 0..30 {
+            w.step();
+        }
+        let (a, b, c) = (pos(&w, plain).x, pos(&w, sail).x, pos(&w, still).x);
+        assert!(a > 0.5, "blown downwind: {a}");
+        assert!(b > a, "more drag, more push: {b} vs {a}");
+        assert_eq!(c, 0.0);
+        let h = w.handle_of(sail).unwrap();
+        assert!(
+            w.bodies[h].linvel().x <= 10.0 + 1e-3,
+            "never outruns the wind"
+        );
+    }
+
+    #[test]
+    fn updrafts_hold_light_bodies_up() {
+        let mut w = world();
+        w.add_force_field(ForceField::updraft(Vec3::ZERO, 3.0, 6.0, 30.0));
+        let leaf = w
+            .add_dynamic_box(
+                vec3(0.0, 1.0, 0.0),
+                vec3(0.5, 0.02, 0.5),
+                0.05,
+                Layers::DEFAULT,
+            )
+            .unwrap();
+        let rock = w
+            .add_dynamic_box(vec3(0.5, 1.0, 0.0), Vec3::splat(0.1), 50.0, Layers::DEFAULT)
+            .unwrap();
+        for _ in 0..240 {
+            w.step();
+        }
+        assert!(
+            pos(&w, leaf).y > 2.0,
+            "leaf rides the updraft: {}",
+            pos(&w, leaf)
+        );
+        assert!(pos(&w, rock).y < 0.25, "rock falls: {}", pos(&w, rock));
+    }
+
+    #[test]
+    fn fields_leave_bodies_outside_them_alone() {
+        let drop = |field: bool| {
+            let mut w = world();
+            if field {
+                w.add_force_field(ForceField::updraft(Vec3::ZERO, 3.0, 6.0, 30.0));
+            }
+            let ball = w
+                .add_dynamic_box(vec3(10.0, 5.0, 0.0), Vec3::splat(0.25), 1.0, "default")
+                .unwrap();
+            let h = w.handle_of(ball).unwrap();
+            w.bodies[h].set_linvel(vector![4.0, 0.0, 0.0], true);
+            for _ in 0..30 {
+                w.step();
+            }
+            (pos(&w, ball), *w.bodies[h].linvel())
+        };
+        assert_eq!(drop(true), drop(false));
+    }
+
+    #[test]
+    fn characters_are_pushed_only_when_they_opt_in() {
+        let mut w = world();
+        let still = w
+            .add_character(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        let blown = w
+            .add_character(vec3(0.0, 1.0, 5.0), vec3(0.3, 0.6, 0.3), Layers::CHARACTER)
+            .unwrap();
+        w.char_map.get_mut(&blown).unwrap().wind_push = 0.5;
+        w.set_wind(Vec3::X, 4.0);
+        w.step();
+        for _ in 0..60 {
+            for id in [still, blown] {
+                w.control_character(id, Vec3::ZERO, 1.0 / 60.0, false);
+            }
+            w.step();
+        }
+        assert!(pos(&w, still).x.abs() < 1e-3);
+        assert!((pos(&w, blown).x - 2.0).abs() < 0.1, "{}", pos(&w, blown));
+
+        // Updrafts lift pushed characters off the ground.
+        w.set_wind(Vec3::ZERO, 0.0);
+        w.add_force_field(ForceField::updraft(vec3(2.0, 0.0, 5.0), 2.0, 4.0, 6.0));
+        for _ in 0..60 {
+            w.control_character(blown, Vec3::ZERO, 1.0 / 60.0, false);
+            w.step();
+        }
+        assert!(pos(&w, blown).y > 2.0, "{}", pos(&w, blown));
+        assert_eq!(w.char_map[&blown].state, CharState::Jumping);
+    }
+}
@@ -31,6 +31,7 @@ fn main() -> anyhow::Result<()> {
         }],
         pois: vec![],
         objective: Some("defeat_boss".into()),
+        relationships: vec![],
     };
 
     let director = BossDirector;
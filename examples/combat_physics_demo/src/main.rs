@@ -5,18 +5,38 @@ use glam::vec3;
 fn main() -> anyhow::Result<()> {
     let mut phys = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
     let _ground = phys.create_ground_plane(vec3(50.0, 0.0, 50.0), 1.0);
-    let player_id = phys.add_dynamic_box(
-        vec3(0.0, 1.0, 0.0),
-        vec3(0.3, 0.5, 0.3),
-        2.0,
-        astraweave_physics::Layers::DEFAULT,
-    );
-    let enemy_id = phys.add_dynamic_box(
-        vec3(1.2, 1.0, 0.0),
-        vec3(0.3, 0.5, 0.3),
-        2.0,
-        astraweave_physics::Layers::DEFAULT,
-    );
+    let player_id = phys.add_dynamic_box(vec3(0.0, 1.0, 0.0), vec3(0.3, 0.5, 0.3), 2.0, "player")?;
+    let enemy_id = phys.add_dynamic_box(vec3(1.2, 1.0, 0.0), vec3(0.3, 0.5, 0.3), 2.0, "enemy")?;
+
+    // A crate behind the enemy; a shove sends the enemy crashing through it.
+    let crate_id = phys.add_destructible_box(
+        vec3(2.4, 0.5, 0.0),
+        vec3(0.4, 0.4, 0.4),
+        3.0,
+        40.0,
+        4.0,
+        "default",
+    )?;
+    if let Some(h) = phys.handle_of(enemy_id) {
+        phys.bodies[h].set_linvel(rapier3d::prelude::Vector::new(12.0, 0.0, 0.0), true);
+    }
+    for _ in 0..60 {
+        phys.step();
+        for ev in phys.drain_break_events() {
+            println!(
+                "Crate {} broke ({:?}) into {} chunks; loot drops at {:?}, navmesh rebakes {:?}..{:?}",
+                ev.id,
+                ev.cause,
+                ev.chunks.len(),
+                ev.position,
+                ev.min,
+                ev.max
+            );
+        }
+    }
+    if phys.destructible(crate_id).is_some() {
+        println!("Crate survived");
+    }
 
     let mut targets = vec![Combatant {
         body: enemy_id,
@@ -28,12 +48,22 @@ fn main() -> anyhow::Result<()> {
         }),
     }];
 
-    // sweep from player forward
+    // the player steps round the debris and swings at wherever the enemy ended up
+    let enemy_pos = phys.body_transform(enemy_id).unwrap().w_axis.truncate();
+    println!(
+        "Enemy at {:?}, in sight: {}",
+        enemy_pos,
+        phys.line_of_sight(
+            player_id,
+            enemy_id,
+            phys.layer_matrix().query_mask("player".into())?
+        )
+    );
     let hit = perform_attack_sweep(
-        &mut phys,
+        &phys,
         player_id,
-        vec3(0.0, 1.0, 0.0),
-        vec3(1.5, 1.0, 0.0),
+        enemy_pos - vec3(0.0, 0.0, 1.5),
+        enemy_pos,
         0.2,
         20,
         DamageType::Physical,
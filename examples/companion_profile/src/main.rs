@@ -1,6 +1,12 @@
-use astraweave_memory::{CompanionProfile, Episode};
+use astraweave_memory::{
+    CompanionProfile, Episode, ProfileSigningKey, RelationshipEvent, TrustedKeys, PLAYER,
+};
 
 fn main() -> anyhow::Result<()> {
+    let key = ProfileSigningKey::generate();
+    let mut trusted = TrustedKeys::new();
+    trusted.add(&key);
+
     let mut p = CompanionProfile::new_default();
     p.episodes.push(Episode {
         title: "rescue_echo".into(),
@@ -9,9 +15,24 @@ fn main() -> anyhow::Result<()> {
         ts: "2025-09-04T12:00:00Z".into(),
     });
     p.distill();
-    p.sign();
+    p.record_relationship_event(&RelationshipEvent::Revived { by: PLAYER.into() });
+    p.apply_dialogue_vars(&[("rel.faction:raiders.fear".into(), "+0.4".into())]);
+    p.sign(&key);
     p.save_to_file("companion.cprof")?;
     let loaded = CompanionProfile::load_from_file("companion.cprof")?;
-    println!("Loaded profile OK? verify={}", loaded.verify());
+    println!(
+        "Loaded profile OK? key={} verify={:?}",
+        loaded.key_id.as_deref().unwrap_or("-"),
+        loaded.verify(&trusted)
+    );
+    for (target, r) in &loaded.relationships.entries {
+        println!(
+            "  {target}: {} (affinity {:.2}, trust {:.2}, fear {:.2})",
+            r.label(),
+            r.affinity,
+            r.trust,
+            r.fear
+        );
+    }
     Ok(())
 }
@@ -1,7 +1,15 @@
+use astraweave_author::{MapMeta, ScriptHost};
 use astraweave_core::{ActionStep, IVec2, PlanIntent, Team, World};
 use astraweave_render::{Camera, CameraController, Renderer};
 use aw_debug::{watch_reload_signal, watch_scripts, ChromeTraceGuard, PerfHud};
-use std::{path::PathBuf, time::Instant};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -24,10 +32,15 @@ struct App {
     hud: PerfHud,
     last_update: Instant,
     system_timers: Vec<(String, f32)>,
+
+    // Hot-reloaded encounter scripts
+    scripts: ScriptHost,
+    encounter_dir: PathBuf,
+    scripts_dirty: Arc<AtomicBool>,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(encounter_dir: &std::path::Path, scripts_dirty: Arc<AtomicBool>) -> Self {
         let mut world = World::new();
         // wall
         for y in 1..=8 {
@@ -70,7 +83,12 @@ impl App {
         hud.log_event("system", "Application started");
         hud.log_event("world", "World initialized with 3 entities");
 
-        Self {
+        let mut scripts = ScriptHost::new();
+        for path in scripts.load_dir(encounter_dir) {
+            hud.log_event("script", &format!("Loaded {}", path.display()));
+        }
+
+        let mut app = Self {
             world,
             player,
             comp,
@@ -79,6 +97,52 @@ impl App {
             hud,
             last_update: Instant::now(),
             system_timers,
+            scripts,
+            encounter_dir: encounter_dir.to_path_buf(),
+            scripts_dirty,
+        };
+        app.flush_script_errors();
+        app
+    }
+
+    fn reload_scripts(&mut self) {
+        if !self.scripts_dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let meta = MapMeta {
+            width: 20,
+            height: 10,
+            enemy_count: 1,
+            difficulty: 2,
+        };
+        // Files created since the last check are loaded as new; edited ones are reloaded.
+        let loaded = self.scripts.load_dir(&self.encounter_dir);
+        for path in &loaded {
+            self.hud
+                .log_event("script", &format!("Loaded {}", path.display()));
+        }
+        let reloaded = self.scripts.reload_changed();
+        for path in &reloaded {
+            self.hud
+                .log_event("script", &format!("Reloaded {}", path.display()));
+        }
+        for path in loaded.iter().chain(&reloaded) {
+            if let Ok((budget, _hints)) = self.scripts.run_configure(path, &meta) {
+                self.hud.log_event(
+                    "script",
+                    &format!(
+                        "Budget: traps={}, terrain={}, spawns={}",
+                        budget.traps, budget.terrain_edits, budget.spawns
+                    ),
+                );
+            }
+        }
+        self.flush_script_errors();
+    }
+
+    fn flush_script_errors(&mut self) {
+        for err in self.scripts.drain_errors() {
+            self.hud.log_event("error", &err.to_string());
         }
     }
 
@@ -95,6 +159,8 @@ impl App {
         self.system_timers[1].1 =
             (start.elapsed().as_secs_f32() * 1000.0) - self.system_timers[0].1;
 
+        self.reload_scripts();
+
         // Update HUD with latest system timings
         self.hud.systems_snapshot = self.system_timers.clone();
 
@@ -122,9 +188,13 @@ fn main() -> anyhow::Result<()> {
     let content_dir = PathBuf::from("content");
     std::fs::create_dir_all(&content_dir).ok();
 
-    let _script_watcher = watch_scripts(content_dir.join("encounters"), || {
-        println!("Script changed, reloading...");
-        // In a real app, you would reload your scripts here
+    let encounter_dir = content_dir.join("encounters");
+    std::fs::create_dir_all(&encounter_dir).ok();
+    let scripts_dirty = Arc::new(AtomicBool::new(false));
+    let dirty = scripts_dirty.clone();
+    let _script_watcher = watch_scripts(encounter_dir.clone(), move || {
+        // Recompilation happens on the main thread in `App::update`
+        dirty.store(true, Ordering::Relaxed);
     })
     .ok();
 
@@ -164,7 +234,7 @@ fn main() -> anyhow::Result<()> {
         egui_wgpu::Renderer::new(renderer.device(), renderer.surface_format(), None, 1);
 
     // Create our app
-    let mut app = App::new();
+    let mut app = App::new(&encounter_dir, scripts_dirty);
 
     // Run the event loop
     event_loop.run(move |event, elwt| {
@@ -1,7 +1,7 @@
 use astraweave_ai::{Orchestrator, RuleOrchestrator};
 use astraweave_core::{
-    build_snapshot, step, validate_and_execute, IVec2, PerceptionConfig, SimConfig, Team,
-    ValidateCfg, World,
+    build_snapshot, step, validate_and_execute, IVec2, PerceptionConfig, SimConfig, TacticsConfig,
+    Team, ValidateCfg, World,
 };
 
 fn main() -> anyhow::Result<()> {
@@ -24,15 +24,25 @@ fn main() -> anyhow::Result<()> {
     }
 
     let orch = RuleOrchestrator;
-    let p_cfg = PerceptionConfig { los_max: 12 };
     let v_cfg = ValidateCfg {
         world_bounds: (0, 0, 19, 9),
     };
+    let p_cfg = PerceptionConfig {
+        los_max: 12,
+        tactics: Some(TacticsConfig {
+            bounds: v_cfg.world_bounds,
+            influence_radius: 6,
+            max_steps: 8,
+        }),
+    };
     let s_cfg = SimConfig { dt: 0.25 };
 
     // Build snapshot & propose plan
     let enemies = vec![enemy];
     let snap = build_snapshot(&w, player, comp, &enemies, Some("extract".into()), &p_cfg);
+    for poi in &snap.pois {
+        println!("POI {} at {:?}", poi.k, poi.pos);
+    }
     let plan = orch.propose_plan(&snap);
 
     let mut log = |line: String| {
@@ -32,6 +32,7 @@ async fn main() -> anyhow::Result<()> {
         }],
         pois: vec![],
         objective: Some("extract".into()),
+        relationships: vec![],
     };
 
     let plan = ws_client_roundtrip("ws://127.0.0.1:8088", &snap).await?;
@@ -160,6 +160,7 @@ fn create_test_scenario() -> WorldSnapshot {
             },
         ],
         objective: Some("Reach extraction point while providing cover".into()),
+        relationships: vec![],
     }
 }
 
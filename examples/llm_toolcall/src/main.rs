@@ -26,6 +26,7 @@ async fn main() -> anyhow::Result<()> {
         }],
         pois: vec![],
         objective: Some("extract".into()),
+        relationships: vec![],
     };
     let reg = ToolRegistry {
         tools: vec![
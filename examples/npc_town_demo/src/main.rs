@@ -1,7 +1,7 @@
 use std::fs;
 use std::time::Instant;
 
-use glam::{vec3, Vec2};
+use glam::{vec3, Vec2, Vec3};
 use winit::{
     dpi::PhysicalSize,
     event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent},
@@ -10,12 +10,51 @@ use winit::{
 };
 
 use astraweave_audio::AudioEngine;
+use astraweave_nav::{BakeConfig, Crowd, CrowdAgentParams, CrowdAgentState, NavMesh, Triangle};
 use astraweave_npc::{
     llm::MockLlm, load_profile_from_toml_str, EngineCommandSink, NpcManager, NpcWorldView,
+    NPC_FEET_TO_CENTRE, NPC_HALF_EXTENTS,
 };
 use astraweave_physics::PhysicsWorld;
 use astraweave_render::{Camera, CameraController, Instance, Renderer};
 
+/// Two triangles of a horizontal quad.
+fn quad(min: Vec3, max: Vec3, y: f32) -> [Triangle; 2] {
+    let (a, b) = (vec3(min.x, y, min.z), vec3(max.x, y, max.z));
+    let (c, d) = (vec3(max.x, y, min.z), vec3(min.x, y, max.z));
+    [Triangle { a, b, c }, Triangle { a, b: d, c: b }]
+}
+
+/// Market square with a ring of stalls; each stall is a solid box shoppers walk around.
+fn market() -> (Vec<Triangle>, Vec<Vec3>) {
+    let mut tris = quad(vec3(-15.0, 0.0, -15.0), vec3(15.0, 0.0, 15.0), 0.0).to_vec();
+    let mut stalls = vec![];
+    for k in 0..6 {
+        let angle = k as f32 * std::f32::consts::TAU / 6.0;
+        let c = vec3(angle.cos(), 0.0, angle.sin()) * 8.0;
+        let (min, max) = (c - vec3(1.5, 0.0, 1.0), c + vec3(1.5, 2.5, 1.0));
+        // Top and four walls; the bake only needs the solid to block headroom.
+        tris.extend(quad(min, max, max.y));
+        for (a, b) in [
+            (vec3(min.x, 0.0, min.z), vec3(max.x, 0.0, min.z)),
+            (vec3(max.x, 0.0, min.z), vec3(max.x, 0.0, max.z)),
+            (vec3(max.x, 0.0, max.z), vec3(min.x, 0.0, max.z)),
+            (vec3(min.x, 0.0, max.z), vec3(min.x, 0.0, min.z)),
+        ] {
+            let up = vec3(0.0, max.y, 0.0);
+            tris.push(Triangle { a, b, c: b + up });
+            tris.push(Triangle {
+                a,
+                b: b + up,
+                c: a + up,
+            });
+        }
+        // Customers queue at the counter facing the square's center.
+        stalls.push(c * 0.7);
+    }
+    (tris, stalls)
+}
+
 fn main() -> anyhow::Result<()> {
     // Window + renderer
     let event_loop = EventLoop::new()?;
@@ -52,8 +91,29 @@ fn main() -> anyhow::Result<()> {
     let merchant = load_profile_from_toml_str(&merchant_toml)?;
     let guard = load_profile_from_toml_str(&guard_toml)?;
 
-    let merchant_id = npcs.spawn_from_profile(&mut phys, merchant);
-    let guard_id = npcs.spawn_from_profile(&mut phys, guard);
+    let merchant_id = npcs.spawn_from_profile(&mut phys, merchant)?;
+    let guard_id = npcs.spawn_from_profile(&mut phys, guard)?;
+
+    // Market crowd: shoppers wander between stalls, steering around each other
+    let (market_tris, stalls) = market();
+    let nav = NavMesh::bake_with(&market_tris, &BakeConfig::default());
+    npcs.set_navmesh(nav.clone());
+    let mut crowd = Crowd::new();
+    // Shoppers pick new stalls in bursts; spread their path searches over frames
+    crowd.path_queue_mut().budget = 128;
+    let mut shoppers = vec![];
+    for i in 0..16 {
+        let angle = i as f32 * std::f32::consts::TAU / 16.0;
+        let pos = vec3(angle.cos(), 0.0, angle.sin()) * 12.0;
+        let body = phys.add_character(pos + NPC_FEET_TO_CENTRE, NPC_HALF_EXTENTS, "character")?;
+        let params = CrowdAgentParams {
+            max_speed: 1.2 + (i % 4) as f32 * 0.3,
+            ..CrowdAgentParams::default()
+        };
+        let agent = crowd.add_agent(pos, params);
+        crowd.set_target(agent, stalls[i % stalls.len()]);
+        shoppers.push((body, agent, i));
+    }
 
     // Demo input: "utterance mode"
     let mut utter_hello = true; // toggles hello / buy / danger
@@ -201,6 +261,23 @@ fn main() -> anyhow::Result<()> {
                 };
                 npcs.update(dt, &mut glue, &views);
 
+                for (body, agent, _) in &shoppers {
+                    if let Some(m) = phys.body_transform(*body) {
+                        crowd.set_position(*agent, m.w_axis.truncate() - NPC_FEET_TO_CENTRE);
+                    }
+                }
+                crowd.update(&nav, dt);
+                for (body, agent, next) in shoppers.iter_mut() {
+                    let a = crowd.agent(*agent).unwrap();
+                    let (state, vel) = (a.state, a.vel);
+                    if state == CrowdAgentState::Arrived {
+                        // Done at this stall; browse the next one over
+                        *next += 1;
+                        crowd.set_target(*agent, stalls[*next % stalls.len()]);
+                    }
+                    phys.control_character(*body, vel, dt, false);
+                }
+
                 // Render simple cubes for "town" + NPCs
                 instances.clear();
                 // ground is drawn by renderer; add NPC markers:
@@ -215,6 +292,22 @@ fn main() -> anyhow::Result<()> {
                     vec3(0.6, 1.0, 0.6),
                     [0.2, 0.6, 1.0, 1.0],
                 )); // guard
+                for (body, _, _) in &shoppers {
+                    if let Some(m) = phys.body_transform(*body) {
+                        instances.push(Instance::from_pos_scale_color(
+                            m.w_axis.truncate() - vec3(0.0, 0.5, 0.0),
+                            vec3(0.5, 1.0, 0.5),
+                            [0.9, 0.7, 0.3, 1.0],
+                        ));
+                    }
+                }
+                for &s in &stalls {
+                    instances.push(Instance::from_pos_scale_color(
+                        s / 0.7 + vec3(0.0, 1.25, 0.0),
+                        vec3(3.0, 2.5, 2.0),
+                        [0.6, 0.4, 0.25, 1.0],
+                    ));
+                }
 
                 renderer.update_instances(&instances);
                 if let Err(e) = renderer.render() {
@@ -1,10 +1,88 @@
-use astraweave_persona::load_persona_zip;
+use astraweave_memory::{ProfileSigningKey, TrustedKeys};
+use astraweave_persona::{build_persona_pack, load_persona_pack, sign_persona_zip, PackPolicy};
+use std::path::Path;
+
+const MANIFEST: &str = r#"
+schema_version = 2
+
+[pack]
+id = "sniper"
+name = "Sniper"
+version = "1.0.0"
+
+[persona]
+tone = "laconic"
+risk = "high"
+humor = "dry"
+voice = "v02"
+
+[[skills]]
+name = "smoke_timing"
+level = 4
+notes = "always smoke before push"
+
+[assets]
+voice_bank = "voice/bank.toml"
+dialogue = ["dialogue/intro.toml"]
+prompts = { system = "prompts/system.txt" }
+
+[behavior_presets.overwatch]
+stealth_bias = 0.9
+aggro_risk = 0.3
+"#;
+
+fn write_source(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir.join("voice"))?;
+    std::fs::create_dir_all(dir.join("dialogue"))?;
+    std::fs::create_dir_all(dir.join("prompts"))?;
+    std::fs::write(dir.join("persona_manifest.toml"), MANIFEST)?;
+    std::fs::write(
+        dir.join("voice/bank.toml"),
+        "[speakers.Sniper]\nfolder = \"voice/Sniper\"\ntts_voice = \"sniper_v1\"\n",
+    )?;
+    std::fs::write(
+        dir.join("dialogue/intro.toml"),
+        "id = \"intro\"\nstart = \"n0\"\n\n[[nodes]]\nid = \"n0\"\nline = { speaker = \"Sniper\", text = \"Eyes up.\" }\nend = true\n",
+    )?;
+    std::fs::write(
+        dir.join("prompts/system.txt"),
+        "You are a {tone} sniper who never wastes words.",
+    )?;
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
-    let p = load_persona_zip("sniper_persona.zip")?;
+    let src = std::env::temp_dir().join("sniper_persona");
+    write_source(&src)?;
+    build_persona_pack(&src, Path::new("sniper_persona.zip"))?;
+
+    let key = ProfileSigningKey::generate();
+    sign_persona_zip("sniper_persona.zip", &key)?;
+    let mut trusted = TrustedKeys::new();
+    trusted.add(&key);
+
+    let pack = load_persona_pack("sniper_persona.zip", &PackPolicy::require(trusted))?;
+    println!(
+        "Loaded persona {} v{}: tone={}, signed_by={}",
+        pack.manifest.pack.id,
+        pack.manifest.pack.version,
+        pack.profile.persona.tone,
+        pack.profile.key_id.as_deref().unwrap_or("<unsigned>")
+    );
+    println!(
+        "Assets: {} dialogue(s), prompts {:?}, presets {:?}",
+        pack.dialogues.len(),
+        pack.prompts.keys().collect::<Vec<_>>(),
+        pack.manifest.behavior_presets.keys().collect::<Vec<_>>()
+    );
+    let issues = pack.validate();
     println!(
-        "Loaded persona: tone={}, verify={}",
-        p.persona.tone,
-        p.verify()
+        "Validation: {}",
+        if issues.is_empty() {
+            "ok".to_string()
+        } else {
+            issues.join("; ")
+        }
     );
     Ok(())
 }
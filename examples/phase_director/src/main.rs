@@ -29,6 +29,7 @@ fn main() -> anyhow::Result<()> {
         }],
         pois: vec![],
         objective: Some("defeat_boss".into()),
+        relationships: vec![],
     };
     let budget = DirectorBudget {
         traps: 2,
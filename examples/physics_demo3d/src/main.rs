@@ -1,6 +1,6 @@
-use astraweave_physics::{Layers, PhysicsWorld};
+use astraweave_physics::{Layers, PhysicsWorld, Skeleton};
 use astraweave_render::{Camera, CameraController, Instance, Renderer};
-use glam::{vec3, Vec2, Vec3};
+use glam::{vec3, Quat, Vec2, Vec3};
 use std::{sync::Arc, time::Instant};
 use winit::{
     dpi::PhysicalSize,
@@ -47,10 +47,10 @@ fn main() -> anyhow::Result<()> {
         ],
         &[[0, 1, 2], [3, 2, 1]],
         Layers::CHARACTER | Layers::DEFAULT,
-    );
+    )?;
 
     // Character (kinematic)
-    let char_id = phys.add_character(vec3(-2.0, 1.0, 0.0), vec3(0.4, 0.9, 0.4));
+    let char_id = phys.add_character(vec3(-2.0, 1.0, 0.0), vec3(0.4, 0.9, 0.4), "player")?;
 
     // Destructible demo crate
     let mut destruct_ids: Vec<u64> = vec![];
@@ -60,7 +60,8 @@ fn main() -> anyhow::Result<()> {
         3.0,
         50.0,
         12.0,
-    ));
+        Layers::DEFAULT,
+    )?);
 
     // Water pool toggle
     let mut water_on = true;
@@ -138,6 +139,9 @@ fn main() -> anyhow::Result<()> {
                         KeyCode::KeyC => {
                             climb_try = down;
                         }
+                        KeyCode::KeyU if down => {
+                            phys.jump_character(char_id);
+                        }
 
                         // Wind toggle
                         KeyCode::KeyT if down => {
@@ -168,7 +172,7 @@ fn main() -> anyhow::Result<()> {
 
                         // Drop dynamic box
                         KeyCode::KeyF if down => {
-                            phys.add_dynamic_box(
+                            let _ = phys.add_dynamic_box(
                                 vec3(0.0, 4.0, 0.0),
                                 vec3(0.3, 0.3, 0.3),
                                 1.0,
@@ -176,28 +180,32 @@ fn main() -> anyhow::Result<()> {
                             );
                         }
 
-                        // Spawn ragdoll (using dynamic box as placeholder)
+                        // Spawn ragdoll
                         KeyCode::KeyB if down => {
-                            let _rag = phys.add_dynamic_box(
+                            if let Ok(rag) = phys.spawn_ragdoll(
+                                &Skeleton::humanoid(1.8, 70.0),
                                 vec3(0.0, 1.2, -1.5),
-                                vec3(0.2, 0.5, 0.2),
-                                70.0,
+                                Quat::IDENTITY,
+                                Vec3::ZERO,
                                 Layers::DEFAULT,
-                            );
-                            println!("Spawned ragdoll (box placeholder)");
+                            ) {
+                                println!("Spawned ragdoll ({} bones)", rag.bones.len());
+                            }
                         }
 
                         // Spawn destructible
                         KeyCode::KeyN if down => {
-                            let id = phys.add_destructible_box(
+                            if let Ok(id) = phys.add_destructible_box(
                                 vec3(-0.5, 1.0, -1.0),
                                 vec3(0.4, 0.4, 0.4),
                                 3.0,
                                 60.0,
                                 14.0,
-                            );
-                            destruct_ids.push(id);
-                            println!("Spawned destructible");
+                                Layers::DEFAULT,
+                            ) {
+                                destruct_ids.push(id);
+                                println!("Spawned destructible");
+                            }
                         }
 
                         // Force-break nearest
@@ -234,7 +242,7 @@ fn main() -> anyhow::Result<()> {
 
                 cam_ctl.update_camera(&mut camera, dt);
 
-                // Character movement (simplified - no vertical control)
+                // Character movement; gravity, steps and slopes are handled by the controller
                 let desired = vec3(move_dir.x, 0.0, move_dir.z);
                 phys.control_character(char_id, desired, dt, climb_try);
                 phys.step();
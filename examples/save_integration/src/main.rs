@@ -6,6 +6,7 @@
 use anyhow::Result;
 use astraweave_core::{World, Team, IVec2};
 use aw_save::{SaveManager, SaveBundleV2, WorldState, PlayerInventory, ItemStack, CompanionProfile, SAVE_SCHEMA_VERSION};
+use astraweave_memory::{self as memory, RelationshipEvent, PLAYER};
 use std::collections::HashMap;
 use time::OffsetDateTime;
 use uuid::Uuid;
@@ -33,6 +34,10 @@ fn main() -> Result<()> {
     
     println!("After 10 ticks, world time: {:.1}", world.t);
     
+    // Echo's relationships live in its memory profile; the player revived it once
+    let mut echo_memory = memory::CompanionProfile::new_default();
+    echo_memory.record_relationship_event(&RelationshipEvent::Revived { by: PLAYER.into() });
+
     // Create companion profiles (simplified for this example)
     let echo_profile = CompanionProfile {
         id: "echo_001".to_string(),
@@ -48,6 +53,7 @@ fn main() -> Result<()> {
             "Tutorial: First activation and bonding".to_string(),
             "Mission 1: Facility escape operation".to_string(),
         ],
+        relationships: echo_memory.relationships.to_save_states(),
     };
     
     // Convert AstraWeave data to save format
@@ -78,6 +84,10 @@ fn main() -> Result<()> {
     if !loaded_bundle.companions.is_empty() {
         println!("Companion name: {}", loaded_bundle.companions[0].name);
         println!("Companion facts: {:?}", loaded_bundle.companions[0].facts);
+        let relationships = memory::Relationships::from_save_states(&loaded_bundle.companions[0].relationships);
+        for (target, r) in &relationships.entries {
+            println!("Companion relationship: {} is {} (affinity {:.2}, trust {:.2}, fear {:.2}, {} interactions)", target, r.label(), r.affinity, r.trust, r.fear, r.interactions);
+        }
     }
     
     // Demonstrate world restoration
@@ -1,7 +1,7 @@
 use astraweave_core::{IVec2, Team, World};
 use astraweave_gameplay::biome::generate_island_room;
 use astraweave_gameplay::*;
-use astraweave_nav::NavMesh;
+use astraweave_nav::{BakeConfig, NavEvent, TiledNavMesh};
 use astraweave_physics::PhysicsWorld;
 use astraweave_render::{Camera, CameraController, Instance, Renderer};
 use glam::{vec3, Vec2};
@@ -39,6 +39,8 @@ fn main() -> anyhow::Result<()> {
     // Core world & physics
     let mut w = World::new();
     let mut phys = PhysicsWorld::new(vec3(0.0, -9.81, 0.0));
+    // Flooded pit for the LowerWater weave (key 4) to drain
+    phys.add_water_aabb(vec3(-3.0, -2.0, -3.0), vec3(3.0, 0.5, 3.0), 1000.0, 2.0);
 
     // Spawn some tokens to visualize
     let _player = w.spawn("Player", IVec2 { x: 2, y: 2 }, Team { id: 0 }, 100, 0);
@@ -47,7 +49,16 @@ fn main() -> anyhow::Result<()> {
 
     // Simple island triangles (for nav + visual anchors)
     let tris = generate_island_room();
-    let _nav = NavMesh::bake(&tris, 0.5, 55.0);
+    let mut nav = TiledNavMesh::new(
+        &tris,
+        BakeConfig {
+            max_step: 0.5,
+            max_slope_deg: 55.0,
+            ..BakeConfig::default()
+        },
+        8.0,
+    );
+    nav.spawn_worker();
 
     // Weave budget
     let mut budget = WeaveBudget {
@@ -90,7 +101,7 @@ fn main() -> anyhow::Result<()> {
                                 if let Ok(cons) = apply_weave_op(
                                     &mut w,
                                     &mut phys,
-                                    &tris,
+                                    &mut nav,
                                     &mut budget,
                                     &op,
                                     &mut log,
@@ -111,7 +122,7 @@ fn main() -> anyhow::Result<()> {
                                 let _ = apply_weave_op(
                                     &mut w,
                                     &mut phys,
-                                    &tris,
+                                    &mut nav,
                                     &mut budget,
                                     &op,
                                     &mut log,
@@ -127,7 +138,7 @@ fn main() -> anyhow::Result<()> {
                                 let _ = apply_weave_op(
                                     &mut w,
                                     &mut phys,
-                                    &tris,
+                                    &mut nav,
                                     &mut budget,
                                     &op,
                                     &mut log,
@@ -143,7 +154,23 @@ fn main() -> anyhow::Result<()> {
                                 let _ = apply_weave_op(
                                     &mut w,
                                     &mut phys,
-                                    &tris,
+                                    &mut nav,
+                                    &mut budget,
+                                    &op,
+                                    &mut log,
+                                );
+                            }
+                            KeyCode::Digit5 => {
+                                let op = WeaveOp {
+                                    kind: WeaveOpKind::RaisePlatform,
+                                    a: vec3(4.0, 0.0, -2.0),
+                                    b: Some(vec3(8.0, 0.0, -2.0)),
+                                    budget_cost: 1,
+                                };
+                                let _ = apply_weave_op(
+                                    &mut w,
+                                    &mut phys,
+                                    &mut nav,
                                     &mut budget,
                                     &op,
                                     &mut log,
@@ -174,6 +201,16 @@ fn main() -> anyhow::Result<()> {
                 last += std::time::Duration::from_secs_f32(dt);
                 cam_ctl.update_camera(&mut camera, dt);
                 phys.step();
+                nav.tick_links(dt);
+                nav.update();
+                for ev in nav.drain_events() {
+                    match ev {
+                        NavEvent::TrisRemapped(m) => {
+                            println!("Nav: {} triangles renumbered", m.len())
+                        }
+                        ev => println!("Nav: {:?}", ev),
+                    }
+                }
 
                 // Rebuild instances (simple viz)
                 instances.clear();
@@ -27,7 +27,7 @@ use uuid::Uuid;
 const MAGIC: &[u8; 4] = b"ASVS";
 const CODEC_LZ4: u8 = 1;
 /// Bump this when you change SaveBundle layout. Add explicit migrations below.
-pub const SAVE_SCHEMA_VERSION: u16 = 2;
+pub const SAVE_SCHEMA_VERSION: u16 = 3;
 
 /// Public, stable entrypoint
 #[derive(Debug, Clone)]
@@ -82,14 +82,7 @@ impl SaveManager {
     /// Migration: read any old file and produce current V2 bundle; optionally resave.
     pub fn migrate_file_to_latest(&self, path: &Path, resave: bool) -> Result<SaveBundleV2> {
         let AnySave { version, blob } = read_any_version(path)?;
-        let v2 = match version {
-            1 => {
-                let v1: SaveBundleV1 = postcard::from_bytes(&blob).context("decode v1")?;
-                v1.into_v2()
-            }
-            2 => postcard::from_bytes::<SaveBundleV2>(&blob).context("decode v2")?,
-            other => bail!("unknown save version: {}", other),
-        };
+        let v2 = decode_bundle(version, &blob)?;
         if resave {
             write_awsv(path, &v2)?;
         }
@@ -98,6 +91,7 @@ impl SaveManager {
 }
 
 /// What's inside the postcard payload (CURRENT).
+/// Schema 3 kept this layout but added `CompanionProfile::relationships`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SaveBundleV2 {
     pub schema: u16,                 // == SAVE_SCHEMA_VERSION
@@ -145,6 +139,19 @@ pub struct CompanionProfile {
     pub skills: Vec<String>,
     pub facts: Vec<String>,
     pub episodes_summarized: Vec<String>,
+    /// Companion's standing toward the player, NPCs and factions (schema 3+).
+    pub relationships: Vec<RelationshipState>,
+}
+
+/// One relationship entry (mirrors `astraweave_memory::Relationship`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationshipState {
+    pub target: String,
+    pub affinity: f32,
+    pub trust: f32,
+    pub fear: f32,
+    /// Events applied so far.
+    pub interactions: u32,
 }
 
 // --------- V1 schema + migration ----------
@@ -177,6 +184,100 @@ impl SaveBundleV1 {
     }
 }
 
+// --------- Pre-relationship (schema 1-2) payload layouts ----------
+// postcard is not self-describing, so older payloads must be decoded with their exact layout.
+
+#[derive(Serialize, Deserialize)]
+struct CompanionProfileS2 {
+    id: String,
+    name: String,
+    level: u8,
+    skills: Vec<String>,
+    facts: Vec<String>,
+    episodes_summarized: Vec<String>,
+}
+
+impl From<CompanionProfileS2> for CompanionProfile {
+    fn from(c: CompanionProfileS2) -> Self {
+        CompanionProfile {
+            id: c.id,
+            name: c.name,
+            level: c.level,
+            skills: c.skills,
+            facts: c.facts,
+            episodes_summarized: c.episodes_summarized,
+            relationships: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveBundleV1S1 {
+    player_id: String,
+    slot: u8,
+    created_at: OffsetDateTime,
+    world: WorldState,
+    inventory: PlayerInventory,
+    companion: Option<CompanionProfileS2>,
+    meta: HashMap<String, String>,
+}
+
+impl From<SaveBundleV1S1> for SaveBundleV1 {
+    fn from(b: SaveBundleV1S1) -> Self {
+        SaveBundleV1 {
+            player_id: b.player_id,
+            slot: b.slot,
+            created_at: b.created_at,
+            world: b.world,
+            inventory: b.inventory,
+            companion: b.companion.map(Into::into),
+            meta: b.meta,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveBundleV2S2 {
+    schema: u16,
+    save_id: Uuid,
+    created_at: OffsetDateTime,
+    player_id: String,
+    slot: u8,
+    world: WorldState,
+    companions: Vec<CompanionProfileS2>,
+    inventory: PlayerInventory,
+    meta: HashMap<String, String>,
+}
+
+impl From<SaveBundleV2S2> for SaveBundleV2 {
+    fn from(b: SaveBundleV2S2) -> Self {
+        SaveBundleV2 {
+            schema: SAVE_SCHEMA_VERSION,
+            save_id: b.save_id,
+            created_at: b.created_at,
+            player_id: b.player_id,
+            slot: b.slot,
+            world: b.world,
+            companions: b.companions.into_iter().map(Into::into).collect(),
+            inventory: b.inventory,
+            meta: b.meta,
+        }
+    }
+}
+
+/// Decode a payload of any known schema version into the current bundle.
+fn decode_bundle(version: u16, blob: &[u8]) -> Result<SaveBundleV2> {
+    Ok(match version {
+        1 => {
+            let v1: SaveBundleV1S1 = postcard::from_bytes(blob).context("decode v1")?;
+            SaveBundleV1::from(v1).into_v2()
+        }
+        2 => postcard::from_bytes::<SaveBundleV2S2>(blob).context("decode v2")?.into(),
+        3 => postcard::from_bytes::<SaveBundleV2>(blob).context("decode v3")?,
+        other => bail!("unknown save version: {}", other),
+    })
+}
+
 // --------- On-disk index (quality-of-life) ----------
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -234,16 +335,19 @@ fn scan_dir_for_meta(dir: &Path) -> Result<Vec<SaveMeta>> {
 // --------- File format I/O (atomic, checksummed, compressed) ----------
 
 fn write_awsv(path: &Path, v2: &SaveBundleV2) -> Result<()> {
-    let payload = postcard::to_allocvec(v2)?;
+    write_payload(path, SAVE_SCHEMA_VERSION, &postcard::to_allocvec(v2)?)
+}
+
+fn write_payload(path: &Path, version: u16, payload: &[u8]) -> Result<()> {
     // compress
-    let payload = lz4_flex::compress_prepend_size(&payload);
+    let payload = lz4_flex::compress_prepend_size(payload);
     let mut crc = Crc32::new();
     crc.update(&payload);
     let crc = crc.finalize();
 
     let mut buf: Vec<u8> = Vec::with_capacity(4 + 2 + 1 + 1 + 4 + 4 + payload.len());
     buf.extend_from_slice(MAGIC);
-    buf.extend_from_slice(&SAVE_SCHEMA_VERSION.to_le_bytes());
+    buf.extend_from_slice(&version.to_le_bytes());
     buf.push(CODEC_LZ4);
     buf.push(0); // reserved
     buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
@@ -263,14 +367,7 @@ fn write_awsv(path: &Path, v2: &SaveBundleV2) -> Result<()> {
 
 fn read_awsv(path: &Path) -> Result<SaveBundleV2> {
     let AnySave { version, blob } = read_any_version(path)?;
-    match version {
-        2 => Ok(postcard::from_bytes::<SaveBundleV2>(&blob)?),
-        1 => {
-            let v1: SaveBundleV1 = postcard::from_bytes(&blob)?;
-            Ok(v1.into_v2())
-        }
-        other => bail!("unknown save version {other}"),
-    }
+    decode_bundle(version, &blob)
 }
 
 struct AnySave { version: u16, blob: Vec<u8> }
@@ -311,4 +408,42 @@ fn sanitize(s: &str) -> String {
     s.chars()
         .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
         .collect()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema2_files_load_without_relationships() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let old = SaveBundleV2S2 {
+            schema: 2,
+            save_id: Uuid::new_v4(),
+            created_at: OffsetDateTime::now_utc(),
+            player_id: "p".into(),
+            slot: 1,
+            world: WorldState { tick: 7, ecs_blob: vec![1, 2], state_hash: 3 },
+            companions: vec![CompanionProfileS2 {
+                id: "c".into(),
+                name: "Echo".into(),
+                level: 2,
+                skills: vec![],
+                facts: vec!["old".into()],
+                episodes_summarized: vec![],
+            }],
+            inventory: PlayerInventory { credits: 5, items: vec![] },
+            meta: HashMap::new(),
+        };
+        let path = dir.path().join("slot01_old.awsv");
+        write_payload(&path, 2, &postcard::to_allocvec(&old)?)?;
+
+        let sm = SaveManager::new(dir.path());
+        let b = sm.migrate_file_to_latest(&path, true)?;
+        assert_eq!(b.schema, SAVE_SCHEMA_VERSION);
+        assert_eq!(b.companions[0].facts, vec!["old".to_string()]);
+        assert!(b.companions[0].relationships.is_empty());
+        assert_eq!(read_any_version(&path)?.version, SAVE_SCHEMA_VERSION);
+        Ok(())
+    }
+}
@@ -48,7 +48,8 @@ fn test_save_load_roundtrip() -> Result<()> {
                 level: 25, 
                 skills: vec!["Combat".to_string(), "Hacking".to_string()], 
                 facts: vec!["Remembers the old world".to_string()], 
-                episodes_summarized: vec!["Helped in the tutorial mission".to_string()] 
+                episodes_summarized: vec!["Helped in the tutorial mission".to_string()],
+                relationships: vec![]
             }
         ],
         meta: {
@@ -31,6 +31,7 @@ fn test_v1_to_v2_migration() -> Result<()> {
             skills: vec!["Legacy Skill".to_string()],
             facts: vec!["Was saved in V1 format".to_string()],
             episodes_summarized: vec![],
+            relationships: vec![],
         }),
         meta: {
             let mut meta = HashMap::new();
@@ -1,4 +1,6 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use astraweave_nav::{source_checksum, BakeConfig, Capabilities, DebugColoring, NavMesh, Triangle};
+use glam::{Mat4, Vec3};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
@@ -25,6 +27,18 @@ enum Rule {
     Model { glob: String },
     #[serde(rename = "audio")]
     Audio { glob: String },
+    /// Bake level geometry into a navmesh. Nodes named `spawn*` are spawn points; the
+    /// build fails if one cannot reach the others or part of the mesh is cut off.
+    #[serde(rename = "navmesh")]
+    Navmesh {
+        glob: String,
+        /// Tolerate islands no spawn point reaches (rooftops, props).
+        #[serde(default)]
+        allow_islands: bool,
+        /// Also write a glTF of the mesh coloured by island for inspection.
+        #[serde(default)]
+        debug_export: bool,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,6 +80,16 @@ fn main() -> Result<()> {
                     manifest.push(record("audio", &entry, &out)?);
                 }
             }
+            Rule::Navmesh {
+                glob,
+                allow_islands,
+                debug_export,
+            } => {
+                for entry in globwalk(&cfg.source, glob)? {
+                    let out = process_navmesh(&entry, &cfg.output, *allow_islands, *debug_export)?;
+                    manifest.push(record("navmesh", &entry, &out)?);
+                }
+            }
         }
     }
 
@@ -200,3 +224,87 @@ fn process_audio(src: &Path, out_root: &str) -> Result<PathBuf> {
     fs::copy(src, &out_wav)?;
     Ok(out_wav)
 }
+
+fn process_navmesh(
+    src: &Path,
+    out_root: &str,
+    allow_islands: bool,
+    debug_export: bool,
+) -> Result<PathBuf> {
+    fs::create_dir_all(out_root)?;
+    let stem = src.file_stem().unwrap().to_string_lossy();
+    let out = Path::new(out_root).join(format!("{stem}.awnav"));
+    let (doc, buffers, _images) =
+        gltf::import(src).with_context(|| format!("read {}", src.display()))?;
+    let mut level = LevelGeometry::default();
+    for scene in doc.scenes() {
+        for node in scene.nodes() {
+            level.add_node(&node, &buffers, Mat4::IDENTITY);
+        }
+    }
+
+    let cfg = BakeConfig::default();
+    let nav = NavMesh::bake_with(&level.tris, &cfg);
+
+    // Check before writing so a failed bake leaves no output behind.
+    let mut report = nav.connectivity_report(&level.spawns, Capabilities::ALL, 2.0);
+    if allow_islands {
+        report.disconnected.clear();
+    }
+    if !report.is_ok() {
+        bail!("{}: navmesh connectivity check failed: {report}", src.display());
+    }
+    nav.save(&out, source_checksum(&level.tris, &cfg))?;
+    if debug_export {
+        let debug = Path::new(out_root).join(format!("{stem}.navmesh.gltf"));
+        fs::write(debug, nav.export_gltf(DebugColoring::Island))?;
+    }
+    println!("{}: {} nav triangles, {report}", src.display(), nav.tris.len());
+    Ok(out)
+}
+
+#[derive(Default)]
+struct LevelGeometry {
+    tris: Vec<Triangle>,
+    spawns: Vec<Vec3>,
+}
+
+impl LevelGeometry {
+    fn add_node(&mut self, node: &gltf::Node, buffers: &[gltf::buffer::Data], parent: Mat4) {
+        let world = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if node
+            .name()
+            .is_some_and(|n| n.to_ascii_lowercase().starts_with("spawn"))
+        {
+            self.spawns.push(world.transform_point3(Vec3::ZERO));
+        }
+        if let Some(mesh) = node.mesh() {
+            for prim in mesh.primitives() {
+                if prim.mode() != gltf::mesh::Mode::Triangles {
+                    continue;
+                }
+                let reader = prim.reader(|b| buffers.get(b.index()).map(|d| &d.0[..]));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+                let verts: Vec<Vec3> = positions
+                    .map(|p| world.transform_point3(Vec3::from(p)))
+                    .collect();
+                let indices: Vec<usize> = match reader.read_indices() {
+                    Some(ix) => ix.into_u32().map(|i| i as usize).collect(),
+                    None => (0..verts.len()).collect(),
+                };
+                for f in indices.chunks_exact(3) {
+                    if let (Some(&a), Some(&b), Some(&c)) =
+                        (verts.get(f[0]), verts.get(f[1]), verts.get(f[2]))
+                    {
+                        self.tris.push(Triangle { a, b, c });
+                    }
+                }
+            }
+        }
+        for child in node.children() {
+            self.add_node(&child, buffers, world);
+        }
+    }
+}
@@ -64,7 +64,7 @@ fn main() -> Result<()> {
                     ],
                 },
                 companions: vec![
-                    CompanionProfile { id: "c1".into(), name: "Echo".into(), level: 4, skills: vec!["Scan".into()], facts: vec![], episodes_summarized: vec![] }
+                    CompanionProfile { id: "c1".into(), name: "Echo".into(), level: 4, skills: vec!["Scan".into()], facts: vec![], episodes_summarized: vec![], relationships: vec![] }
                 ],
                 meta: Default::default(),
             };
@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use astraweave_memory::{ProfileSigningKey, TrustedKeys};
+use astraweave_persona::{
+    build_persona_pack, load_persona_pack, sign_persona_zip, PackPolicy, PersonaPack,
+};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "persona_pack",
+    version,
+    about = "Build, validate and inspect persona packs"
+)]
+struct Cli {
+    #[command(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// Zip a pack source directory (containing persona_manifest.toml)
+    Build {
+        src: PathBuf,
+        out: PathBuf,
+        /// Hex seed file of the signing key; the pack is signed after building
+        #[arg(long)]
+        sign_key: Option<PathBuf>,
+    },
+    /// Load a pack, check its assets and (optionally) its signature
+    Validate {
+        pack: PathBuf,
+        /// Trusted key set (JSON); when given the pack must be signed by one of them
+        #[arg(long)]
+        trusted: Option<PathBuf>,
+    },
+    /// Print manifest, assets and signature info
+    Inspect { pack: PathBuf },
+    /// Generate a signing key; writes <out> (secret seed) and <out>.pub
+    Keygen { out: PathBuf },
+    /// Add a public key (<key>.pub) to a trusted key set, creating it if needed
+    Trust { public: PathBuf, store: PathBuf },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.cmd {
+        Cmd::Build { src, out, sign_key } => {
+            build_persona_pack(&src, &out)?;
+            let out_s = out.to_string_lossy();
+            if let Some(key_path) = sign_key {
+                let key = ProfileSigningKey::from_hex(&std::fs::read_to_string(key_path)?)?;
+                sign_persona_zip(&out_s, &key)?;
+                println!("Signed with key {}", key.key_id());
+            }
+            let pack = load_persona_pack(&out_s, &PackPolicy::default())?;
+            report_issues(&pack)?;
+            println!("Built {} ({} files)", out.display(), pack.files.len());
+        }
+        Cmd::Validate { pack, trusted } => {
+            let policy = match trusted {
+                Some(p) => PackPolicy::require(TrustedKeys::load_from_file(&p.to_string_lossy())?),
+                None => PackPolicy::default(),
+            };
+            let p = load_persona_pack(&pack.to_string_lossy(), &policy)?;
+            report_issues(&p)?;
+            println!("OK: {} v{}", p.manifest.pack.id, p.manifest.pack.version);
+        }
+        Cmd::Inspect { pack } => {
+            let p = load_persona_pack(&pack.to_string_lossy(), &PackPolicy::default())?;
+            let m = &p.manifest;
+            println!(
+                "pack:       {} v{} (schema {})",
+                m.pack.id, m.pack.version, m.schema_version
+            );
+            println!(
+                "persona:    tone={} risk={} humor={} voice={}",
+                m.persona.tone, m.persona.risk, m.persona.humor, m.persona.voice
+            );
+            println!("skills:     {}  facts: {}", m.skills.len(), m.facts.len());
+            for d in &m.dependencies {
+                println!("depends:    {} {}", d.id, d.version);
+            }
+            if let Some(bank) = &p.voice_bank {
+                let mut speakers: Vec<_> = bank.speakers.keys().collect();
+                speakers.sort();
+                println!("voices:     {speakers:?}");
+            }
+            let dialogues: Vec<_> = p.dialogues.iter().map(|d| d.id.as_str()).collect();
+            println!("dialogue:   {dialogues:?}");
+            println!("portraits:  {:?}", p.portraits.keys().collect::<Vec<_>>());
+            println!("prompts:    {:?}", p.prompts.keys().collect::<Vec<_>>());
+            println!(
+                "presets:    {:?}",
+                m.behavior_presets.keys().collect::<Vec<_>>()
+            );
+            println!(
+                "signed by:  {}",
+                p.profile.key_id.as_deref().unwrap_or("<unsigned>")
+            );
+        }
+        Cmd::Keygen { out } => {
+            let key = ProfileSigningKey::generate();
+            std::fs::write(&out, key.to_hex())?;
+            let mut public = out.into_os_string();
+            public.push(".pub");
+            std::fs::write(&public, key.public_hex())?;
+            println!("Key {} written", key.key_id());
+        }
+        Cmd::Trust { public, store } => {
+            let store_s = store.to_string_lossy();
+            // Start a new store only if there is none; never overwrite one that fails to load.
+            let mut keys = match TrustedKeys::load_from_file(&store_s) {
+                Ok(keys) => keys,
+                Err(e)
+                    if e.downcast_ref::<std::io::Error>()
+                        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+                {
+                    TrustedKeys::default()
+                }
+                Err(e) => return Err(e.context(format!("reading key store {store_s}"))),
+            };
+            let id = keys.add_public_hex(&std::fs::read_to_string(public)?)?;
+            keys.save_to_file(&store_s)?;
+            println!("Trusted key {id}");
+        }
+    }
+    Ok(())
+}
+
+fn report_issues(pack: &PersonaPack) -> Result<()> {
+    let issues = pack.validate();
+    for i in &issues {
+        eprintln!("  - {i}");
+    }
+    if !issues.is_empty() {
+        bail!("{} problem(s) found", issues.len());
+    }
+    Ok(())
+}